@@ -656,6 +656,7 @@ async function performSearch() {
           query: searchQuery,
           limit: maxSearchResults,
           filters: filtersToSend,
+          include_directories: true,
         },
       );
 
@@ -682,8 +683,11 @@ async function performSearch() {
           } else {
             console.warn("No results returned from backend");
           }
-          if (resultsCount)
-            resultsCount.textContent = `Found ${lastSearchResults.length} relevant documents`;
+          if (resultsCount) {
+            resultsCount.textContent = response.data.semantic_unavailable
+              ? `Found ${lastSearchResults.length} filename matches (semantic search unavailable: embedding server unreachable)`
+              : `Found ${lastSearchResults.length} relevant documents`;
+          }
           filterResultsBySimilarity();
 
           // Log after filtering
@@ -869,7 +873,8 @@ async function displayResults(results) {
 
     const filePath = result.file_path;
     const fileName = result.file_name || filePath.split(/[\\/]/).pop();
-    const fileIconData = getFileIcon(fileName);
+    const isDirectory = result.is_directory === true;
+    const fileIconData = getFileIcon(fileName, isDirectory);
 
     // Truncate file name and path for display
     const displayFileName = truncateFileName(fileName, 40);
@@ -878,7 +883,9 @@ async function displayResults(results) {
     // Get file preview/description
     let description = "";
     try {
-      const previewResult = await window.electronAPI.readFilePreview(filePath);
+      const previewResult = isDirectory
+        ? { success: false }
+        : await window.electronAPI.readFilePreview(filePath);
       if (previewResult.success && previewResult.isText) {
         const lines = previewResult.preview.split("\n").filter((l) => l.trim());
         description = lines[0]
@@ -900,6 +907,7 @@ async function displayResults(results) {
       </div>
       <div class="result-footer">
         <div class="file-path-tag" title="${escapeHtml(filePath)}">${escapeHtml(displayFilePath)}</div>
+        ${isDirectory ? `<div class="relevance-tag" title="Matched by folder name">Folder</div>` : result.content_indexed === false ? `<div class="relevance-tag" title="Matched by file name only; contents are not indexed">Name only</div>` : ""}
         <div class="relevance-tag">${(result.similarity * 100).toFixed(0)}% Match</div>
       </div>
     `;
@@ -909,6 +917,13 @@ async function displayResults(results) {
       enhanceExeIcon(item.querySelector(".file-icon-wrapper"), filePath);
     }
 
+    // Folders open in the file browser instead of the preview panel
+    if (isDirectory) {
+      item.addEventListener("click", () => openFolderInBrowser(filePath));
+      resultsList.appendChild(item);
+      continue;
+    }
+
     // Single-click: preview. Double-click: open file externally (same as Desktop/Downloads)
     let clickTimer = null;
     item.addEventListener("click", async () => {
@@ -1808,6 +1823,9 @@ async function loadSettings() {
         maxSearchResults = settings.max_search_results;
         const slider = document.getElementById("max-results-slider");
         const valueDisplay = document.getElementById("max-results-value");
+        if (slider && settings.max_search_results_cap) {
+          slider.max = settings.max_search_results_cap;
+        }
         if (slider) slider.value = maxSearchResults;
         if (valueDisplay) valueDisplay.textContent = maxSearchResults;
       }
@@ -1859,6 +1877,15 @@ async function loadSettings() {
         ollamaModelInput.value = settings.ollama_model;
       }
 
+      const openaiBaseUrlInput = document.getElementById("openai-base-url-input");
+      if (openaiBaseUrlInput && settings.openai_base_url) {
+        openaiBaseUrlInput.value = settings.openai_base_url;
+      }
+      const openaiModelInput = document.getElementById("openai-model-input");
+      if (openaiModelInput && settings.openai_model) {
+        openaiModelInput.value = settings.openai_model;
+      }
+
       if (settings.gemini_model) {
         const geminiModelSelect = document.getElementById(
           "gemini-model-select",
@@ -1904,6 +1931,11 @@ async function updateAiProviderUI(provider) {
   const ollamaSettings = document.getElementById("ollama-settings");
   const apiKeySettings = document.getElementById("api-key-settings");
   const geminiSettings = document.getElementById("gemini-settings");
+  const openaiSettings = document.getElementById("openai-settings");
+
+  if (openaiSettings) {
+    openaiSettings.style.display = provider === "openai" ? "block" : "none";
+  }
 
   if (provider === "ollama") {
     if (ollamaSettings) ollamaSettings.style.display = "block";
@@ -2331,6 +2363,15 @@ if (saveSettingsBtn) {
         requestData.api_key = apiKey;
       }
 
+      const openaiBaseUrlEl = document.getElementById("openai-base-url-input");
+      if (openaiBaseUrlEl && openaiBaseUrlEl.value.trim()) {
+        requestData.openai_base_url = openaiBaseUrlEl.value.trim();
+      }
+      const openaiModelEl = document.getElementById("openai-model-input");
+      if (openaiModelEl) {
+        requestData.openai_model = openaiModelEl.value.trim();
+      }
+
       // Add Action Search settings
       requestData.action_search_parsing_model = parsingModel;
       requestData.action_search_analysis_model = analysisModel;
@@ -2590,6 +2631,14 @@ let sortSettings = {
 
 // Folder navigation history per page (for back button)
 let folderHistory = {};
+// Folder to show when the Other Files page next loads, set by folder search results
+let pendingBrowserFolder = null;
+
+// Switch to the Other Files page showing a folder from the search results
+function openFolderInBrowser(folderPath) {
+  pendingBrowserFolder = folderPath;
+  document.getElementById("nav-other-files")?.click();
+}
 
 // Load sort settings from localStorage
 function loadSortSettings() {
@@ -2849,6 +2898,12 @@ async function loadFolderPage(pageType) {
   if (folderPath && fileListId) {
     // Initialize folder history for back navigation
     folderHistory[fileListId] = [folderPath];
+    // A folder opened from search results starts one level below "This PC"
+    if (pageType === "other-files" && pendingBrowserFolder) {
+      folderPath = pendingBrowserFolder;
+      folderHistory[fileListId].push(folderPath);
+      pendingBrowserFolder = null;
+    }
     updateFolderBackButton(fileListId);
     // Check if there's a search query for this page
     const searchInput = document.getElementById(`file-search-${pageType}`);
@@ -3197,7 +3252,11 @@ function showFileContextMenu(event, options) {
           path: filePath,
         });
         if (response.success) {
-          showToast("Deleted", "success");
+          if (response.method === "permanent") {
+            showToast("Trash unavailable - item was permanently deleted", "error", 5000);
+          } else {
+            showToast("Moved to trash", "success");
+          }
           onRefresh?.();
         } else {
           showToast("Failed to delete: " + (response.error || "Unknown error"), "error");
@@ -3455,6 +3514,9 @@ if (browserDeleteBtn) {
         },
       );
       if (response.success) {
+        if (response.method === "permanent") {
+          showToast("Trash unavailable - item was permanently deleted", "error", 5000);
+        }
         selectedBrowserItem = null;
         await browseDirectory(currentBrowserPath);
       } else {
@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
+use tracing::{debug, error, warn};
 use crate::config::AiProvider;
-use crate::api::ai::{call_ollama_chat, call_greenpt_chat, call_gemini_chat, ChatMessage};
+use crate::api::ai::{call_ollama_chat, call_greenpt_chat, call_gemini_chat, call_openai_compatible_chat, ChatMessage, CompletionOptions};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DecomposedIntent {
@@ -24,6 +25,12 @@ pub struct ActiveRagResponse {
     pub action_performed: Option<String>,
     pub confidence: Option<f32>,
     pub error: Option<String>,
+    /// Top-ranked documents left out because they scored below the Active RAG relevance threshold
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sources_below_threshold: Option<usize>,
+    /// Set when the requested `document_limit` was capped at `rag_max_documents`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub document_limit_note: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -57,6 +64,69 @@ pub struct ActiveRagAgent {
     ollama_model: Option<String>,
     gemini_model: Option<String>,
     api_key: Option<String>,
+    max_chars_per_doc: usize,
+    excerpt_chars: usize,
+    ollama_base_url: String,
+    openai_base_url: String,
+    openai_model: Option<String>,
+    greenpt_model: String,
+    completion_options: CompletionOptions,
+    request_timeout_secs: u64,
+    include_sources: bool,
+}
+
+/// Truncate document content to `max_chars`, keeping the beginning and a tail
+/// so conclusions at the end of a document aren't dropped.
+pub fn truncate_head_tail(content: &str, max_chars: usize) -> String {
+    let total_chars = content.chars().count();
+    if total_chars <= max_chars {
+        return content.to_string();
+    }
+
+    // Keep ~75% from the head and ~25% from the tail
+    let tail_chars = max_chars / 4;
+    let head_chars = max_chars - tail_chars;
+    let head: String = content.chars().take(head_chars).collect();
+    let tail: String = content.chars().skip(total_chars - tail_chars).collect();
+    format!("{}\n...\n{}", head, tail)
+}
+
+/// About `max_chars` of `content` centred on the sentence or line sharing the most terms with
+/// `question`, so a source's quote shows the part that answered it rather than the opening.
+pub fn relevant_excerpt(content: &str, question: &str, max_chars: usize) -> String {
+    let terms: std::collections::HashSet<String> = question
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|term| term.chars().count() > 2)
+        .map(|term| term.to_lowercase())
+        .collect();
+
+    // (score, start char, length in chars) of the best segment; ties keep the earliest
+    let mut best = (0, 0, 0);
+    let mut position = 0;
+    for segment in content.split_inclusive(['\n', '.', '?', '!']) {
+        let length = segment.chars().count();
+        let lower = segment.to_lowercase();
+        let score = terms.iter().filter(|term| lower.contains(term.as_str())).count();
+        if score > best.0 {
+            best = (score, position, length);
+        }
+        position += length;
+    }
+
+    let total = position;
+    if total <= max_chars {
+        return content.trim().to_string();
+    }
+    let centre = best.1 + best.2 / 2;
+    let start = centre.saturating_sub(max_chars / 2).min(total - max_chars);
+    let end = start + max_chars;
+    let excerpt: String = content.chars().skip(start).take(max_chars).collect();
+    format!(
+        "{}{}{}",
+        if start > 0 { "..." } else { "" },
+        excerpt.trim(),
+        if end < total { "..." } else { "" }
+    )
 }
 
 impl ActiveRagAgent {
@@ -71,9 +141,58 @@ impl ActiveRagAgent {
             ollama_model,
             gemini_model,
             api_key,
+            max_chars_per_doc: crate::config::AppConfig::default().rag_max_chars_per_doc,
+            excerpt_chars: crate::config::AppConfig::default().rag_excerpt_chars,
+            ollama_base_url: crate::config::DEFAULT_OLLAMA_BASE_URL.to_string(),
+            openai_base_url: crate::config::AppConfig::default().openai_base_url,
+            openai_model: None,
+            greenpt_model: crate::config::AppConfig::default().greenpt_model,
+            completion_options: CompletionOptions::from_config(&crate::config::AppConfig::default()),
+            request_timeout_secs: crate::config::AppConfig::default().ai_request_timeout_secs,
+            include_sources: true,
         }
     }
 
+    pub fn with_request_timeout_secs(mut self, request_timeout_secs: u64) -> Self {
+        self.request_timeout_secs = request_timeout_secs;
+        self
+    }
+
+    /// Model and generation settings for GreenPT / OpenAI-compatible providers
+    pub fn with_completion_settings(mut self, config: &crate::config::AppConfig) -> Self {
+        self.greenpt_model = config.greenpt_model.clone();
+        self.completion_options = CompletionOptions::from_config(config);
+        self
+    }
+
+    pub fn with_openai_compatible(mut self, base_url: &str, model: Option<String>) -> Self {
+        self.openai_base_url = base_url.to_string();
+        self.openai_model = model;
+        self
+    }
+
+    pub fn with_ollama_base_url(mut self, ollama_base_url: &str) -> Self {
+        self.ollama_base_url = ollama_base_url.to_string();
+        self
+    }
+
+    pub fn with_max_chars_per_doc(mut self, max_chars_per_doc: usize) -> Self {
+        self.max_chars_per_doc = max_chars_per_doc;
+        self
+    }
+
+    pub fn with_excerpt_chars(mut self, excerpt_chars: usize) -> Self {
+        self.excerpt_chars = excerpt_chars;
+        self
+    }
+
+    /// Skip building `sources` (matching, excerpts, contributions) when only the answer is wanted
+    pub fn with_sources(mut self, include_sources: bool) -> Self {
+        self.include_sources = include_sources;
+        self
+    }
+
+    #[tracing::instrument(name = "Active RAG Agent", skip_all)]
     pub async fn analyze_documents(
         &self,
         documents: Vec<(String, String, f32)>,
@@ -89,13 +208,15 @@ impl ActiveRagAgent {
                 action_performed: None,
                 confidence: None,
                 error: Some("No documents to analyze".to_string()),
+                sources_below_threshold: None,
+                document_limit_note: None,
             });
         }
 
-        eprintln!("[Active RAG Agent] analyze_documents called with {} documents", documents.len());
-        eprintln!("[Active RAG Agent] User question: '{}'", user_question);
-        eprintln!("[Active RAG Agent] Original query: '{}'", original_query);
-        eprintln!("[Active RAG Agent] Analysis model setting: '{}'", analysis_model);
+        debug!("analyze_documents called with {} documents", documents.len());
+        debug!("User question: '{}'", user_question);
+        debug!("Original query: '{}'", original_query);
+        debug!("Analysis model setting: '{}'", analysis_model);
         
         // Log document details
         for (i, (path, content, score)) in documents.iter().enumerate() {
@@ -103,22 +224,22 @@ impl ActiveRagAgent {
                 .file_name()
                 .and_then(|n| n.to_str())
                 .unwrap_or("unknown");
-            eprintln!("[Active RAG Agent]   Document {}: {} (score: {:.4}, content: {} chars)", 
+            debug!("  Document {}: {} (score: {:.4}, content: {} chars)", 
                 i + 1, file_name, score, content.len());
         }
         
         // Create system prompt for document analysis
         let system_prompt = self.create_analysis_prompt(&documents, user_question, original_query);
         
-        eprintln!("[Active RAG Agent] === AI PROMPT CREATED ===");
-        eprintln!("[Active RAG Agent] System prompt length: {} chars", system_prompt.len());
+        debug!("=== AI PROMPT CREATED ===");
+        debug!("System prompt length: {} chars", system_prompt.len());
         let prompt_preview = if system_prompt.len() > 500 {
             &system_prompt[..500]
         } else {
             &system_prompt
         };
-        eprintln!("[Active RAG Agent] System prompt preview:\n{}...", prompt_preview);
-        eprintln!("[Active RAG Agent] ==============================");
+        debug!("System prompt preview:\n{}...", prompt_preview);
+        debug!("==============================");
 
         // Build conversation messages
         let messages = vec![
@@ -133,99 +254,119 @@ impl ActiveRagAgent {
         ];
 
         // Select AI provider based on analysis model setting
-        eprintln!("[Active RAG Agent] Calling AI API with {} messages", messages.len());
+        debug!("Calling AI API with {} messages", messages.len());
         let ai_response = match analysis_model {
             "same-as-main" => {
-                eprintln!("[Active RAG Agent] Using 'same-as-main' provider: {:?}", self.ai_provider);
+                debug!("Using 'same-as-main' provider: {:?}", self.ai_provider);
                 // Use the same AI provider as configured for main
                 match self.ai_provider {
                     AiProvider::Ollama => {
                         let model = self.ollama_model.as_deref().unwrap_or("llama3.2:1b");
-                        eprintln!("[Active RAG Agent] Calling Ollama with model: {} (timeout: 60s)", model);
+                        debug!("Calling Ollama with model: {} (timeout: {}s)", model, self.request_timeout_secs);
                         match tokio::time::timeout(
-                            std::time::Duration::from_secs(60),
-                            call_ollama_chat(model, &messages)
+                            std::time::Duration::from_secs(self.request_timeout_secs),
+                            call_ollama_chat(&self.ollama_base_url, model, &messages, self.request_timeout_secs)
                         ).await {
                             Ok(Ok(response)) => {
-                                eprintln!("[Active RAG Agent] ✓ Ollama response received");
+                                debug!("✓ Ollama response received");
                                 response
                             }
                             Ok(Err(e)) => {
-                                eprintln!("[Active RAG Agent] ✗ Ollama API error: {}", e);
+                                error!("✗ Ollama API error: {}", e);
                                 return Err(format!("Ollama API error: {}", e).into());
                             }
                             Err(_) => {
-                                eprintln!("[Active RAG Agent] ✗ Ollama API call timed out after 60 seconds");
-                                return Err("Ollama API call timed out after 60 seconds".into());
+                                error!("✗ Ollama API call timed out after {} seconds", self.request_timeout_secs);
+                                return Err(format!("Ollama API call timed out after {} seconds", self.request_timeout_secs).into());
                             }
                         }
                     }
                     AiProvider::GreenPT => {
                         let api_key = self.api_key.as_ref().ok_or("GreenPT API key not configured")?;
-                        eprintln!("[Active RAG Agent] Calling GreenPT (timeout: 60s)");
+                        debug!("Calling GreenPT (timeout: {}s)", self.request_timeout_secs);
                         match tokio::time::timeout(
-                            std::time::Duration::from_secs(60),
-                            call_greenpt_chat(api_key, &messages)
+                            std::time::Duration::from_secs(self.request_timeout_secs),
+                            call_greenpt_chat(api_key, &self.greenpt_model, &messages, self.completion_options, self.request_timeout_secs)
                         ).await {
                             Ok(Ok(response)) => {
-                                eprintln!("[Active RAG Agent] ✓ GreenPT response received");
+                                debug!("✓ GreenPT response received");
                                 response
                             }
                             Ok(Err(e)) => {
-                                eprintln!("[Active RAG Agent] ✗ GreenPT API error: {}", e);
+                                error!("✗ GreenPT API error: {}", e);
                                 return Err(format!("GreenPT API error: {}", e).into());
                             }
                             Err(_) => {
-                                eprintln!("[Active RAG Agent] ✗ GreenPT API call timed out after 60 seconds");
-                                return Err("GreenPT API call timed out after 60 seconds".into());
+                                error!("✗ GreenPT API call timed out after {} seconds", self.request_timeout_secs);
+                                return Err(format!("GreenPT API call timed out after {} seconds", self.request_timeout_secs).into());
                             }
                         }
                     }
                     AiProvider::Gemini => {
                         let api_key = self.api_key.as_ref().ok_or("Gemini API key not configured")?;
                         let model = self.gemini_model.as_deref().unwrap_or("gemini-pro");
-                        eprintln!("[Active RAG Agent] Calling Gemini with model: {} (timeout: 60s)", model);
+                        debug!("Calling Gemini with model: {} (timeout: {}s)", model, self.request_timeout_secs);
                         match tokio::time::timeout(
-                            std::time::Duration::from_secs(60),
-                            call_gemini_chat(api_key, model, &messages)
+                            std::time::Duration::from_secs(self.request_timeout_secs),
+                            call_gemini_chat(api_key, model, &messages, self.request_timeout_secs)
                         ).await {
                             Ok(Ok(response)) => {
-                                eprintln!("[Active RAG Agent] ✓ Gemini response received");
+                                debug!("✓ Gemini response received");
                                 response
                             }
                             Ok(Err(e)) => {
-                                eprintln!("[Active RAG Agent] ✗ Gemini API error: {}", e);
+                                error!("✗ Gemini API error: {}", e);
                                 return Err(format!("Gemini API error: {}", e).into());
                             }
                             Err(_) => {
-                                eprintln!("[Active RAG Agent] ✗ Gemini API call timed out after 60 seconds");
-                                return Err("Gemini API call timed out after 60 seconds".into());
+                                error!("✗ Gemini API call timed out after {} seconds", self.request_timeout_secs);
+                                return Err(format!("Gemini API call timed out after {} seconds", self.request_timeout_secs).into());
+                            }
+                        }
+                    }
+                    AiProvider::OpenAI => {
+                        let model = self.openai_model.as_deref().ok_or("OpenAI-compatible model not configured")?;
+                        debug!("Calling OpenAI-compatible API with model: {} (timeout: {}s)", model, self.request_timeout_secs);
+                        match tokio::time::timeout(
+                            std::time::Duration::from_secs(self.request_timeout_secs),
+                            call_openai_compatible_chat(&self.openai_base_url, self.api_key.as_deref(), model, &messages, self.completion_options, self.request_timeout_secs)
+                        ).await {
+                            Ok(Ok(response)) => {
+                                debug!("✓ OpenAI-compatible response received");
+                                response
+                            }
+                            Ok(Err(e)) => {
+                                error!("✗ OpenAI-compatible API error: {}", e);
+                                return Err(format!("OpenAI-compatible API error: {}", e).into());
+                            }
+                            Err(_) => {
+                                error!("✗ OpenAI-compatible API call timed out after {} seconds", self.request_timeout_secs);
+                                return Err(format!("OpenAI-compatible API call timed out after {} seconds", self.request_timeout_secs).into());
                             }
                         }
                     }
-                    AiProvider::OpenAI => return Err("OpenAI provider not yet implemented for Active RAG".into()),
                 }
             }
             "ollama" => {
                 // Force use Ollama for analysis
                 // Use configured model if present; default to a fast local model
                 let model = self.ollama_model.as_deref().unwrap_or("llama3.2:1b");
-                eprintln!("[Active RAG Agent] Forcing Ollama with model: {} (timeout: 60s)", model);
+                debug!("Forcing Ollama with model: {} (timeout: {}s)", model, self.request_timeout_secs);
                 match tokio::time::timeout(
-                    std::time::Duration::from_secs(60),
-                    call_ollama_chat(model, &messages)
+                    std::time::Duration::from_secs(self.request_timeout_secs),
+                    call_ollama_chat(&self.ollama_base_url, model, &messages, self.request_timeout_secs)
                 ).await {
                     Ok(Ok(response)) => {
-                        eprintln!("[Active RAG Agent] ✓ Ollama response received");
+                        debug!("✓ Ollama response received");
                         response
                     }
                     Ok(Err(e)) => {
-                        eprintln!("[Active RAG Agent] ✗ Ollama API error: {}", e);
+                        error!("✗ Ollama API error: {}", e);
                         return Err(format!("Ollama API error: {}", e).into());
                     }
                     Err(_) => {
-                        eprintln!("[Active RAG Agent] ✗ Ollama API call timed out after 60 seconds");
-                        return Err("Ollama API call timed out after 60 seconds".into());
+                        error!("✗ Ollama API call timed out after {} seconds", self.request_timeout_secs);
+                        return Err(format!("Ollama API call timed out after {} seconds", self.request_timeout_secs).into());
                     }
                 }
             }
@@ -233,37 +374,37 @@ impl ActiveRagAgent {
                 // Force use Gemini for analysis
                 let api_key = self.api_key.as_ref().ok_or("Gemini API key not configured")?;
                 let model = self.gemini_model.as_deref().unwrap_or("gemini-pro");
-                eprintln!("[Active RAG Agent] Forcing Gemini with model: {} (timeout: 60s)", model);
+                debug!("Forcing Gemini with model: {} (timeout: {}s)", model, self.request_timeout_secs);
                 match tokio::time::timeout(
-                    std::time::Duration::from_secs(60),
-                    call_gemini_chat(api_key, model, &messages)
+                    std::time::Duration::from_secs(self.request_timeout_secs),
+                    call_gemini_chat(api_key, model, &messages, self.request_timeout_secs)
                 ).await {
                     Ok(Ok(response)) => {
-                        eprintln!("[Active RAG Agent] ✓ Gemini response received");
+                        debug!("✓ Gemini response received");
                         response
                     }
                     Ok(Err(e)) => {
-                        eprintln!("[Active RAG Agent] ✗ Gemini API error: {}", e);
+                        error!("✗ Gemini API error: {}", e);
                         return Err(format!("Gemini API error: {}", e).into());
                     }
                     Err(_) => {
-                        eprintln!("[Active RAG Agent] ✗ Gemini API call timed out after 60 seconds");
-                        return Err("Gemini API call timed out after 60 seconds".into());
+                        error!("✗ Gemini API call timed out after {} seconds", self.request_timeout_secs);
+                        return Err(format!("Gemini API call timed out after {} seconds", self.request_timeout_secs).into());
                     }
                 }
             }
             _ => {
-                eprintln!("[Active RAG Agent] ERROR: Unsupported analysis model: {}", analysis_model);
+                error!("Unsupported analysis model: {}", analysis_model);
                 return Err(format!("Unsupported analysis model: {}", analysis_model).into());
             }
         };
 
-        eprintln!("[Active RAG Agent] ✓ AI API call completed");
-        eprintln!("[Active RAG Agent] Raw response length: {} chars", ai_response.len());
+        debug!("✓ AI API call completed");
+        debug!("Raw response length: {} chars", ai_response.len());
         
         // Validate response is not empty
         if ai_response.trim().is_empty() {
-            eprintln!("[Active RAG Agent] ✗ ERROR: AI returned empty response!");
+            error!("✗ AI returned empty response!");
             return Err("AI returned empty response".into());
         }
         
@@ -272,26 +413,27 @@ impl ActiveRagAgent {
         } else {
             &ai_response
         };
-        eprintln!("[Active RAG Agent] Raw response preview:\n{}...", response_preview);
+        debug!("Raw response preview:\n{}...", response_preview);
 
         // Parse AI response and create structured response
-        eprintln!("[Active RAG Agent] Parsing AI response...");
+        debug!("Parsing AI response...");
         let parsed_response = self.parse_ai_response(ai_response, documents, user_question).await;
         
         match &parsed_response {
             Ok(resp) => {
-                eprintln!("[Active RAG Agent] ✓ Response parsed successfully");
-                eprintln!("[Active RAG Agent] Parsed response - success: {}, answer present: {}, sources: {}", 
+                debug!("✓ Response parsed successfully");
+                debug!("Parsed response - success: {}, answer present: {}, sources: {}", 
                     resp.success, resp.answer.is_some(), resp.sources.len());
             }
             Err(e) => {
-                eprintln!("[Active RAG Agent] ✗ Response parsing failed: {}", e);
+                error!("✗ Response parsing failed: {}", e);
             }
         }
         
         parsed_response
     }
 
+    #[tracing::instrument(name = "Active RAG Agent", skip_all)]
     pub async fn decompose_intent(
         &self,
         user_prompt: &str,
@@ -335,12 +477,12 @@ impl ActiveRagAgent {
             "ollama" => {
                 // Use configured model if present; default to a fast local model for parsing
                 let model = self.ollama_model.as_deref().unwrap_or("llama3.2:1b");
-                call_ollama_chat(model, &messages).await?
+                call_ollama_chat(&self.ollama_base_url, model, &messages, self.request_timeout_secs).await?
             }
             "gemini" => {
                 let api_key = self.api_key.as_ref().ok_or("Gemini API key not configured")?;
                 let model = self.gemini_model.as_deref().unwrap_or("gemini-pro");
-                call_gemini_chat(api_key, model, &messages).await?
+                call_gemini_chat(api_key, model, &messages, self.request_timeout_secs).await?
             }
             _ => {
                 return Err(format!("Unsupported parsing model: {}", parsing_model).into());
@@ -348,14 +490,14 @@ impl ActiveRagAgent {
         };
 
         // Extract JSON from response (handling potential markdown formatting)
-        eprintln!("[Active RAG Agent] decompose_intent: Raw response length: {} chars", ai_response.len());
+        debug!("decompose_intent: Raw response length: {} chars", ai_response.len());
         
         // Try to extract JSON (similar to parse_ai_response)
         let json_str = if let Some(json_start) = ai_response.find("```json") {
             let after_start = &ai_response[json_start + 7..];
             if let Some(end_marker) = after_start.find("```") {
                 let json_content = after_start[..end_marker].trim();
-                eprintln!("[Active RAG Agent] Found JSON in markdown code block");
+                debug!("Found JSON in markdown code block");
                 json_content
             } else if let Some(start) = after_start.find('{') {
                 if let Some(end) = after_start.rfind('}') {
@@ -370,24 +512,24 @@ impl ActiveRagAgent {
             if let Some(end) = ai_response.rfind('}') {
                 &ai_response[start..=end]
             } else {
-                eprintln!("[Active RAG Agent] Found '{{' but no matching '}}'");
+                debug!("Found '{{' but no matching '}}'");
                 &ai_response
             }
         } else {
-            eprintln!("[Active RAG Agent] No '{{' found in decomposition response");
+            debug!("No '{{' found in decomposition response");
             &ai_response
         };
         
-        eprintln!("[Active RAG Agent] Extracted JSON for decomposition: {} chars", json_str.len());
+        debug!("Extracted JSON for decomposition: {} chars", json_str.len());
         
-        match serde_json::from_str::<DecomposedIntent>(json_str) {
+        match crate::llm_json::parse_lenient::<DecomposedIntent>(json_str, "intent decomposition") {
             Ok(decomposed) => {
-                eprintln!("[Active RAG] Intent Decomposed: {:?}", decomposed);
+                debug!("[Active RAG] Intent Decomposed: {:?}", decomposed);
                 Ok(decomposed)
             }
             Err(e) => {
-                eprintln!("[Active RAG Agent] JSON parse error: {}", e);
-                eprintln!("[Active RAG Agent] Attempted to parse: {}", json_str);
+                error!("JSON parse error: {}", e);
+                debug!("Attempted to parse: {}", json_str);
                 Err(format!("Failed to parse decomposition JSON: {}", e).into())
             }
         }
@@ -409,7 +551,7 @@ impl ActiveRagAgent {
                 .and_then(|n| n.to_str())
                 .unwrap_or("unknown");
 
-            let truncated_content: String = content.chars().take(2000).collect();
+            let truncated_content = truncate_head_tail(content, self.max_chars_per_doc);
             prompt.push_str(&format!(
                 "Document {} ({}): Relevance Score: {:.3}\n{}\n\n",
                 i + 1,
@@ -464,13 +606,14 @@ impl ActiveRagAgent {
         prompt
     }
 
+    #[tracing::instrument(name = "Active RAG Agent", skip_all)]
     async fn parse_ai_response(
         &self,
         ai_response: String,
         documents: Vec<(String, String, f32)>,
         user_question: &str,
     ) -> Result<ActiveRagResponse, Box<dyn std::error::Error>> {
-        eprintln!("[Active RAG Agent] parse_ai_response: Attempting to parse response...");
+        debug!("parse_ai_response: Attempting to parse response...");
         
         // Try to extract JSON from response (handling markdown code blocks)
         // First, try to find JSON in markdown code blocks (```json ... ```)
@@ -479,21 +622,21 @@ impl ActiveRagAgent {
             let after_start = &ai_response[json_start + 7..]; // Skip "```json"
             if let Some(end_marker) = after_start.find("```") {
                 let json_content = after_start[..end_marker].trim();
-                eprintln!("[Active RAG Agent] Found JSON in markdown code block ({} chars)", json_content.len());
+                debug!("Found JSON in markdown code block ({} chars)", json_content.len());
                 json_content
             } else {
                 // No closing ```, try to find JSON object
                 if let Some(start) = after_start.find('{') {
                     if let Some(end) = after_start.rfind('}') {
                         let extracted = &after_start[start..=end];
-                        eprintln!("[Active RAG Agent] Extracted JSON from code block ({} chars)", extracted.len());
+                        debug!("Extracted JSON from code block ({} chars)", extracted.len());
                         extracted
                     } else {
-                        eprintln!("[Active RAG Agent] Found '{{' but no matching '}}' in code block");
+                        debug!("Found '{{' but no matching '}}' in code block");
                         &ai_response
                     }
                 } else {
-                    eprintln!("[Active RAG Agent] No '{{' found in code block");
+                    debug!("No '{{' found in code block");
                     &ai_response
                 }
             }
@@ -503,21 +646,21 @@ impl ActiveRagAgent {
             if let Some(end_marker) = after_start.find("```") {
                 let code_content = after_start[..end_marker].trim();
                 if code_content.starts_with('{') {
-                    eprintln!("[Active RAG Agent] Found JSON in code block ({} chars)", code_content.len());
+                    debug!("Found JSON in code block ({} chars)", code_content.len());
                     code_content
                 } else {
                     // Not JSON, fall through to regular extraction
                     if let Some(start) = ai_response.find('{') {
                         if let Some(end) = ai_response.rfind('}') {
                             let extracted = &ai_response[start..=end];
-                            eprintln!("[Active RAG Agent] Extracted JSON substring ({} chars)", extracted.len());
+                            debug!("Extracted JSON substring ({} chars)", extracted.len());
                             extracted
                         } else {
-                            eprintln!("[Active RAG Agent] Found '{{' but no matching '}}'");
+                            debug!("Found '{{' but no matching '}}'");
                             &ai_response
                         }
                     } else {
-                        eprintln!("[Active RAG Agent] No '{{' found in response");
+                        debug!("No '{{' found in response");
                         &ai_response
                     }
                 }
@@ -526,14 +669,14 @@ impl ActiveRagAgent {
                 if let Some(start) = ai_response.find('{') {
                     if let Some(end) = ai_response.rfind('}') {
                         let extracted = &ai_response[start..=end];
-                        eprintln!("[Active RAG Agent] Extracted JSON substring ({} chars)", extracted.len());
+                        debug!("Extracted JSON substring ({} chars)", extracted.len());
                         extracted
                     } else {
-                        eprintln!("[Active RAG Agent] Found '{{' but no matching '}}'");
+                        debug!("Found '{{' but no matching '}}'");
                         &ai_response
                     }
                 } else {
-                    eprintln!("[Active RAG Agent] No '{{' found in response");
+                    debug!("No '{{' found in response");
                     &ai_response
                 }
             }
@@ -541,73 +684,76 @@ impl ActiveRagAgent {
             // Regular JSON extraction
             if let Some(end) = ai_response.rfind('}') {
                 let extracted = &ai_response[start..=end];
-                eprintln!("[Active RAG Agent] Extracted JSON substring ({} chars)", extracted.len());
+                debug!("Extracted JSON substring ({} chars)", extracted.len());
                 extracted
             } else {
-                eprintln!("[Active RAG Agent] Found '{{' but no matching '}}', using full response");
+                debug!("Found '{{' but no matching '}}', using full response");
                 &ai_response
             }
         } else {
-            eprintln!("[Active RAG Agent] No '{{' found in response, treating as plain text");
+            debug!("No '{{' found in response, treating as plain text");
             &ai_response
         };
         
         // Try to parse as JSON first
-        eprintln!("[Active RAG Agent] Attempting JSON parse...");
-        match serde_json::from_str::<serde_json::Value>(json_str) {
+        debug!("Attempting JSON parse...");
+        match crate::llm_json::parse_lenient::<serde_json::Value>(json_str, "Active RAG analysis") {
             Ok(parsed) => {
-                eprintln!("[Active RAG Agent] ✓ JSON parse successful");
-                eprintln!("[Active RAG Agent] Parsed JSON keys: {:?}", parsed.as_object().map(|o| o.keys().collect::<Vec<_>>()));
-                return self.create_structured_response(parsed, documents);
+                debug!("✓ JSON parse successful");
+                debug!("Parsed JSON keys: {:?}", parsed.as_object().map(|o| o.keys().collect::<Vec<_>>()));
+                return self.create_structured_response(parsed, documents, user_question);
             }
             Err(e) => {
-                eprintln!("[Active RAG Agent] ✗ JSON parse failed: {}", e);
-                eprintln!("[Active RAG Agent] Falling back to plain text response");
+                error!("✗ JSON parse failed: {}", e);
+                debug!("Falling back to plain text response");
             }
         }
 
         // Fallback: create response from plain text
-        eprintln!("[Active RAG Agent] Creating fallback response from plain text");
+        debug!("Creating fallback response from plain text");
         self.create_fallback_response(&ai_response, documents, user_question).await
     }
 
+    #[tracing::instrument(name = "Active RAG Agent", skip_all)]
     fn create_structured_response(
         &self,
         parsed: serde_json::Value,
         documents: Vec<(String, String, f32)>,
+        user_question: &str,
     ) -> Result<ActiveRagResponse, Box<dyn std::error::Error>> {
-        eprintln!("[Active RAG Agent] create_structured_response: Extracting fields from JSON...");
+        debug!("create_structured_response: Extracting fields from JSON...");
         
         let answer = parsed.get("answer")
             .and_then(|v| v.as_str())
             .map(|s| s.to_string());
         
-        eprintln!("[Active RAG Agent]   answer field: {}", 
+        debug!("  answer field: {}", 
             if answer.is_some() { "present" } else { "missing" });
         if let Some(ref ans) = answer {
             let preview = if ans.len() > 100 { &ans[..100] } else { ans };
-            eprintln!("[Active RAG Agent]   answer preview: '{}...'", preview);
+            debug!("  answer preview: '{}...'", preview);
         }
 
         let confidence = parsed.get("confidence")
             .and_then(|v| v.as_f64())
             .map(|f| f as f32);
         
-        eprintln!("[Active RAG Agent]   confidence field: {:?}", confidence);
+        debug!("  confidence field: {:?}", confidence);
 
         let sources = parsed.get("sources")
+            .filter(|_| self.include_sources)
             .and_then(|v| v.as_array())
             .map(|arr| {
-                eprintln!("[Active RAG Agent]   sources array found with {} items", arr.len());
+                debug!("  sources array found with {} items", arr.len());
                 arr.iter().enumerate().filter_map(|(idx, source)| {
-                    eprintln!("[Active RAG Agent]     Processing source {}...", idx + 1);
+                    debug!("    Processing source {}...", idx + 1);
                     let file_path = source.get("file_path")?.as_str()?.to_string();
                     let used_in_answer = source.get("used_in_answer")?.as_bool().unwrap_or(false);
                     let relevance_score = source.get("relevance_score")?.as_f64().unwrap_or(0.0) as f32;
                     
-                    eprintln!("[Active RAG Agent]       file_path: {}", file_path);
-                    eprintln!("[Active RAG Agent]       used_in_answer: {}", used_in_answer);
-                    eprintln!("[Active RAG Agent]       relevance_score: {:.4}", relevance_score);
+                    debug!("      file_path: {}", file_path);
+                    debug!("      used_in_answer: {}", used_in_answer);
+                    debug!("      relevance_score: {:.4}", relevance_score);
                     
                     let key_contributions = source.get("key_contributions")
                         .and_then(|v| v.as_array())
@@ -644,15 +790,9 @@ impl ActiveRagAgent {
                         })
                         .unwrap_or_else(|| (file_path.clone(), file_path.clone()));
 
-                    // Create excerpt from document content
+                    // Quote the part of the document that best matches the question
                     let excerpt = doc_info
-                        .and_then(|(_, content, _)| {
-                            if content.len() > 200 {
-                                Some(content[..200].to_string() + "...")
-                            } else {
-                                Some(content.clone())
-                            }
-                        });
+                        .map(|(_, content, _)| relevant_excerpt(content, user_question, self.excerpt_chars));
 
                     Some(ActiveRagSource {
                         file_path: actual_path,
@@ -667,15 +807,17 @@ impl ActiveRagAgent {
                 .collect::<Vec<_>>()
             })
             .unwrap_or_else(|| {
-                eprintln!("[Active RAG Agent]   WARNING: No 'sources' array found in JSON");
+                if self.include_sources {
+                    warn!("No 'sources' array found in JSON");
+                }
                 vec![]
             });
         
-        eprintln!("[Active RAG Agent]   Final sources count: {}", sources.len());
+        debug!("  Final sources count: {}", sources.len());
         
         // If answer is missing but we have documents, use the first document's content as fallback
         let final_answer = if answer.is_none() && !documents.is_empty() {
-            eprintln!("[Active RAG Agent]   WARNING: No answer in JSON, using first document as fallback");
+            warn!("No answer in JSON, using first document as fallback");
             let (_, content, _) = &documents[0];
             Some(format!("Based on the document '{}': {}", 
                 std::path::Path::new(&documents[0].0)
@@ -699,23 +841,26 @@ impl ActiveRagAgent {
             action_performed: Some("Document analysis completed".to_string()),
             confidence,
             error: None,
+            sources_below_threshold: None,
+            document_limit_note: None,
         };
         
-        eprintln!("[Active RAG Agent] ✓ Structured response created - success: {}, answer present: {}", 
+        debug!("✓ Structured response created - success: {}, answer present: {}", 
             response.success, response.answer.is_some());
         
         Ok(response)
     }
 
+    #[tracing::instrument(name = "Active RAG Agent", skip_all)]
     async fn create_fallback_response(
         &self,
         ai_response: &str,
         documents: Vec<(String, String, f32)>,
         user_question: &str,
     ) -> Result<ActiveRagResponse, Box<dyn std::error::Error>> {
-        eprintln!("[Active RAG Agent] create_fallback_response: Creating response from plain text");
-        eprintln!("[Active RAG Agent]   AI response length: {} chars", ai_response.len());
-        eprintln!("[Active RAG Agent]   Documents count: {}", documents.len());
+        debug!("create_fallback_response: Creating response from plain text");
+        debug!("  AI response length: {} chars", ai_response.len());
+        debug!("  Documents count: {}", documents.len());
         
         // Try to determine which documents are actually used based on:
         // 1. Filename mentions in the answer
@@ -731,7 +876,7 @@ impl ActiveRagAgent {
             .collect();
         
         // Create sources from available documents
-        let sources = documents.iter().enumerate().map(|(i, (path, content, score))| {
+        let sources = documents.iter().enumerate().filter(|_| self.include_sources).map(|(i, (path, content, score))| {
             let file_name = std::path::Path::new(path)
                 .file_name()
                 .and_then(|n| n.to_str())
@@ -761,7 +906,7 @@ impl ActiveRagAgent {
                 || (matches_question && has_substantial_content)
                 || (i == 0 && has_substantial_content && score > &0.4);
 
-            eprintln!("[Active RAG Agent]     Source {}: {} (score: {:.4}, used: {}, mentioned: {}, matches: {}, substantial: {})", 
+            debug!("    Source {}: {} (score: {:.4}, used: {}, mentioned: {}, matches: {}, substantial: {})", 
                 i + 1, file_name, score, used_in_answer, mentioned_in_answer, matches_question, has_substantial_content);
 
             ActiveRagSource {
@@ -782,11 +927,32 @@ impl ActiveRagAgent {
             action_performed: Some("Document analysis completed".to_string()),
             confidence: Some(0.7), // Default confidence for fallback
             error: None,
+            sources_below_threshold: None,
+            document_limit_note: None,
         };
         
-        eprintln!("[Active RAG Agent] ✓ Fallback response created - answer present: {}", 
+        debug!("✓ Fallback response created - answer present: {}", 
             response.answer.is_some());
         
         Ok(response)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relevant_excerpt_centres_on_matching_sentence() {
+        let filler = "Nothing of interest happens in this sentence. ".repeat(20);
+        let content = format!("{}The invoice total was 420 euros, due in March. {}", filler, filler);
+
+        let excerpt = relevant_excerpt(&content, "What was the invoice total?", 120);
+        assert!(excerpt.contains("invoice total was 420 euros"));
+        assert!(excerpt.starts_with("...") && excerpt.ends_with("..."));
+
+        // Short documents are returned whole; no match falls back to the opening
+        assert_eq!(relevant_excerpt("  Short note.  ", "anything", 300), "Short note.");
+        assert!(relevant_excerpt(&content, "zebra", 50).starts_with("Nothing"));
+    }
+}
@@ -1,39 +1,170 @@
 use axum::{
-    extract::State,
+    extract::{Query, State},
+    http::StatusCode,
     response::Json,
 };
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{debug, error, info, warn};
 use crate::AppState;
-use crate::active_rag_agent::{ActiveRagAgent, ActiveRagResponse, DecomposedIntent};
-use crate::api::search::{deduplicate_by_embedding, score_search_results, SearchRequest, SearchResult};
+use crate::api::ai::{apply_ai_override, AiRequestError};
+use crate::active_rag_agent::{truncate_head_tail, ActiveRagAgent, ActiveRagResponse};
+use crate::api::search::{deduplicate_by_embedding, score_search_results, SearchGranularity, SearchMode, SearchRequest, SearchResult};
 use crate::parsers::ParserRegistry;
-use crate::config::FileTypeFilters;
+use crate::config::{AiProvider, FileTypeFilters};
 
 #[derive(Deserialize)]
 pub struct ActiveRagApiRequest {
     pub query: String,
     pub user_question: String,
     pub document_limit: Option<usize>,
+    /// Minimum search score (0.0-1.0) for a document to be analyzed; defaults to `rag_min_similarity`
+    #[serde(default)]
+    pub min_similarity: Option<f32>,
+    /// Use this provider instead of the configured one for this run only
+    #[serde(default)]
+    pub provider: Option<AiProvider>,
+    /// Use this model instead of the provider's configured one for this run only
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Return per-document sources and excerpts; false answers with just the answer and confidence
+    #[serde(default = "default_include_sources")]
+    pub include_sources: bool,
 }
 
+fn default_include_sources() -> bool {
+    true
+}
+
+/// Pipeline stage of a running Active RAG request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActiveRagPhase {
+    Decomposing,
+    Retrieving,
+    Extracting,
+    Analyzing,
+}
+
+pub struct ActiveRagRun {
+    phase: ActiveRagPhase,
+    /// Documents being read/analyzed, once retrieval has picked them
+    documents: Option<usize>,
+    started: std::time::Instant,
+    cancel: Arc<tokio::sync::Notify>,
+}
+
+/// Registers a run in `AppState::active_rag_runs` and removes it when the request ends,
+/// including when the client disconnects and the handler is dropped
+struct ActiveRagRunGuard {
+    runs: Arc<std::sync::Mutex<std::collections::HashMap<String, ActiveRagRun>>>,
+    request_id: String,
+}
+
+impl ActiveRagRunGuard {
+    fn start(state: &AppState, request_id: &str) -> (Self, Arc<tokio::sync::Notify>) {
+        let cancel = Arc::new(tokio::sync::Notify::new());
+        state.active_rag_runs.lock().unwrap().insert(request_id.to_string(), ActiveRagRun {
+            phase: ActiveRagPhase::Decomposing,
+            documents: None,
+            started: std::time::Instant::now(),
+            cancel: cancel.clone(),
+        });
+        let guard = Self {
+            runs: state.active_rag_runs.clone(),
+            request_id: request_id.to_string(),
+        };
+        (guard, cancel)
+    }
+
+    fn set_phase(&self, phase: ActiveRagPhase, documents: Option<usize>) {
+        if let Some(run) = self.runs.lock().unwrap().get_mut(&self.request_id) {
+            run.phase = phase;
+            run.documents = documents.or(run.documents);
+        }
+    }
+}
+
+impl Drop for ActiveRagRunGuard {
+    fn drop(&mut self) {
+        if let Ok(mut runs) = self.runs.lock() {
+            runs.remove(&self.request_id);
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ActiveRagRunRequest {
+    /// `"{query}_{user_question}"`, both trimmed, as sent to `/api/search/active-rag`, followed by
+    /// `"_{provider}_{model}"` (lowercase provider, empty model if unset) when either was overridden
+    pub request_id: String,
+}
+
+#[derive(Serialize)]
+pub struct ActiveRagStatusResponse {
+    request_id: String,
+    phase: ActiveRagPhase,
+    documents: Option<usize>,
+    elapsed_secs: f64,
+}
+
+/// Current phase of a running Active RAG request; 404 once it has finished (or never started)
+pub async fn active_rag_status(
+    State(state): State<AppState>,
+    Query(request): Query<ActiveRagRunRequest>,
+) -> Result<Json<ActiveRagStatusResponse>, StatusCode> {
+    let runs = state.active_rag_runs.lock().unwrap();
+    let run = runs.get(&request.request_id).ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(ActiveRagStatusResponse {
+        request_id: request.request_id.clone(),
+        phase: run.phase,
+        documents: run.documents,
+        elapsed_secs: run.started.elapsed().as_secs_f64(),
+    }))
+}
+
+/// Stop a running Active RAG request; its pending LLM calls are dropped and it answers with an error
+pub async fn cancel_active_rag(
+    State(state): State<AppState>,
+    Json(request): Json<ActiveRagRunRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let runs = state.active_rag_runs.lock().unwrap();
+    let run = runs.get(&request.request_id).ok_or(StatusCode::NOT_FOUND)?;
+    run.cancel.notify_one();
+    info!("Cancelling Active RAG request: {}", request.request_id);
+    Ok(Json(serde_json::json!({ "success": true })))
+}
+
+#[tracing::instrument(name = "Active RAG", skip_all)]
 pub async fn active_rag_search(
     State(state): State<AppState>,
     Json(request): Json<ActiveRagApiRequest>,
-) -> Result<Json<ActiveRagResponse>, axum::http::StatusCode> {
-    // Create a unique request ID to detect duplicates
-    let request_id = format!("{}_{}", request.query.trim(), request.user_question.trim());
-    eprintln!("=== Active RAG Search Request ===");
-    eprintln!("[Active RAG] Request ID: {}", request_id);
-    eprintln!("[Active RAG] Query: '{}'", request.query);
-    eprintln!("[Active RAG] User Question: '{}'", request.user_question);
-    eprintln!("[Active RAG] Document Limit: {:?}", request.document_limit);
+) -> Result<Json<ActiveRagResponse>, AiRequestError> {
+    // Create a unique request ID to detect duplicate in-flight requests. Every option that changes
+    // the response is part of it, joined with NUL so no two different requests spell the same ID.
+    let provider = request.provider.as_ref().map(|p| format!("{:?}", p).to_lowercase()).unwrap_or_default();
+    let request_id = [
+        request.query.trim().to_string(),
+        request.user_question.trim().to_string(),
+        provider,
+        request.model.as_deref().unwrap_or("").trim().to_string(),
+        request.include_sources.to_string(),
+        format!("{:?}", request.document_limit),
+        format!("{:?}", request.min_similarity),
+    ]
+    .join("\u{0}");
+    debug!("=== Active RAG Search Request ===");
+    debug!("Request ID: {:?}", request_id);
+    debug!("Query: '{}'", request.query);
+    debug!("User Question: '{}'", request.user_question);
+    debug!("Document Limit: {:?}", request.document_limit);
     
     // Validate inputs
     let query = request.query.trim();
     let user_question = request.user_question.trim();
     
     if query.is_empty() {
-        eprintln!("ERROR: Empty query received");
+        error!("Empty query received");
         return Ok(Json(ActiveRagResponse {
             success: false,
             answer: None,
@@ -41,11 +172,13 @@ pub async fn active_rag_search(
             action_performed: None,
             confidence: None,
             error: Some("Search query cannot be empty".to_string()),
+            sources_below_threshold: None,
+            document_limit_note: None,
         }));
     }
     
     if user_question.is_empty() {
-        eprintln!("ERROR: Empty user question received");
+        error!("Empty user question received");
         return Ok(Json(ActiveRagResponse {
             success: false,
             answer: None,
@@ -53,19 +186,21 @@ pub async fn active_rag_search(
             action_performed: None,
             confidence: None,
             error: Some("User question cannot be empty".to_string()),
+            sources_below_threshold: None,
+            document_limit_note: None,
         }));
     }
 
     // Reload config to get latest AI settings
-    let config = match crate::config::AppConfig::load_or_default().await {
+    let mut config = match crate::config::AppConfig::load_or_default().await {
         Ok(cfg) => cfg,
         Err(e) => {
-            eprintln!("[Active RAG] Error loading config: {}", e);
+            error!("Error loading config: {}", e);
             state.config.as_ref().clone()
         }
     };
     
-    eprintln!("[Active RAG] AI Features Enabled: {}", config.ai_features_enabled);
+    debug!("AI Features Enabled: {}", config.ai_features_enabled);
     
     // Check if AI features are enabled
     if !config.ai_features_enabled {
@@ -76,8 +211,73 @@ pub async fn active_rag_search(
             action_performed: None,
             confidence: None,
             error: Some("AI features are disabled in settings".to_string()),
+            sources_below_threshold: None,
+            document_limit_note: None,
+        }));
+    }
+
+    if let Err(e) = apply_ai_override(&mut config, request.provider.clone(), request.model.as_deref()) {
+        return Ok(Json(ActiveRagResponse {
+            success: false,
+            answer: None,
+            sources: vec![],
+            action_performed: None,
+            confidence: None,
+            error: Some(e),
+            sources_below_threshold: None,
+            document_limit_note: None,
         }));
     }
+    let (analysis_limit, document_limit_note) = resolve_document_limit(request.document_limit, &config);
+
+    // Share the result of an identical in-flight request instead of re-running the pipeline
+    let result_tx = {
+        let mut in_flight = state.active_rag_in_flight.lock().await;
+        match in_flight.get(&request_id) {
+            // A closed channel means the original request was dropped before it finished
+            Some(rx) if rx.has_changed().is_ok() => Err(rx.clone()),
+            _ => {
+                let (tx, rx) = tokio::sync::watch::channel(None);
+                in_flight.insert(request_id.clone(), rx);
+                Ok(tx)
+            }
+        }
+    };
+    let result_tx = match result_tx {
+        Ok(tx) => tx,
+        Err(mut rx) => {
+            debug!("Identical request already in flight, awaiting its result");
+            let shared = rx.wait_for(|r| r.is_some()).await.ok().and_then(|r| (*r).clone());
+            return Ok(Json(shared.unwrap_or_else(|| ActiveRagResponse {
+                success: false,
+                answer: None,
+                sources: vec![],
+                action_performed: None,
+                confidence: None,
+                error: Some("Identical request was cancelled before completing".to_string()),
+                sources_below_threshold: None,
+                document_limit_note: None,
+            })));
+        }
+    };
+
+    // One AI slot covers the whole pipeline; when the queue is full, duplicates share the rejection
+    let Some(_permit) = state.ai_limiter.acquire().await else {
+        state.active_rag_in_flight.lock().await.remove(&request_id);
+        let _ = result_tx.send(Some(ActiveRagResponse {
+            success: false,
+            answer: None,
+            sources: vec![],
+            action_performed: None,
+            confidence: None,
+            error: Some("Too many AI requests are queued, try again shortly".to_string()),
+            sources_below_threshold: None,
+            document_limit_note: None,
+        }));
+        return Err(AiRequestError::Busy);
+    };
+
+    let (run, cancel) = ActiveRagRunGuard::start(&state, &request_id);
 
     // Wrap analysis in a timeout to prevent indefinite hangs
     use tokio::time::{timeout, Duration};
@@ -89,17 +289,24 @@ pub async fn active_rag_search(
             config.ollama_model.clone(),
             config.gemini_model.clone(),
             config.api_key.clone(),
-        );
+        )
+        .with_max_chars_per_doc(config.rag_max_chars_per_doc)
+        .with_excerpt_chars(config.rag_excerpt_chars)
+        .with_ollama_base_url(&config.ollama_base_url)
+        .with_openai_compatible(&config.openai_base_url, config.openai_model.clone())
+        .with_completion_settings(&config)
+        .with_request_timeout_secs(config.ai_request_timeout_secs)
+        .with_sources(request.include_sources);
 
         // DECOMPOSITION STEP: Parse intent using AI
-        eprintln!("[Active RAG] Decomposing intent for prompt: '{}' (Query: '{}')", user_question, query);
+        debug!("Decomposing intent for prompt: '{}' (Query: '{}')", user_question, query);
         let decomposed = match agent.decompose_intent(user_question, query, &config.action_search_parsing_model).await {
             Ok(d) => {
-                eprintln!("[Active RAG] Decomposition successful. Vector query: '{}'", d.vector_query);
+                debug!("Decomposition successful. Vector query: '{}'", d.vector_query);
                 d
             }
             Err(e) => {
-                eprintln!("[Active RAG] Decomposition failed, falling back to raw inputs: {}", e);
+                warn!("Decomposition failed, falling back to raw inputs: {}", e);
                 crate::active_rag_agent::DecomposedIntent {
                     vector_query: query.to_string(),
                     action_question: user_question.to_string(),
@@ -110,23 +317,30 @@ pub async fn active_rag_search(
 
         // Use decomposed vector_query for retrieval
         // Search with higher limit to ensure relevant files aren't missed, then take top N for analysis
-        let analysis_limit = request.document_limit.unwrap_or(3);
-        let search_limit = (analysis_limit * 10).max(30); // Search 30+ files, analyze top 3
+        let search_limit = (analysis_limit * 10).max(30); // Search 30+ files, analyze top N
         let search_request = SearchRequest {
             query: decomposed.vector_query.clone(),
             limit: Some(search_limit),
             filters: None, // TODO: Apply AI-extracted filters if possible
+            separate_metadata_only: false,
+            granularity: SearchGranularity::File,
+            min_similarity: None,
+            explain: false,
+            mode: SearchMode::Single,
+            cluster_duplicates: false,
+            include_directories: false,
         };
 
-        eprintln!("[Active RAG] Performing vector search for Active RAG...");
-        eprintln!("[Active RAG] Search query: '{}'", search_request.query);
-        eprintln!("[Active RAG] Document limit: {:?}", search_request.limit);
+        run.set_phase(ActiveRagPhase::Retrieving, None);
+        debug!("Performing vector search for Active RAG...");
+        debug!("Search query: '{}'", search_request.query);
+        debug!("Document limit: {:?}", search_request.limit);
         
         let mut search_results: Vec<SearchResult> = match perform_vector_search(&state, &search_request).await {
             Ok(results) => {
-                eprintln!("[Active RAG] Vector search returned {} results", results.len());
+                debug!("Vector search returned {} results", results.len());
                 for (i, result) in results.iter().take(5).enumerate() {
-                    eprintln!("[Active RAG]   Result {}: {} (score: {:.4})", 
+                    debug!("  Result {}: {} (score: {:.4})", 
                         i + 1, 
                         result.file_name, 
                         result.similarity
@@ -135,7 +349,7 @@ pub async fn active_rag_search(
                 results
             },
             Err(e) => {
-                eprintln!("[Active RAG] ERROR: Search failed: {}", e);
+                error!("Search failed: {}", e);
                 return ActiveRagResponse {
                     success: false,
                     answer: None,
@@ -143,6 +357,8 @@ pub async fn active_rag_search(
                     action_performed: None,
                     confidence: None,
                     error: Some(format!("Search failed: {}", e)),
+                    sources_below_threshold: None,
+                    document_limit_note: None,
                 };
             },
         };
@@ -155,18 +371,46 @@ pub async fn active_rag_search(
                 action_performed: None,
                 confidence: None,
                 error: Some("No search results found to analyze".to_string()),
+                sources_below_threshold: None,
+                document_limit_note: None,
             };
         }
 
-        // Take only top N for AI analysis (we searched more to ensure relevance)
-        search_results.truncate(analysis_limit);
-        eprintln!("[Active RAG] Taking top {} documents for AI analysis", search_results.len());
+        // Take only top N for AI analysis (we searched more to ensure relevance),
+        // skipping any that fall below the relevance floor
+        let min_similarity = request.min_similarity
+            .unwrap_or(config.rag_min_similarity)
+            .clamp(0.0, 1.0);
+        // Relevant candidates past the top N stay as backfill for documents that fail to extract
+        let expected = search_results.len().min(analysis_limit);
+        search_results.retain(|r| r.similarity >= min_similarity);
+        let below_threshold = expected - search_results.len().min(analysis_limit);
+        if below_threshold > 0 {
+            debug!("{} of the top {} documents scored below {:.2}", below_threshold, expected, min_similarity);
+        }
+        if search_results.is_empty() {
+            return ActiveRagResponse {
+                success: false,
+                answer: None,
+                sources: vec![],
+                action_performed: None,
+                confidence: None,
+                error: Some(format!(
+                    "None of the {} best matches were relevant enough to analyze (similarity threshold {:.2})",
+                    expected, min_similarity
+                )),
+                sources_below_threshold: Some(below_threshold),
+                document_limit_note: None,
+            };
+        }
+        run.set_phase(ActiveRagPhase::Extracting, Some(search_results.len().min(analysis_limit)));
+        debug!("Taking top {} documents for AI analysis", search_results.len().min(analysis_limit));
 
         // Extract content from top documents
-        eprintln!("[Active RAG] Extracting content from {} documents...", search_results.len());
-        let documents_with_content = match extract_document_content(&search_results).await {
+        debug!("Extracting content from up to {} of {} documents...", analysis_limit, search_results.len());
+        let documents_with_content = match extract_document_content(&search_results, analysis_limit, config.rag_max_chars_per_doc, crate::text_cache::TextCache::from_config(&config)).await {
             Ok(docs) => {
-                eprintln!("[Active RAG] Successfully extracted content from {} documents", docs.len());
+                debug!("Successfully extracted content from {} documents", docs.len());
                 for (i, (path, content, score)) in docs.iter().enumerate() {
                     let file_name = std::path::Path::new(path)
                         .file_name()
@@ -177,7 +421,7 @@ pub async fn active_rag_search(
                     } else {
                         content
                     };
-                    eprintln!("[Active RAG]   Doc {}: {} (score: {:.4}, content length: {} chars, preview: '{}...')", 
+                    debug!("  Doc {}: {} (score: {:.4}, content length: {} chars, preview: '{}...')", 
                         i + 1, 
                         file_name,
                         score,
@@ -188,7 +432,7 @@ pub async fn active_rag_search(
                 docs
             },
             Err(e) => {
-                eprintln!("[Active RAG] ERROR: Failed to extract document content: {}", e);
+                error!("Failed to extract document content: {}", e);
                 return ActiveRagResponse {
                     success: false,
                     answer: None,
@@ -196,13 +440,16 @@ pub async fn active_rag_search(
                     action_performed: None,
                     confidence: None,
                     error: Some(format!("Failed to read documents: {}", e)),
+                    sources_below_threshold: None,
+                    document_limit_note: None,
                 };
             },
         };
 
-        eprintln!("[Active RAG] Starting AI analysis of {} documents...", documents_with_content.len());
-        eprintln!("[Active RAG] Action question: '{}'", decomposed.action_question);
-        eprintln!("[Active RAG] Analysis model setting: '{}'", config.action_search_analysis_model);
+        run.set_phase(ActiveRagPhase::Analyzing, Some(documents_with_content.len()));
+        debug!("Starting AI analysis of {} documents...", documents_with_content.len());
+        debug!("Action question: '{}'", decomposed.action_question);
+        debug!("Analysis model setting: '{}'", config.action_search_analysis_model);
         
         let analysis_result = agent.analyze_documents(
             documents_with_content.clone(),
@@ -212,22 +459,23 @@ pub async fn active_rag_search(
         ).await;
         
         match analysis_result {
-            Ok(response) => {
-                eprintln!("[Active RAG] ✓ Analysis completed successfully");
-                eprintln!("[Active RAG] Response success: {}", response.success);
-                eprintln!("[Active RAG] Answer present: {}", response.answer.is_some());
+            Ok(mut response) => {
+                response.sources_below_threshold = (below_threshold > 0).then_some(below_threshold);
+                debug!("✓ Analysis completed successfully");
+                debug!("Response success: {}", response.success);
+                debug!("Answer present: {}", response.answer.is_some());
                 if let Some(ref answer) = response.answer {
                     let answer_preview = if answer.len() > 200 {
                         &answer[..200]
                     } else {
                         answer
                     };
-                    eprintln!("[Active RAG] Answer preview: '{}...'", answer_preview);
+                    debug!("Answer preview: '{}...'", answer_preview);
                 }
-                eprintln!("[Active RAG] Confidence: {:?}", response.confidence);
-                eprintln!("[Active RAG] Sources count: {}", response.sources.len());
+                debug!("Confidence: {:?}", response.confidence);
+                debug!("Sources count: {}", response.sources.len());
                 for (i, source) in response.sources.iter().enumerate() {
-                    eprintln!("[Active RAG]   Source {}: {} (used: {}, score: {:.4})", 
+                    debug!("  Source {}: {} (used: {}, score: {:.4})", 
                         i + 1, 
                         source.file_name, 
                         source.used_in_answer,
@@ -235,13 +483,13 @@ pub async fn active_rag_search(
                     );
                 }
                 if let Some(ref error) = response.error {
-                    eprintln!("[Active RAG] WARNING: Response has error: {}", error);
+                    warn!("Response has error: {}", error);
                 }
                 response
             }
             Err(e) => {
-                eprintln!("[Active RAG] ERROR: Analysis failed: {}", e);
-                eprintln!("[Active RAG] Error details: {:?}", e);
+                error!("Analysis failed: {}", e);
+                error!("Error details: {:?}", e);
                 ActiveRagResponse {
                     success: false,
                     answer: None,
@@ -249,44 +497,84 @@ pub async fn active_rag_search(
                     action_performed: None,
                     confidence: None,
                     error: Some(format!("Analysis failed: {}", e)),
+                    sources_below_threshold: None,
+                    document_limit_note: None,
                 }
             }
         }
     };
 
-    match timeout(Duration::from_secs(90), analysis_future).await {
-        Ok(response) => Ok(Json(response)),
-        Err(_) => {
-            eprintln!("[Active RAG] Analysis timed out after 90 seconds");
-            Ok(Json(ActiveRagResponse {
+    // Dropping the pipeline on cancel aborts its in-flight LLM request
+    let mut response = tokio::select! {
+        result = timeout(Duration::from_secs(config.rag_total_timeout_secs), analysis_future) => match result {
+            Ok(response) => response,
+            Err(_) => {
+                debug!("Analysis timed out after {} seconds", config.rag_total_timeout_secs);
+                ActiveRagResponse {
+                    success: false,
+                    answer: None,
+                    sources: vec![],
+                    action_performed: None,
+                    confidence: None,
+                    error: Some("AI analysis timed out. Try a simpler question or fewer documents.".to_string()),
+                    sources_below_threshold: None,
+                    document_limit_note: None,
+                }
+            }
+        },
+        _ = cancel.notified() => {
+            debug!("Request cancelled by client");
+            ActiveRagResponse {
                 success: false,
                 answer: None,
                 sources: vec![],
                 action_performed: None,
                 confidence: None,
-                error: Some("AI analysis timed out. Try a simpler question or fewer documents.".to_string()),
-            }))
+                error: Some("Request was cancelled".to_string()),
+                sources_below_threshold: None,
+                document_limit_note: None,
+            }
         }
-    }
+    };
+    drop(run);
+    response.document_limit_note = document_limit_note;
+
+    // Release the in-flight slot and hand the result to any waiting duplicates
+    state.active_rag_in_flight.lock().await.remove(&request_id);
+    let _ = result_tx.send(Some(response.clone()));
+
+    Ok(Json(response))
 }
 
+/// Documents to analyze for a requested limit: unset uses `rag_default_documents`, and anything
+/// over `rag_max_documents` is capped, with a note saying so for the response
+fn resolve_document_limit(requested: Option<usize>, config: &crate::config::AppConfig) -> (usize, Option<String>) {
+    let max = config.rag_max_documents.max(1);
+    let limit = requested.unwrap_or(config.rag_default_documents).clamp(1, max);
+    let note = requested.filter(|&n| n > max).map(|n| {
+        format!("Requested {} documents, but at most {} are analyzed (rag_max_documents)", n, max)
+    });
+    (limit, note)
+}
+
+#[tracing::instrument(name = "Vector Search", skip_all)]
 async fn perform_vector_search(
     state: &AppState,
     request: &SearchRequest,
 ) -> Result<Vec<SearchResult>, Box<dyn std::error::Error>> {
     let query = request.query.trim();
     let limit = request.limit.unwrap_or(3);
-    eprintln!("[Vector Search] Starting vector search (same pipeline as regular search)...");
-    eprintln!("[Vector Search] Query: '{}'", query);
-    eprintln!("[Vector Search] Limit: {}", limit);
+    debug!("Starting vector search (same pipeline as regular search)...");
+    debug!("Query: '{}'", query);
+    debug!("Limit: {}", limit);
 
-    let embedding_service = crate::embedding::EmbeddingService::new(
-        state.config.embedding_model.clone(),
-    );
-    let query_embedding = embedding_service.generate_embedding(query).await?;
-    eprintln!("[Vector Search] ✓ Query embedding generated (dimension: {})", query_embedding.len());
+    let embedding_service = crate::embedding::EmbeddingService::from_config(&state.config);
+    let mut query_embedding = embedding_service.generate_embedding(query).await?;
+    crate::search::prepare_vector(state.config.similarity_metric, &mut query_embedding);
+    debug!("✓ Query embedding generated (dimension: {})", query_embedding.len());
 
     // Use same pipeline as regular search: HNSW (or linear) + hybrid scoring + length penalties
+    let filename_matcher = crate::search::FilenameMatcher::from_config(&state.config);
     let mut results: Vec<(crate::storage::FileMetadata, f32)> = Vec::new();
 
     let hnsw_guard = state.hnsw_index.read().await;
@@ -294,35 +582,38 @@ async fn perform_vector_search(
         if hnsw.len() > 0 {
             let candidate_count = (limit * 50).max(100); // Match regular search: get many candidates for hybrid scoring
             if let Ok(hnsw_results) = hnsw.search(query_embedding.clone(), candidate_count) {
-                eprintln!("[Vector Search] HNSW returned {} candidates", hnsw_results.len());
-                results = score_search_results(query, hnsw_results);
+                debug!("HNSW returned {} candidates", hnsw_results.len());
+                results = score_search_results(query, hnsw_results, &filename_matcher);
             }
         }
     }
     drop(hnsw_guard);
 
     if results.is_empty() {
-        eprintln!("[Vector Search] HNSW unavailable or empty, using linear search...");
+        debug!("HNSW unavailable or empty, using linear search...");
         let files_with_embeddings = state.storage.get_all_embeddings().await?;
+        let metric = state.config.similarity_metric;
+        let query_norm = crate::search::vector_norm(&query_embedding);
         let raw_results: Vec<_> = files_with_embeddings
             .into_iter()
             .map(|(metadata, embedding)| {
-                let vector_sim = crate::search::cosine_similarity(&query_embedding, &embedding);
+                let vector_sim = crate::search::vector_similarity(metric, &query_embedding, &embedding);
+                let vector_sim = crate::search::scale_similarity(metric, vector_sim, query_norm);
                 (metadata, vector_sim)
             })
             .collect();
-        eprintln!("[Vector Search] Raw results before scoring: {}", raw_results.len());
-        results = score_search_results(query, raw_results);
-        eprintln!("[Vector Search] Results after hybrid scoring: {}", results.len());
+        debug!("Raw results before scoring: {}", raw_results.len());
+        results = score_search_results(query, raw_results, &filename_matcher);
+        debug!("Results after hybrid scoring: {}", results.len());
         if results.len() > 0 {
-            eprintln!("[Vector Search] Top 5 after scoring: {:?}", 
+            debug!("Top 5 after scoring: {:?}", 
                 results.iter().take(5).map(|(m, s)| (m.file_name.clone(), *s)).collect::<Vec<_>>());
         }
     }
 
     if state.config.filter_duplicate_files {
         results = deduplicate_by_embedding(results, state).await;
-        eprintln!("[Vector Search] Results after deduplication: {}", results.len());
+        debug!("Results after deduplication: {}", results.len());
     }
 
     // Re-sort after deduplication (dedup can change order)
@@ -332,24 +623,37 @@ async fn perform_vector_search(
         .into_iter()
         .take(limit)
         .map(|(metadata, similarity)| {
-            eprintln!("[Vector Search]   Selected: {} (score: {:.4})", metadata.file_name, similarity);
+            debug!("  Selected: {} (score: {:.4})", metadata.file_name, similarity);
             SearchResult {
                 file_path: metadata.file_path,
                 file_name: metadata.file_name,
+                modified_time: metadata.modified_time,
+                file_size: metadata.file_size,
                 similarity,
                 preview: None,
+                content_indexed: metadata.embedding_length > 0,
+                is_directory: false,
+                passage: None,
+                score_breakdown: None,
+                fusion_score: None,
+                duplicate_count: None,
+                duplicate_paths: None,
             }
         })
         .collect();
 
-    eprintln!("[Vector Search] ✓ Search complete, returning {} results", search_results.len());
+    debug!("✓ Search complete, returning {} results", search_results.len());
     Ok(search_results)
 }
 
+#[tracing::instrument(name = "Content Extraction", skip_all)]
 async fn extract_document_content(
     search_results: &[SearchResult],
+    limit: usize,
+    max_chars: usize,
+    text_cache: Option<crate::text_cache::TextCache>,
 ) -> Result<Vec<(String, String, f32)>, Box<dyn std::error::Error>> {
-    eprintln!("[Content Extraction] Starting content extraction from {} files...", search_results.len());
+    debug!("Starting content extraction from {} files...", search_results.len());
     
     let mut documents = Vec::new();
     
@@ -361,57 +665,66 @@ async fn extract_document_content(
         include_xlsx: true,
         excluded_extensions: Vec::new(),
     };
-    let registry = ParserRegistry::new(&filters);
+    let registry = ParserRegistry::new(&filters).with_text_cache(text_cache);
 
     for (i, result) in search_results.iter().enumerate() {
-        eprintln!("[Content Extraction] Processing file {}: {}", i + 1, result.file_name);
-        eprintln!("[Content Extraction]   Path: {}", result.file_path);
-        eprintln!("[Content Extraction]   Similarity: {:.4}", result.similarity);
+        if documents.len() >= limit {
+            break;
+        }
+        debug!("Processing file {}: {}", i + 1, result.file_name);
+        debug!("  Path: {}", result.file_path);
+        debug!("  Similarity: {:.4}", result.similarity);
         
         match registry.extract_text(&result.file_path) {
             Ok(content) => {
                 let original_len = content.chars().count();
-                // Limit content length for AI processing (safe char-aware truncation)
-                let max_chars = 3000;
-                let truncated_content: String = if original_len > max_chars {
-                    content.chars().take(max_chars).collect::<String>() + "..."
-                } else {
-                    content
-                };
+                // Limit content length for AI processing (keeps head and tail)
+                let truncated_content = truncate_head_tail(&content, max_chars);
                 
-                eprintln!("[Content Extraction]   ✓ Extracted {} chars (truncated to {} chars)", 
+                debug!("  ✓ Extracted {} chars (truncated to {} chars)", 
                     original_len, truncated_content.chars().count());
                 
                 documents.push((result.file_path.clone(), truncated_content, result.similarity));
             }
             Err(e) => {
-                eprintln!("[Content Extraction]   ✗ Parser failed: {}", e);
-                eprintln!("[Content Extraction]   Attempting plain text fallback...");
+                error!("  ✗ Parser failed: {}", e);
+                debug!("  Attempting plain text fallback...");
                 // Try to read as plain text fallback
                 match tokio::fs::read_to_string(&result.file_path).await {
                     Ok(content) => {
                         let original_len = content.chars().count();
-                        let max_chars = 3000;
-                        let truncated_content: String = if original_len > max_chars {
-                            content.chars().take(max_chars).collect::<String>() + "..."
-                        } else {
-                            content
-                        };
+                        let truncated_content = truncate_head_tail(&content, max_chars);
                         
-                        eprintln!("[Content Extraction]   ✓ Plain text read successful ({} chars, truncated to {} chars)", 
+                        debug!("  ✓ Plain text read successful ({} chars, truncated to {} chars)", 
                             original_len, truncated_content.chars().count());
                         
                         documents.push((result.file_path.clone(), truncated_content, result.similarity));
                     }
                     Err(read_err) => {
-                        eprintln!("[Content Extraction]   ✗ Could not read as plain text: {}", read_err);
-                        eprintln!("[Content Extraction]   Skipping this file");
+                        error!("  ✗ Could not read as plain text: {}", read_err);
+                        debug!("  Skipping this file");
                     }
                 }
             }
         }
     }
 
-    eprintln!("[Content Extraction] ✓ Extraction complete: {} documents extracted", documents.len());
+    debug!("✓ Extraction complete: {} documents extracted", documents.len());
     Ok(documents)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document_limit_defaults_and_caps() {
+        let config = crate::config::AppConfig::default();
+        assert_eq!(resolve_document_limit(None, &config), (3, None));
+        assert_eq!(resolve_document_limit(Some(0), &config), (1, None));
+        assert_eq!(resolve_document_limit(Some(10), &config), (10, None));
+        let (limit, note) = resolve_document_limit(Some(25), &config);
+        assert_eq!(limit, 10);
+        assert!(note.unwrap().contains("25"));
+    }
+}
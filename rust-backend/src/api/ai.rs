@@ -1,16 +1,121 @@
 use axum::{
     extract::{State, Query},
-    response::Json,
+    http::{header, StatusCode},
+    response::{IntoResponse, Json, Response},
 };
 use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::{debug, error, info, warn};
 use crate::AppState;
-use crate::config::AiProvider;
+use crate::config::{AiProvider, AppConfig};
+use crate::tokenizer::TokenCounter;
 
-const OLLAMA_BASE_URL: &str = "http://localhost:11434";
+/// Generation settings for OpenAI-compatible chat completions
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CompletionOptions {
+    pub temperature: f32,
+    pub max_tokens: Option<u32>,
+}
+
+impl CompletionOptions {
+    pub fn from_config(config: &AppConfig) -> Self {
+        Self {
+            temperature: config.temperature.unwrap_or(0.7),
+            max_tokens: Some(config.max_tokens.unwrap_or(2000)),
+        }
+    }
+}
+
+/// Seconds a client is told to wait after being turned away by a full AI queue
+const AI_BUSY_RETRY_AFTER_SECS: u64 = 5;
+
+/// Caps how many LLM requests run at once and how many may queue behind them
+pub struct AiLimiter {
+    semaphore: Arc<Semaphore>,
+    waiting: AtomicUsize,
+    max_queued: usize,
+}
+
+impl AiLimiter {
+    pub fn new(max_concurrent: usize, max_queued: usize) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(max_concurrent.max(1))),
+            waiting: AtomicUsize::new(0),
+            max_queued,
+        }
+    }
+
+    pub fn from_config(config: &AppConfig) -> Self {
+        Self::new(config.ai_max_concurrent_requests, config.ai_max_queued_requests)
+    }
+
+    /// Wait for a slot to run an LLM request; None if the queue is already full.
+    /// The slot is released when the permit is dropped.
+    pub async fn acquire(&self) -> Option<OwnedSemaphorePermit> {
+        if let Ok(permit) = self.semaphore.clone().try_acquire_owned() {
+            return Some(permit);
+        }
+        if self.waiting.fetch_add(1, Ordering::SeqCst) >= self.max_queued {
+            self.waiting.fetch_sub(1, Ordering::SeqCst);
+            warn!("AI request rejected, {} already queued", self.max_queued);
+            return None;
+        }
+        // Leave the queue even if the caller is dropped while waiting
+        let _queued = QueueSlot(&self.waiting);
+        debug!("Waiting for a free AI request slot");
+        self.semaphore.clone().acquire_owned().await.ok()
+    }
+}
+
+struct QueueSlot<'a>(&'a AtomicUsize);
+
+impl Drop for QueueSlot<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Error from an AI endpoint: a bare status, or a 429 when the AI queue is full
+#[derive(Debug)]
+pub enum AiRequestError {
+    Status(StatusCode),
+    Busy,
+}
+
+impl From<StatusCode> for AiRequestError {
+    fn from(status: StatusCode) -> Self {
+        Self::Status(status)
+    }
+}
+
+impl IntoResponse for AiRequestError {
+    fn into_response(self) -> Response {
+        match self {
+            Self::Status(status) => status.into_response(),
+            Self::Busy => (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, AI_BUSY_RETRY_AFTER_SECS.to_string())],
+                Json(serde_json::json!({
+                    "success": false,
+                    "error": "Too many AI requests are queued, try again shortly",
+                })),
+            )
+                .into_response(),
+        }
+    }
+}
 
 #[derive(Deserialize)]
 pub struct SummarizeRequest {
     pub file_path: String,
+    /// Use this provider instead of the configured one for this call only
+    #[serde(default)]
+    pub provider: Option<AiProvider>,
+    /// Use this model instead of the provider's configured one for this call only
+    #[serde(default)]
+    pub model: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -18,6 +123,43 @@ pub struct ChatRequest {
     pub file_path: String,
     pub message: String,
     pub conversation_history: Option<Vec<ChatMessage>>,
+    #[serde(default)]
+    pub provider: Option<AiProvider>,
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+/// Point `config` at a per-request provider and/or model, leaving the saved settings untouched.
+/// The saved API key belongs to the configured provider, so it is never sent to another one;
+/// fails when the resulting provider has no API key or model to call it with.
+pub(crate) fn apply_ai_override(config: &mut AppConfig, provider: Option<AiProvider>, model: Option<&str>) -> Result<(), String> {
+    if let Some(provider) = provider {
+        if provider != config.ai_provider {
+            config.api_key = None;
+        }
+        config.ai_provider = provider;
+    }
+    if let Some(model) = model.map(str::trim).filter(|m| !m.is_empty()) {
+        match config.ai_provider {
+            AiProvider::Ollama => config.ollama_model = Some(model.to_string()),
+            AiProvider::OpenAI => config.openai_model = Some(model.to_string()),
+            AiProvider::GreenPT => config.greenpt_model = model.to_string(),
+            AiProvider::Gemini => config.gemini_model = Some(model.to_string()),
+        }
+    }
+
+    let needs_key = matches!(config.ai_provider, AiProvider::GreenPT | AiProvider::Gemini);
+    let has_key = config.api_key.as_deref().is_some_and(|key| !key.trim().is_empty());
+    if needs_key && !has_key {
+        return Err(format!(
+            "No API key configured for {:?}; the saved key is only used with the provider selected in settings",
+            config.ai_provider
+        ));
+    }
+    if config.ai_provider == AiProvider::OpenAI && config.openai_model.is_none() {
+        return Err("No model configured for the OpenAI-compatible provider".to_string());
+    }
+    Ok(())
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -41,21 +183,22 @@ pub struct ChatResponse {
 }
 
 // Summarize document using Ollama
+#[tracing::instrument(name = "AI", skip_all)]
 pub async fn summarize_document(
     State(state): State<AppState>,
     Json(request): Json<SummarizeRequest>,
-) -> Result<Json<SummarizeResponse>, axum::http::StatusCode> {
+) -> Result<Json<SummarizeResponse>, AiRequestError> {
     // Reload config from disk to ensure we have the latest settings
-    let config = match crate::config::AppConfig::load_or_default().await {
+    let mut config = match crate::config::AppConfig::load_or_default().await {
         Ok(cfg) => cfg,
         Err(e) => {
-            eprintln!("[AI] Error loading config from disk: {}", e);
+            error!("Error loading config from disk: {}", e);
             // Fallback to in-memory config if disk read fails
             state.config.as_ref().clone()
         }
     };
     
-    eprintln!("[AI] summarize_document: ai_features_enabled = {}", config.ai_features_enabled);
+    debug!("summarize_document: ai_features_enabled = {}", config.ai_features_enabled);
     
     // Check if AI features are enabled
     if !config.ai_features_enabled {
@@ -66,6 +209,14 @@ pub async fn summarize_document(
         }));
     }
 
+    if let Err(e) = apply_ai_override(&mut config, request.provider.clone(), request.model.as_deref()) {
+        return Ok(Json(SummarizeResponse {
+            success: false,
+            summary: None,
+            error: Some(e),
+        }));
+    }
+
     // Get file content from preview endpoint logic
     let content = match get_file_content_for_ai(&request.file_path).await {
         Ok(c) => c,
@@ -86,40 +237,51 @@ pub async fn summarize_document(
         }));
     }
 
-    // Create summarize prompt
-    let prompt = format!(
-        "Please provide a concise summary of the following document. Focus on the main points, key information, and important details:\n\n{}",
-        content
-    );
+    // Large documents are sampled down to the provider's prompt budget rather than overflowing it
+    let token_counter = crate::tokenizer::token_counter_for_model(config.active_chat_model());
+    let budget = config
+        .ai_context_tokens()
+        .saturating_sub(token_counter.count_tokens(SUMMARIZE_PROMPT) + CHAT_MESSAGE_OVERHEAD_TOKENS);
+    let prompt = format!("{}{}", SUMMARIZE_PROMPT, sample_excerpts(&content, budget, token_counter.as_ref()));
+
+    let _permit = state.ai_limiter.acquire().await.ok_or(AiRequestError::Busy)?;
 
     // Call appropriate API based on provider
     let result = match config.ai_provider {
         AiProvider::Ollama => {
             let model = config.ollama_model.as_deref()
                 .unwrap_or("llama3.2:1b");
-            eprintln!("[AI] Calling Ollama (model: {}) for summary", model);
-            call_ollama_generate(model, &prompt, false).await
+            debug!("Calling Ollama (model: {}) for summary", model);
+            call_ollama_generate(&config.ollama_base_url, model, &prompt, false, config.ai_request_timeout_secs).await
         }
         AiProvider::GreenPT => {
             let api_key = config.api_key.as_ref()
                 .ok_or_else(|| axum::http::StatusCode::BAD_REQUEST)?;
-            eprintln!("[AI] Calling GreenPT for summary");
-            call_greenpt_chat_single(api_key, &prompt).await
+            debug!("Calling GreenPT for summary");
+            call_greenpt_chat_single(api_key, &config.greenpt_model, &prompt, CompletionOptions::from_config(&config), config.ai_request_timeout_secs).await
         }
         AiProvider::OpenAI => {
-            return Ok(Json(SummarizeResponse {
-                success: false,
-                summary: None,
-                error: Some("OpenAI provider not yet implemented".to_string()),
-            }));
+            let Some(model) = config.openai_model.as_deref() else {
+                return Ok(Json(SummarizeResponse {
+                    success: false,
+                    summary: None,
+                    error: Some("No model configured for the OpenAI-compatible provider".to_string()),
+                }));
+            };
+            debug!("Calling OpenAI-compatible API at {} (model: {}) for summary", config.openai_base_url, model);
+            let messages = vec![ChatMessage {
+                role: "user".to_string(),
+                content: prompt.clone(),
+            }];
+            call_openai_compatible_chat(&config.openai_base_url, config.api_key.as_deref(), model, &messages, CompletionOptions::from_config(&config), config.ai_request_timeout_secs).await
         }
         AiProvider::Gemini => {
             let api_key = config.api_key.as_ref()
                 .ok_or_else(|| axum::http::StatusCode::BAD_REQUEST)?;
             let model = config.gemini_model.as_deref()
                 .unwrap_or("gemini-pro");
-            eprintln!("[AI] Calling Gemini (model: {}) for summary", model);
-            call_gemini_chat_single(api_key, model, &prompt).await
+            debug!("Calling Gemini (model: {}) for summary", model);
+            call_gemini_chat_single(api_key, model, &prompt, config.ai_request_timeout_secs).await
         }
     };
 
@@ -138,21 +300,22 @@ pub async fn summarize_document(
 }
 
 // Chat about document using Ollama
+#[tracing::instrument(name = "AI", skip_all)]
 pub async fn chat_about_document(
     State(state): State<AppState>,
     Json(request): Json<ChatRequest>,
-) -> Result<Json<ChatResponse>, axum::http::StatusCode> {
+) -> Result<Json<ChatResponse>, AiRequestError> {
     // Reload config from disk to ensure we have the latest settings
-    let config = match crate::config::AppConfig::load_or_default().await {
+    let mut config = match crate::config::AppConfig::load_or_default().await {
         Ok(cfg) => cfg,
         Err(e) => {
-            eprintln!("[AI] Error loading config from disk: {}", e);
+            error!("Error loading config from disk: {}", e);
             // Fallback to in-memory config if disk read fails
             state.config.as_ref().clone()
         }
     };
     
-    eprintln!("[AI] chat_about_document: ai_features_enabled = {}", config.ai_features_enabled);
+    debug!("chat_about_document: ai_features_enabled = {}", config.ai_features_enabled);
     
     // Check if AI features are enabled
     if !config.ai_features_enabled {
@@ -163,6 +326,14 @@ pub async fn chat_about_document(
         }));
     }
 
+    if let Err(e) = apply_ai_override(&mut config, request.provider.clone(), request.model.as_deref()) {
+        return Ok(Json(ChatResponse {
+            success: false,
+            message: None,
+            error: Some(e),
+        }));
+    }
+
     // Get file content
     let content = match get_file_content_for_ai(&request.file_path).await {
         Ok(c) => c,
@@ -183,56 +354,64 @@ pub async fn chat_about_document(
         }));
     }
 
+    // Fit document, history and question into the configured prompt budget
+    let token_counter = crate::tokenizer::token_counter_for_model(config.active_chat_model());
+    let context = fit_chat_context(
+        &content,
+        request.conversation_history.unwrap_or_default(),
+        &request.message,
+        config.ai_context_tokens(),
+        token_counter.as_ref(),
+    );
+
     // Build conversation context
     let mut messages = Vec::new();
     
     // System message with document context
     messages.push(ChatMessage {
         role: "system".to_string(),
-        content: format!(
-            "You are a helpful assistant. The user is asking questions about the following document. Use the document content to answer their questions accurately.\n\nDocument content:\n{}",
-            content
-        ),
+        content: format!("{}{}", CHAT_SYSTEM_PROMPT, context.document),
     });
 
     // Add conversation history if provided
-    if let Some(history) = request.conversation_history {
-        for msg in history {
-            messages.push(msg);
-        }
-    }
+    messages.extend(context.history);
 
     // Add current user message
     messages.push(ChatMessage {
         role: "user".to_string(),
-        content: request.message,
+        content: context.message,
     });
 
+    let _permit = state.ai_limiter.acquire().await.ok_or(AiRequestError::Busy)?;
+
     // Call appropriate API based on provider
     let result = match config.ai_provider {
         AiProvider::Ollama => {
             let model = config.ollama_model.as_deref()
                 .unwrap_or("llama3.2:1b");
-            call_ollama_chat(model, &messages).await
+            call_ollama_chat(&config.ollama_base_url, model, &messages, config.ai_request_timeout_secs).await
         }
         AiProvider::GreenPT => {
             let api_key = config.api_key.as_ref()
                 .ok_or_else(|| axum::http::StatusCode::BAD_REQUEST)?;
-            call_greenpt_chat(api_key, &messages).await
+            call_greenpt_chat(api_key, &config.greenpt_model, &messages, CompletionOptions::from_config(&config), config.ai_request_timeout_secs).await
         }
         AiProvider::OpenAI => {
-            return Ok(Json(ChatResponse {
-                success: false,
-                message: None,
-                error: Some("OpenAI provider not yet implemented".to_string()),
-            }));
+            let Some(model) = config.openai_model.as_deref() else {
+                return Ok(Json(ChatResponse {
+                    success: false,
+                    message: None,
+                    error: Some("No model configured for the OpenAI-compatible provider".to_string()),
+                }));
+            };
+            call_openai_compatible_chat(&config.openai_base_url, config.api_key.as_deref(), model, &messages, CompletionOptions::from_config(&config), config.ai_request_timeout_secs).await
         }
         AiProvider::Gemini => {
             let api_key = config.api_key.as_ref()
                 .ok_or_else(|| axum::http::StatusCode::BAD_REQUEST)?;
             let model = config.gemini_model.as_deref()
                 .unwrap_or("gemini-pro");
-            call_gemini_chat(api_key, model, &messages).await
+            call_gemini_chat(api_key, model, &messages, config.ai_request_timeout_secs).await
         }
     };
 
@@ -250,6 +429,194 @@ pub async fn chat_about_document(
     }
 }
 
+const SUMMARIZE_PROMPT: &str = "Please provide a concise summary of the following document. Focus on the main points, key information, and important details:\n\n";
+const CHAT_SYSTEM_PROMPT: &str = "You are a helpful assistant. The user is asking questions about the following document. Use the document content to answer their questions accurately.\n\nDocument content:\n";
+/// Share of the chat budget that conversation history may take before the oldest turns are dropped
+const CHAT_HISTORY_SHARE: f32 = 0.3;
+/// Size of the document excerpts ranked or sampled when the whole document doesn't fit
+const CHAT_EXCERPT_TOKENS: usize = 300;
+/// Per-message allowance for role markers and formatting
+const CHAT_MESSAGE_OVERHEAD_TOKENS: usize = 4;
+
+struct ChatContext {
+    document: String,
+    history: Vec<ChatMessage>,
+    message: String,
+}
+
+/// Trim a document chat to `budget` tokens: the question is kept, history drops its oldest turns
+/// once it exceeds its share, and the document is cut down to the excerpts most relevant to the
+/// conversation.
+fn fit_chat_context(
+    document: &str,
+    history: Vec<ChatMessage>,
+    message: &str,
+    budget: usize,
+    counter: &dyn TokenCounter,
+) -> ChatContext {
+    let message = counter.truncate(message, budget / 2);
+    let available = budget
+        .saturating_sub(counter.count_tokens(CHAT_SYSTEM_PROMPT))
+        .saturating_sub(counter.count_tokens(&message) + 2 * CHAT_MESSAGE_OVERHEAD_TOKENS);
+
+    let history_budget = (available as f32 * CHAT_HISTORY_SHARE) as usize;
+    let mut history_tokens = 0;
+    let mut kept: Vec<ChatMessage> = history
+        .into_iter()
+        .rev()
+        .take_while(|turn| {
+            history_tokens += counter.count_tokens(&turn.content) + CHAT_MESSAGE_OVERHEAD_TOKENS;
+            history_tokens <= history_budget
+        })
+        .collect();
+    kept.reverse();
+    let history_tokens: usize = kept
+        .iter()
+        .map(|turn| counter.count_tokens(&turn.content) + CHAT_MESSAGE_OVERHEAD_TOKENS)
+        .sum();
+
+    // Relevance is judged against the question and the user's recent turns
+    let query = kept
+        .iter()
+        .filter(|turn| turn.role == "user")
+        .map(|turn| turn.content.as_str())
+        .chain(std::iter::once(message.as_str()))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let document = select_relevant_excerpts(document, &query, available - history_tokens, counter);
+
+    ChatContext {
+        document,
+        history: kept,
+        message,
+    }
+}
+
+/// Return the whole document if it fits, otherwise the highest-scoring excerpts (by overlap
+/// with `query`'s terms) in document order, separated by "[...]"
+fn select_relevant_excerpts(document: &str, query: &str, budget: usize, counter: &dyn TokenCounter) -> String {
+    if counter.count_tokens(document) <= budget {
+        return document.to_string();
+    }
+
+    let excerpts = split_excerpts(document, counter);
+    let terms: std::collections::HashSet<String> = query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|term| term.chars().count() > 2)
+        .map(|term| term.to_lowercase())
+        .collect();
+    let mut ranked: Vec<(usize, usize, usize)> = excerpts
+        .iter()
+        .enumerate()
+        .map(|(index, excerpt)| {
+            let lower = excerpt.to_lowercase();
+            let mut score = terms.iter().filter(|term| lower.contains(term.as_str())).count();
+            // Openings usually carry the title and summary
+            if index == 0 {
+                score += 1;
+            }
+            (index, score, counter.count_tokens(excerpt))
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    let separator_tokens = counter.count_tokens("\n[...]\n");
+    let mut used = 0;
+    let mut chosen = Vec::new();
+    for (index, _, tokens) in ranked {
+        if used + tokens + separator_tokens <= budget {
+            used += tokens + separator_tokens;
+            chosen.push(index);
+        }
+    }
+    if chosen.is_empty() {
+        return counter.truncate(document, budget);
+    }
+    chosen.sort_unstable();
+
+    debug!("Document chat: kept {} of {} excerpts within {} tokens", chosen.len(), excerpts.len(), budget);
+    join_excerpts(&excerpts, &chosen)
+}
+
+/// Return the whole document if it fits, otherwise excerpts spread evenly from its start to its
+/// end, separated by "[...]", so a summary covers the whole document rather than its opening
+fn sample_excerpts(document: &str, budget: usize, counter: &dyn TokenCounter) -> String {
+    if counter.count_tokens(document) <= budget {
+        return document.to_string();
+    }
+
+    let excerpts = split_excerpts(document, counter);
+    let separator_tokens = counter.count_tokens("\n[...]\n");
+    let tokens: Vec<usize> = excerpts.iter().map(|excerpt| counter.count_tokens(excerpt) + separator_tokens).collect();
+    let average = tokens.iter().sum::<usize>() / tokens.len().max(1);
+    let mut count = (budget / average.max(1)).min(excerpts.len());
+    while count > 0 {
+        // First and last excerpts are always among the `count` evenly spaced picks
+        let chosen: Vec<usize> = match count {
+            1 => vec![0],
+            _ => (0..count).map(|i| i * (excerpts.len() - 1) / (count - 1)).collect(),
+        };
+        if chosen.iter().map(|&index| tokens[index]).sum::<usize>() <= budget {
+            info!(
+                "Document too long for the {}-token budget, summarizing {} of {} excerpts",
+                budget, chosen.len(), excerpts.len()
+            );
+            return join_excerpts(&excerpts, &chosen);
+        }
+        count -= 1;
+    }
+    counter.truncate(document, budget)
+}
+
+/// Cut a document into consecutive runs of whole lines of about `CHAT_EXCERPT_TOKENS` each
+fn split_excerpts(document: &str, counter: &dyn TokenCounter) -> Vec<String> {
+    let mut excerpts: Vec<String> = Vec::new();
+    let mut current = String::new();
+    for line in document.split_inclusive('\n') {
+        current.push_str(line);
+        if counter.count_tokens(&current) >= CHAT_EXCERPT_TOKENS {
+            excerpts.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.trim().is_empty() {
+        excerpts.push(current);
+    }
+    excerpts
+}
+
+/// The excerpts at `chosen` (in ascending order), separated by "[...]"
+fn join_excerpts(excerpts: &[String], chosen: &[usize]) -> String {
+    chosen
+        .iter()
+        .map(|&index| excerpts[index].trim_end().to_string())
+        .collect::<Vec<_>>()
+        .join("\n[...]\n")
+}
+
+/// Extensions whose files are never text; they get a clear error instead of a failed UTF-8 read
+const BINARY_EXTENSIONS: &[&str] = &[
+    // Images (the image parser only yields the filename)
+    "jpg", "jpeg", "png", "gif", "bmp", "webp", "ico", "tiff", "tif", "heic",
+    // Archives and installers
+    "zip", "gz", "tgz", "tar", "7z", "rar", "bz2", "xz", "iso", "dmg", "msi",
+    // Executables and libraries
+    "exe", "dll", "so", "dylib", "bin", "class", "jar", "o", "a", "lib", "wasm",
+    // Media
+    "mp3", "mp4", "m4a", "wav", "flac", "ogg", "mov", "avi", "mkv", "webm",
+    // Office formats without a parser, databases and fonts
+    "doc", "ppt", "pptx", "odt", "sqlite", "db", "ttf", "otf", "woff", "woff2",
+];
+
+/// Bytes sniffed for NULs when deciding whether an unknown file is binary
+const BINARY_SNIFF_BYTES: usize = 8192;
+
+fn unreadable_for_ai(extension: &str) -> String {
+    match extension {
+        "" => "This file can't be read as text for AI".to_string(),
+        ext => format!("This file type (.{}) can't be read as text for AI", ext),
+    }
+}
+
 // Helper function to get file content for AI processing
 async fn get_file_content_for_ai(file_path: &str) -> Result<String, Box<dyn std::error::Error>> {
     use crate::parsers::ParserRegistry;
@@ -264,6 +631,15 @@ async fn get_file_content_for_ai(file_path: &str) -> Result<String, Box<dyn std:
         return Err("Cannot process directories".into());
     }
 
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+    if BINARY_EXTENSIONS.contains(&extension.as_str()) {
+        return Err(unreadable_for_ai(&extension).into());
+    }
+
     // Use parser registry to extract text
     // Create default filters (include all file types for AI processing)
     let filters = FileTypeFilters {
@@ -273,7 +649,8 @@ async fn get_file_content_for_ai(file_path: &str) -> Result<String, Box<dyn std:
         include_xlsx: true,
         excluded_extensions: Vec::new(),
     };
-    let registry = ParserRegistry::new(&filters);
+    let config = AppConfig::load_or_default().await.unwrap_or_default();
+    let registry = ParserRegistry::new(&filters).with_text_cache(crate::text_cache::TextCache::from_config(&config));
     
     // Try to extract text using the registry's public API
     if registry.can_parse(file_path) {
@@ -283,18 +660,23 @@ async fn get_file_content_for_ai(file_path: &str) -> Result<String, Box<dyn std:
         }
     }
 
-    // If no parser found, try to read as plain text
-    match tokio::fs::read_to_string(file_path).await {
-        Ok(content) => Ok(content),
-        Err(e) => Err(format!("Failed to read file: {}", e).into()),
+    // If no parser found, read it as plain text unless it looks binary
+    let bytes = tokio::fs::read(file_path)
+        .await
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+    if bytes[..bytes.len().min(BINARY_SNIFF_BYTES)].contains(&0) {
+        return Err(unreadable_for_ai(&extension).into());
     }
+    String::from_utf8(bytes).map_err(|_| unreadable_for_ai(&extension).into())
 }
 
 // Call Ollama generate endpoint
 async fn call_ollama_generate(
+    base_url: &str,
     model: &str,
     prompt: &str,
     stream: bool,
+    timeout_secs: u64,
 ) -> Result<String, Box<dyn std::error::Error>> {
     use reqwest::Client;
     
@@ -311,9 +693,9 @@ async fn call_ollama_generate(
     }
 
     let client = Client::builder()
-        .timeout(std::time::Duration::from_secs(60))
+        .timeout(std::time::Duration::from_secs(timeout_secs))
         .build()?;
-    let url = format!("{}/api/generate", OLLAMA_BASE_URL);
+    let url = format!("{}/api/generate", base_url.trim_end_matches('/'));
     
     let request_body = GenerateRequest {
         model: model.to_string(),
@@ -337,8 +719,10 @@ async fn call_ollama_generate(
 
 // Call Ollama chat endpoint
 pub(crate) async fn call_ollama_chat(
+    base_url: &str,
     model: &str,
     messages: &[ChatMessage],
+    timeout_secs: u64,
 ) -> Result<String, Box<dyn std::error::Error>> {
     use reqwest::Client;
     
@@ -361,9 +745,9 @@ pub(crate) async fn call_ollama_chat(
     }
 
     let client = Client::builder()
-        .timeout(std::time::Duration::from_secs(60))
+        .timeout(std::time::Duration::from_secs(timeout_secs))
         .build()?;
-    let url = format!("{}/api/chat", OLLAMA_BASE_URL);
+    let url = format!("{}/api/chat", base_url.trim_end_matches('/'));
     
     let request_body = ChatRequest {
         model: model.to_string(),
@@ -388,11 +772,29 @@ pub(crate) async fn call_ollama_chat(
 // Call GreenPT API (OpenAI-compatible endpoint)
 pub(crate) async fn call_greenpt_chat(
     api_key: &str,
+    model: &str,
     messages: &[ChatMessage],
+    options: CompletionOptions,
+    timeout_secs: u64,
 ) -> Result<String, Box<dyn std::error::Error>> {
-    use reqwest::Client;
-    
     const GREENPT_BASE_URL: &str = "https://api.greenpt.ai/v1";
+
+    call_openai_compatible_chat(GREENPT_BASE_URL, Some(api_key), model, messages, options, timeout_secs)
+        .await
+        .map_err(|e| format!("GreenPT {}", e).into())
+}
+
+// Call any OpenAI-compatible chat completions endpoint (OpenAI, LiteLLM, vLLM, ...)
+// The API key is optional since self-hosted gateways often don't require one
+pub(crate) async fn call_openai_compatible_chat(
+    base_url: &str,
+    api_key: Option<&str>,
+    model: &str,
+    messages: &[ChatMessage],
+    options: CompletionOptions,
+    timeout_secs: u64,
+) -> Result<String, Box<dyn std::error::Error>> {
+    use reqwest::Client;
     
     #[derive(Serialize)]
     struct GreenPTMessage {
@@ -425,11 +827,11 @@ pub(crate) async fn call_greenpt_chat(
     }
 
     let client = Client::builder()
-        .timeout(std::time::Duration::from_secs(60))
+        .timeout(std::time::Duration::from_secs(timeout_secs))
         .build()?;
-    let url = format!("{}/chat/completions", GREENPT_BASE_URL);
+    let url = format!("{}/chat/completions", base_url.trim_end_matches('/'));
     
-    // Convert messages to GreenPT format
+    // Convert messages to OpenAI format
     let greenpt_messages: Vec<GreenPTMessage> = messages
         .iter()
         .map(|m| GreenPTMessage {
@@ -439,16 +841,19 @@ pub(crate) async fn call_greenpt_chat(
         .collect();
     
     let request_body = GreenPTChatRequest {
-        model: "greenpt".to_string(), // Default model, can be made configurable
+        model: model.to_string(),
         messages: greenpt_messages,
-        temperature: 0.7,
-        max_tokens: Some(2000),
+        temperature: options.temperature,
+        max_tokens: options.max_tokens,
     };
 
-    let response = client
+    let mut request = client
         .post(&url)
-        .header("Authorization", format!("Bearer {}", api_key))
-        .header("Content-Type", "application/json")
+        .header("Content-Type", "application/json");
+    if let Some(key) = api_key.filter(|k| !k.is_empty()) {
+        request = request.header("Authorization", format!("Bearer {}", key));
+    }
+    let response = request
         .json(&request_body)
         .send()
         .await?;
@@ -456,7 +861,7 @@ pub(crate) async fn call_greenpt_chat(
     let status = response.status();
     if !status.is_success() {
         let error_text = response.text().await.unwrap_or_default();
-        return Err(format!("GreenPT API error: {} - {}", status, error_text).into());
+        return Err(format!("API error: {} - {}", status, error_text).into());
     }
 
     let chat_response: GreenPTChatResponse = response.json().await?;
@@ -464,20 +869,23 @@ pub(crate) async fn call_greenpt_chat(
     if let Some(choice) = chat_response.choices.first() {
         Ok(choice.message.content.clone())
     } else {
-        Err("No response from GreenPT API".into())
+        Err("No response from API".into())
     }
 }
 
 // Call GreenPT for single prompt (summarize)
 async fn call_greenpt_chat_single(
     api_key: &str,
+    model: &str,
     prompt: &str,
+    options: CompletionOptions,
+    timeout_secs: u64,
 ) -> Result<String, Box<dyn std::error::Error>> {
     let messages = vec![ChatMessage {
         role: "user".to_string(),
         content: prompt.to_string(),
     }];
-    call_greenpt_chat(api_key, &messages).await
+    call_greenpt_chat(api_key, model, &messages, options, timeout_secs).await
 }
 
 // Fetch available Gemini models
@@ -504,7 +912,7 @@ pub async fn get_gemini_models(
     let status = response.status();
     if !status.is_success() {
         let _error_text = response.text().await.unwrap_or_default();
-        eprintln!("Failed to fetch Gemini models: HTTP {}", status);
+        error!("Failed to fetch Gemini models: HTTP {}", status);
         return Err(axum::http::StatusCode::BAD_REQUEST);
     }
     
@@ -532,11 +940,110 @@ pub async fn get_gemini_models(
     })))
 }
 
+/// Timeout for the test call made by `validate_api_key`
+const VALIDATE_KEY_TIMEOUT_SECS: u64 = 20;
+
+#[derive(Deserialize)]
+pub struct ValidateKeyRequest {
+    pub provider: AiProvider,
+    /// Ignored for Ollama; optional for self-hosted OpenAI-compatible gateways
+    #[serde(default)]
+    pub api_key: String,
+    /// Model to test; defaults to the one configured for the provider
+    pub model: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ValidateKeyResponse {
+    pub valid: bool,
+    pub model: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Check an API key / model pair with a one-token completion before it is saved
+#[tracing::instrument(name = "AI", skip_all)]
+pub async fn validate_api_key(
+    State(state): State<AppState>,
+    Json(request): Json<ValidateKeyRequest>,
+) -> Result<Json<ValidateKeyResponse>, AiRequestError> {
+    let config = match crate::config::AppConfig::load_or_default().await {
+        Ok(cfg) => cfg,
+        Err(e) => {
+            error!("Error loading config from disk: {}", e);
+            state.config.as_ref().clone()
+        }
+    };
+
+    let model = request.model.filter(|m| !m.trim().is_empty()).or(match request.provider {
+        AiProvider::Ollama => Some(config.ollama_model.unwrap_or_else(|| "llama3.2:1b".to_string())),
+        AiProvider::OpenAI => config.openai_model,
+        AiProvider::GreenPT => Some(config.greenpt_model),
+        AiProvider::Gemini => Some(config.gemini_model.unwrap_or_else(|| "gemini-pro".to_string())),
+    });
+    let Some(model) = model else {
+        return Ok(Json(ValidateKeyResponse {
+            valid: false,
+            model: None,
+            error: Some("No model given and none configured for the OpenAI-compatible provider".to_string()),
+        }));
+    };
+    let needs_key = matches!(request.provider, AiProvider::GreenPT | AiProvider::Gemini);
+    if needs_key && request.api_key.trim().is_empty() {
+        return Ok(Json(ValidateKeyResponse {
+            valid: false,
+            model: Some(model),
+            error: Some("API key is required for this provider".to_string()),
+        }));
+    }
+
+    let messages = vec![ChatMessage {
+        role: "user".to_string(),
+        content: "Reply with OK.".to_string(),
+    }];
+    let options = CompletionOptions { temperature: 0.0, max_tokens: Some(1) };
+    let api_key = request.api_key.trim();
+
+    let _permit = state.ai_limiter.acquire().await.ok_or(AiRequestError::Busy)?;
+    debug!("Validating {:?} credentials with model {}", request.provider, model);
+    let result = match request.provider {
+        AiProvider::Ollama => call_ollama_chat(&config.ollama_base_url, &model, &messages, VALIDATE_KEY_TIMEOUT_SECS)
+            .await
+            .map_err(|e| e.to_string()),
+        AiProvider::GreenPT => call_greenpt_chat(api_key, &model, &messages, options, VALIDATE_KEY_TIMEOUT_SECS)
+            .await
+            .map_err(|e| e.to_string()),
+        AiProvider::OpenAI => call_openai_compatible_chat(&config.openai_base_url, Some(api_key), &model, &messages, options, VALIDATE_KEY_TIMEOUT_SECS)
+            .await
+            .map_err(|e| e.to_string()),
+        AiProvider::Gemini => call_gemini_chat(api_key, &model, &messages, VALIDATE_KEY_TIMEOUT_SECS)
+            .await
+            .map_err(|e| e.to_string()),
+    };
+
+    Ok(Json(match result {
+        Ok(_) => ValidateKeyResponse {
+            valid: true,
+            model: Some(model),
+            error: None,
+        },
+        Err(e) => {
+            debug!("API key validation failed: {}", e);
+            ValidateKeyResponse {
+                valid: false,
+                model: Some(model),
+                error: Some(e),
+            }
+        }
+    }))
+}
+
 // Call Gemini API for chat
+#[tracing::instrument(name = "AI", skip_all)]
 pub(crate) async fn call_gemini_chat(
     api_key: &str,
     model: &str,
     messages: &[ChatMessage],
+    timeout_secs: u64,
 ) -> Result<String, Box<dyn std::error::Error>> {
     use reqwest::Client;
     
@@ -558,28 +1065,8 @@ pub(crate) async fn call_gemini_chat(
         contents: Vec<GeminiContent>,
     }
 
-    #[derive(Deserialize)]
-    struct GeminiPartResponse {
-        text: String,
-    }
-    
-    #[derive(Deserialize)]
-    struct GeminiCandidate {
-        content: GeminiContentResponse,
-    }
-    
-    #[derive(Deserialize)]
-    struct GeminiContentResponse {
-        parts: Vec<GeminiPartResponse>,
-    }
-    
-    #[derive(Deserialize)]
-    struct GeminiResponse {
-        candidates: Vec<GeminiCandidate>,
-    }
-
     let client = Client::builder()
-        .timeout(std::time::Duration::from_secs(60))
+        .timeout(std::time::Duration::from_secs(timeout_secs))
         .build()?;
     let url = format!("{}/models/{}:generateContent", GEMINI_BASE_URL, model);
     
@@ -648,7 +1135,7 @@ pub(crate) async fn call_gemini_chat(
         .send()
         .await?;
     
-    eprintln!("[AI] Gemini response status: {}", response.status());
+    debug!("Gemini response status: {}", response.status());
 
     let status = response.status();
     if !status.is_success() {
@@ -657,16 +1144,71 @@ pub(crate) async fn call_gemini_chat(
     }
 
     let gemini_response: GeminiResponse = response.json().await?;
-    
-    if let Some(candidate) = gemini_response.candidates.first() {
-        if let Some(part) = candidate.content.parts.first() {
-            Ok(part.text.clone())
-        } else {
-            Err("No content in Gemini response".into())
-        }
-    } else {
-        Err("No candidates in Gemini response".into())
+    gemini_response_text(gemini_response).map_err(|e| e.into())
+}
+
+#[derive(Deserialize)]
+struct GeminiPartResponse {
+    #[serde(default)]
+    text: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiCandidate {
+    #[serde(default)]
+    content: Option<GeminiContentResponse>,
+    #[serde(default)]
+    finish_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GeminiContentResponse {
+    #[serde(default)]
+    parts: Vec<GeminiPartResponse>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiPromptFeedback {
+    #[serde(default)]
+    block_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiResponse {
+    #[serde(default)]
+    candidates: Vec<GeminiCandidate>,
+    #[serde(default)]
+    prompt_feedback: Option<GeminiPromptFeedback>,
+}
+
+/// Pull the reply text out of a Gemini response, explaining blocked or empty responses
+fn gemini_response_text(response: GeminiResponse) -> Result<String, String> {
+    let block_reason = response.prompt_feedback.and_then(|f| f.block_reason);
+    let Some(candidate) = response.candidates.into_iter().next() else {
+        return Err(match block_reason {
+            Some(reason) => format!("Gemini blocked the request (reason: {}). Try rephrasing or using a different document.", reason),
+            None => "Gemini returned no response candidates".to_string(),
+        });
+    };
+
+    let text: String = candidate.content
+        .map(|c| c.parts.into_iter().map(|p| p.text).collect())
+        .unwrap_or_default();
+    if !text.is_empty() {
+        return Ok(text);
     }
+
+    Err(match candidate.finish_reason.as_deref() {
+        Some(reason @ ("SAFETY" | "RECITATION" | "BLOCKLIST" | "PROHIBITED_CONTENT" | "SPII")) => {
+            format!("Gemini withheld its response because it was safety-filtered (reason: {})", reason)
+        }
+        Some("MAX_TOKENS") => "Gemini hit its output token limit before producing any text".to_string(),
+        Some(reason) => format!("Gemini returned an empty response (finish reason: {})", reason),
+        None => "Gemini returned an empty response".to_string(),
+    })
 }
 
 // Call Gemini for single prompt (summarize)
@@ -674,10 +1216,137 @@ async fn call_gemini_chat_single(
     api_key: &str,
     model: &str,
     prompt: &str,
+    timeout_secs: u64,
 ) -> Result<String, Box<dyn std::error::Error>> {
     let messages = vec![ChatMessage {
         role: "user".to_string(),
         content: prompt.to_string(),
     }];
-    call_gemini_chat(api_key, model, &messages).await
+    call_gemini_chat(api_key, model, &messages, timeout_secs).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> Result<String, String> {
+        gemini_response_text(serde_json::from_str(json).unwrap())
+    }
+
+    #[test]
+    fn test_chat_context_fits_budget() {
+        use crate::tokenizer::HeuristicTokenCounter;
+
+        let counter = HeuristicTokenCounter;
+        let mut document = String::new();
+        for i in 0..200 {
+            document.push_str(&format!("Paragraph {} is about gardening and soil.\n", i));
+        }
+        document.push_str("The warranty expires after twelve months.\n");
+        let history: Vec<ChatMessage> = (0..50)
+            .map(|i| ChatMessage {
+                role: if i % 2 == 0 { "user" } else { "assistant" }.to_string(),
+                content: format!("turn {} {}", i, "x".repeat(200)),
+            })
+            .collect();
+
+        let context = fit_chat_context(&document, history, "When does the warranty expire?", 1000, &counter);
+        let total = counter.count_tokens(CHAT_SYSTEM_PROMPT)
+            + counter.count_tokens(&context.document)
+            + context.history.iter().map(|t| counter.count_tokens(&t.content)).sum::<usize>()
+            + counter.count_tokens(&context.message);
+        assert!(total <= 1000);
+        assert!(context.document.contains("warranty expires"));
+        // Oldest turns go first
+        assert!(!context.history.is_empty());
+        assert!(context.history.last().unwrap().content.starts_with("turn 49"));
+    }
+
+    #[tokio::test]
+    async fn test_binary_files_are_refused_clearly() {
+        let dir = std::env::temp_dir().join(format!("gist-ai-binary-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let blob = dir.join("data.xyz");
+        std::fs::write(&blob, [0x7f, b'E', b'L', b'F', 0, 0, 1]).unwrap();
+        let archive = dir.join("backup.zip");
+        std::fs::write(&archive, b"PK").unwrap();
+        let notes = dir.join("notes.custom");
+        std::fs::write(&notes, "plain notes").unwrap();
+
+        let error = get_file_content_for_ai(blob.to_str().unwrap()).await.unwrap_err();
+        assert_eq!(error.to_string(), "This file type (.xyz) can't be read as text for AI");
+        let error = get_file_content_for_ai(archive.to_str().unwrap()).await.unwrap_err();
+        assert_eq!(error.to_string(), "This file type (.zip) can't be read as text for AI");
+        assert_eq!(get_file_content_for_ai(notes.to_str().unwrap()).await.unwrap(), "plain notes");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_summarize_sample_spans_document() {
+        use crate::tokenizer::HeuristicTokenCounter;
+
+        let counter = HeuristicTokenCounter;
+        let document: String = (0..500).map(|i| format!("Line {} of the annual report.\n", i)).collect();
+        assert_eq!(sample_excerpts("short", 1000, &counter), "short");
+
+        let sample = sample_excerpts(&document, 2000, &counter);
+        assert!(counter.count_tokens(&sample) <= 2000);
+        assert!(sample.starts_with("Line 0 "));
+        assert!(sample.contains("Line 499 "));
+        assert!(sample.contains("[...]"));
+    }
+
+    #[test]
+    fn test_gemini_blocked_responses_are_explained() {
+        assert_eq!(parse(r#"{"candidates":[{"content":{"parts":[{"text":"hi"}]}}]}"#).unwrap(), "hi");
+
+        let err = parse(r#"{"promptFeedback":{"blockReason":"SAFETY"}}"#).unwrap_err();
+        assert!(err.contains("blocked") && err.contains("SAFETY"));
+
+        let err = parse(r#"{"candidates":[{"finishReason":"SAFETY","index":0}]}"#).unwrap_err();
+        assert!(err.contains("safety-filtered"));
+    }
+
+    #[test]
+    fn test_ai_override_needs_key() {
+        let mut config = AppConfig::default();
+        config.api_key = None;
+        assert!(apply_ai_override(&mut config, Some(AiProvider::Gemini), None).is_err());
+
+        // A key saved for another provider must not be sent to Gemini
+        config.ai_provider = AiProvider::GreenPT;
+        config.api_key = Some("greenpt-key".to_string());
+        assert!(apply_ai_override(&mut config.clone(), Some(AiProvider::Gemini), None).is_err());
+
+        config.ai_provider = AiProvider::Gemini;
+        config.api_key = Some("key".to_string());
+        apply_ai_override(&mut config, Some(AiProvider::Gemini), Some("gemini-1.5-flash")).unwrap();
+        assert_eq!(config.ai_provider, AiProvider::Gemini);
+        assert_eq!(config.gemini_model.as_deref(), Some("gemini-1.5-flash"));
+        assert_eq!(config.api_key.as_deref(), Some("key"));
+
+        // Keyless providers still work, without the key
+        config.openai_model = Some("local-model".to_string());
+        apply_ai_override(&mut config, Some(AiProvider::OpenAI), None).unwrap();
+        assert_eq!(config.api_key, None);
+    }
+
+    #[tokio::test]
+    async fn test_limiter_rejects_when_queue_full() {
+        let limiter = Arc::new(AiLimiter::new(1, 1));
+        let running = limiter.acquire().await.expect("free slot");
+
+        let queued = tokio::spawn({
+            let limiter = limiter.clone();
+            async move { limiter.acquire().await.is_some() }
+        });
+        while limiter.waiting.load(Ordering::SeqCst) == 0 {
+            tokio::task::yield_now().await;
+        }
+        assert!(limiter.acquire().await.is_none());
+
+        drop(running);
+        assert!(queued.await.unwrap());
+        assert_eq!(limiter.waiting.load(Ordering::SeqCst), 0);
+    }
 }
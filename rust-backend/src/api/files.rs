@@ -1,9 +1,11 @@
 use axum::{
-    extract::State,
+    extract::{Query, State},
     response::Json,
 };
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use tracing::error;
 
+use crate::storage::FileMetadata;
 use crate::AppState;
 
 #[derive(Serialize)]
@@ -52,3 +54,102 @@ pub async fn list_files(State(state): State<AppState>) -> Result<Json<FilesRespo
         files: file_infos,
     }))
 }
+
+#[derive(Deserialize)]
+pub struct FileRecordQuery {
+    path: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileRecordStatus {
+    Indexed,
+    /// On disk but absent from the index
+    NotIndexed,
+    NotFound,
+}
+
+#[derive(Serialize)]
+pub struct FileRecordResponse {
+    status: FileRecordStatus,
+    exists_on_disk: bool,
+    record: Option<FileMetadata>,
+    has_embedding: bool,
+    embedding_dimensions: Option<usize>,
+    /// Stored sections including the first (large files are split into several)
+    section_count: usize,
+    passage_count: usize,
+    /// Unix timestamp of the last index write, if known
+    last_indexed: Option<i64>,
+    /// The file's size or modified time no longer matches the record
+    stale: bool,
+}
+
+/// Inspect the stored index record for one path, to debug why it does or doesn't match searches
+pub async fn get_file_record(
+    State(state): State<AppState>,
+    Query(query): Query<FileRecordQuery>,
+) -> Result<Json<FileRecordResponse>, axum::http::StatusCode> {
+    if query.path.trim().is_empty() {
+        return Err(axum::http::StatusCode::BAD_REQUEST);
+    }
+
+    // Stored paths are canonical (see Indexer::canonical_path)
+    let index_path = crate::indexer::Indexer::canonical_path(&query.path);
+    let record = state.storage.get_file_metadata(&index_path).await.map_err(|e| {
+        error!("Error loading record for {}: {}", query.path, e);
+        axum::http::StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let disk_metadata = tokio::fs::metadata(&query.path).await.ok();
+
+    let Some(record) = record else {
+        return Ok(Json(FileRecordResponse {
+            status: if disk_metadata.is_some() { FileRecordStatus::NotIndexed } else { FileRecordStatus::NotFound },
+            exists_on_disk: disk_metadata.is_some(),
+            record: None,
+            has_embedding: false,
+            embedding_dimensions: None,
+            section_count: 0,
+            passage_count: 0,
+            last_indexed: None,
+            stale: false,
+        }));
+    };
+
+    let embedding_dimensions = if record.embedding_length > 0 {
+        match state.storage.get_embedding(&record).await {
+            Ok(embedding) => Some(embedding.len()),
+            Err(e) => {
+                error!("Error reading embedding for {}: {}", record.file_path, e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    let (last_indexed, extra_sections, passage_count) = state.storage
+        .get_index_details(&record.file_path)
+        .await
+        .map_err(|_| axum::http::StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let stale = disk_metadata.as_ref().is_some_and(|m| {
+        let modified = m.modified()
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        modified != record.modified_time || m.len() as i64 != record.file_size
+    });
+
+    Ok(Json(FileRecordResponse {
+        status: FileRecordStatus::Indexed,
+        exists_on_disk: disk_metadata.is_some(),
+        has_embedding: record.embedding_length > 0,
+        embedding_dimensions,
+        section_count: if record.embedding_length > 0 { 1 + extra_sections } else { 0 },
+        passage_count,
+        last_indexed,
+        stale,
+        record: Some(record),
+    }))
+}
@@ -1,12 +1,14 @@
 use axum::{
     extract::{State, Query},
+    http::StatusCode,
     response::Json,
 };
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::fs;
 use dirs;
 use walkdir::WalkDir;
+use tracing::{error, info, warn};
 
 use crate::AppState;
 
@@ -40,9 +42,107 @@ pub struct CreateFolderRequest {
     name: String,
 }
 
+#[derive(Deserialize)]
+pub struct RevealRequest {
+    path: String,
+}
+
+/// Show a file (or folder) selected in the platform file manager
+pub async fn reveal_item(
+    Json(request): Json<RevealRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let path = PathBuf::from(&request.path);
+    check_allowed_path(&path).await?;
+
+    let method = tokio::task::spawn_blocking(move || reveal_in_file_manager(&path))
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .map_err(|e| {
+            error!("Failed to reveal {}: {}", request.path, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "method": method
+    })))
+}
+
+#[cfg(target_os = "windows")]
+fn reveal_in_file_manager(path: &Path) -> std::io::Result<&'static str> {
+    use std::os::windows::process::CommandExt;
+
+    // Explorer only parses `/select,"path"` unescaped, and exits non-zero even on success
+    std::process::Command::new("explorer")
+        .raw_arg(format!("/select,\"{}\"", path.display()))
+        .spawn()?;
+    Ok("explorer")
+}
+
+#[cfg(target_os = "macos")]
+fn reveal_in_file_manager(path: &Path) -> std::io::Result<&'static str> {
+    let status = std::process::Command::new("open").arg("-R").arg(path).status()?;
+    if !status.success() {
+        return Err(std::io::Error::other(format!("open -R exited with {}", status)));
+    }
+    Ok("open")
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn reveal_in_file_manager(path: &Path) -> std::io::Result<&'static str> {
+    // Most desktops implement the freedesktop FileManager1 interface, which selects the item
+    let shown = std::process::Command::new("dbus-send")
+        .args([
+            "--session",
+            "--print-reply",
+            "--reply-timeout=2000",
+            "--dest=org.freedesktop.FileManager1",
+            "--type=method_call",
+            "/org/freedesktop/FileManager1",
+            "org.freedesktop.FileManager1.ShowItems",
+        ])
+        .arg(format!("array:string:{}", file_uri(path)))
+        .arg("string:")
+        .output();
+    if matches!(shown, Ok(ref output) if output.status.success()) {
+        return Ok("freedesktop");
+    }
+
+    // Otherwise just open the containing folder
+    let folder = if path.is_dir() { path } else { path.parent().unwrap_or(path) };
+    let status = std::process::Command::new("xdg-open").arg(folder).status()?;
+    if !status.success() {
+        return Err(std::io::Error::other(format!("xdg-open exited with {}", status)));
+    }
+    Ok("xdg-open")
+}
+
+/// `file://` URI for an absolute path, percent-encoding everything but unreserved characters and `/`
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn file_uri(path: &Path) -> String {
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut uri = String::from("file://");
+    for &byte in path.as_os_str().as_bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-._~".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    uri
+}
+
 #[derive(Deserialize)]
 pub struct DeleteRequest {
     path: String,
+    /// Move to the OS trash instead of deleting permanently
+    #[serde(default = "default_trash")]
+    trash: bool,
+}
+
+fn default_trash() -> bool {
+    true
 }
 
 #[derive(Deserialize)]
@@ -51,6 +151,63 @@ pub struct RenameRequest {
     new_name: String,
 }
 
+/// Roots the file browser may operate in: indexed directories plus the special folders.
+/// Canonicalized so symlinked roots compare correctly; roots that don't exist are skipped.
+#[tracing::instrument(name = "FILES", skip_all)]
+async fn allowed_roots() -> Vec<PathBuf> {
+    let mut roots: Vec<PathBuf> = match crate::config::AppConfig::load_or_default().await {
+        Ok(config) => config.indexed_directories.iter().map(PathBuf::from).collect(),
+        Err(e) => {
+            error!("Failed to load config for path allowlist: {}", e);
+            Vec::new()
+        }
+    };
+    roots.extend(
+        [dirs::home_dir(), dirs::desktop_dir(), dirs::document_dir(), dirs::download_dir()]
+            .into_iter()
+            .flatten(),
+    );
+
+    roots.iter().filter_map(|root| root.canonicalize().ok()).collect()
+}
+
+/// True if an already-canonicalized path is one of the roots or lies beneath one.
+fn is_within_roots(path: &Path, roots: &[PathBuf]) -> bool {
+    roots.iter().any(|root| path.starts_with(root))
+}
+
+/// Canonicalize `path` (resolving `..` and symlinks) and check it against the allowlist.
+/// Returns 404 if the path doesn't exist, 403 if it escapes the allowed roots.
+/// Callers keep using the path as given so responses and index lookups stay unchanged.
+#[tracing::instrument(name = "FILES", skip_all)]
+pub(crate) async fn check_allowed_path(path: &Path) -> Result<(), StatusCode> {
+    let canonical = path.canonicalize().map_err(|_| StatusCode::NOT_FOUND)?;
+    if is_within_roots(&canonical, &allowed_roots().await) {
+        Ok(())
+    } else {
+        info!("Rejected path outside allowed roots: {}", path.display());
+        Err(StatusCode::FORBIDDEN)
+    }
+}
+
+/// Like `check_allowed_path`, but only checks the parent so that a symlink entry is
+/// judged by where it lives rather than its target. Rejects paths ending in `..`.
+async fn check_allowed_entry(path: &Path) -> Result<(), StatusCode> {
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(_)) => check_allowed_path(parent).await,
+        _ => Err(StatusCode::BAD_REQUEST),
+    }
+}
+
+/// A new file or folder name must be a single path component (no separators, `.` or `..`).
+fn is_valid_entry_name(name: &str) -> bool {
+    let mut components = Path::new(name).components();
+    matches!(
+        (components.next(), components.next()),
+        (Some(std::path::Component::Normal(_)), None)
+    )
+}
+
 pub async fn browse_directory(
     Query(params): Query<BrowseRequest>,
 ) -> Result<Json<BrowseResponse>, axum::http::StatusCode> {
@@ -121,10 +278,7 @@ pub async fn browse_directory(
     });
 
     let path = PathBuf::from(&target_path);
-    
-    if !path.exists() {
-        return Err(axum::http::StatusCode::NOT_FOUND);
-    }
+    check_allowed_path(&path).await?;
 
     if !path.is_dir() {
         return Err(axum::http::StatusCode::BAD_REQUEST);
@@ -277,7 +431,12 @@ pub async fn create_folder(
     State(_state): State<AppState>,
     Json(request): Json<CreateFolderRequest>,
 ) -> Result<Json<serde_json::Value>, axum::http::StatusCode> {
+    if !is_valid_entry_name(&request.name) {
+        return Err(axum::http::StatusCode::BAD_REQUEST);
+    }
+
     let parent_path = PathBuf::from(&request.path);
+    check_allowed_path(&parent_path).await?;
     let new_folder_path = parent_path.join(&request.name);
 
     match fs::create_dir(&new_folder_path) {
@@ -295,52 +454,138 @@ pub async fn create_folder(
     }
 }
 
+#[tracing::instrument(name = "FILES", skip_all)]
 pub async fn delete_item(
     State(state): State<AppState>,
     Json(request): Json<DeleteRequest>,
 ) -> Result<Json<serde_json::Value>, axum::http::StatusCode> {
-    let path = PathBuf::from(&request.path);
+    let deletion = delete_path(&state, &request.path, request.trash)
+        .await
+        .map_err(|(status, _)| status)?;
+    spawn_hnsw_rebuild(&state);
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "method": deletion.method,
+        "trash_error": deletion.trash_error
+    })))
+}
 
-    if !path.exists() {
-        return Err(axum::http::StatusCode::NOT_FOUND);
-    }
+#[derive(Deserialize)]
+pub struct DeleteBatchRequest {
+    paths: Vec<String>,
+    #[serde(default = "default_trash")]
+    trash: bool,
+}
 
-    // Remove from index if it's a file
-    if path.is_file() {
-        if let Some(path_str) = path.to_str() {
-            let _ = state.storage.delete_file(path_str).await;
-        }
-    } else if path.is_dir() {
-        // Remove all files in directory from index
-        if let Ok(all_files) = state.storage.get_all_files().await {
-            for file in all_files {
-                if file.file_path.starts_with(&request.path) {
-                    let _ = state.storage.delete_file(&file.file_path).await;
-                }
+/// Delete several paths, reporting each one's outcome so one locked file doesn't stop the rest
+#[tracing::instrument(name = "FILES", skip_all)]
+pub async fn delete_batch(
+    State(state): State<AppState>,
+    Json(request): Json<DeleteBatchRequest>,
+) -> Result<Json<serde_json::Value>, axum::http::StatusCode> {
+    let mut results = Vec::with_capacity(request.paths.len());
+    let mut deleted = 0;
+    for path in &request.paths {
+        match delete_path(&state, path, request.trash).await {
+            Ok(deletion) => {
+                deleted += 1;
+                results.push(serde_json::json!({
+                    "path": path,
+                    "success": true,
+                    "method": deletion.method,
+                    "trash_error": deletion.trash_error
+                }));
+            }
+            Err((_, error)) => {
+                info!("Batch delete failed for {}: {}", path, error);
+                results.push(serde_json::json!({
+                    "path": path,
+                    "success": false,
+                    "error": error
+                }));
             }
         }
     }
+    if deleted > 0 {
+        spawn_hnsw_rebuild(&state);
+    }
 
-    // Delete from filesystem
-    let result = if path.is_dir() {
-        fs::remove_dir_all(&path)
-    } else {
-        fs::remove_file(&path)
-    };
+    Ok(Json(serde_json::json!({
+        "success": deleted == request.paths.len(),
+        "deleted": deleted,
+        "failed": request.paths.len() - deleted,
+        "results": results
+    })))
+}
 
-    match result {
-        Ok(_) => Ok(Json(serde_json::json!({
-            "success": true
-        }))),
-        Err(_) => Err(axum::http::StatusCode::INTERNAL_SERVER_ERROR),
+/// How a path went: "trash", or "permanent" along with why trashing failed (if it was tried)
+struct Deletion {
+    method: &'static str,
+    trash_error: Option<String>,
+}
+
+/// Trash or delete one allowed path, then drop it and everything beneath it from the index.
+/// Errors carry the status for single deletes and a message for batch results.
+async fn delete_path(state: &AppState, path_str: &str, trash: bool) -> Result<Deletion, (StatusCode, String)> {
+    let path = PathBuf::from(path_str);
+    check_allowed_entry(&path).await.map_err(|status| {
+        let message = match status {
+            StatusCode::FORBIDDEN => "Path is outside the allowed folders",
+            StatusCode::BAD_REQUEST => "Invalid path",
+            _ => "Not found",
+        };
+        (status, message.to_string())
+    })?;
+    let entry = fs::symlink_metadata(&path).map_err(|_| (StatusCode::NOT_FOUND, "Not found".to_string()))?;
+
+    // The index stores canonical paths; removing a symlink leaves its target indexed
+    let index_path = (!entry.file_type().is_symlink()).then(|| crate::indexer::Indexer::canonical_path(path_str));
+
+    // Move to trash if requested; fall back to a permanent delete if the platform has no trash
+    let mut deletion = Deletion { method: "permanent", trash_error: None };
+    if trash {
+        match trash::delete(&path) {
+            Ok(_) => deletion.method = "trash",
+            Err(e) => {
+                info!("Trash unavailable for {}, deleting permanently: {}", path_str, e);
+                deletion.trash_error = Some(e.to_string());
+            }
+        }
+    }
+    if deletion.method == "permanent" {
+        let result = if entry.is_dir() {
+            fs::remove_dir_all(&path)
+        } else {
+            fs::remove_file(&path)
+        };
+        result.map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
     }
+
+    if let Some(index_path) = index_path {
+        if entry.is_dir() {
+            if let Err(e) = state.storage.remove_directory(&index_path).await {
+                error!("Failed to remove {} from index: {}", index_path, e);
+            }
+        }
+        if let Err(e) = state.storage.delete_file(&index_path).await {
+            error!("Failed to remove {} from index: {}", index_path, e);
+        }
+    }
+
+    Ok(deletion)
 }
 
 pub async fn rename_item(
     State(state): State<AppState>,
     Json(request): Json<RenameRequest>,
 ) -> Result<Json<serde_json::Value>, axum::http::StatusCode> {
+    if !is_valid_entry_name(&request.new_name) {
+        return Err(axum::http::StatusCode::BAD_REQUEST);
+    }
+
     let old_path = PathBuf::from(&request.path);
+    check_allowed_entry(&old_path).await?;
     let parent = old_path.parent().map(|p| p.to_path_buf()).unwrap_or_else(|| PathBuf::from("."));
     let new_path = parent.join(&request.new_name);
 
@@ -348,20 +593,12 @@ pub async fn rename_item(
         return Err(axum::http::StatusCode::NOT_FOUND);
     }
 
-    // Update index if it's a file - remove old entry, will need to re-index
+    // Update index - remove old entries, will need to re-index. The index stores canonical paths.
+    let index_path = crate::indexer::Indexer::canonical_path(&request.path);
     if old_path.is_file() {
-        if let Some(old_str) = old_path.to_str() {
-            let _ = state.storage.delete_file(old_str).await;
-        }
+        let _ = state.storage.delete_file(&index_path).await;
     } else if old_path.is_dir() {
-        // Remove all files in directory from index
-        if let Ok(all_files) = state.storage.get_all_files().await {
-            for file in all_files {
-                if file.file_path.starts_with(&request.path) {
-                    let _ = state.storage.delete_file(&file.file_path).await;
-                }
-            }
-        }
+        let _ = state.storage.remove_directory(&index_path).await;
     }
 
     // Rename in filesystem
@@ -374,11 +611,185 @@ pub async fn rename_item(
     }
 }
 
+#[derive(Deserialize)]
+pub struct TransferRequest {
+    source: String,
+    destination_dir: String,
+}
+
+/// Validate a move/copy request and return (source, destination) paths.
+async fn resolve_transfer(request: &TransferRequest) -> Result<(PathBuf, PathBuf), StatusCode> {
+    let source = PathBuf::from(&request.source);
+    let destination_dir = PathBuf::from(&request.destination_dir);
+    check_allowed_entry(&source).await?;
+    check_allowed_path(&destination_dir).await?;
+
+    if !source.exists() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    if !destination_dir.is_dir() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    // Refuse to move or copy a directory into itself
+    if let (Ok(src), Ok(dst)) = (source.canonicalize(), destination_dir.canonicalize()) {
+        if source.is_dir() && dst.starts_with(&src) {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    }
+
+    let name = source.file_name().ok_or(StatusCode::BAD_REQUEST)?;
+    let destination = destination_dir.join(name);
+    if destination.exists() {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    Ok((source, destination))
+}
+
+/// Recursively copy a file or directory.
+fn copy_recursively(source: &Path, destination: &Path) -> std::io::Result<()> {
+    if !source.is_dir() {
+        return fs::copy(source, destination).map(|_| ());
+    }
+
+    for entry in WalkDir::new(source) {
+        let entry = entry.map_err(std::io::Error::other)?;
+        let relative = entry.path().strip_prefix(source).map_err(std::io::Error::other)?;
+        let target = destination.join(relative);
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&target)?;
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Rebuild the HNSW index in the background after index paths change.
+pub(crate) fn spawn_hnsw_rebuild(state: &AppState) {
+    let storage = state.storage.clone();
+    let hnsw_index = state.hnsw_index.clone();
+    let hnsw_min_corpus_size = state.config.performance_mode.profile().hnsw_min_corpus_size;
+    // Must match how storage was opened, so not reloaded from disk like the other parameters
+    let similarity_metric = state.config.similarity_metric;
+    tokio::spawn(async move {
+        if let Ok(embeddings) = storage.get_all_embeddings().await {
+            if embeddings.len() < hnsw_min_corpus_size {
+                *hnsw_index.write().await = None;
+            } else if !embeddings.is_empty() {
+                let dimensions = embeddings[0].1.len();
+                let params = crate::hnsw_index::HnswParams {
+                    metric: similarity_metric,
+                    ..crate::hnsw_index::HnswParams::from_config(
+                        &crate::config::AppConfig::load_or_default().await.unwrap_or_default()
+                    )
+                };
+                let mut new_index = crate::hnsw_index::HnswIndex::new(dimensions).with_params(params);
+                if let Err(e) = new_index.rebuild_from_embeddings(embeddings) {
+                    warn!("[HNSW] {}", e);
+                }
+                *hnsw_index.write().await = Some(new_index);
+            }
+        }
+    });
+}
+
+pub async fn move_item(
+    State(state): State<AppState>,
+    Json(request): Json<TransferRequest>,
+) -> Result<Json<serde_json::Value>, axum::http::StatusCode> {
+    let (source, destination) = resolve_transfer(&request).await?;
+    // The index stores canonical paths; resolve the source while it still exists
+    let index_source = crate::indexer::Indexer::canonical_path(&request.source);
+
+    let (src, dst) = (source.clone(), destination.clone());
+    let result = tokio::task::spawn_blocking(move || match fs::rename(&src, &dst) {
+        Ok(_) => Ok("rename"),
+        // rename can't cross filesystems; copy then remove the original instead
+        Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {
+            copy_recursively(&src, &dst)?;
+            if src.is_dir() {
+                fs::remove_dir_all(&src)?;
+            } else {
+                fs::remove_file(&src)?;
+            }
+            Ok("copy_delete")
+        }
+        Err(e) => Err(e),
+    })
+    .await
+    .map_err(|_| axum::http::StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let method = match result {
+        Ok(method) => method,
+        Err(e) => {
+            error!("[FILES] Failed to move {} to {}: {}", source.display(), destination.display(), e);
+            return Err(axum::http::StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    // Re-point index entries; embeddings stay valid since contents are unchanged
+    let new_path = destination.to_string_lossy().to_string();
+    let index_destination = crate::indexer::Indexer::canonical_path(&new_path);
+    match state.storage.move_path(&index_source, &index_destination).await {
+        Ok(count) if count > 0 => spawn_hnsw_rebuild(&state),
+        Ok(_) => {}
+        Err(e) => error!("[FILES] Failed to update index after move: {}", e),
+    }
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "new_path": new_path,
+        "method": method
+    })))
+}
+
+pub async fn copy_item(
+    State(state): State<AppState>,
+    Json(request): Json<TransferRequest>,
+) -> Result<Json<serde_json::Value>, axum::http::StatusCode> {
+    let (source, destination) = resolve_transfer(&request).await?;
+
+    let (src, dst) = (source.clone(), destination.clone());
+    let result = tokio::task::spawn_blocking(move || copy_recursively(&src, &dst))
+        .await
+        .map_err(|_| axum::http::StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if let Err(e) = result {
+        error!("[FILES] Failed to copy {} to {}: {}", source.display(), destination.display(), e);
+        return Err(axum::http::StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    // Index the copy by sharing the source's embeddings
+    let new_path = destination.to_string_lossy().to_string();
+    let index_source = crate::indexer::Indexer::canonical_path(&request.source);
+    let index_destination = crate::indexer::Indexer::canonical_path(&new_path);
+    match state.storage.copy_path(&index_source, &index_destination).await {
+        Ok(count) if count > 0 => spawn_hnsw_rebuild(&state),
+        Ok(_) => {}
+        Err(e) => error!("[FILES] Failed to index copy: {}", e),
+    }
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "new_path": new_path
+    })))
+}
+
 #[derive(Deserialize)]
 pub struct FileSearchRequest {
     query: String,
     path: Option<String>,
     limit: Option<usize>,
+    #[serde(default)]
+    case_sensitive: bool,
+    /// Only match whole words (regex word boundaries)
+    #[serde(default)]
+    whole_word: bool,
+    /// Treat `query` as a regular expression
+    #[serde(default)]
+    regex: bool,
 }
 
 #[derive(Serialize)]
@@ -387,10 +798,28 @@ pub struct FileSearchResponse {
     count: usize,
 }
 
+/// Build the name/content matcher for a browser search
+fn build_search_matcher(query: &str, case_sensitive: bool, whole_word: bool, is_regex: bool) -> Result<regex::Regex, regex::Error> {
+    let pattern = if is_regex { query.to_string() } else { regex::escape(query) };
+    let pattern = if whole_word { format!(r"\b(?:{})\b", pattern) } else { pattern };
+    regex::RegexBuilder::new(&pattern)
+        .case_insensitive(!case_sensitive)
+        .size_limit(1 << 20)
+        .build()
+}
+
+/// Read up to `max_bytes` of a file as (lossy) UTF-8
+fn read_file_prefix(path: &Path, max_bytes: u64) -> std::io::Result<String> {
+    use std::io::Read;
+    let mut buffer = Vec::new();
+    fs::File::open(path)?.take(max_bytes).read_to_end(&mut buffer)?;
+    Ok(String::from_utf8_lossy(&buffer).into_owned())
+}
+
 pub async fn search_files(
     Query(params): Query<FileSearchRequest>,
 ) -> Result<Json<FileSearchResponse>, axum::http::StatusCode> {
-    let search_query = params.query.to_lowercase();
+    let search_query = params.query.as_str();
     if search_query.is_empty() {
         return Ok(Json(FileSearchResponse {
             results: Vec::new(),
@@ -406,9 +835,20 @@ pub async fn search_files(
     
     let limit = params.limit.unwrap_or(100);
     let mut results = Vec::new();
+
+    let matcher = build_search_matcher(search_query, params.case_sensitive, params.whole_word, params.regex)
+        .map_err(|e| {
+            info!("Rejecting invalid search pattern '{}': {}", search_query, e);
+            StatusCode::BAD_REQUEST
+        })?;
+    let config = crate::config::AppConfig::load_or_default().await.unwrap_or_default();
+    let text_extensions: Vec<String> = config.content_search_extensions.iter()
+        .map(|e| e.trim_start_matches('.').to_lowercase())
+        .collect();
     
     let path_buf = PathBuf::from(&search_path);
-    if !path_buf.exists() || !path_buf.is_dir() {
+    check_allowed_path(&path_buf).await?;
+    if !path_buf.is_dir() {
         return Err(axum::http::StatusCode::NOT_FOUND);
     }
     
@@ -426,10 +866,9 @@ pub async fn search_files(
             .unwrap_or_else(|| "unknown".to_string());
         
         let is_directory = entry_path.is_dir();
-        let name_lower = name.to_lowercase();
         
         // Check filename match
-        let matches_name = name_lower.contains(&search_query);
+        let matches_name = matcher.is_match(&name);
         
         // For files, also check content if it's a text file
         let matches_content = if !is_directory {
@@ -440,19 +879,13 @@ pub async fn search_files(
                 .unwrap_or_default();
             
             // Check if it's a text file we can search
-            let is_text_file = matches!(
-                ext.as_str(),
-                "txt" | "md" | "js" | "ts" | "py" | "rs" | "java" | "cpp" | "c" | "h" | "hpp"
-                    | "json" | "xml" | "html" | "css" | "yaml" | "yml" | "toml" | "ini" | "log"
-            );
+            let is_text_file = text_extensions.contains(&ext);
             
-            if is_text_file {
-                // Try to read and search file content
-                if let Ok(content) = fs::read_to_string(entry_path) {
-                    content.to_lowercase().contains(&search_query)
-                } else {
-                    false
-                }
+            if !matches_name && is_text_file {
+                // Search only the first `content_search_max_bytes` so huge logs can't stall the request
+                read_file_prefix(entry_path, config.content_search_max_bytes)
+                    .map(|content| matcher.is_match(&content))
+                    .unwrap_or(false)
             } else {
                 false
             }
@@ -505,3 +938,45 @@ pub async fn search_files(
         results,
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    fn test_file_uri_encodes_path() {
+        assert_eq!(file_uri(Path::new("/home/me/My Notes/ä.txt")), "file:///home/me/My%20Notes/%C3%A4.txt");
+    }
+
+    #[test]
+    fn test_is_within_roots() {
+        let roots = vec![PathBuf::from("/home/user/Documents")];
+        assert!(is_within_roots(Path::new("/home/user/Documents"), &roots));
+        assert!(is_within_roots(Path::new("/home/user/Documents/notes/a.txt"), &roots));
+        assert!(!is_within_roots(Path::new("/home/user/Documents-old"), &roots));
+        assert!(!is_within_roots(Path::new("/etc/passwd"), &roots));
+    }
+
+    #[test]
+    fn test_build_search_matcher() {
+        let matcher = build_search_matcher("report", false, true, false).unwrap();
+        assert!(matcher.is_match("Q3 Report.pdf"));
+        assert!(!matcher.is_match("reports.pdf"));
+
+        let matcher = build_search_matcher("a.b", true, false, false).unwrap();
+        assert!(matcher.is_match("a.b"));
+        assert!(!matcher.is_match("axb"));
+
+        assert!(build_search_matcher("(unclosed", false, false, true).is_err());
+    }
+
+    #[test]
+    fn test_is_valid_entry_name() {
+        assert!(is_valid_entry_name("New Folder"));
+        assert!(!is_valid_entry_name(".."));
+        assert!(!is_valid_entry_name("../escape"));
+        assert!(!is_valid_entry_name("a/b"));
+        assert!(!is_valid_entry_name(""));
+    }
+}
@@ -1,14 +1,166 @@
 use axum::{
-    extract::State,
-    response::Json,
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Path, Query, State,
+    },
+    response::{Json, Response},
 };
 use serde::{Deserialize, Serialize};
+use tracing::{debug, error, info, warn};
 
 use crate::AppState;
+use crate::indexer::{IndexRunStats, SyncStats};
+
+/// How many finished index jobs are kept for `GET /api/index/jobs`
+const INDEX_JOB_HISTORY: usize = 20;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IndexJobState {
+    /// Waiting for an earlier job to finish
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl IndexJobState {
+    fn is_finished(self) -> bool {
+        matches!(self, Self::Completed | Self::Failed | Self::Cancelled)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IndexJobKind {
+    /// `POST /api/index/start`: index one directory
+    Index,
+    /// `POST /api/index/sync`: bring every configured directory up to date
+    Sync,
+}
+
+/// One `POST /api/index/start` or `POST /api/index/sync` request and how it went
+#[derive(Clone, Serialize)]
+pub struct IndexJob {
+    job_id: u64,
+    kind: IndexJobKind,
+    /// The directory being indexed; None for syncs, which cover all configured directories
+    directory: Option<String>,
+    resume: bool,
+    state: IndexJobState,
+    created_at: i64,
+    started_at: Option<i64>,
+    finished_at: Option<i64>,
+    files_indexed: Option<usize>,
+    /// Stats of the finished run, once completed
+    stats: Option<IndexRunStats>,
+    /// Counts of a finished sync
+    sync: Option<SyncStats>,
+    error: Option<String>,
+    /// Set to stop the run at its next batch boundary
+    #[serde(skip)]
+    cancel: std::sync::Arc<tokio::sync::watch::Sender<bool>>,
+}
+
+/// Index jobs, oldest first. Jobs run one at a time; later ones queue behind `run_lock`, the
+/// index writer lock that the startup sync, file watcher writes and idle maintenance also take.
+#[derive(Default)]
+pub struct IndexJobs {
+    next_id: u64,
+    jobs: std::collections::VecDeque<IndexJob>,
+    run_lock: std::sync::Arc<tokio::sync::Mutex<()>>,
+}
+
+impl IndexJobs {
+    /// Queue jobs behind an index writer lock shared with other writers
+    pub fn with_run_lock(run_lock: std::sync::Arc<tokio::sync::Mutex<()>>) -> Self {
+        Self { run_lock, ..Self::default() }
+    }
+
+    /// The index writer lock, held while a job runs
+    pub fn run_lock(&self) -> std::sync::Arc<tokio::sync::Mutex<()>> {
+        self.run_lock.clone()
+    }
+
+    /// Queue a job, returning its id and the receiver its run watches for cancellation
+    fn create(
+        &mut self,
+        kind: IndexJobKind,
+        directory: Option<&str>,
+        resume: bool,
+    ) -> (u64, tokio::sync::watch::Receiver<bool>) {
+        self.next_id += 1;
+        let (cancel, cancelled) = tokio::sync::watch::channel(false);
+        self.jobs.push_back(IndexJob {
+            job_id: self.next_id,
+            kind,
+            directory: directory.map(str::to_string),
+            resume,
+            state: IndexJobState::Queued,
+            created_at: chrono::Utc::now().timestamp(),
+            started_at: None,
+            finished_at: None,
+            files_indexed: None,
+            stats: None,
+            sync: None,
+            error: None,
+            cancel: std::sync::Arc::new(cancel),
+        });
+        // Forget the oldest finished jobs beyond the history limit; unfinished ones are always kept
+        let mut finished = self.jobs.iter().filter(|job| job.state.is_finished()).count();
+        self.jobs.retain(|job| {
+            if finished > INDEX_JOB_HISTORY && job.state.is_finished() {
+                finished -= 1;
+                return false;
+            }
+            true
+        });
+        (self.next_id, cancelled)
+    }
+
+    fn get_mut(&mut self, job_id: u64) -> Option<&mut IndexJob> {
+        self.jobs.iter_mut().find(|job| job.job_id == job_id)
+    }
+
+    /// Apply `update` to a job unless it was cancelled in the meantime
+    fn update(&mut self, job_id: u64, update: impl FnOnce(&mut IndexJob)) {
+        if let Some(job) = self.get_mut(job_id).filter(|job| job.state != IndexJobState::Cancelled) {
+            update(job);
+        }
+    }
+
+    /// Mark a job that got its turn at `run_lock` as running, or as cancelled if `stopped`.
+    /// Returns whether it should run.
+    fn begin(&mut self, job_id: u64, stopped: bool) -> bool {
+        self.update(job_id, |job| {
+            if stopped {
+                job.state = IndexJobState::Cancelled;
+                job.finished_at = Some(chrono::Utc::now().timestamp());
+            } else {
+                job.state = IndexJobState::Running;
+                job.started_at = Some(chrono::Utc::now().timestamp());
+            }
+        });
+        !stopped
+    }
+
+    /// Mark a running job failed with `error`
+    fn fail(&mut self, job_id: u64, error: &anyhow::Error) {
+        self.update(job_id, |job| {
+            job.state = IndexJobState::Failed;
+            job.finished_at = Some(chrono::Utc::now().timestamp());
+            job.error = Some(error.to_string());
+        });
+    }
+}
 
 #[derive(Deserialize)]
 pub struct StartIndexingRequest {
     directory: String,
+    /// Continue an interrupted run from its saved cursor instead of starting over
+    #[serde(default)]
+    resume: bool,
 }
 
 #[derive(Serialize)]
@@ -27,11 +179,11 @@ pub async fn start_indexing(
 ) -> Result<Json<serde_json::Value>, axum::http::StatusCode> {
     // Create indexer with progress tracker
     let embedding_service = std::sync::Arc::new(
-        crate::embedding::EmbeddingService::new(state.config.embedding_model.clone())
+        crate::embedding::EmbeddingService::from_config(&state.config)
     );
     
     let parser_registry = std::sync::Arc::new(
-        crate::parsers::ParserRegistry::new(&state.config.file_type_filters)
+        crate::parsers::ParserRegistry::from_config(&state.config)
     );
     
     let indexer = crate::indexer::Indexer::new(
@@ -39,42 +191,215 @@ pub async fn start_indexing(
         embedding_service,
         parser_registry,
         state.config.clone(),
-    ).with_progress_tracker(state.indexing_progress.clone());
+    ).with_progress_tracker(state.indexing_progress.clone())
+    .with_progress_channel(state.indexing_progress_tx.clone())
+    .with_stats_tracker(state.last_index_stats.clone())
+    .with_cursors(crate::index_cursor::IndexCursors::new(&crate::config::AppConfig::data_dir()))
+    .with_shutdown(state.shutdown.subscribe());
 
     // Start indexing in background
     let directory = request.directory.clone();
+    let resume = request.resume;
     let storage_clone = state.storage.clone();
     let hnsw_index_clone = state.hnsw_index.clone();
+    let hnsw_min_corpus_size = state.config.performance_mode.profile().hnsw_min_corpus_size;
+    // Must match how storage was opened, so not reloaded from disk like the other parameters
+    let similarity_metric = state.config.similarity_metric;
+    let (job_id, cancelled) = state.index_jobs.lock().unwrap().create(IndexJobKind::Index, Some(&directory), resume);
+    let indexer = indexer.with_cancel(cancelled.clone());
+    let jobs = state.index_jobs.clone();
+    let run_lock = jobs.lock().unwrap().run_lock.clone();
+    let last_index_stats = state.last_index_stats.clone();
+    let shutdown = state.shutdown.subscribe();
     tokio::spawn(async move {
-        match indexer.index_directory(&directory).await {
+        let _running = run_lock.lock().await;
+        if !jobs.lock().unwrap().begin(job_id, *shutdown.borrow() || *cancelled.borrow()) {
+            return;
+        }
+
+        let result = if resume {
+            indexer.resume_directory(&directory).await
+        } else {
+            indexer.index_directory(&directory).await
+        };
+        // Rebuild the HNSW index even after a failed or cancelled run, which may have stored some files
+        if let Ok(embeddings) = storage_clone.get_all_embeddings().await {
+            if embeddings.len() < hnsw_min_corpus_size {
+                // Small corpus in lightweight mode: linear search is cheap, skip the index's memory
+                *hnsw_index_clone.write().await = None;
+                info!("[HNSW] Skipped index for {} items (linear search below {})", embeddings.len(), hnsw_min_corpus_size);
+            } else if !embeddings.is_empty() {
+                let dimensions = embeddings[0].1.len();
+                let params = crate::hnsw_index::HnswParams {
+                    metric: similarity_metric,
+                    ..crate::hnsw_index::HnswParams::from_config(
+                        &crate::config::AppConfig::load_or_default().await.unwrap_or_default()
+                    )
+                };
+                let mut new_index = crate::hnsw_index::HnswIndex::new(dimensions).with_params(params);
+                // Rejected vectors are reported by verify_index; the rest are still searchable
+                if let Err(e) = new_index.rebuild_from_embeddings(embeddings) {
+                    warn!("[HNSW] {}", e);
+                }
+                let mut index_guard = hnsw_index_clone.write().await;
+                *index_guard = Some(new_index);
+                info!("[HNSW] Index rebuilt with {} items", index_guard.as_ref().map(|i| i.len()).unwrap_or(0));
+            }
+        }
+
+        match result {
             Ok(count) => {
-                println!("Indexed {} files from {}", count, directory);
-                
-                // Rebuild HNSW index after indexing completes
-                if let Ok(embeddings) = storage_clone.get_all_embeddings().await {
-                    if !embeddings.is_empty() {
-                        let dimensions = embeddings[0].1.len();
-                        let mut new_index = crate::hnsw_index::HnswIndex::new(dimensions);
-                        if new_index.rebuild_from_embeddings(embeddings).is_ok() {
-                            let mut index_guard = hnsw_index_clone.write().await;
-                            *index_guard = Some(new_index);
-                            eprintln!("[HNSW] Index rebuilt with {} items", index_guard.as_ref().map(|i| i.len()).unwrap_or(0));
-                        }
-                    }
+                debug!("Indexed {} files from {}", count, directory);
+                let stats = last_index_stats.read().await.clone();
+                jobs.lock().unwrap().update(job_id, |job| {
+                    job.state = IndexJobState::Completed;
+                    job.finished_at = Some(chrono::Utc::now().timestamp());
+                    job.files_indexed = Some(count);
+                    job.stats = stats;
+                });
+            }
+            Err(e) => {
+                error!("Indexing error: {}", e);
+                jobs.lock().unwrap().fail(job_id, &e);
+            }
+        }
+    });
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "message": "Indexing started",
+        "job_id": job_id
+    })))
+}
+
+/// Wait until no index job is running. Called on shutdown, after which the running job stops at
+/// its next batch boundary and queued ones cancel themselves.
+pub async fn wait_for_index_jobs(jobs: &std::sync::Mutex<IndexJobs>) {
+    let run_lock = jobs.lock().unwrap().run_lock.clone();
+    let _idle = run_lock.lock().await;
+}
+
+/// State and, once finished, stats or error of one index job; 404 if unknown or aged out of the history
+pub async fn get_index_job(
+    State(state): State<AppState>,
+    Path(job_id): Path<u64>,
+) -> Result<Json<IndexJob>, axum::http::StatusCode> {
+    let mut jobs = state.index_jobs.lock().unwrap();
+    let job = jobs.get_mut(job_id).ok_or(axum::http::StatusCode::NOT_FOUND)?;
+    Ok(Json(job.clone()))
+}
+
+/// Recent index jobs, newest first
+pub async fn list_index_jobs(
+    State(state): State<AppState>,
+) -> Json<Vec<IndexJob>> {
+    Json(state.index_jobs.lock().unwrap().jobs.iter().rev().cloned().collect())
+}
+
+/// Stop a queued or running index job. A running job finishes its current batch (so no
+/// embedding or store task is left behind), saves its checkpoint and rebuilds the HNSW index;
+/// continue it later with `"resume": true`.
+pub async fn cancel_index_job(
+    State(state): State<AppState>,
+    Path(job_id): Path<u64>,
+) -> Result<Json<serde_json::Value>, axum::http::StatusCode> {
+    {
+        let mut jobs = state.index_jobs.lock().unwrap();
+        let job = jobs.get_mut(job_id).ok_or(axum::http::StatusCode::NOT_FOUND)?;
+        if job.state.is_finished() {
+            return Err(axum::http::StatusCode::CONFLICT);
+        }
+        job.cancel.send_replace(true);
+        job.state = IndexJobState::Cancelled;
+        job.finished_at = Some(chrono::Utc::now().timestamp());
+    }
+    info!("Cancelled index job {}", job_id);
+    Ok(Json(serde_json::json!({ "success": true })))
+}
+
+/// Incrementally sync the configured directories (the startup scan, on demand) as an index
+/// job: only new or changed files are embedded and deleted ones are purged. Returns the
+/// `job_id`; the job's `sync` holds the counts once it completes.
+pub async fn sync_index(
+    State(state): State<AppState>,
+) -> Result<Json<serde_json::Value>, axum::http::StatusCode> {
+    let config = crate::config::AppConfig::load_or_default()
+        .await
+        .map_err(|_| axum::http::StatusCode::INTERNAL_SERVER_ERROR)?;
+    if config.indexed_directories.is_empty() {
+        return Ok(Json(serde_json::json!({
+            "success": false,
+            "error": "No indexed directories are configured"
+        })));
+    }
+
+    let embedding_service = std::sync::Arc::new(
+        crate::embedding::EmbeddingService::from_config(&config)
+    );
+    let parser_registry = std::sync::Arc::new(
+        crate::parsers::ParserRegistry::from_config(&config)
+    );
+    let (job_id, cancelled) = state.index_jobs.lock().unwrap().create(IndexJobKind::Sync, None, false);
+    let indexer = crate::indexer::Indexer::new(
+        state.storage.clone(),
+        embedding_service,
+        parser_registry,
+        std::sync::Arc::new(config),
+    ).with_progress_tracker(state.indexing_progress.clone())
+    .with_progress_channel(state.indexing_progress_tx.clone())
+    .with_shutdown(state.shutdown.subscribe())
+    .with_cancel(cancelled.clone());
+
+    let jobs = state.index_jobs.clone();
+    let run_lock = jobs.lock().unwrap().run_lock.clone();
+    let shutdown = state.shutdown.subscribe();
+    tokio::spawn(async move {
+        let _running = run_lock.lock().await;
+        if !jobs.lock().unwrap().begin(job_id, *shutdown.borrow() || *cancelled.borrow()) {
+            return;
+        }
+
+        match indexer.sync_indexed_directories().await {
+            Ok(stats) => {
+                if stats.added + stats.updated + stats.removed > 0 {
+                    crate::api::files_browser::spawn_hnsw_rebuild(&state);
                 }
+                jobs.lock().unwrap().update(job_id, |job| {
+                    job.state = IndexJobState::Completed;
+                    job.finished_at = Some(chrono::Utc::now().timestamp());
+                    job.files_indexed = Some(stats.added + stats.updated);
+                    job.sync = Some(stats);
+                });
             }
             Err(e) => {
-                eprintln!("Indexing error: {}", e);
+                error!("Index sync failed: {}", e);
+                // An interrupted sync keeps the files it already indexed
+                crate::api::files_browser::spawn_hnsw_rebuild(&state);
+                jobs.lock().unwrap().fail(job_id, &e);
             }
         }
     });
 
     Ok(Json(serde_json::json!({
         "success": true,
-        "message": "Indexing started"
+        "message": "Sync started",
+        "job_id": job_id
     })))
 }
 
+/// Directory index runs that were interrupted and can be continued with `resume: true`
+pub async fn get_resumable_indexing() -> Json<serde_json::Value> {
+    let cursors = crate::index_cursor::IndexCursors::new(&crate::config::AppConfig::data_dir())
+        .load_all()
+        .await;
+    let mut cursors: Vec<_> = cursors.into_values().collect();
+    cursors.sort_by(|a, b| a.directory.cmp(&b.directory));
+    Json(serde_json::json!({
+        "success": true,
+        "runs": cursors
+    }))
+}
+
 pub async fn get_index_status(
     State(state): State<AppState>,
 ) -> Json<IndexStatusResponse> {
@@ -101,6 +426,227 @@ pub async fn get_index_status(
     }
 }
 
+/// Stream indexing progress over a WebSocket. Sends `{"type":"idle"}` if nothing is running yet,
+/// `{"type":"progress", ...}` on every change, then `{"type":"complete"}` and closes when the run ends.
+pub async fn index_progress_ws(
+    ws: WebSocketUpgrade,
+    State(state): State<AppState>,
+) -> Response {
+    let progress_rx = state.indexing_progress_tx.subscribe();
+    ws.on_upgrade(move |socket| stream_index_progress(socket, progress_rx))
+}
+
+async fn stream_index_progress(
+    mut socket: WebSocket,
+    mut progress_rx: tokio::sync::watch::Receiver<Option<crate::indexer::IndexingProgress>>,
+) {
+    let mut seen_run = false;
+    loop {
+        let message = match progress_rx.borrow_and_update().clone() {
+            Some(progress) => {
+                seen_run = true;
+                serde_json::json!({ "type": "progress", "progress": progress })
+            }
+            None if seen_run => serde_json::json!({ "type": "complete" }),
+            None => serde_json::json!({ "type": "idle" }),
+        };
+        let finished = message["type"] == "complete";
+
+        if socket.send(Message::Text(message.to_string())).await.is_err() {
+            debug!("Progress WebSocket client disconnected");
+            return;
+        }
+        if finished {
+            break;
+        }
+
+        tokio::select! {
+            changed = progress_rx.changed() => {
+                if changed.is_err() {
+                    break;
+                }
+            }
+            incoming = socket.recv() => {
+                // Clients don't send anything meaningful; stop once they go away
+                if matches!(incoming, None | Some(Err(_)) | Some(Ok(Message::Close(_)))) {
+                    debug!("Progress WebSocket client disconnected");
+                    return;
+                }
+            }
+        }
+    }
+
+    let _ = socket.close().await;
+}
+
+#[derive(Serialize)]
+pub struct IndexStatsResponse {
+    last_run: Option<crate::indexer::IndexRunStats>,
+}
+
+pub async fn get_index_stats(
+    State(state): State<AppState>,
+) -> Json<IndexStatsResponse> {
+    Json(IndexStatsResponse {
+        last_run: state.last_index_stats.read().await.clone(),
+    })
+}
+
+/// Dashboard breakdowns of the whole index: files by extension, size per indexed directory, and file ages
+pub async fn get_index_aggregates(
+    State(state): State<AppState>,
+) -> Result<Json<crate::storage::IndexAggregates>, axum::http::StatusCode> {
+    let config = crate::config::AppConfig::load_or_default()
+        .await
+        .map_err(|_| axum::http::StatusCode::INTERNAL_SERVER_ERROR)?;
+    let now = chrono::Utc::now().timestamp();
+    state.storage.aggregate(&config.indexed_directories, now)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            error!("Failed to aggregate index statistics: {}", e);
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+/// Feed length when `limit` isn't given, and the most one request can ask for
+const DEFAULT_RECENT_LIMIT: usize = 20;
+const MAX_RECENT_LIMIT: usize = 500;
+
+#[derive(Deserialize)]
+pub struct RecentIndexedQuery {
+    limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct RecentIndexedFile {
+    file_path: String,
+    file_name: String,
+    file_type: String,
+    file_size: i64,
+    modified_time: i64,
+    /// Unix timestamp when the indexer last wrote the file's row
+    indexed_at: i64,
+    content_indexed: bool,
+}
+
+/// Activity feed: the files the indexer (or watcher) wrote most recently, newest first
+pub async fn get_recently_indexed(
+    State(state): State<AppState>,
+    Query(query): Query<RecentIndexedQuery>,
+) -> Result<Json<serde_json::Value>, axum::http::StatusCode> {
+    let limit = query.limit.unwrap_or(DEFAULT_RECENT_LIMIT).clamp(1, MAX_RECENT_LIMIT);
+    let files: Vec<RecentIndexedFile> = state.storage.recently_indexed(limit)
+        .await
+        .map_err(|e| {
+            error!("Failed to load recently indexed files: {}", e);
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .into_iter()
+        .map(|(metadata, indexed_at)| RecentIndexedFile {
+            content_indexed: metadata.embedding_length > 0,
+            file_path: metadata.file_path,
+            file_name: metadata.file_name,
+            file_type: metadata.file_type,
+            file_size: metadata.file_size,
+            modified_time: metadata.modified_time,
+            indexed_at,
+        })
+        .collect();
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "files": files
+    })))
+}
+
+#[derive(Deserialize)]
+pub struct ClearDirectoryRequest {
+    path: String,
+}
+
+/// Drop everything indexed under one directory, reclaim its embedding space when nothing
+/// holds the index writer lock, and rebuild the HNSW index. Skipped compaction is left to
+/// maintenance.
+pub async fn clear_directory(
+    State(state): State<AppState>,
+    Json(request): Json<ClearDirectoryRequest>,
+) -> Result<Json<serde_json::Value>, axum::http::StatusCode> {
+    if request.path.trim().is_empty() {
+        return Err(axum::http::StatusCode::BAD_REQUEST);
+    }
+
+    // Index jobs, syncs and the file watcher append to embeddings.bin under this lock
+    let run_lock = state.index_jobs.lock().unwrap().run_lock();
+    let writing = run_lock.try_lock().ok();
+
+    let removed = state.storage.remove_directory(&request.path)
+        .await
+        .map_err(|_| axum::http::StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let bytes_reclaimed = if removed > 0 && writing.is_some() {
+        state.storage.compact_embeddings().await.unwrap_or_else(|e| {
+            error!("Embedding compaction failed: {}", e);
+            0
+        })
+    } else {
+        0
+    };
+    drop(writing);
+
+    if removed > 0 {
+        crate::api::files_browser::spawn_hnsw_rebuild(&state);
+    }
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "removed": removed,
+        "bytes_reclaimed": bytes_reclaimed
+    })))
+}
+
+#[derive(Deserialize)]
+pub struct RemoveFromIndexRequest {
+    file_path: String,
+}
+
+/// Drop one file from the index, leaving it on disk (unlike `/api/files/delete`). `found` is
+/// false when the file wasn't indexed. Syncs and the file watcher keep it out until it's modified;
+/// its embedding space is reclaimed by maintenance compaction.
+pub async fn remove_from_index(
+    State(state): State<AppState>,
+    Json(request): Json<RemoveFromIndexRequest>,
+) -> Result<Json<serde_json::Value>, axum::http::StatusCode> {
+    if request.file_path.trim().is_empty() {
+        return Err(axum::http::StatusCode::BAD_REQUEST);
+    }
+
+    let remove = |path: String| {
+        let storage = state.storage.clone();
+        async move {
+            storage.remove_file(&path).await.map_err(|e| {
+                error!("Failed to remove {} from index: {}", path, e);
+                axum::http::StatusCode::INTERNAL_SERVER_ERROR
+            })
+        }
+    };
+    // Indexed paths are canonical; accept the path as the user sees it too
+    let mut found = remove(request.file_path.clone()).await?;
+    let canonical = crate::indexer::Indexer::canonical_path(&request.file_path);
+    if !found && canonical != request.file_path {
+        found = remove(canonical).await?;
+    }
+
+    if found {
+        crate::api::files_browser::spawn_hnsw_rebuild(&state);
+    }
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "found": found
+    })))
+}
+
 pub async fn clear_index(
     State(state): State<AppState>,
 ) -> Result<Json<serde_json::Value>, axum::http::StatusCode> {
@@ -120,3 +666,26 @@ pub async fn clear_index(
         "message": "Index cleared successfully"
     })))
 }
+
+/// Recover from a missing or truncated embeddings.bin: drop all vectors and mark every
+/// embedded file for reindexing by the next sync, keeping the indexed file list. Answers 409
+/// while an index job, sync or the file watcher holds the index writer lock.
+pub async fn reset_embeddings(
+    State(state): State<AppState>,
+) -> Result<Json<serde_json::Value>, axum::http::StatusCode> {
+    let run_lock = state.index_jobs.lock().unwrap().run_lock();
+    let Ok(_writing) = run_lock.try_lock() else {
+        return Err(axum::http::StatusCode::CONFLICT);
+    };
+    let marked = state.storage.reset_embeddings().await.map_err(|e| {
+        error!("Failed to reset embeddings: {}", e);
+        axum::http::StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    *state.hnsw_index.write().await = None;
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "files_marked": marked,
+        "message": "Embeddings reset; run an index sync to re-embed the marked files"
+    })))
+}
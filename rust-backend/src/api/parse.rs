@@ -3,9 +3,15 @@ use axum::{
     response::Json,
 };
 use serde::Serialize;
+use std::sync::Arc;
+use tracing::{debug, warn};
 
 use crate::AppState;
-use crate::query_parser::{ParsedQuery, QueryParser};
+use crate::query_parser::{ParsedQuery, QueryAnalysis, QueryCache, QueryParser};
+use crate::spell::Vocabulary;
+
+/// How long a counted spell-correction vocabulary is reused before recounting the corpus
+const VOCABULARY_TTL: std::time::Duration = std::time::Duration::from_secs(300);
 
 #[derive(Serialize)]
 pub struct ParseResponse {
@@ -13,8 +19,16 @@ pub struct ParseResponse {
     data: ParsedQuery,
 }
 
+/// A parse plus the complexity score and routing decision behind it
+#[derive(Serialize)]
+pub struct AnalyzeResponse {
+    parsed: ParsedQuery,
+    #[serde(flatten)]
+    analysis: QueryAnalysis,
+}
+
 pub async fn parse_query(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
     Json(request): Json<serde_json::Value>,
 ) -> Result<Json<ParsedQuery>, axum::http::StatusCode> {
     let query = request
@@ -22,8 +36,7 @@ pub async fn parse_query(
         .and_then(|v| v.as_str())
         .ok_or(axum::http::StatusCode::BAD_REQUEST)?;
 
-    // Create parser with LLM model (use llama3.2:1b for parsing)
-    let parser = QueryParser::new("llama3.2:1b".to_string());
+    let parser = build_parser(&state).await;
     
     // Parse query (will try pattern matching first, then LLM if needed)
     // If LLM fails, it falls back to pattern matching automatically
@@ -31,3 +44,62 @@ pub async fn parse_query(
 
     Ok(Json(parsed))
 }
+
+/// Parse a query and report why the LLM was or wasn't used, for debugging ignored filters
+pub async fn analyze_query(
+    State(state): State<AppState>,
+    Json(request): Json<serde_json::Value>,
+) -> Result<Json<AnalyzeResponse>, axum::http::StatusCode> {
+    let query = request
+        .get("query")
+        .and_then(|v| v.as_str())
+        .ok_or(axum::http::StatusCode::BAD_REQUEST)?;
+
+    let parser = build_parser(&state).await;
+    let analysis = parser.analyze(query);
+    let parsed = parser.parse(query).await;
+
+    Ok(Json(AnalyzeResponse { parsed, analysis }))
+}
+
+async fn build_parser(state: &AppState) -> QueryParser {
+    // Create parser with LLM model (use llama3.2:1b for parsing)
+    let mut parser = QueryParser::new("llama3.2:1b".to_string())
+        .with_ollama_url(&state.config.ollama_base_url)
+        .with_future_dates(state.config.allow_future_dates)
+        .with_cache(state.query_cache.clone());
+    if state.config.persist_query_cache {
+        parser = parser.with_persistent_cache(
+            QueryCache::default_file(),
+            state.config.query_cache_ttl_secs,
+        );
+    }
+    if state.config.spell_correction {
+        if let Some(vocabulary) = spell_vocabulary(state).await {
+            parser = parser.with_spell_correction(vocabulary);
+        }
+    }
+    parser
+}
+
+/// The cached corpus vocabulary, recounted from storage once it is older than `VOCABULARY_TTL`
+async fn spell_vocabulary(state: &AppState) -> Option<Arc<Vocabulary>> {
+    if let Some((built_at, ref vocabulary)) = *state.spell_vocabulary.read().await {
+        if built_at.elapsed() < VOCABULARY_TTL {
+            return Some(vocabulary.clone());
+        }
+    }
+
+    match state.storage.term_counts().await {
+        Ok(counts) => {
+            let vocabulary = Arc::new(Vocabulary::from_counts(counts));
+            debug!("Built spell-correction vocabulary of {} terms", vocabulary.len());
+            *state.spell_vocabulary.write().await = Some((std::time::Instant::now(), vocabulary.clone()));
+            Some(vocabulary)
+        }
+        Err(e) => {
+            warn!("Skipping spell correction, failed to count corpus terms: {}", e);
+            None
+        }
+    }
+}
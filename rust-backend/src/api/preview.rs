@@ -1,12 +1,17 @@
 use axum::{
     extract::Query,
+    http::StatusCode,
     response::Json,
 };
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::io::{BufRead, Read};
+use std::path::{Path, PathBuf};
 use std::fs;
+use tracing::warn;
 
-use crate::parsers::{DocumentParser, PdfParser, DocxParser, TextParser};
+use crate::config::FileTypeFilters;
+use crate::parsers::{DocumentParser, ParserRegistry, TextParser};
+use crate::text_cache::TextCache;
 
 #[derive(Deserialize)]
 pub struct PreviewRequest {
@@ -128,7 +133,9 @@ pub async fn get_file_preview(
     
     // Extract content if preview is available
     let content = if preview_available {
-        match extract_preview_content(&file_path, &file_type) {
+        let config = crate::config::AppConfig::load_or_default().await.unwrap_or_default();
+        let text_cache = crate::text_cache::TextCache::from_config(&config);
+        match extract_preview_content(&file_path, &file_type, text_cache) {
             Ok(text) => Some(text),
             Err(e) => {
                 return Ok(Json(PreviewResponse {
@@ -209,6 +216,7 @@ fn determine_file_type(ext: &str) -> String {
 fn extract_preview_content(
     file_path: &PathBuf,
     file_type: &str,
+    text_cache: Option<TextCache>,
 ) -> Result<String, Box<dyn std::error::Error>> {
     let file_path_str = file_path.to_string_lossy();
     
@@ -218,15 +226,17 @@ fn extract_preview_content(
             let parser: &dyn DocumentParser = &TextParser;
             parser.extract_text(&file_path_str).map_err(|e| e.into())
         }
-        "pdf" => {
-            // Use PDF parser
-            let parser: &dyn DocumentParser = &PdfParser;
-            parser.extract_text(&file_path_str).map_err(|e| e.into())
-        }
-        "docx" => {
-            // Use DOCX parser
-            let parser: &dyn DocumentParser = &DocxParser;
-            parser.extract_text(&file_path_str).map_err(|e| e.into())
+        "pdf" | "docx" => {
+            // Go through the registry so repeat previews are served from the text cache
+            let filters = FileTypeFilters {
+                include_pdf: true,
+                include_docx: true,
+                include_text: true,
+                include_xlsx: true,
+                excluded_extensions: Vec::new(),
+            };
+            let registry = ParserRegistry::new(&filters).with_text_cache(text_cache);
+            registry.extract_text(&file_path_str).map_err(|e| e.into())
         }
         "image" => {
             // For images, we don't extract text content
@@ -236,3 +246,188 @@ fn extract_preview_content(
         _ => Err("Unsupported file type for preview".into()),
     }
 }
+
+const DEFAULT_PREVIEW_LINES: usize = 40;
+const MAX_PREVIEW_LINES: usize = 500;
+/// Upper bound on preview text returned, whatever the file type
+const MAX_PREVIEW_BYTES: usize = 64 * 1024;
+const THUMBNAIL_SIZE: u32 = 256;
+
+#[derive(Deserialize)]
+pub struct BrowserPreviewRequest {
+    path: String,
+    lines: Option<usize>,
+    #[serde(default)]
+    thumbnail: bool,
+}
+
+#[derive(Serialize)]
+pub struct BrowserPreviewResponse {
+    path: String,
+    file_type: String, // text, code, pdf, docx, image
+    content: Option<String>,
+    truncated: bool,
+    width: Option<u32>,
+    height: Option<u32>,
+    /// PNG thumbnail as base64, only when requested
+    thumbnail: Option<String>,
+}
+
+/// Quick, size-bounded preview for the file browser. Unsupported types get a 415.
+pub async fn get_browser_preview(
+    Query(params): Query<BrowserPreviewRequest>,
+) -> Result<Json<BrowserPreviewResponse>, StatusCode> {
+    if params.path.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let file_path = PathBuf::from(&params.path);
+    crate::api::files_browser::check_allowed_path(&file_path).await?;
+    if !file_path.is_file() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let ext = file_path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|s| s.to_lowercase())
+        .unwrap_or_default();
+    let file_type = determine_file_type(&ext);
+    let max_lines = params.lines.unwrap_or(DEFAULT_PREVIEW_LINES).clamp(1, MAX_PREVIEW_LINES);
+
+    let mut response = BrowserPreviewResponse {
+        path: params.path.clone(),
+        file_type: file_type.clone(),
+        content: None,
+        truncated: false,
+        width: None,
+        height: None,
+        thumbnail: None,
+    };
+
+    match file_type.as_str() {
+        "text" | "code" => {
+            let path = file_path.clone();
+            let (content, truncated) = tokio::task::spawn_blocking(move || read_first_lines(&path, max_lines))
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            response.content = Some(content);
+            response.truncated = truncated;
+        }
+        "pdf" | "docx" => {
+            let config = crate::config::AppConfig::load_or_default().await.unwrap_or_default();
+            let registry = crate::parsers::ParserRegistry::new(&config.file_type_filters)
+                .with_text_cache(crate::text_cache::TextCache::from_config(&config));
+            let path_str = file_path.to_string_lossy().to_string();
+            if !registry.can_parse(&path_str) {
+                return Err(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+            }
+            let text = tokio::task::spawn_blocking(move || registry.extract_text(&path_str))
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+                .map_err(|e| {
+                    warn!("[PREVIEW] Failed to extract {}: {}", params.path, e);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?;
+            // pdf-extract separates pages with form feeds; docx has no pages, so take the start
+            let first_page = text.split('\u{c}').next().unwrap_or("");
+            let (content, truncated) = truncate_preview(first_page);
+            response.content = Some(content);
+            response.truncated = truncated || first_page.len() < text.len();
+        }
+        "image" => {
+            let path = file_path.clone();
+            let want_thumbnail = params.thumbnail;
+            let (width, height, thumbnail) = tokio::task::spawn_blocking(move || image_preview(&path, want_thumbnail))
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+                .map_err(|_| StatusCode::UNSUPPORTED_MEDIA_TYPE)?;
+            response.width = Some(width);
+            response.height = Some(height);
+            response.thumbnail = thumbnail;
+        }
+        _ => return Err(StatusCode::UNSUPPORTED_MEDIA_TYPE),
+    }
+
+    Ok(Json(response))
+}
+
+/// Read up to `max_lines` lines without loading the whole file, stopping at `MAX_PREVIEW_BYTES`.
+/// Reads go through a byte limit, so a huge file without newlines is never buffered whole.
+fn read_first_lines(path: &Path, max_lines: usize) -> std::io::Result<(String, bool)> {
+    let file = fs::File::open(path)?.take(MAX_PREVIEW_BYTES as u64 + 1);
+    let mut reader = std::io::BufReader::new(file);
+    let mut content = String::new();
+    let mut line = Vec::new();
+    let mut consumed = 0;
+    for _ in 0..max_lines {
+        line.clear();
+        let read = reader.read_until(b'\n', &mut line)?;
+        if read == 0 {
+            return Ok((content, false));
+        }
+        consumed += read;
+        let text = String::from_utf8_lossy(&line);
+        if consumed > MAX_PREVIEW_BYTES {
+            let (rest, _) = truncate_preview(&format!("{}{}", content, text));
+            return Ok((rest, true));
+        }
+        content.push_str(text.trim_end_matches('\n').trim_end_matches('\r'));
+        content.push('\n');
+    }
+    Ok((content, !reader.fill_buf()?.is_empty()))
+}
+
+/// Cut text to `MAX_PREVIEW_BYTES` on a char boundary
+fn truncate_preview(text: &str) -> (String, bool) {
+    if text.len() <= MAX_PREVIEW_BYTES {
+        return (text.to_string(), false);
+    }
+    let mut end = MAX_PREVIEW_BYTES;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    (text[..end].to_string(), true)
+}
+
+fn image_preview(path: &Path, want_thumbnail: bool) -> anyhow::Result<(u32, u32, Option<String>)> {
+    use base64::{Engine as _, engine::general_purpose::STANDARD};
+
+    let (width, height) = image::image_dimensions(path)?;
+    if !want_thumbnail {
+        return Ok((width, height, None));
+    }
+
+    let thumbnail = image::open(path)?.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE);
+    let mut png = std::io::Cursor::new(Vec::new());
+    thumbnail.write_to(&mut png, image::ImageFormat::Png)?;
+    Ok((width, height, Some(STANDARD.encode(png.into_inner()))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_preview_respects_char_boundaries() {
+        let text = "é".repeat(MAX_PREVIEW_BYTES);
+        let (truncated, was_truncated) = truncate_preview(&text);
+        assert!(was_truncated);
+        assert!(truncated.len() <= MAX_PREVIEW_BYTES);
+        assert_eq!(truncate_preview("short"), ("short".to_string(), false));
+    }
+
+    #[test]
+    fn test_read_first_lines_bounds_a_file_without_newlines() {
+        let path = std::env::temp_dir().join(format!("gist-preview-test-{}.txt", std::process::id()));
+        std::fs::write(&path, "x".repeat(MAX_PREVIEW_BYTES * 4)).unwrap();
+        let (content, truncated) = read_first_lines(&path, 10).unwrap();
+        assert!(truncated);
+        assert!(content.len() <= MAX_PREVIEW_BYTES);
+
+        std::fs::write(&path, "one\r\ntwo\nthree").unwrap();
+        assert_eq!(read_first_lines(&path, 2).unwrap(), ("one\ntwo\n".to_string(), true));
+        assert_eq!(read_first_lines(&path, 5).unwrap(), ("one\ntwo\nthree\n".to_string(), false));
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -1,57 +1,249 @@
 use axum::{
     extract::State,
-    response::Json,
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
 };
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use tracing::{debug, error, warn};
 
 use crate::AppState;
-use crate::search::{cosine_similarity, filename_similarity, hybrid_similarity};
-
-/// Adjust similarity score based on file name length and content size
-/// This helps reduce false positives from single-word files
-fn adjust_similarity_for_file_length(
-    base_similarity: f32,
-    file_name: &str,
-    file_size: i64,
-    query_word_count: usize,
-) -> f32 {
-    let mut adjusted = base_similarity;
-    
+use crate::config::ScoreCalibration;
+use crate::storage::{FileMetadata, Passage, EMBEDDINGS_MISSING_ERROR};
+use crate::search::{
+    cosine_similarity, dot_similarity, freshness_boost, hybrid_similarity, normalize, path_boost, prepare_vector, type_boost,
+    reciprocal_rank_fusion, scale_similarity, vector_norm, vector_similarity, FilenameMatcher,
+};
+
+/// Penalty multipliers for short file names and small files: (short name, small file, short query).
+/// These reduce false positives from single-word files.
+fn file_length_penalties(file_name: &str, file_size: i64, query_word_count: usize) -> (f32, f32, f32) {
     // Count words in filename (split by common separators)
-    let file_name_words: Vec<&str> = file_name
+    let file_name_word_count = file_name
         .split(|c: char| c.is_whitespace() || c == '-' || c == '_' || c == '.')
         .filter(|s| !s.is_empty())
-        .collect();
-    let file_name_word_count = file_name_words.len();
-    
+        .count();
+
     // Penalize very short filenames (1-2 words) more heavily
-    if file_name_word_count <= 2 {
-        // Apply penalty: reduce similarity by 15-25% for very short names
-        let penalty = if file_name_word_count == 1 {
-            0.25 // 25% penalty for single-word files
-        } else {
-            0.15 // 15% penalty for two-word files
-        };
-        adjusted = adjusted * (1.0 - penalty);
-    }
-    
-    // Penalize very small files (likely minimal content)
+    let short_name = match file_name_word_count {
+        1 => 0.75,     // 25% penalty for single-word files
+        0 | 2 => 0.85, // 15% penalty for two-word files
+        _ => 1.0,
+    };
+
     // Files under 100 bytes are likely to have minimal semantic content
-    if file_size < 100 {
-        adjusted = adjusted * 0.85; // 15% penalty
+    let small_file = if file_size < 100 {
+        0.85 // 15% penalty
     } else if file_size < 500 {
-        adjusted = adjusted * 0.92; // 8% penalty
-    }
-    
+        0.92 // 8% penalty
+    } else {
+        1.0
+    };
+
     // For short queries (1-2 words), be more strict with short filenames
-    if query_word_count <= 2 && file_name_word_count <= 2 {
-        // Additional penalty when both query and filename are short
-        adjusted = adjusted * 0.90; // Additional 10% penalty
+    let short_query = if query_word_count <= 2 && file_name_word_count <= 2 {
+        0.90 // Additional 10% penalty
+    } else {
+        1.0
+    };
+
+    (short_name, small_file, short_query)
+}
+
+/// How a result's similarity was computed, returned when the request sets `explain`.
+/// Penalty and boost fields are multipliers; 1.0 means that step didn't apply.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScoreBreakdown {
+    pub vector_sim: f32,
+    pub filename_sim: f32,
+    pub vector_weight: f32,
+    pub filename_weight: f32,
+    /// Weighted blend of the two similarities, before penalties
+    pub hybrid_sim: f32,
+    /// Strong content match whose filename doesn't match at all
+    pub content_only_penalty: f32,
+    /// Single-word query with a weak filename match
+    pub single_word_penalty: f32,
+    pub short_name_penalty: f32,
+    pub small_file_penalty: f32,
+    pub short_query_penalty: f32,
+    /// Score after penalties; the similarity floor is applied to this
+    pub adjusted: f32,
+    pub path_boost: f32,
+    /// File extension multiplier from `type_boosts`
+    pub type_boost: f32,
+    /// Recency multiplier from `freshness_weight`
+    pub freshness_boost: f32,
+    /// `adjusted` times the boosts, uncapped, so boosted strong matches still differ; results are ranked by it
+    pub ranking_score: f32,
+    /// `ranking_score` capped at 1.0; reported as is unless `score_calibration` rescaled it
+    pub final_score: f32,
+    /// The reported similarity after `score_calibration`, when set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub calibrated_score: Option<f32>,
+}
+
+impl ScoreBreakdown {
+    /// Plain vector similarity with no hybrid weighting or penalties (passage search)
+    fn vector_only(vector_sim: f32) -> Self {
+        Self {
+            vector_sim,
+            filename_sim: 0.0,
+            vector_weight: 1.0,
+            filename_weight: 0.0,
+            hybrid_sim: vector_sim,
+            content_only_penalty: 1.0,
+            single_word_penalty: 1.0,
+            short_name_penalty: 1.0,
+            small_file_penalty: 1.0,
+            short_query_penalty: 1.0,
+            adjusted: vector_sim,
+            path_boost: 1.0,
+            type_boost: 1.0,
+            freshness_boost: 1.0,
+            ranking_score: vector_sim,
+            final_score: vector_sim,
+            calibrated_score: None,
+        }
+    }
+
+    fn with_boosts(mut self, boosts: ResultBoosts) -> Self {
+        self.path_boost = boosts.path;
+        self.type_boost = boosts.file_type;
+        self.freshness_boost = boosts.freshness;
+        self.ranking_score = self.adjusted * boosts.product();
+        self.final_score = self.ranking_score.min(1.0);
+        self
+    }
+}
+
+/// Blend vector and filename similarity for one file and apply the ranking penalties
+fn score_hybrid(
+    query: &str,
+    meta: &FileMetadata,
+    vector_sim: f32,
+    filename_matcher: &FilenameMatcher,
+) -> ScoreBreakdown {
+    let filename_sim = filename_matcher.similarity(query, &meta.file_name);
+
+    // Determine weights based on query characteristics
+    let query_lower = query.to_lowercase();
+    let word_count = query.split_whitespace().count();
+    let has_extension = query.contains('.');
+    let is_short = query.len() < 20;
+
+    // Domain terms (config `semantic_keywords`) that are single words but semantic
+    let is_semantic_keyword = filename_matcher.is_semantic_keyword(&query_lower);
+
+    // Only treat as filename query if:
+    // - Has file extension, OR
+    // - Multiple words AND short AND high filename similarity, OR
+    // - Single word BUT not a semantic keyword AND high filename similarity
+    let is_filename_query = has_extension
+        || (word_count > 1 && is_short && filename_sim > 0.7)
+        || (word_count == 1 && !is_semantic_keyword && filename_sim > 0.8);
+    let (vector_weight, filename_weight) = if is_filename_query {
+        (0.3, 0.7) // Favor filename matching for filename-like queries
+    } else {
+        (0.8, 0.2) // Favor vector similarity for semantic queries
+    };
+    let hybrid_sim = hybrid_similarity(vector_sim, filename_sim, (vector_weight, filename_weight));
+
+    // A high vector score with no filename overlap at all is often a false positive
+    let content_only_penalty = if filename_sim < 0.1 && vector_sim > 0.6 { 0.8 } else { 1.0 };
+    let single_word_penalty = if word_count == 1 && filename_sim < 0.3 { 0.85 } else { 1.0 };
+    let (short_name_penalty, small_file_penalty, short_query_penalty) =
+        file_length_penalties(&meta.file_name, meta.file_size, word_count);
+    let adjusted = (hybrid_sim
+        * content_only_penalty
+        * single_word_penalty
+        * short_name_penalty
+        * small_file_penalty
+        * short_query_penalty)
+        .clamp(0.0, 1.0);
+
+    ScoreBreakdown {
+        vector_sim,
+        filename_sim,
+        vector_weight,
+        filename_weight,
+        hybrid_sim,
+        content_only_penalty,
+        single_word_penalty,
+        short_name_penalty,
+        small_file_penalty,
+        short_query_penalty,
+        adjusted,
+        path_boost: 1.0,
+        type_boost: 1.0,
+        freshness_boost: 1.0,
+        ranking_score: adjusted,
+        final_score: adjusted,
+        calibrated_score: None,
+    }
+}
+
+/// Score for a file without an embedding, which can only match by name
+fn score_filename_only(filename_sim: f32, meta: &FileMetadata, query_word_count: usize) -> ScoreBreakdown {
+    // A folder entry's size is always 0, which says nothing about what it holds
+    let file_size = if meta.is_directory() { i64::MAX } else { meta.file_size };
+    let (short_name_penalty, small_file_penalty, short_query_penalty) =
+        file_length_penalties(&meta.file_name, file_size, query_word_count);
+    let adjusted = (filename_sim * short_name_penalty * small_file_penalty * short_query_penalty)
+        .clamp(0.0, 1.0);
+    ScoreBreakdown {
+        vector_sim: 0.0,
+        filename_sim,
+        vector_weight: 0.0,
+        filename_weight: 1.0,
+        hybrid_sim: filename_sim,
+        short_name_penalty,
+        small_file_penalty,
+        short_query_penalty,
+        adjusted,
+        ranking_score: adjusted,
+        final_score: adjusted,
+        ..ScoreBreakdown::vector_only(0.0)
     }
-    
-    // Ensure similarity stays in valid range
-    adjusted.max(0.0).min(1.0)
+}
+
+/// Path, file type and freshness multipliers applied to a result after the similarity floor
+#[derive(Debug, Clone, Copy)]
+struct ResultBoosts {
+    path: f32,
+    file_type: f32,
+    freshness: f32,
+}
+
+impl ResultBoosts {
+    fn product(self) -> f32 {
+        self.path * self.file_type * self.freshness
+    }
+}
+
+fn result_boosts(meta: &FileMetadata, config: &crate::config::AppConfig, now: i64) -> ResultBoosts {
+    ResultBoosts {
+        path: path_boost(&meta.file_path, &config.boost_paths),
+        file_type: type_boost(&meta.file_path, &config.type_boosts),
+        freshness: freshness_boost(meta.modified_time, now, config.freshness_half_life_days, config.freshness_weight),
+    }
+}
+
+/// Breakdown behind a boosted result, from breakdowns already collapsed by `collapse_breakdowns`
+fn breakdown_for(
+    breakdowns: &HashMap<String, ScoreBreakdown>,
+    file_path: &str,
+    boosts: ResultBoosts,
+) -> Option<ScoreBreakdown> {
+    breakdowns.get(file_path).map(|breakdown| breakdown.clone().with_boosts(boosts))
+}
+
+/// Best score first; equal scores (common for metadata-only files) fall back to path order,
+/// so repeating a query never reshuffles them
+fn by_score_then_path(a: &(FileMetadata, f32), b: &(FileMetadata, f32)) -> std::cmp::Ordering {
+    b.1.partial_cmp(&a.1)
+        .unwrap_or(std::cmp::Ordering::Equal)
+        .then_with(|| a.0.file_path.cmp(&b.0.file_path))
 }
 
 /// Apply the same scoring pipeline used by the main search API.
@@ -59,62 +251,39 @@ fn adjust_similarity_for_file_length(
 pub fn score_search_results(
     query: &str,
     results: Vec<(crate::storage::FileMetadata, f32)>,
+    filename_matcher: &FilenameMatcher,
 ) -> Vec<(crate::storage::FileMetadata, f32)> {
-    let query_word_count = query.split_whitespace().count();
     let mut scored: Vec<_> = results
         .into_iter()
         .map(|(meta, vector_sim)| {
-            let filename_sim = filename_similarity(query, &meta.file_name);
-            let query_lower = query.to_lowercase();
-            let word_count = query.split_whitespace().count();
-            let has_extension = query.contains('.');
-            let is_short = query.len() < 20;
-            let semantic_keywords = [
-                "calculus", "algebra", "geometry", "physics", "chemistry", "biology",
-                "history", "literature", "philosophy", "psychology", "sociology",
-                "programming", "algorithm", "database", "network", "security",
-                "homework", "assignment", "project", "report", "essay", "thesis",
-                "mathematics", "math", "science", "engineering", "computer",
-            ];
-            let is_semantic_keyword = semantic_keywords
-                .iter()
-                .any(|kw| query_lower == *kw || query_lower.starts_with(kw));
-            let is_filename_query = has_extension
-                || (word_count > 1 && is_short && filename_sim > 0.7)
-                || (word_count == 1 && !is_semantic_keyword && filename_sim > 0.8);
-            let (vector_weight, filename_weight) = if is_filename_query {
-                (0.3, 0.7)
-            } else {
-                (0.8, 0.2)
-            };
-            let mut hybrid_sim =
-                hybrid_similarity(vector_sim, filename_sim, (vector_weight, filename_weight));
-            if filename_sim < 0.1 && vector_sim > 0.6 {
-                hybrid_sim *= 0.8;
-            }
-            if word_count == 1 && filename_sim < 0.3 {
-                hybrid_sim *= 0.85;
-            }
-            let adjusted = adjust_similarity_for_file_length(
-                hybrid_sim,
-                &meta.file_name,
-                meta.file_size,
-                query_word_count,
-            );
+            let adjusted = score_hybrid(query, &meta, vector_sim, filename_matcher).adjusted;
             (meta, adjusted)
         })
         .collect();
-    scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    scored.sort_by(by_score_then_path);
     scored
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FilterOptions {
     pub date_range: Option<DateRange>,
+    /// Raw extensions ("pdf", ".png") and/or category names ("image", "document", "video")
     pub file_types: Option<Vec<String>>,
     pub folder_paths: Option<Vec<String>>,
 }
 
+impl FilterOptions {
+    /// Copy of these filters with category names in `file_types` expanded to extensions
+    fn with_expanded_file_types(&self) -> Self {
+        Self {
+            file_types: self.file_types.as_deref().map(crate::query_parser::expand_file_types),
+            ..self.clone()
+        }
+    }
+}
+
+/// Modification-date filter. Clients can send inclusive `start`/`end` Unix timestamps directly
+/// (e.g. from a calendar picker) or `month`/`year`; every field that is set must match.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DateRange {
     pub start: Option<i64>, // Unix timestamp
@@ -123,187 +292,305 @@ pub struct DateRange {
     pub year: Option<i32>,
 }
 
+impl DateRange {
+    /// Reject ranges that can never match anything
+    pub fn is_valid(&self) -> bool {
+        let ordered = match (self.start, self.end) {
+            (Some(start), Some(end)) => start <= end,
+            _ => true,
+        };
+        ordered && self.month.is_none_or(|m| (1..=12).contains(&m))
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchRequest {
     pub query: String,
     pub limit: Option<usize>,
     #[serde(default)]
     pub filters: Option<FilterOptions>,
+    /// Return metadata-only (filename) matches in a separate `metadata_only_results` list
+    #[serde(default)]
+    pub separate_metadata_only: bool,
+    #[serde(default)]
+    pub granularity: SearchGranularity,
+    /// Drop results scoring below this (0.0-1.0) after ranking; defaults to `min_search_similarity` in config
+    #[serde(default)]
+    pub min_similarity: Option<f32>,
+    /// Attach a `score_breakdown` to each result showing how its similarity was computed
+    #[serde(default)]
+    pub explain: bool,
+    #[serde(default)]
+    pub mode: SearchMode,
+    /// Fold near-identical documents (config `duplicate_cluster_similarity`) into their best-ranked
+    /// copy, which then lists the others in `duplicate_paths`
+    #[serde(default)]
+    pub cluster_duplicates: bool,
+    /// Let folder entries (stored when `index_directory_names` is on) match by name alongside files
+    #[serde(default)]
+    pub include_directories: bool,
+}
+
+/// Rank with the active embedding model only, or fuse its ranking with every other indexed model's
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchMode {
+    #[default]
+    Single,
+    Fusion,
+}
+
+/// Whether search ranks whole files or individual passages (chunks) within them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchGranularity {
+    #[default]
+    File,
+    Passage,
 }
 
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchResponse {
     results: Vec<SearchResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata_only_results: Option<Vec<SearchResult>>,
+    /// The query couldn't be embedded (embedding server unreachable), so results match by filename only
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    semantic_unavailable: bool,
+    /// Why nothing was returned: nothing indexed, everything filtered out, or everything below `min_similarity`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    diagnostic: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchResult {
     pub file_path: String,
     pub file_name: String,
+    /// Unix timestamp, as indexed
+    #[serde(default)]
+    pub modified_time: i64,
+    /// Bytes, as indexed
+    #[serde(default)]
+    pub file_size: i64,
     pub similarity: f32,
     pub preview: Option<String>,
+    /// False for files indexed by filename only (images, configs, logs, ...)
+    #[serde(default)]
+    pub content_indexed: bool,
+    /// The result is a folder, returned when the request set `include_directories`
+    #[serde(default)]
+    pub is_directory: bool,
+    /// The matching chunk and its location, for passage-granularity searches
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub passage: Option<Passage>,
+    /// How `similarity` was computed, when the request set `explain`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score_breakdown: Option<ScoreBreakdown>,
+    /// Reciprocal rank fusion score the result was ordered by, in fusion mode
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fusion_score: Option<f32>,
+    /// Near-duplicates folded into this result, when the request set `cluster_duplicates`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duplicate_count: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duplicate_paths: Option<Vec<String>>,
 }
 
+/// Search, answering 503 with a reindex hint instead of empty results when embeddings.bin is gone
 pub async fn search_files(
     State(state): State<AppState>,
     Json(request): Json<SearchRequest>,
+) -> Result<Json<SearchResponse>, Response> {
+    checked_search(&state, request).await.map(Json)
+}
+
+/// Same search as `search_files`, streamed as NDJSON: one `SearchResult` per line in rank order
+/// (metadata-only matches follow when `separate_metadata_only` is set; `content_indexed` tells them
+/// apart), then a `{"done": true, ...}` summary line. Ranking needs every candidate, so the results
+/// are ranked in full first; only their serialization is deferred until the body is read. A result
+/// that fails to serialize is sent as an `{"error": ...}` line in its place.
+pub async fn search_files_stream(
+    State(state): State<AppState>,
+    Json(request): Json<SearchRequest>,
+) -> Result<Response, Response> {
+    let started = std::time::Instant::now();
+    let response = checked_search(&state, request).await?;
+    let metadata_only = response.metadata_only_results.unwrap_or_default();
+    let summary = serde_json::json!({
+        "done": true,
+        "count": response.results.len(),
+        "metadata_only_count": metadata_only.len(),
+        "semantic_unavailable": response.semantic_unavailable,
+        "elapsed_ms": started.elapsed().as_millis() as u64,
+    });
+
+    let lines = response
+        .results
+        .into_iter()
+        .chain(metadata_only)
+        .map(|result| {
+            serde_json::to_value(&result).unwrap_or_else(|e| {
+                warn!("Failed to serialize search result {}: {}", result.file_path, e);
+                serde_json::json!({ "error": format!("failed to serialize result {}: {}", result.file_path, e) })
+            })
+        })
+        .chain(std::iter::once(summary))
+        .map(|line| Ok::<_, std::convert::Infallible>(format!("{}\n", line)));
+    Ok((
+        [(axum::http::header::CONTENT_TYPE, "application/x-ndjson")],
+        axum::body::Body::from_stream(futures::stream::iter(lines)),
+    )
+        .into_response())
+}
+
+/// `run_search` behind the embeddings.bin check shared by the search endpoints
+async fn checked_search(state: &AppState, request: SearchRequest) -> Result<SearchResponse, Response> {
+    let missing = || {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({ "success": false, "error": EMBEDDINGS_MISSING_ERROR })),
+        )
+            .into_response()
+    };
+    if state.storage.embeddings_missing() {
+        return Err(missing());
+    }
+    // A failed read may have just discovered the file is gone
+    run_search(state, request).await.map(|Json(response)| response).map_err(|status| {
+        if state.storage.embeddings_missing() { missing() } else { status.into_response() }
+    })
+}
+
+#[tracing::instrument(name = "SEARCH", skip_all)]
+async fn run_search(
+    state: &AppState,
+    request: SearchRequest,
 ) -> Result<Json<SearchResponse>, axum::http::StatusCode> {
-    eprintln!("=== Search Request ===");
-    eprintln!("Query: '{}'", request.query);
-    eprintln!("Limit: {:?}", request.limit);
-    eprintln!("Filters: {:?}", request.filters);
+    debug!("=== Search Request ===");
+    debug!("Query: '{}'", request.query);
+    debug!("Limit: {:?}", request.limit);
+    debug!("Filters: {:?}", request.filters);
     
     // Validate query is not empty
     let query = request.query.trim();
     if query.is_empty() {
-        eprintln!("ERROR: Empty query received");
+        warn!("Empty query received");
         return Err(axum::http::StatusCode::BAD_REQUEST);
     }
+    if let Some(date_range) = request.filters.as_ref().and_then(|f| f.date_range.as_ref()) {
+        if !date_range.is_valid() {
+            warn!("Invalid date range: {:?}", date_range);
+            return Err(axum::http::StatusCode::BAD_REQUEST);
+        }
+    }
     
-    // Use config's max_search_results as default, but allow override up to 200
+    // Use config's max_search_results as default, but allow override up to max_search_results_cap
     let default_limit = state.config.max_search_results;
-    let limit = request.limit.unwrap_or(default_limit).min(200);
+    let limit = request.limit.unwrap_or(default_limit).min(state.config.max_search_results_cap);
+    let filename_matcher = std::sync::Arc::new(FilenameMatcher::from_config(&state.config));
+    let min_similarity = request.min_similarity
+        .unwrap_or(state.config.min_search_similarity)
+        .clamp(0.0, 1.0);
     
     // Generate embedding for query
-    let embedding_service = crate::embedding::EmbeddingService::new(
-        state.config.embedding_model.clone()
-    );
+    let embedding_service = crate::embedding::EmbeddingService::from_config(&state.config);
     
-    eprintln!("Generating embedding for query: '{}'", query);
-    let query_embedding = embedding_service.generate_embedding(query)
-        .await
-        .map_err(|e| {
-            eprintln!("Error generating query embedding: {}", e);
-            axum::http::StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-    
-    eprintln!("Generated query embedding with {} dimensions", query_embedding.len());
+    debug!("Generating embedding for query: '{}'", query);
+    // With the embedder unreachable, filename matching over every indexed file still gives answers
+    let query_embedding = match embedding_service.generate_embedding(query).await {
+        Ok(mut query_embedding) => {
+            // Stored vectors are unit length under cosine, so scoring against a unit query is a dot product
+            prepare_vector(state.config.similarity_metric, &mut query_embedding);
+            debug!("Generated query embedding with {} dimensions", query_embedding.len());
+            Some(query_embedding)
+        }
+        Err(e) => {
+            warn!("Query embedding failed, falling back to filename-only search: {}", e);
+            None
+        }
+    };
+    let semantic_unavailable = query_embedding.is_none();
+
+    if let (Some(ref query_embedding), SearchGranularity::Passage) = (&query_embedding, request.granularity) {
+        return search_passages(state, &request, query_embedding, limit, min_similarity).await.map(Json);
+    }
 
     // Try to use HNSW index if available, otherwise fall back to linear search
     let mut results: Vec<(crate::storage::FileMetadata, f32)> = Vec::new();
+    // Per-path score breakdowns, only collected when the request asks for them
+    let mut breakdowns: HashMap<String, ScoreBreakdown> = HashMap::new();
     
     // Calculate query word count for weighting
     let query_words: Vec<&str> = query.split_whitespace().collect();
     let query_word_count = query_words.len();
-    eprintln!("Query word count: {}", query_word_count);
+    debug!("Query word count: {}", query_word_count);
     
     let hnsw_guard = state.hnsw_index.read().await;
-    if let Some(ref hnsw) = *hnsw_guard {
+    if let (Some(hnsw), Some(query_embedding)) = (hnsw_guard.as_ref(), query_embedding.as_ref()) {
         // Use HNSW search (or optimized in-memory search)
         if hnsw.len() > 0 {
             let stats = hnsw.get_stats();
-            eprintln!("[SEARCH] HNSW index available: {} items, {} dimensions, ready={}", 
+            debug!("HNSW index available: {} items, {} dimensions, ready={}", 
                      stats.item_count, stats.dimensions, stats.is_ready);
             
             // Verify index integrity (only log, don't fail)
             let verification = hnsw.verify_index();
             if !verification.is_valid {
-                eprintln!("[SEARCH] WARNING: HNSW index verification failed with {} errors", 
+                warn!("HNSW index verification failed with {} errors", 
                          verification.errors.len());
                 for error in &verification.errors {
-                    eprintln!("[SEARCH]   Error: {}", error);
+                    error!("  Error: {}", error);
                 }
             }
             if !verification.warnings.is_empty() {
-                eprintln!("[SEARCH] HNSW index has {} warnings", verification.warnings.len());
+                warn!("HNSW index has {} warnings", verification.warnings.len());
                 for warning in &verification.warnings {
-                    eprintln!("[SEARCH]   Warning: {}", warning);
+                    warn!("  Warning: {}", warning);
                 }
             }
             
             let search_start = std::time::Instant::now();
-            eprintln!("[SEARCH] Using HNSW index with {} items", hnsw.len());
+            debug!("Using HNSW index with {} items", hnsw.len());
             if let Ok(hnsw_results) = hnsw.search(query_embedding.clone(), limit * 2) {
                 let search_duration = search_start.elapsed();
-                eprintln!("[SEARCH] HNSW search completed in {:.2}ms, returned {} results", 
+                debug!("HNSW search completed in {:.2}ms, returned {} results", 
                          search_duration.as_secs_f64() * 1000.0, hnsw_results.len());
                 // Apply hybrid search (vector + filename) to HNSW results
                 results = hnsw_results.into_iter().map(|(meta, vector_sim)| {
-                    // Calculate filename similarity
-                    let filename_sim = filename_similarity(query, &meta.file_name);
-                    
-                    // Determine weights based on query characteristics
-                    let query_lower = query.to_lowercase();
-                    let word_count = query.split_whitespace().count();
-                    let has_extension = query.contains('.');
-                    let is_short = query.len() < 20;
-                    
-                    // Academic/technical terms that are single words but semantic
-                    let semantic_keywords = [
-                        "calculus", "algebra", "geometry", "physics", "chemistry", "biology",
-                        "history", "literature", "philosophy", "psychology", "sociology",
-                        "programming", "algorithm", "database", "network", "security",
-                        "homework", "assignment", "project", "report", "essay", "thesis",
-                        "mathematics", "math", "science", "engineering", "computer",
-                    ];
-                    
-                    let is_semantic_keyword = semantic_keywords.iter()
-                        .any(|kw| query_lower == *kw || query_lower.starts_with(kw));
-                    
-                    // Only treat as filename query if:
-                    // - Has file extension, OR
-                    // - Multiple words AND short AND high filename similarity, OR  
-                    // - Single word BUT not a semantic keyword AND high filename similarity
-                    let is_filename_query = has_extension || (
-                        word_count > 1 && is_short && filename_sim > 0.7
-                    ) || (
-                        word_count == 1 && !is_semantic_keyword && filename_sim > 0.8
-                    );
-                    
-                    let (vector_weight, filename_weight) = if is_filename_query {
-                        (0.3, 0.7) // Favor filename matching for filename-like queries
-                    } else {
-                        (0.8, 0.2) // Favor vector similarity for semantic queries
-                    };
-                    
-                    // Combine vector and filename similarity
-                    let mut hybrid_sim = hybrid_similarity(vector_sim, filename_sim, (vector_weight, filename_weight));
-                    
-                    // Add content-based penalty to reduce false positives
-                    if filename_sim < 0.1 && vector_sim > 0.6 {
-                        hybrid_sim = hybrid_sim * 0.8;
-                    }
-                    
-                    if word_count == 1 && filename_sim < 0.3 {
-                        hybrid_sim = hybrid_sim * 0.85;
+                    let breakdown = score_hybrid(query, &meta, vector_sim, &filename_matcher);
+                    let adjusted = breakdown.adjusted;
+                    if request.explain {
+                        breakdowns.insert(meta.file_path.clone(), breakdown);
                     }
-                    
-                    // Apply penalties for short file names/content
-                    let adjusted = adjust_similarity_for_file_length(
-                        hybrid_sim,
-                        &meta.file_name,
-                        meta.file_size,
-                        query_word_count
-                    );
                     (meta, adjusted)
                 }).collect();
             } else {
-                eprintln!("[SEARCH] HNSW search failed, falling back to linear search");
+                warn!("HNSW search failed, falling back to linear search");
             }
         } else {
-            eprintln!("[SEARCH] HNSW index is empty (0 items), falling back to linear search");
+            debug!("HNSW index is empty (0 items), falling back to linear search");
         }
     } else {
-        eprintln!("[SEARCH] No HNSW index available (None), using linear search");
+        debug!("No HNSW index available (None), using linear search");
     }
     drop(hnsw_guard);
     
     // If HNSW didn't return results, use linear search
-    if results.is_empty() {
-        eprintln!("[SEARCH] HNSW returned no results, falling back to linear search");
+    if let Some(query_embedding) = query_embedding.as_ref().filter(|_| results.is_empty()) {
+        debug!("HNSW returned no results, falling back to linear search");
         let linear_search_start = std::time::Instant::now();
         let files_with_embeddings = match state.storage.get_all_embeddings().await {
             Ok(embeddings) => {
                 if embeddings.is_empty() {
-                    eprintln!("[SEARCH] Warning: No embeddings found in storage");
+                    warn!("No embeddings found in storage");
                 } else {
-                    eprintln!("[SEARCH] Linear search: Found {} files with embeddings", embeddings.len());
+                    debug!("Linear search: Found {} files with embeddings", embeddings.len());
                 }
                 embeddings
             }
             Err(e) => {
-                eprintln!("Error getting embeddings: {}", e);
+                error!("Error getting embeddings: {}", e);
                 return Err(axum::http::StatusCode::INTERNAL_SERVER_ERROR);
             }
         };
@@ -312,6 +599,8 @@ pub async fn search_files(
         use futures::future::join_all;
         let chunk_size = 100;
         let mut all_results = Vec::new();
+        let metric = state.config.similarity_metric;
+        let query_norm = vector_norm(query_embedding);
         
         for chunk in files_with_embeddings.chunks(chunk_size) {
             let chunk_tasks: Vec<_> = chunk.iter().map(|(metadata, embedding)| {
@@ -319,109 +608,57 @@ pub async fn search_files(
                 let emb = embedding.clone();
                 let meta = metadata.clone();
                 let query_str = query.to_string();
+                let filename_matcher = filename_matcher.clone();
                 tokio::spawn(async move {
-                    // Calculate vector similarity
-                    let vector_sim = cosine_similarity(&query_emb, &emb);
-                    
-                    // Calculate filename similarity
-                    let filename_sim = filename_similarity(&query_str, &meta.file_name);
-                    
-                    // Determine weights based on query characteristics
-                    // Single-word academic/technical terms should be treated as semantic queries
-                    let query_lower = query_str.to_lowercase();
-                    let word_count = query_str.split_whitespace().count();
-                    let has_extension = query_str.contains('.');
-                    let is_short = query_str.len() < 20;
-                    
-                    // Academic/technical terms that are single words but semantic
-                    let semantic_keywords = [
-                        "calculus", "algebra", "geometry", "physics", "chemistry", "biology",
-                        "history", "literature", "philosophy", "psychology", "sociology",
-                        "programming", "algorithm", "database", "network", "security",
-                        "homework", "assignment", "project", "report", "essay", "thesis",
-                        "mathematics", "math", "science", "engineering", "computer",
-                    ];
-                    
-                    let is_semantic_keyword = semantic_keywords.iter()
-                        .any(|kw| query_lower == *kw || query_lower.starts_with(kw));
-                    
-                    // Only treat as filename query if:
-                    // - Has file extension, OR
-                    // - Multiple words AND short AND high filename similarity, OR  
-                    // - Single word BUT not a semantic keyword AND high filename similarity
-                    let is_filename_query = has_extension || (
-                        word_count > 1 && is_short && filename_sim > 0.7
-                    ) || (
-                        word_count == 1 && !is_semantic_keyword && filename_sim > 0.8
-                    );
-                    
-                    let (vector_weight, filename_weight) = if is_filename_query {
-                        (0.3, 0.7) // Favor filename matching for filename-like queries
-                    } else {
-                        (0.8, 0.2) // Favor vector similarity for semantic queries (increased from 0.7/0.3)
-                    };
-                    
-                    // Combine vector and filename similarity
-                    let mut hybrid_sim = hybrid_similarity(vector_sim, filename_sim, (vector_weight, filename_weight));
-                    
-                    // Add content-based penalty to reduce false positives
-                    // If filename similarity is very low (< 0.1) but vector similarity is high,
-                    // this might be a false positive - apply penalty
-                    if filename_sim < 0.1 && vector_sim > 0.6 {
-                        // Reduce similarity by 20% if filename doesn't match at all
-                        hybrid_sim = hybrid_sim * 0.8;
-                    }
-                    
-                    // Also penalize if query is a single word and filename doesn't contain it
-                    if word_count == 1 && filename_sim < 0.3 {
-                        // Additional penalty for single-word queries with poor filename match
-                        hybrid_sim = hybrid_sim * 0.85;
-                    }
-                    
-                    // Apply penalties for short file names/content
-                    let adjusted_similarity = adjust_similarity_for_file_length(
-                        hybrid_sim,
-                        &meta.file_name,
-                        meta.file_size,
-                        query_word_count
-                    );
-                    
-                    (meta, adjusted_similarity)
+                    let vector_sim = scale_similarity(metric, vector_similarity(metric, &query_emb, &emb), query_norm);
+                    let breakdown = score_hybrid(&query_str, &meta, vector_sim, &filename_matcher);
+                    (meta, breakdown)
                 })
             }).collect();
             
             let chunk_results = join_all(chunk_tasks).await;
-            for task_result in chunk_results {
-                if let Ok(result) = task_result {
-                    all_results.push(result);
+            for (meta, breakdown) in chunk_results.into_iter().flatten() {
+                let adjusted = breakdown.adjusted;
+                if request.explain {
+                    breakdowns.insert(meta.file_path.clone(), breakdown);
                 }
+                all_results.push((meta, adjusted));
             }
         }
         
         results = all_results;
         let linear_search_duration = linear_search_start.elapsed();
-        eprintln!("[SEARCH] Linear search completed in {:.2}ms, found {} results", 
+        debug!("Linear search completed in {:.2}ms, found {} results", 
                  linear_search_duration.as_secs_f64() * 1000.0, results.len());
     }
     
-    // Add keyword-based search for files without embeddings
-    eprintln!("[SEARCH] Performing keyword search for files without embeddings");
-    match state.storage.get_files_without_embeddings().await {
+    // Add keyword-based search for files without embeddings, or for every file when there's no query vector
+    debug!("Performing keyword search for files without embeddings");
+    let keyword_candidates = if semantic_unavailable {
+        state.storage.get_all_files().await.map(|files| {
+            files.into_iter().filter(|meta| !meta.file_path.contains("#section")).collect::<Vec<_>>()
+        })
+    } else {
+        state.storage.get_files_without_embeddings().await
+    };
+    match keyword_candidates {
         Ok(files_without) => {
-            eprintln!("[SEARCH] Found {} files without embeddings", files_without.len());
+            debug!("Found {} files without embeddings", files_without.len());
             for meta in files_without {
+                if meta.is_directory() && !request.include_directories {
+                    continue;
+                }
                 // Calculate filename similarity
-                let filename_sim = filename_similarity(query, &meta.file_name);
+                let filename_sim = filename_matcher.similarity(query, &meta.file_name);
                 
                 // Only include if there's a decent keyword match
                 if filename_sim > 0.1 {
                     // Apply penalties for short file names
-                    let adjusted = adjust_similarity_for_file_length(
-                        filename_sim,
-                        &meta.file_name,
-                        meta.file_size,
-                        query_word_count
-                    );
+                    let breakdown = score_filename_only(filename_sim, &meta, query_word_count);
+                    let adjusted = breakdown.adjusted;
+                    if request.explain {
+                        breakdowns.insert(meta.file_path.clone(), breakdown);
+                    }
                     
                     // Add to results
                     // Check if already present (unlikely since we split by embedding existence)
@@ -430,10 +667,18 @@ pub async fn search_files(
             }
         }
         Err(e) => {
-            eprintln!("[SEARCH] Error getting files without embeddings: {}", e);
+            error!("Error getting files without embeddings: {}", e);
         }
     }
 
+    let fusion_scores = if request.mode == SearchMode::Fusion && !semantic_unavailable {
+        fuse_model_rankings(state, query, &mut results, &filename_matcher, limit * 2).await
+    } else {
+        None
+    };
+
+    let candidate_count = results.len();
+
     // Apply filters if provided and not empty
     if let Some(ref filters) = request.filters {
         // Only apply filters if at least one filter is actually set
@@ -442,135 +687,624 @@ pub async fn search_files(
             || filters.folder_paths.is_some();
         
         if has_any_filters {
-            eprintln!("Applying filters: date_range={:?}, file_types={:?}, folder_paths={:?}", 
+            debug!("Applying filters: date_range={:?}, file_types={:?}, folder_paths={:?}", 
                 filters.date_range.is_some(), 
                 filters.file_types.is_some(), 
                 filters.folder_paths.is_some());
             let before_count = results.len();
             results = apply_filters(results, filters, &state.config.file_type_filters.excluded_extensions);
-            eprintln!("Filtered results: {} -> {} (removed {})", before_count, results.len(), before_count - results.len());
+            debug!("Filtered results: {} -> {} (removed {})", before_count, results.len(), before_count - results.len());
         } else {
-            eprintln!("Filters provided but all empty, skipping filter application");
+            debug!("Filters provided but all empty, skipping filter application");
         }
     } else {
-        eprintln!("No filters provided");
+        debug!("No filters provided");
         // Still apply global exclusion if no per-request filters
-        if !state.config.file_type_filters.excluded_extensions.is_empty() {
-            results = results.into_iter().filter(|(meta, _)| {
-                let file_ext = std::path::Path::new(&meta.file_path)
-                    .extension()
-                    .and_then(|e| e.to_str())
-                    .unwrap_or("")
-                    .to_lowercase();
-                !state.config.file_type_filters.excluded_extensions.iter()
-                    .any(|e| e.trim_start_matches('.').to_lowercase() == file_ext)
-            }).collect();
-        }
+        let excluded_extensions = &state.config.file_type_filters.excluded_extensions;
+        results.retain(|(meta, _)| !is_excluded_extension(meta, excluded_extensions));
     }
 
-    eprintln!("Results before sorting: {}", results.len());
+    debug!("Results before sorting: {}", results.len());
     if !results.is_empty() {
-        eprintln!("Sample similarities before sorting: {:?}", 
+        debug!("Sample similarities before sorting: {:?}", 
             results.iter().take(5).map(|(m, s)| (m.file_name.clone(), *s)).collect::<Vec<_>>());
     }
 
     // Deduplicate by identical embeddings when enabled (keep lexicographically smaller path)
     if state.config.filter_duplicate_files {
-        results = deduplicate_by_embedding(results, &state).await;
-        eprintln!("Results after deduplication: {}", results.len());
+        results = deduplicate_by_embedding(results, state).await;
+        debug!("Results after deduplication: {}", results.len());
+    }
+
+    // Large files are stored as several sections; report each file once with its best section
+    if request.explain {
+        breakdowns = collapse_breakdowns(&results, breakdowns);
+    }
+    results = collapse_sections(results);
+
+    // Nothing relevant is better than padding the list with noise
+    let before_floor = results.len();
+    results.retain(|(_, similarity)| *similarity >= min_similarity);
+    debug!("Similarity floor {:.2} removed {} results", min_similarity, before_floor - results.len());
+
+    // Pinned directories, preferred file types and recent edits rank above equal matches elsewhere;
+    // the floor above judges relevance unboosted. Scores stay uncapped until reported, so boosted
+    // strong matches don't all tie at 1.0.
+    let now = chrono::Utc::now().timestamp();
+    if !state.config.boost_paths.is_empty() || !state.config.type_boosts.is_empty() || state.config.freshness_weight > 0.0 {
+        for (meta, similarity) in results.iter_mut() {
+            *similarity *= result_boosts(meta, &state.config, now).product();
+        }
+    }
+
+    // Sort by similarity (descending), or by fused rank when several models were searched
+    match fusion_scores {
+        Some(ref fused) => {
+            let fused_score = |meta: &FileMetadata| {
+                fused.get(&meta.file_path).copied().unwrap_or(0.0) * result_boosts(meta, &state.config, now).product()
+            };
+            results.sort_by(|a, b| {
+                fused_score(&b.0)
+                    .partial_cmp(&fused_score(&a.0))
+                    .unwrap_or(std::cmp::Ordering::Equal)
+                    .then_with(|| a.0.file_path.cmp(&b.0.file_path))
+            });
+        }
+        None => results.sort_by(by_score_then_path),
     }
 
-    // Sort by similarity (descending)
-    results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    let mut duplicates = HashMap::new();
+    if request.cluster_duplicates {
+        (results, duplicates) = cluster_duplicates(results, limit, state).await;
+    }
 
     // Take top results
     let search_results: Vec<SearchResult> = results
         .into_iter()
         .take(limit)
         .map(|(metadata, similarity)| {
+            let duplicate_paths = duplicates.remove(&metadata.file_path);
+            let similarity = similarity.min(1.0);
             SearchResult {
                 file_path: metadata.file_path.clone(),
                 file_name: metadata.file_name.clone(),
+                modified_time: metadata.modified_time,
+                file_size: metadata.file_size,
                 similarity,
                 preview: None, // Could add file preview logic here
+                content_indexed: metadata.embedding_length > 0,
+                is_directory: metadata.is_directory(),
+                passage: None,
+                score_breakdown: request.explain
+                    .then(|| breakdown_for(&breakdowns, &metadata.file_path, result_boosts(&metadata, &state.config, now)))
+                    .flatten(),
+                fusion_score: fusion_scores.as_ref().and_then(|fused| fused.get(&metadata.file_path).copied()),
+                duplicate_count: duplicate_paths.as_ref().map(Vec::len),
+                duplicate_paths,
             }
         })
         .collect();
 
-    eprintln!("Returning {} search results", search_results.len());
+    let mut search_results = search_results;
+    calibrate_scores(&mut search_results, state.config.score_calibration);
+
+    let (search_results, metadata_only_results) = if request.separate_metadata_only {
+        let (content, metadata_only): (Vec<_>, Vec<_>) =
+            search_results.into_iter().partition(|r| r.content_indexed);
+        (content, Some(metadata_only))
+    } else {
+        (search_results, None)
+    };
+
+    debug!("Returning {} search results", search_results.len());
     if !search_results.is_empty() {
-        eprintln!("Top result similarity: {:.3} ({:.1}%)", 
+        debug!("Top result similarity: {:.3} ({:.1}%)", 
             search_results[0].similarity, 
             search_results[0].similarity * 100.0);
     }
 
+    let diagnostic = if !search_results.is_empty() || metadata_only_results.as_ref().is_some_and(|r| !r.is_empty()) {
+        None
+    } else if candidate_count == 0 {
+        match state.storage.file_count().await {
+            Ok(0) => Some("no files indexed".to_string()),
+            _ => Some("no indexed file matched the query".to_string()),
+        }
+    } else {
+        Some(empty_diagnostic(candidate_count, before_floor))
+    };
+
     Ok(Json(SearchResponse {
         results: search_results,
+        metadata_only_results,
+        semantic_unavailable,
+        diagnostic,
     }))
 }
 
-// Apply filters to search results
-fn apply_filters(
-    results: Vec<(crate::storage::FileMetadata, f32)>,
-    filters: &FilterOptions,
-    excluded_extensions: &[String],
-) -> Vec<(crate::storage::FileMetadata, f32)> {
-    results
-        .into_iter()
-        .filter(|(metadata, _)| {
-            // Apply date filter
-            if let Some(ref date_range) = filters.date_range {
-                if !matches_date_range(metadata.modified_time, date_range) {
-                    return false;
-                }
+/// Fusion mode: rank files by each other indexed model's vectors and fuse those rankings with
+/// the active model's (`results`), adding files only another model found. Returns the fused
+/// score per file path, or None to fall back to single-model ranking when no other model is indexed.
+async fn fuse_model_rankings(
+    state: &AppState,
+    query: &str,
+    results: &mut Vec<(FileMetadata, f32)>,
+    filename_matcher: &FilenameMatcher,
+    depth: usize,
+) -> Option<HashMap<String, f32>> {
+    let active_model = state.config.active_embedding_model();
+    let models: Vec<String> = match state.storage.embedding_models().await {
+        Ok(models) => models.into_iter().filter(|model| model != active_model).collect(),
+        Err(e) => {
+            warn!("Fusion search unavailable, failed to list embedding models: {}", e);
+            return None;
+        }
+    };
+    if models.is_empty() {
+        debug!("Fusion search requested but only {} is indexed", active_model);
+        return None;
+    }
+
+    let mut primary = collapse_sections(results.clone());
+    primary.sort_by(by_score_then_path);
+    let mut ranked_lists = vec![primary.into_iter().take(depth).map(|(meta, _)| meta.file_path).collect::<Vec<_>>()];
+    let mut known: std::collections::HashSet<String> = results.iter().map(|(meta, _)| meta.file_path.clone()).collect();
+
+    for model in models {
+        let service = crate::embedding::EmbeddingService::for_model(&state.config, &model);
+        let mut query_embedding = match service.generate_embedding(query).await {
+            Ok(embedding) => embedding,
+            Err(e) => {
+                warn!("Skipping {} in fusion search: {}", model, e);
+                continue;
             }
+        };
+        normalize(&mut query_embedding);
+        let embeddings = match state.storage.get_model_embeddings(&model).await {
+            Ok(embeddings) => embeddings,
+            Err(e) => {
+                warn!("Skipping {} in fusion search: {}", model, e);
+                continue;
+            }
+        };
 
-            // Apply file type filter
-            if let Some(ref file_types) = filters.file_types {
-                let file_ext = std::path::Path::new(&metadata.file_path)
-                    .extension()
-                    .and_then(|e| e.to_str())
-                    .unwrap_or("")
-                    .to_lowercase();
-                
-                if !file_types.iter().any(|ft| file_ext == *ft) {
-                    return false;
-                }
+        let mut scored: Vec<(FileMetadata, f32)> = embeddings
+            .into_iter()
+            .map(|(meta, embedding)| {
+                let vector_sim = dot_similarity(&query_embedding, &embedding);
+                let adjusted = score_hybrid(query, &meta, vector_sim, filename_matcher).adjusted;
+                (meta, adjusted)
+            })
+            .collect();
+        scored.sort_by(by_score_then_path);
+        scored.truncate(depth);
+        debug!("Fusion: {} ranked {} files", model, scored.len());
+
+        ranked_lists.push(scored.iter().map(|(meta, _)| meta.file_path.clone()).collect());
+        for (meta, score) in scored {
+            if known.insert(meta.file_path.clone()) {
+                results.push((meta, score));
             }
+        }
+    }
 
-            // Apply folder path filter
-            if let Some(ref folder_paths) = filters.folder_paths {
-                let file_path_lower = metadata.file_path.to_lowercase();
-                let matches_folder = folder_paths.iter().any(|folder| {
-                    let folder_lower = folder.to_lowercase();
-                    // Check if file path contains folder name (case-insensitive)
-                    file_path_lower.contains(&folder_lower)
-                });
-                
-                if !matches_folder {
-                    return false;
+    if ranked_lists.len() < 2 {
+        return None;
+    }
+    Some(reciprocal_rank_fusion(&ranked_lists).into_iter().collect())
+}
+
+/// Rank stored passages against the query embedding, returning the matching chunks
+#[tracing::instrument(name = "SEARCH", skip_all)]
+async fn search_passages(
+    state: &AppState,
+    request: &SearchRequest,
+    query_embedding: &[f32],
+    limit: usize,
+    min_similarity: f32,
+) -> Result<SearchResponse, axum::http::StatusCode> {
+    let search_start = std::time::Instant::now();
+    let now = chrono::Utc::now().timestamp();
+    let passages = state.storage.get_all_passage_embeddings().await.map_err(|e| {
+        error!("Error getting passage embeddings: {}", e);
+        axum::http::StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    if passages.is_empty() {
+        warn!("No passages indexed (is index_passages disabled?)");
+    }
+    let candidate_count = passages.len();
+
+    let metric = state.config.similarity_metric;
+    let query_norm = vector_norm(query_embedding);
+    let excluded_extensions = &state.config.file_type_filters.excluded_extensions;
+    let filters = request.filters.as_ref().map(FilterOptions::with_expanded_file_types);
+    let mut filtered_count = 0;
+    let mut scored: Vec<(FileMetadata, Passage, f32, Option<ScoreBreakdown>)> = passages
+        .into_iter()
+        .filter(|(metadata, _, _)| match filters {
+            Some(ref filters) => matches_filters(metadata, filters, excluded_extensions),
+            None => !is_excluded_extension(metadata, excluded_extensions),
+        })
+        .inspect(|_| filtered_count += 1)
+        .map(|(metadata, passage, embedding)| {
+            let similarity = vector_similarity(metric, query_embedding, &embedding);
+            let similarity = scale_similarity(metric, similarity, query_norm);
+            (metadata, passage, similarity)
+        })
+        .filter(|(_, _, similarity)| *similarity >= min_similarity)
+        .map(|(metadata, passage, similarity)| {
+            let boosts = result_boosts(&metadata, &state.config, now);
+            let breakdown = request.explain.then(|| ScoreBreakdown::vector_only(similarity).with_boosts(boosts));
+            (metadata, passage, similarity * boosts.product(), breakdown)
+        })
+        .collect();
+
+    scored.sort_by(|a, b| {
+        b.2.partial_cmp(&a.2)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.0.file_path.cmp(&b.0.file_path))
+            .then_with(|| a.1.byte_start.cmp(&b.1.byte_start))
+    });
+    debug!("Passage search over {} chunks completed in {:.2}ms",
+             scored.len(), search_start.elapsed().as_secs_f64() * 1000.0);
+
+    let diagnostic = scored.is_empty().then(|| match candidate_count {
+        0 => "no passages indexed".to_string(),
+        _ => empty_diagnostic(candidate_count, filtered_count),
+    });
+
+    let mut results: Vec<SearchResult> = scored
+        .into_iter()
+        .take(limit)
+        .map(|(metadata, passage, similarity, score_breakdown)| SearchResult {
+            file_path: metadata.file_path,
+            file_name: metadata.file_name,
+            modified_time: metadata.modified_time,
+            file_size: metadata.file_size,
+            similarity: similarity.min(1.0),
+            preview: Some(passage.text.clone()),
+            content_indexed: true,
+            is_directory: false,
+            passage: Some(passage),
+            score_breakdown,
+            fusion_score: None,
+            duplicate_count: None,
+            duplicate_paths: None,
+        })
+        .collect();
+    calibrate_scores(&mut results, state.config.score_calibration);
+
+    Ok(SearchResponse {
+        results,
+        metadata_only_results: None,
+        semantic_unavailable: false,
+        diagnostic,
+    })
+}
+
+/// Rescale the returned similarities for display relative to each other, keeping their order.
+/// The raw score stays in the breakdown's `final_score`. Results without any spread are left raw,
+/// since there is nothing to calibrate against.
+fn calibrate_scores(results: &mut [SearchResult], calibration: ScoreCalibration) {
+    if calibration == ScoreCalibration::None || results.len() < 2 {
+        return;
+    }
+    let count = results.len() as f32;
+    let (min, max) = results
+        .iter()
+        .fold((f32::MAX, f32::MIN), |(min, max), r| (min.min(r.similarity), max.max(r.similarity)));
+    let mean = results.iter().map(|r| r.similarity).sum::<f32>() / count;
+    let std_dev = (results.iter().map(|r| (r.similarity - mean).powi(2)).sum::<f32>() / count).sqrt();
+    if std_dev < f32::EPSILON {
+        return;
+    }
+
+    for result in results.iter_mut() {
+        let calibrated = match calibration {
+            ScoreCalibration::None => result.similarity,
+            ScoreCalibration::MinMax => (result.similarity - min) / (max - min),
+            ScoreCalibration::Sigmoid => 1.0 / (1.0 + (-(result.similarity - mean) / std_dev).exp()),
+        };
+        result.similarity = calibrated;
+        if let Some(ref mut breakdown) = result.score_breakdown {
+            breakdown.calibrated_score = Some(calibrated);
+        }
+    }
+}
+
+/// Explain an empty result list given how many candidates there were and how many survived the filters
+fn empty_diagnostic(candidates: usize, after_filters: usize) -> String {
+    if after_filters == 0 {
+        format!("all {} candidates removed by filters", candidates)
+    } else {
+        format!("{} candidates below min_similarity", after_filters)
+    }
+}
+
+/// The file a "{path}#section{n}" row belongs to; a whole-file row is its own
+fn section_base(file_path: &str) -> &str {
+    match file_path.rsplit_once("#section") {
+        Some((base_path, section)) if section.parse::<usize>().is_ok() => base_path,
+        _ => file_path,
+    }
+}
+
+/// Index of each file's best-scoring row, keyed by base path; on a tie the earlier row wins
+fn best_section_rows(results: &[(FileMetadata, f32)]) -> HashMap<&str, usize> {
+    let mut best: HashMap<&str, usize> = HashMap::new();
+    for (index, (metadata, score)) in results.iter().enumerate() {
+        let best_index = best.entry(section_base(&metadata.file_path)).or_insert(index);
+        if results[*best_index].1 < *score {
+            *best_index = index;
+        }
+    }
+    best
+}
+
+/// Merge "{path}#section{n}" entries into their base file, keeping the best score
+fn collapse_sections(results: Vec<(FileMetadata, f32)>) -> Vec<(FileMetadata, f32)> {
+    let kept: std::collections::HashSet<usize> = best_section_rows(&results).into_values().collect();
+
+    results
+        .into_iter()
+        .enumerate()
+        .filter(|(index, _)| kept.contains(index))
+        .map(|(_, (mut metadata, score))| {
+            if let Some((base_path, section)) = metadata.file_path.rsplit_once("#section") {
+                if section.parse::<usize>().is_ok() {
+                    let suffix = format!(" (section {})", section);
+                    metadata.file_name = metadata.file_name.trim_end_matches(&suffix).to_string();
+                    metadata.file_path = base_path.to_string();
                 }
             }
+            (metadata, score)
+        })
+        .collect()
+}
 
-            // Apply global file type exclusion (normalize: "mca" and ".mca" both match)
-            if !excluded_extensions.is_empty() {
-                let file_ext = std::path::Path::new(&metadata.file_path)
-                    .extension()
-                    .and_then(|e| e.to_str())
-                    .unwrap_or("")
-                    .to_lowercase();
-                
-                if excluded_extensions.iter()
-                    .any(|e| e.trim_start_matches('.').to_lowercase() == file_ext) {
-                    return false;
+/// Re-key `breakdowns` by base file path, keeping the breakdown of the row `collapse_sections`
+/// keeps for each file in `results`
+fn collapse_breakdowns(
+    results: &[(FileMetadata, f32)],
+    mut breakdowns: HashMap<String, ScoreBreakdown>,
+) -> HashMap<String, ScoreBreakdown> {
+    best_section_rows(results)
+        .into_iter()
+        .filter_map(|(base_path, index)| {
+            breakdowns.remove(&results[index].0.file_path).map(|breakdown| (base_path.to_string(), breakdown))
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SimilarFilesRequest {
+    pub file_path: String,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SimilarFilesResponse {
+    success: bool,
+    results: Vec<SearchResult>,
+    error: Option<String>,
+}
+
+impl SimilarFilesResponse {
+    fn error(message: impl Into<String>) -> Json<Self> {
+        Json(Self {
+            success: false,
+            results: Vec::new(),
+            error: Some(message.into()),
+        })
+    }
+}
+
+/// Average a set of equally sized embeddings (used for multi-section files)
+fn average_embeddings(embeddings: &[Vec<f32>]) -> Option<Vec<f32>> {
+    let first = embeddings.first()?;
+    let mut sum = vec![0.0f32; first.len()];
+    let mut count = 0usize;
+    for embedding in embeddings.iter().filter(|e| e.len() == first.len()) {
+        for (acc, value) in sum.iter_mut().zip(embedding) {
+            *acc += value;
+        }
+        count += 1;
+    }
+    Some(sum.into_iter().map(|v| v / count as f32).collect())
+}
+
+/// Find files whose content is closest to an already indexed file
+#[tracing::instrument(name = "SIMILAR", skip_all)]
+pub async fn similar_files(
+    State(state): State<AppState>,
+    Json(request): Json<SimilarFilesRequest>,
+) -> Result<Json<SimilarFilesResponse>, axum::http::StatusCode> {
+    if request.file_path.trim().is_empty() {
+        return Ok(SimilarFilesResponse::error("File path is required"));
+    }
+    // Stored paths are canonical (see Indexer::canonical_path)
+    let file_path = &crate::indexer::Indexer::canonical_path(request.file_path.trim());
+    let limit = request.limit.unwrap_or(state.config.max_search_results).min(state.config.max_search_results_cap);
+
+    let metadata = match state.storage.get_file_metadata(file_path).await {
+        Ok(Some(metadata)) => metadata,
+        Ok(None) => return Ok(SimilarFilesResponse::error(format!("File is not indexed: {}", file_path))),
+        Err(e) => {
+            error!("Error loading metadata for {}: {}", file_path, e);
+            return Err(axum::http::StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+    if metadata.embedding_length == 0 {
+        return Ok(SimilarFilesResponse::error(format!(
+            "{} is indexed by filename only, so there is no content embedding to compare",
+            metadata.file_name
+        )));
+    }
+
+    // Extra sections of large files are stored as "{path}#section{n}"
+    let section_prefix = format!("{}#section", file_path);
+    let sections: Vec<_> = state.storage.get_all_files().await
+        .map_err(|e| {
+            error!("Error listing files: {}", e);
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .into_iter()
+        .filter(|f| f.file_path.starts_with(&section_prefix) && f.embedding_length > 0)
+        .collect();
+
+    let mut embeddings = Vec::with_capacity(1 + sections.len());
+    for meta in std::iter::once(&metadata).chain(sections.iter()) {
+        match state.storage.get_embedding(meta).await {
+            Ok(embedding) if !embedding.is_empty() => embeddings.push(embedding),
+            Ok(_) => {}
+            Err(e) => error!("Error loading embedding for {}: {}", meta.file_path, e),
+        }
+    }
+    let Some(mut query_embedding) = average_embeddings(&embeddings) else {
+        return Ok(SimilarFilesResponse::error(format!("No stored embedding found for {}", metadata.file_name)));
+    };
+    let metric = state.config.similarity_metric;
+    prepare_vector(metric, &mut query_embedding);
+    let query_norm = vector_norm(&query_embedding);
+    debug!("Finding files similar to {} ({} section(s))", file_path, embeddings.len());
+
+    let is_self = |path: &str| path == file_path || path.starts_with(&section_prefix);
+    // Over-fetch so excluding the file's own sections still leaves enough results
+    let fetch = limit + embeddings.len();
+
+    let mut results: Vec<(crate::storage::FileMetadata, f32)> = Vec::new();
+    {
+        let hnsw_guard = state.hnsw_index.read().await;
+        if let Some(ref hnsw) = *hnsw_guard {
+            if hnsw.len() > 0 {
+                if let Ok(hnsw_results) = hnsw.search(query_embedding.clone(), fetch) {
+                    results = hnsw_results;
                 }
             }
+        }
+    }
+
+    if results.is_empty() {
+        debug!("Using linear search");
+        let files_with_embeddings = state.storage.get_all_embeddings().await.map_err(|e| {
+            error!("Error getting embeddings: {}", e);
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+        results = files_with_embeddings
+            .into_iter()
+            .map(|(meta, embedding)| {
+                let similarity = vector_similarity(metric, &query_embedding, &embedding);
+                let similarity = scale_similarity(metric, similarity, query_norm);
+                (meta, similarity)
+            })
+            .collect();
+    }
+
+    results.retain(|(meta, _)| !is_self(&meta.file_path));
+    results = collapse_sections(results);
+
+    if state.config.filter_duplicate_files {
+        results = deduplicate_by_embedding(results, &state).await;
+    }
 
-            true
+    results.sort_by(by_score_then_path);
+
+    let results = results
+        .into_iter()
+        .take(limit)
+        .map(|(metadata, similarity)| SearchResult {
+            file_path: metadata.file_path,
+            file_name: metadata.file_name,
+            modified_time: metadata.modified_time,
+            file_size: metadata.file_size,
+            similarity,
+            preview: None,
+            content_indexed: metadata.embedding_length > 0,
+            is_directory: false,
+            passage: None,
+            score_breakdown: None,
+            fusion_score: None,
+            duplicate_count: None,
+            duplicate_paths: None,
         })
+        .collect();
+
+    Ok(Json(SimilarFilesResponse {
+        success: true,
+        results,
+        error: None,
+    }))
+}
+
+// Apply filters to search results
+fn apply_filters(
+    results: Vec<(crate::storage::FileMetadata, f32)>,
+    filters: &FilterOptions,
+    excluded_extensions: &[String],
+) -> Vec<(crate::storage::FileMetadata, f32)> {
+    let filters = filters.with_expanded_file_types();
+    results
+        .into_iter()
+        .filter(|(metadata, _)| matches_filters(metadata, &filters, excluded_extensions))
         .collect()
 }
 
+/// Check a single file against the request filters and the global extension exclusions
+fn matches_filters(
+    metadata: &FileMetadata,
+    filters: &FilterOptions,
+    excluded_extensions: &[String],
+) -> bool {
+    // Apply date filter
+    if let Some(ref date_range) = filters.date_range {
+        if !matches_date_range(metadata.modified_time, date_range) {
+            return false;
+        }
+    }
+
+    // Apply file type filter
+    if let Some(ref file_types) = filters.file_types {
+        let file_ext = file_extension(metadata);
+        if !file_types.contains(&file_ext) {
+            return false;
+        }
+    }
+
+    // Apply folder path filter
+    if let Some(ref folder_paths) = filters.folder_paths {
+        let file_path_lower = metadata.file_path.to_lowercase();
+        let matches_folder = folder_paths.iter().any(|folder| {
+            let folder_lower = folder.to_lowercase();
+            // Check if file path contains folder name (case-insensitive)
+            file_path_lower.contains(&folder_lower)
+        });
+        
+        if !matches_folder {
+            return false;
+        }
+    }
+
+    !is_excluded_extension(metadata, excluded_extensions)
+}
+
+fn file_extension(metadata: &FileMetadata) -> String {
+    std::path::Path::new(&metadata.file_path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase()
+}
+
+/// Global file type exclusion (normalize: "mca" and ".mca" both match)
+fn is_excluded_extension(metadata: &FileMetadata, excluded_extensions: &[String]) -> bool {
+    if excluded_extensions.is_empty() {
+        return false;
+    }
+    let file_ext = file_extension(metadata);
+    excluded_extensions.iter()
+        .any(|e| e.trim_start_matches('.').to_lowercase() == file_ext)
+}
+
 /// Deduplicate results by identical embeddings. When two files have the same embedding,
 /// keep only the one with the lexicographically smaller file_path (e.g., fileA.pdf before fileA (1).pdf).
 /// Files without embeddings (metadata-only) are kept as-is.
@@ -630,6 +1364,56 @@ pub(crate) async fn deduplicate_by_embedding(
     deduped
 }
 
+/// How far past `limit` ranked results are scanned for duplicates of the representatives
+const DUPLICATE_SCAN_FACTOR: usize = 4;
+
+/// A ranked result and its embedding, if it has one
+type RankedWithEmbedding = ((FileMetadata, f32), Option<Vec<f32>>);
+/// Cluster representatives in rank order, and the paths each one absorbed
+type DuplicateClusters = (Vec<(FileMetadata, f32)>, HashMap<String, Vec<String>>);
+
+/// Fold ranked results whose embeddings are at least `duplicate_cluster_similarity` alike into
+/// the best-ranked member of their cluster. Returns the representatives in rank order and, for
+/// each one that absorbed others, the paths it absorbed.
+async fn cluster_duplicates(
+    results: Vec<(FileMetadata, f32)>,
+    limit: usize,
+    state: &AppState,
+) -> DuplicateClusters {
+    let mut ranked = Vec::new();
+    for (meta, score) in results.into_iter().take(limit.saturating_mul(DUPLICATE_SCAN_FACTOR)) {
+        let embedding = if meta.embedding_length > 0 {
+            state.storage.get_embedding(&meta).await.ok()
+        } else {
+            None
+        };
+        ranked.push(((meta, score), embedding));
+    }
+    group_duplicates(ranked, state.config.duplicate_cluster_similarity)
+}
+
+/// Greedy clustering in rank order: a result joins the first representative it is at least
+/// `threshold` similar to, otherwise it becomes one. Results without an embedding stand alone.
+fn group_duplicates(
+    ranked: Vec<RankedWithEmbedding>,
+    threshold: f32,
+) -> DuplicateClusters {
+    let mut representatives: Vec<RankedWithEmbedding> = Vec::new();
+    let mut duplicates: HashMap<String, Vec<String>> = HashMap::new();
+    for (result, embedding) in ranked {
+        let cluster = embedding.as_ref().and_then(|embedding| {
+            representatives.iter().find(|(_, rep)| {
+                rep.as_ref().is_some_and(|rep| cosine_similarity(rep, embedding) >= threshold)
+            })
+        });
+        match cluster {
+            Some(((rep, _), _)) => duplicates.entry(rep.file_path.clone()).or_default().push(result.0.file_path),
+            None => representatives.push((result, embedding)),
+        }
+    }
+    (representatives.into_iter().map(|(result, _)| result).collect(), duplicates)
+}
+
 /// Check if a timestamp matches the date range filter
 fn matches_date_range(timestamp: i64, date_range: &DateRange) -> bool {
     // If start/end timestamps are provided, use those
@@ -663,3 +1447,129 @@ fn matches_date_range(timestamp: i64, date_range: &DateRange) -> bool {
 
     true
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calibration_rescales_without_reordering() {
+        let result = |similarity: f32| SearchResult {
+            file_path: String::new(),
+            file_name: String::new(),
+            modified_time: 0,
+            file_size: 0,
+            similarity,
+            preview: None,
+            content_indexed: true,
+            is_directory: false,
+            passage: None,
+            score_breakdown: Some(ScoreBreakdown::vector_only(similarity)),
+            fusion_score: None,
+            duplicate_count: None,
+            duplicate_paths: None,
+        };
+
+        let mut results = vec![result(0.78), result(0.72), result(0.66)];
+        calibrate_scores(&mut results, ScoreCalibration::MinMax);
+        let scores: Vec<f32> = results.iter().map(|r| r.similarity).collect();
+        assert!((scores[0] - 1.0).abs() < 1e-5 && (scores[1] - 0.5).abs() < 1e-5 && scores[2].abs() < 1e-5);
+        // The raw score stays in the breakdown
+        assert_eq!(results[0].score_breakdown.as_ref().unwrap().final_score, 0.78);
+
+        let mut results = vec![result(0.78), result(0.72), result(0.66)];
+        calibrate_scores(&mut results, ScoreCalibration::Sigmoid);
+        assert!(results[0].similarity > 0.7 && (results[1].similarity - 0.5).abs() < 1e-5 && results[2].similarity < 0.3);
+
+        // A lone result has nothing to be calibrated against
+        let mut results = vec![result(0.72)];
+        calibrate_scores(&mut results, ScoreCalibration::MinMax);
+        assert_eq!(results[0].similarity, 0.72);
+    }
+
+    #[test]
+    fn test_group_duplicates_keeps_best_ranked_copy() {
+        let result = |path: &str| FileMetadata {
+            id: 0,
+            file_path: path.to_string(),
+            file_name: path.to_string(),
+            file_size: 1,
+            modified_time: 0,
+            file_type: "txt".to_string(),
+            embedding_offset: 0,
+            embedding_length: if path == "notes" { 0 } else { 8 },
+        };
+        let ranked = vec![
+            ((result("report"), 0.9), Some(vec![1.0, 0.0])),
+            ((result("other"), 0.8), Some(vec![0.0, 1.0])),
+            ((result("report (1)"), 0.7), Some(vec![0.999, 0.0447])),
+            ((result("notes"), 0.6), None),
+        ];
+
+        let (representatives, duplicates) = group_duplicates(ranked, 0.98);
+        let paths: Vec<&str> = representatives.iter().map(|(meta, _)| meta.file_path.as_str()).collect();
+        assert_eq!(paths, vec!["report", "other", "notes"]);
+        assert_eq!(duplicates.get("report"), Some(&vec!["report (1)".to_string()]));
+        assert!(!duplicates.contains_key("other"));
+    }
+
+    #[test]
+    fn test_equal_scores_sort_by_path() {
+        let result = |path: &str, similarity: f32| (
+            FileMetadata {
+                id: 0,
+                file_path: path.to_string(),
+                file_name: path.to_string(),
+                file_size: 0,
+                modified_time: 0,
+                file_type: "txt".to_string(),
+                embedding_offset: 0,
+                embedding_length: 0,
+            },
+            similarity,
+        );
+        let mut results = [result("/b", 0.5), result("/c", 0.9), result("/a", 0.5)];
+        results.sort_by(by_score_then_path);
+        let paths: Vec<&str> = results.iter().map(|(meta, _)| meta.file_path.as_str()).collect();
+        assert_eq!(paths, vec!["/c", "/a", "/b"]);
+    }
+
+    #[test]
+    fn test_boosts_rank_uncapped_but_report_capped() {
+        let fresh = ResultBoosts { path: 1.0, file_type: 1.0, freshness: 1.5 };
+        let strong = ScoreBreakdown::vector_only(0.95).with_boosts(fresh);
+        let weaker = ScoreBreakdown::vector_only(0.8).with_boosts(fresh);
+
+        assert!(strong.ranking_score > weaker.ranking_score && weaker.ranking_score > 1.0);
+        assert_eq!((strong.final_score, weaker.final_score), (1.0, 1.0));
+    }
+
+    #[test]
+    fn test_collapsed_breakdown_follows_best_section() {
+        let row = |path: &str, score: f32| (
+            FileMetadata {
+                id: 0,
+                file_path: path.to_string(),
+                file_name: path.to_string(),
+                file_size: 1,
+                modified_time: 0,
+                file_type: "txt".to_string(),
+                embedding_offset: 0,
+                embedding_length: 8,
+            },
+            score,
+        );
+        let results = vec![row("/a.txt", 0.4), row("/a.txt#section2", 0.7), row("/b.txt", 0.5)];
+        let breakdowns = results
+            .iter()
+            .map(|(meta, score)| (meta.file_path.clone(), ScoreBreakdown::vector_only(*score)))
+            .collect();
+
+        let breakdowns = collapse_breakdowns(&results, breakdowns);
+        let collapsed = collapse_sections(results);
+        assert_eq!(collapsed.len(), 2);
+        for (meta, score) in collapsed {
+            assert_eq!(breakdowns[&meta.file_path].adjusted, score);
+        }
+    }
+}
@@ -3,6 +3,7 @@ use axum::{
     response::Json,
 };
 use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
 
 use crate::AppState;
 
@@ -13,14 +14,85 @@ pub struct SettingsResponse {
     indexed_directories: Vec<String>,
     file_type_filters: FileTypeFiltersResponse,
     chunk_size: usize,
+    chunk_overlap: usize,
     max_context_tokens: usize,
     auto_index: bool,
     max_search_results: usize,
+    max_search_results_cap: usize,
     filter_duplicate_files: bool,
     ai_features_enabled: bool,
     ai_provider: String,
     ollama_model: Option<String>,
     gemini_model: Option<String>,
+    rag_max_chars_per_doc: usize,
+    rag_excerpt_chars: usize,
+    boost_paths: Vec<crate::config::PathBoost>,
+    freshness_half_life_days: f32,
+    freshness_weight: f32,
+    type_boosts: std::collections::HashMap<String, f32>,
+    ignore_patterns: Vec<String>,
+    max_index_depth: Option<usize>,
+    max_content_file_size_bytes: u64,
+    follow_symlinks: bool,
+    index_hidden: bool,
+    index_hidden_dirs: Vec<String>,
+    index_directory_names: bool,
+    metadata_only_extensions: Vec<String>,
+    excluded_filenames: Vec<String>,
+    spell_correction: bool,
+    allow_future_dates: bool,
+    fuzzy_max_edit_distance: usize,
+    normalize_filenames: bool,
+    filename_stop_tokens: Vec<String>,
+    semantic_keywords: Vec<String>,
+    ollama_base_url: String,
+    embedding_provider: String,
+    openai_embedding_model: String,
+    fusion_embedding_models: Vec<String>,
+    embedding_base_url: Option<String>,
+    /// Whether an embedding API key is stored; the key itself is never sent
+    embedding_api_key_set: bool,
+    openai_base_url: String,
+    openai_model: Option<String>,
+    greenpt_model: String,
+    max_tokens: Option<u32>,
+    temperature: Option<f32>,
+    ai_request_timeout_secs: u64,
+    rag_total_timeout_secs: u64,
+    ai_max_concurrent_requests: usize,
+    ai_max_queued_requests: usize,
+    index_passages: bool,
+    index_archives: bool,
+    archive_max_depth: usize,
+    archive_max_mb: u64,
+    archive_text_entry_max_bytes: u64,
+    spreadsheet_header_context: bool,
+    spreadsheet_sheet_sections: bool,
+    hash_file_contents: bool,
+    skip_empty_files: bool,
+    index_excluded_as_metadata: bool,
+    embedding_truncation: String,
+    similarity_metric: String,
+    score_calibration: String,
+    persist_query_cache: bool,
+    query_cache_ttl_secs: u64,
+    log_level: String,
+    content_search_extensions: Vec<String>,
+    content_search_max_bytes: u64,
+    auto_maintenance: bool,
+    auto_maintenance_idle_secs: u64,
+    auto_maintenance_bloat_ratio: f32,
+    text_cache_max_mb: u64,
+    hnsw_m: usize,
+    hnsw_ef_construction: usize,
+    hnsw_ef_search: usize,
+    min_search_similarity: f32,
+    duplicate_cluster_similarity: f32,
+    chat_context_tokens: usize,
+    provider_context_tokens: crate::config::ProviderContextTokens,
+    rag_min_similarity: f32,
+    rag_default_documents: usize,
+    rag_max_documents: usize,
     #[serde(skip_serializing_if = "Option::is_none")]
     api_key: Option<String>, // Don't send API key to frontend for security
 }
@@ -40,15 +112,95 @@ pub struct UpdateSettingsRequest {
     indexed_directories: Option<Vec<String>>,
     file_type_filters: Option<FileTypeFiltersRequest>,
     chunk_size: Option<usize>,
+    chunk_overlap: Option<usize>,
     max_context_tokens: Option<usize>,
     auto_index: Option<bool>,
     max_search_results: Option<usize>,
+    max_search_results_cap: Option<usize>,
     filter_duplicate_files: Option<bool>,
     ai_features_enabled: Option<bool>,
     ai_provider: Option<String>,
     ollama_model: Option<String>,
     gemini_model: Option<String>,
     api_key: Option<String>,
+    rag_max_chars_per_doc: Option<usize>,
+    rag_excerpt_chars: Option<usize>,
+    boost_paths: Option<Vec<crate::config::PathBoost>>,
+    freshness_half_life_days: Option<f32>,
+    freshness_weight: Option<f32>,
+    /// Replaces all file type boosts
+    type_boosts: Option<std::collections::HashMap<String, f32>>,
+    ignore_patterns: Option<Vec<String>>,
+    /// 0 clears the limit
+    max_index_depth: Option<usize>,
+    max_content_file_size_bytes: Option<u64>,
+    follow_symlinks: Option<bool>,
+    index_hidden: Option<bool>,
+    index_hidden_dirs: Option<Vec<String>>,
+    index_directory_names: Option<bool>,
+    metadata_only_extensions: Option<Vec<String>>,
+    excluded_filenames: Option<Vec<String>>,
+    spell_correction: Option<bool>,
+    allow_future_dates: Option<bool>,
+    fuzzy_max_edit_distance: Option<usize>,
+    normalize_filenames: Option<bool>,
+    filename_stop_tokens: Option<Vec<String>>,
+    semantic_keywords: Option<Vec<String>>,
+    ollama_base_url: Option<String>,
+    /// "ollama" or "openai"
+    embedding_provider: Option<String>,
+    openai_embedding_model: Option<String>,
+    fusion_embedding_models: Option<Vec<String>>,
+    /// Empty string clears the override
+    embedding_base_url: Option<String>,
+    /// Empty string clears the key
+    embedding_api_key: Option<String>,
+    openai_base_url: Option<String>,
+    openai_model: Option<String>,
+    greenpt_model: Option<String>,
+    /// 0 resets to the default
+    max_tokens: Option<u32>,
+    /// Negative resets to the default
+    temperature: Option<f32>,
+    ai_request_timeout_secs: Option<u64>,
+    rag_total_timeout_secs: Option<u64>,
+    ai_max_concurrent_requests: Option<usize>,
+    ai_max_queued_requests: Option<usize>,
+    index_passages: Option<bool>,
+    index_archives: Option<bool>,
+    archive_max_depth: Option<usize>,
+    archive_max_mb: Option<u64>,
+    archive_text_entry_max_bytes: Option<u64>,
+    spreadsheet_header_context: Option<bool>,
+    spreadsheet_sheet_sections: Option<bool>,
+    hash_file_contents: Option<bool>,
+    skip_empty_files: Option<bool>,
+    index_excluded_as_metadata: Option<bool>,
+    /// "head", "head_tail" or "sampled"
+    embedding_truncation: Option<String>,
+    /// "cosine", "dot" or "euclidean"; takes effect after a restart, and after a reindex when leaving cosine
+    similarity_metric: Option<String>,
+    score_calibration: Option<String>,
+    persist_query_cache: Option<bool>,
+    query_cache_ttl_secs: Option<u64>,
+    log_level: Option<String>,
+    content_search_extensions: Option<Vec<String>>,
+    content_search_max_bytes: Option<u64>,
+    auto_maintenance: Option<bool>,
+    auto_maintenance_idle_secs: Option<u64>,
+    auto_maintenance_bloat_ratio: Option<f32>,
+    text_cache_max_mb: Option<u64>,
+    hnsw_m: Option<usize>,
+    hnsw_ef_construction: Option<usize>,
+    hnsw_ef_search: Option<usize>,
+    min_search_similarity: Option<f32>,
+    duplicate_cluster_similarity: Option<f32>,
+    chat_context_tokens: Option<usize>,
+    /// Replaces all per-provider budgets; omitted providers take their defaults
+    provider_context_tokens: Option<crate::config::ProviderContextTokens>,
+    rag_min_similarity: Option<f32>,
+    rag_default_documents: Option<usize>,
+    rag_max_documents: Option<usize>,
 }
 
 #[derive(Deserialize)]
@@ -60,6 +212,7 @@ pub struct FileTypeFiltersRequest {
     excluded_extensions: Option<Vec<String>>,
 }
 
+#[tracing::instrument(name = "SETTINGS", skip_all)]
 pub async fn get_settings(State(state): State<AppState>) -> Json<SettingsResponse> {
     // Reload config from disk to ensure we have the latest values
     // This ensures settings persist correctly after save
@@ -83,12 +236,14 @@ pub async fn get_settings(State(state): State<AppState>) -> Json<SettingsRespons
             excluded_extensions: config.file_type_filters.excluded_extensions.clone(),
         },
         chunk_size: config.chunk_size,
+        chunk_overlap: config.chunk_overlap,
         max_context_tokens: config.max_context_tokens,
         auto_index: config.auto_index,
         max_search_results: config.max_search_results,
+        max_search_results_cap: config.max_search_results_cap,
         filter_duplicate_files: config.filter_duplicate_files,
         ai_features_enabled: {
-            eprintln!("[SETTINGS] get_settings returning ai_features_enabled = {}", config.ai_features_enabled);
+            info!("get_settings returning ai_features_enabled = {}", config.ai_features_enabled);
             config.ai_features_enabled
         },
         ai_provider: match config.ai_provider {
@@ -99,10 +254,104 @@ pub async fn get_settings(State(state): State<AppState>) -> Json<SettingsRespons
         },
         ollama_model: config.ollama_model.clone(),
         gemini_model: config.gemini_model.clone(),
+        rag_max_chars_per_doc: config.rag_max_chars_per_doc,
+        rag_excerpt_chars: config.rag_excerpt_chars,
+        boost_paths: config.boost_paths.clone(),
+        freshness_half_life_days: config.freshness_half_life_days,
+        freshness_weight: config.freshness_weight,
+        type_boosts: config.type_boosts.clone(),
+        ignore_patterns: config.ignore_patterns.clone(),
+        max_index_depth: config.max_index_depth,
+        max_content_file_size_bytes: config.max_content_file_size_bytes,
+        follow_symlinks: config.follow_symlinks,
+        index_hidden: config.index_hidden,
+        index_hidden_dirs: config.index_hidden_dirs.clone(),
+        index_directory_names: config.index_directory_names,
+        metadata_only_extensions: config.metadata_only_extensions.clone(),
+        excluded_filenames: config.excluded_filenames.clone(),
+        spell_correction: config.spell_correction,
+        allow_future_dates: config.allow_future_dates,
+        fuzzy_max_edit_distance: config.fuzzy_max_edit_distance,
+        normalize_filenames: config.normalize_filenames,
+        filename_stop_tokens: config.filename_stop_tokens.clone(),
+        semantic_keywords: config.semantic_keywords.clone(),
+        ollama_base_url: config.ollama_base_url.clone(),
+        embedding_provider: match config.embedding_provider {
+            crate::config::EmbeddingProvider::Ollama => "ollama".to_string(),
+            crate::config::EmbeddingProvider::OpenAI => "openai".to_string(),
+        },
+        openai_embedding_model: config.openai_embedding_model.clone(),
+        fusion_embedding_models: config.fusion_embedding_models.clone(),
+        embedding_base_url: config.embedding_base_url.clone(),
+        embedding_api_key_set: config.embedding_api_key.is_some(),
+        openai_base_url: config.openai_base_url.clone(),
+        openai_model: config.openai_model.clone(),
+        greenpt_model: config.greenpt_model.clone(),
+        max_tokens: config.max_tokens,
+        temperature: config.temperature,
+        ai_request_timeout_secs: config.ai_request_timeout_secs,
+        rag_total_timeout_secs: config.rag_total_timeout_secs,
+        ai_max_concurrent_requests: config.ai_max_concurrent_requests,
+        ai_max_queued_requests: config.ai_max_queued_requests,
+        index_passages: config.index_passages,
+        index_archives: config.index_archives,
+        archive_max_depth: config.archive_max_depth,
+        archive_max_mb: config.archive_max_mb,
+        archive_text_entry_max_bytes: config.archive_text_entry_max_bytes,
+        spreadsheet_header_context: config.spreadsheet_header_context,
+        spreadsheet_sheet_sections: config.spreadsheet_sheet_sections,
+        hash_file_contents: config.hash_file_contents,
+        skip_empty_files: config.skip_empty_files,
+        index_excluded_as_metadata: config.index_excluded_as_metadata,
+        embedding_truncation: match config.embedding_truncation {
+            crate::config::EmbeddingTruncation::Head => "head".to_string(),
+            crate::config::EmbeddingTruncation::HeadTail => "head_tail".to_string(),
+            crate::config::EmbeddingTruncation::Sampled => "sampled".to_string(),
+        },
+        similarity_metric: match config.similarity_metric {
+            crate::config::SimilarityMetric::Cosine => "cosine".to_string(),
+            crate::config::SimilarityMetric::Dot => "dot".to_string(),
+            crate::config::SimilarityMetric::Euclidean => "euclidean".to_string(),
+        },
+        score_calibration: match config.score_calibration {
+            crate::config::ScoreCalibration::None => "none".to_string(),
+            crate::config::ScoreCalibration::MinMax => "min_max".to_string(),
+            crate::config::ScoreCalibration::Sigmoid => "sigmoid".to_string(),
+        },
+        persist_query_cache: config.persist_query_cache,
+        query_cache_ttl_secs: config.query_cache_ttl_secs,
+        log_level: config.log_level.clone(),
+        content_search_extensions: config.content_search_extensions.clone(),
+        content_search_max_bytes: config.content_search_max_bytes,
+        auto_maintenance: config.auto_maintenance,
+        auto_maintenance_idle_secs: config.auto_maintenance_idle_secs,
+        auto_maintenance_bloat_ratio: config.auto_maintenance_bloat_ratio,
+        text_cache_max_mb: config.text_cache_max_mb,
+        hnsw_m: config.hnsw_m,
+        hnsw_ef_construction: config.hnsw_ef_construction,
+        hnsw_ef_search: config.hnsw_ef_search,
+        min_search_similarity: config.min_search_similarity,
+        duplicate_cluster_similarity: config.duplicate_cluster_similarity,
+        chat_context_tokens: config.chat_context_tokens,
+        provider_context_tokens: config.provider_context_tokens.clone(),
+        rag_min_similarity: config.rag_min_similarity,
+        rag_default_documents: config.rag_default_documents,
+        rag_max_documents: config.rag_max_documents,
         api_key: None, // Never send API key to frontend
     })
 }
 
+/// Normalize a server base URL, or None if it isn't a well-formed http(s) URL
+fn validate_base_url(url: &str) -> Option<String> {
+    let trimmed = url.trim().trim_end_matches('/');
+    let parsed = reqwest::Url::parse(trimmed).ok()?;
+    if !matches!(parsed.scheme(), "http" | "https") || parsed.host_str().is_none() {
+        return None;
+    }
+    Some(trimmed.to_string())
+}
+
+#[tracing::instrument(name = "SETTINGS", skip_all)]
 pub async fn update_settings(
     State(state): State<AppState>,
     Json(request): Json<UpdateSettingsRequest>,
@@ -138,12 +387,12 @@ pub async fn update_settings(
                     if !dirs.contains(old_dir) {
                         // Stop watching
                         if let Err(e) = watcher.remove_directory(old_dir) {
-                            eprintln!("Warning: Failed to remove directory {} from watcher: {}", old_dir, e);
+                            warn!("Failed to remove directory {} from watcher: {}", old_dir, e);
                         }
                         
                         // Remove from index/storage
                         if let Err(e) = state.storage.remove_directory(old_dir).await {
-                             eprintln!("Warning: Failed to remove directory {} from index: {}", old_dir, e);
+                             warn!("Failed to remove directory {} from index: {}", old_dir, e);
                         } else {
                             needs_reindex = true; 
                         }
@@ -154,7 +403,7 @@ pub async fn update_settings(
                 for new_dir in &dirs {
                     if !old_dirs.contains(new_dir) {
                         if let Err(e) = watcher.add_directory(new_dir) {
-                            eprintln!("Warning: Failed to add directory {} to watcher: {}", new_dir, e);
+                            warn!("Failed to add directory {} to watcher: {}", new_dir, e);
                         }
                     }
                 }
@@ -188,6 +437,12 @@ pub async fn update_settings(
         config.chunk_size = val;
     }
 
+    if let Some(val) = request.chunk_overlap {
+        config.chunk_overlap = val;
+    }
+    // Chunks must advance, so the overlap stays below the chunk size
+    config.chunk_overlap = config.chunk_overlap.min(config.chunk_size.saturating_sub(1));
+
     if let Some(val) = request.max_context_tokens {
         // Clamp between 500 and 8000 tokens
         config.max_context_tokens = val.max(500).min(8000);
@@ -211,9 +466,14 @@ pub async fn update_settings(
         }
     }
 
+    if let Some(val) = request.max_search_results_cap {
+        config.max_search_results_cap = val.clamp(10, 10_000);
+        config.max_search_results = config.max_search_results.min(config.max_search_results_cap);
+    }
+
     if let Some(val) = request.max_search_results {
-        // Clamp between 10 and 200
-        config.max_search_results = val.max(10).min(200);
+        // Clamp between 10 and the configured cap
+        config.max_search_results = val.clamp(10, config.max_search_results_cap.max(10));
     }
 
     if let Some(val) = request.filter_duplicate_files {
@@ -223,10 +483,10 @@ pub async fn update_settings(
     // Always update ai_features_enabled if provided in request
     // Use explicit check to ensure we're updating even if value is false
     if let Some(enabled) = request.ai_features_enabled {
-        eprintln!("[SETTINGS] Updating ai_features_enabled from {} to {}", config.ai_features_enabled, enabled);
+        info!("Updating ai_features_enabled from {} to {}", config.ai_features_enabled, enabled);
         config.ai_features_enabled = enabled;
     } else {
-        eprintln!("[SETTINGS] ai_features_enabled not provided in request, keeping current value: {}", config.ai_features_enabled);
+        info!("ai_features_enabled not provided in request, keeping current value: {}", config.ai_features_enabled);
     }
 
     if let Some(provider_str) = request.ai_provider {
@@ -256,6 +516,397 @@ pub async fn update_settings(
         }
     }
 
+    if let Some(val) = request.rag_max_chars_per_doc {
+        // Clamp between 500 and 50000 characters per document
+        config.rag_max_chars_per_doc = val.clamp(500, 50000);
+    }
+
+    if let Some(val) = request.rag_excerpt_chars {
+        config.rag_excerpt_chars = val.clamp(50, 2000);
+    }
+
+    if let Some(boosts) = request.boost_paths {
+        // Stored paths are canonical, so spell prefixes the same way
+        config.boost_paths = boosts
+            .into_iter()
+            .filter(|boost| !boost.prefix.trim().is_empty() && boost.multiplier.is_finite())
+            .map(|boost| crate::config::PathBoost {
+                prefix: crate::indexer::Indexer::canonical_path(boost.prefix.trim()),
+                multiplier: boost.multiplier.clamp(0.1, 10.0),
+            })
+            .collect();
+    }
+
+    if let Some(val) = request.freshness_half_life_days {
+        if !val.is_finite() {
+            return Err(axum::http::StatusCode::BAD_REQUEST);
+        }
+        config.freshness_half_life_days = val.clamp(0.1, 3650.0);
+    }
+
+    if let Some(val) = request.freshness_weight {
+        if !val.is_finite() {
+            return Err(axum::http::StatusCode::BAD_REQUEST);
+        }
+        config.freshness_weight = val.clamp(0.0, 1.0);
+    }
+
+    if let Some(boosts) = request.type_boosts {
+        if boosts.values().any(|multiplier| !multiplier.is_finite()) {
+            return Err(axum::http::StatusCode::BAD_REQUEST);
+        }
+        config.type_boosts = boosts
+            .into_iter()
+            .map(|(ext, multiplier)| (ext.trim().trim_start_matches('.').to_lowercase(), multiplier.clamp(0.1, 10.0)))
+            .filter(|(ext, _)| !ext.is_empty())
+            .collect();
+    }
+
+    if let Some(patterns) = request.ignore_patterns {
+        config.ignore_patterns = patterns
+            .into_iter()
+            .map(|p| p.trim().to_string())
+            .filter(|p| !p.is_empty())
+            .collect();
+    }
+
+    if let Some(val) = request.max_index_depth {
+        config.max_index_depth = if val == 0 { None } else { Some(val) };
+    }
+
+    if let Some(val) = request.max_content_file_size_bytes {
+        config.max_content_file_size_bytes = val.max(1024);
+    }
+
+    if let Some(val) = request.follow_symlinks {
+        config.follow_symlinks = val;
+    }
+
+    if let Some(val) = request.index_hidden {
+        config.index_hidden = val;
+    }
+
+    if let Some(val) = request.index_directory_names {
+        config.index_directory_names = val;
+    }
+
+    if let Some(dirs) = request.index_hidden_dirs {
+        config.index_hidden_dirs = dirs
+            .into_iter()
+            .map(|d| d.trim().to_string())
+            .filter(|d| !d.is_empty())
+            .collect();
+    }
+
+    if let Some(val) = request.metadata_only_extensions {
+        config.metadata_only_extensions = val
+            .into_iter()
+            .map(|e| e.trim().trim_start_matches('.').to_lowercase())
+            .filter(|e| !e.is_empty())
+            .collect();
+    }
+
+    if let Some(val) = request.excluded_filenames {
+        config.excluded_filenames = val
+            .into_iter()
+            .map(|name| name.trim().to_lowercase())
+            .filter(|name| !name.is_empty())
+            .collect();
+    }
+
+    if let Some(val) = request.spell_correction {
+        config.spell_correction = val;
+    }
+
+    if let Some(val) = request.allow_future_dates {
+        config.allow_future_dates = val;
+    }
+
+    if let Some(val) = request.fuzzy_max_edit_distance {
+        // Clamp to 0-3; larger distances match unrelated words
+        config.fuzzy_max_edit_distance = val.min(3);
+    }
+
+    if let Some(val) = request.normalize_filenames {
+        config.normalize_filenames = val;
+    }
+
+    if let Some(val) = request.filename_stop_tokens {
+        config.filename_stop_tokens = val
+            .into_iter()
+            .map(|token| token.trim().to_lowercase())
+            .filter(|token| !token.is_empty())
+            .collect();
+    }
+
+    if let Some(val) = request.semantic_keywords {
+        config.semantic_keywords = val
+            .into_iter()
+            .map(|keyword| keyword.trim().to_lowercase())
+            .filter(|keyword| !keyword.is_empty())
+            .collect();
+    }
+
+    if let Some(url) = request.ollama_base_url {
+        config.ollama_base_url = validate_base_url(&url).ok_or(axum::http::StatusCode::BAD_REQUEST)?;
+    }
+
+    if let Some(provider) = request.embedding_provider {
+        let provider = match provider.as_str() {
+            "ollama" => crate::config::EmbeddingProvider::Ollama,
+            "openai" => crate::config::EmbeddingProvider::OpenAI,
+            _ => return Err(axum::http::StatusCode::BAD_REQUEST),
+        };
+        if provider != config.embedding_provider {
+            warn!("Embedding provider changed; existing embeddings must be reindexed before search matches them");
+        }
+        config.embedding_provider = provider;
+    }
+
+    if let Some(model) = request.openai_embedding_model {
+        let model = model.trim();
+        if model.is_empty() {
+            return Err(axum::http::StatusCode::BAD_REQUEST);
+        }
+        config.openai_embedding_model = model.to_string();
+    }
+
+    if let Some(models) = request.fusion_embedding_models {
+        let mut models: Vec<String> = models
+            .into_iter()
+            .map(|model| model.trim().to_string())
+            .filter(|model| !model.is_empty())
+            .collect();
+        models.dedup();
+        config.fusion_embedding_models = models;
+    }
+
+    if let Some(url) = request.embedding_base_url {
+        config.embedding_base_url = if url.trim().is_empty() {
+            None
+        } else {
+            Some(validate_base_url(&url).ok_or(axum::http::StatusCode::BAD_REQUEST)?)
+        };
+    }
+
+    if let Some(key) = request.embedding_api_key {
+        config.embedding_api_key = if key.trim().is_empty() { None } else { Some(key) };
+    }
+
+    if let Some(url) = request.openai_base_url {
+        config.openai_base_url = validate_base_url(&url).ok_or(axum::http::StatusCode::BAD_REQUEST)?;
+    }
+
+    if let Some(model) = request.openai_model {
+        let model = model.trim();
+        config.openai_model = if model.is_empty() { None } else { Some(model.to_string()) };
+    }
+
+    if let Some(model) = request.greenpt_model {
+        let model = model.trim();
+        if !model.is_empty() {
+            config.greenpt_model = model.to_string();
+        }
+    }
+
+    if let Some(val) = request.max_tokens {
+        config.max_tokens = if val == 0 { None } else { Some(val.min(32000)) };
+    }
+
+    if let Some(val) = request.temperature {
+        config.temperature = if val < 0.0 { None } else { Some(val.min(2.0)) };
+    }
+
+    if let Some(val) = request.ai_request_timeout_secs {
+        config.ai_request_timeout_secs = val.clamp(5, 600);
+    }
+
+    if let Some(val) = request.rag_total_timeout_secs {
+        config.rag_total_timeout_secs = val.clamp(10, 1200);
+    }
+
+    if let Some(val) = request.ai_max_concurrent_requests {
+        config.ai_max_concurrent_requests = val.clamp(1, 32);
+    }
+
+    if let Some(val) = request.ai_max_queued_requests {
+        config.ai_max_queued_requests = val.min(256);
+    }
+
+    if let Some(val) = request.index_passages {
+        config.index_passages = val;
+    }
+
+    if let Some(val) = request.index_archives {
+        config.index_archives = val;
+    }
+
+    if let Some(val) = request.archive_max_depth {
+        config.archive_max_depth = val.min(10);
+    }
+
+    if let Some(val) = request.archive_max_mb {
+        config.archive_max_mb = val.clamp(1, 100 * 1024);
+    }
+
+    if let Some(val) = request.archive_text_entry_max_bytes {
+        config.archive_text_entry_max_bytes = val.min(10 * 1024 * 1024);
+    }
+
+    if let Some(val) = request.spreadsheet_header_context {
+        config.spreadsheet_header_context = val;
+    }
+
+    if let Some(val) = request.spreadsheet_sheet_sections {
+        config.spreadsheet_sheet_sections = val;
+    }
+
+    if let Some(val) = request.hash_file_contents {
+        config.hash_file_contents = val;
+    }
+
+    if let Some(val) = request.skip_empty_files {
+        config.skip_empty_files = val;
+    }
+
+    if let Some(val) = request.index_excluded_as_metadata {
+        config.index_excluded_as_metadata = val;
+    }
+
+    if let Some(strategy) = request.embedding_truncation {
+        config.embedding_truncation = match strategy.as_str() {
+            "head" => crate::config::EmbeddingTruncation::Head,
+            "head_tail" => crate::config::EmbeddingTruncation::HeadTail,
+            "sampled" => crate::config::EmbeddingTruncation::Sampled,
+            _ => return Err(axum::http::StatusCode::BAD_REQUEST),
+        };
+    }
+
+    let metric_requested = request.similarity_metric.is_some();
+    if let Some(metric) = request.similarity_metric {
+        config.similarity_metric = match metric.as_str() {
+            "cosine" => crate::config::SimilarityMetric::Cosine,
+            "dot" => crate::config::SimilarityMetric::Dot,
+            "euclidean" => crate::config::SimilarityMetric::Euclidean,
+            _ => return Err(axum::http::StatusCode::BAD_REQUEST),
+        };
+    }
+
+    if let Some(calibration) = request.score_calibration {
+        config.score_calibration = match calibration.as_str() {
+            "none" => crate::config::ScoreCalibration::None,
+            "min_max" => crate::config::ScoreCalibration::MinMax,
+            "sigmoid" => crate::config::ScoreCalibration::Sigmoid,
+            _ => return Err(axum::http::StatusCode::BAD_REQUEST),
+        };
+    }
+
+    if let Some(val) = request.persist_query_cache {
+        config.persist_query_cache = val;
+    }
+
+    if let Some(val) = request.query_cache_ttl_secs {
+        config.query_cache_ttl_secs = val.clamp(60, 90 * 24 * 60 * 60);
+    }
+
+    if let Some(val) = request.log_level {
+        let val = val.trim();
+        if !crate::logging::is_valid_filter(val) {
+            return Err(axum::http::StatusCode::BAD_REQUEST);
+        }
+        config.log_level = val.to_string();
+    }
+
+    if let Some(val) = request.content_search_extensions {
+        config.content_search_extensions = val.into_iter()
+            .map(|ext| ext.trim().trim_start_matches('.').to_lowercase())
+            .filter(|ext| !ext.is_empty())
+            .collect();
+    }
+
+    if let Some(val) = request.content_search_max_bytes {
+        config.content_search_max_bytes = val.clamp(1024, 100 * 1024 * 1024);
+    }
+
+    if let Some(val) = request.auto_maintenance {
+        config.auto_maintenance = val;
+    }
+
+    if let Some(val) = request.auto_maintenance_idle_secs {
+        config.auto_maintenance_idle_secs = val.clamp(60, 7 * 24 * 3600);
+    }
+
+    if let Some(val) = request.auto_maintenance_bloat_ratio {
+        if !val.is_finite() {
+            return Err(axum::http::StatusCode::BAD_REQUEST);
+        }
+        config.auto_maintenance_bloat_ratio = val.clamp(1.1, 100.0);
+    }
+
+    if let Some(val) = request.text_cache_max_mb {
+        config.text_cache_max_mb = val.min(100 * 1024);
+    }
+
+    if let Some(val) = request.hnsw_m {
+        config.hnsw_m = crate::hnsw_index::nearest_supported_m(val);
+    }
+
+    if let Some(val) = request.hnsw_ef_construction {
+        config.hnsw_ef_construction = val.clamp(10, 2000);
+    }
+
+    if let Some(val) = request.hnsw_ef_search {
+        config.hnsw_ef_search = val.clamp(10, 2000);
+    }
+
+    if let Some(val) = request.min_search_similarity {
+        if !val.is_finite() {
+            return Err(axum::http::StatusCode::BAD_REQUEST);
+        }
+        config.min_search_similarity = val.clamp(0.0, 1.0);
+    }
+
+    if let Some(val) = request.duplicate_cluster_similarity {
+        if !val.is_finite() {
+            return Err(axum::http::StatusCode::BAD_REQUEST);
+        }
+        config.duplicate_cluster_similarity = val.clamp(0.5, 1.0);
+    }
+
+    if let Some(val) = request.chat_context_tokens {
+        config.chat_context_tokens = val.clamp(1000, 1_000_000);
+    }
+
+    if let Some(mut budgets) = request.provider_context_tokens {
+        for budget in [&mut budgets.ollama, &mut budgets.openai, &mut budgets.greenpt, &mut budgets.gemini] {
+            *budget = budget.map(|val| val.clamp(1000, 1_000_000));
+        }
+        config.provider_context_tokens = budgets;
+    }
+
+    if let Some(val) = request.rag_min_similarity {
+        if !val.is_finite() {
+            return Err(axum::http::StatusCode::BAD_REQUEST);
+        }
+        config.rag_min_similarity = val.clamp(0.0, 1.0);
+    }
+
+    if let Some(val) = request.rag_max_documents {
+        config.rag_max_documents = val.clamp(1, 50);
+    }
+
+    if let Some(val) = request.rag_default_documents {
+        config.rag_default_documents = val.clamp(1, 50);
+    }
+
+    // Checked once every field is applied, since the embedding model may have changed too
+    if let Some(mismatch) = config.similarity_metric_mismatch() {
+        warn!("{}", mismatch);
+        if metric_requested {
+            return Err(axum::http::StatusCode::BAD_REQUEST);
+        }
+    }
+
     config.save().await.map_err(|_| axum::http::StatusCode::INTERNAL_SERVER_ERROR)?;
     
     // Reload config from disk to ensure we have the latest values
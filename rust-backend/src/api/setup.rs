@@ -9,8 +9,6 @@ use futures::StreamExt;
 use std::process::Command;
 use crate::AppState;
 
-const OLLAMA_BASE_URL: &str = "http://localhost:11434";
-
 #[derive(Debug, Serialize)]
 pub struct SetupStatusResponse {
     pub ollama_running: bool,
@@ -38,7 +36,9 @@ pub async fn get_setup_status(State(state): State<AppState>) -> impl IntoRespons
     let client = Client::new();
     
     // Check if Ollama is running
-    let ollama_running = check_ollama_running(&client).await;
+    let ollama_base_url = state.config.ollama_base_url.clone();
+    let embedding_base_url = state.config.embedding_base_url().to_string();
+    let ollama_running = check_ollama_running(&client, &ollama_base_url).await;
     
     // Check system RAM
     let mut sys = System::new_all();
@@ -57,10 +57,15 @@ pub async fn get_setup_status(State(state): State<AppState>) -> impl IntoRespons
     let current_llm = state.config.ollama_model.clone();
 
     if ollama_running {
-        let installed_models = get_installed_models(&client).await.unwrap_or_default();
+        let installed_models = get_installed_models(&client, &ollama_base_url).await.unwrap_or_default();
         
-        // Check embedding model
-        embedding_installed = installed_models.iter().any(|m| m.starts_with(&current_embedding_model));
+        // Check embedding model (may live on a separate embedding server)
+        let embedding_models = if embedding_base_url == ollama_base_url {
+            installed_models.clone()
+        } else {
+            get_installed_models(&client, &embedding_base_url).await.unwrap_or_default()
+        };
+        embedding_installed = embedding_models.iter().any(|m| m.starts_with(&current_embedding_model));
         
         // Check LLM if configured
         if let Some(ref llm) = current_llm {
@@ -81,6 +86,7 @@ pub async fn get_setup_status(State(state): State<AppState>) -> impl IntoRespons
 }
 
 pub async fn pull_model(
+    State(state): State<AppState>,
     Json(payload): Json<PullModelRequest>,
 ) -> impl IntoResponse {
     // We trigger the pull via command line for simplicity, or we could use the API
@@ -96,7 +102,7 @@ pub async fn pull_model(
     
     // Using the API is more robust across platforms if users didn't add ollama to PATH
     let client = Client::new();
-    let url = format!("{}/api/pull", OLLAMA_BASE_URL);
+    let url = format!("{}/api/pull", state.config.ollama_base_url.trim_end_matches('/'));
     
     // We'll spawn a tokio task to handle the long-running pull
     let model = payload.model.clone();
@@ -118,12 +124,12 @@ pub async fn pull_model(
     })
 }
 
-async fn check_ollama_running(client: &Client) -> bool {
-    client.get(OLLAMA_BASE_URL).send().await.is_ok()
+async fn check_ollama_running(client: &Client, base_url: &str) -> bool {
+    client.get(base_url).send().await.is_ok()
 }
 
-async fn get_installed_models(client: &Client) -> Result<Vec<String>, anyhow::Error> {
-    let url = format!("{}/api/tags", OLLAMA_BASE_URL);
+async fn get_installed_models(client: &Client, base_url: &str) -> Result<Vec<String>, anyhow::Error> {
+    let url = format!("{}/api/tags", base_url.trim_end_matches('/'));
     let resp = client.get(url).send().await?;
     
     if !resp.status().is_success() {
@@ -10,6 +10,8 @@ pub struct SystemInfoResponse {
     available_ram_mb: u64,
     cpu_cores: usize,
     current_mode: String,
+    /// Resource limits in effect for the current mode
+    profile: crate::config::PerformanceProfile,
 }
 
 pub async fn get_system_info(State(state): State<AppState>) -> Json<SystemInfoResponse> {
@@ -27,6 +29,7 @@ pub async fn get_system_info(State(state): State<AppState>) -> Json<SystemInfoRe
         available_ram_mb: available_ram,
         cpu_cores,
         current_mode: current_mode.to_string(),
+        profile: state.config.performance_mode.profile(),
     })
 }
 
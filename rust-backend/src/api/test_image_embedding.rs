@@ -24,9 +24,9 @@ pub async fn test_image_embedding(
         .get("image_path")
         .ok_or(axum::http::StatusCode::BAD_REQUEST)?;
 
-    let embedding_service = crate::embedding::EmbeddingService::new(
+    let embedding_service = crate::embedding::OllamaBackend::new(
         state.config.embedding_model.clone()
-    );
+    ).with_base_url(state.config.embedding_base_url());
 
     match embedding_service.test_image_embedding_support(image_path).await {
         Ok(supported) => {
@@ -12,7 +12,9 @@ use nlp_file_explorer_backend::{
     indexer::Indexer,
     embedding::EmbeddingService,
     parsers::ParserRegistry,
-    hnsw_index::HnswIndex,
+    hnsw_index::{HnswIndex, HnswParams},
+    search::FilenameMatcher,
+    storage::FileMetadata,
 };
 
 #[derive(Parser, Debug)]
@@ -41,8 +43,28 @@ struct Args {
     /// Show top N search results per query for accuracy verification (0 = off)
     #[arg(long, default_value = "0")]
     show_top: usize,
+
+    /// HNSW `M` values to sweep, comma-separated (e.g. 8,16,32). Any sweep flag needs --search-queries;
+    /// parameters not swept use the configured value.
+    #[arg(long, value_delimiter = ',')]
+    sweep_m: Vec<usize>,
+
+    /// HNSW `ef_construction` values to sweep, comma-separated
+    #[arg(long, value_delimiter = ',')]
+    sweep_ef_construction: Vec<usize>,
+
+    /// HNSW `ef_search` values to sweep, comma-separated
+    #[arg(long, value_delimiter = ',')]
+    sweep_ef_search: Vec<usize>,
+
+    /// Compare HNSW top-k against an exact linear scan per query and report recall@k (needs --search-queries)
+    #[arg(long)]
+    measure_recall: bool,
 }
 
+/// Results kept per query, and the k used for recall@k
+const TOP_K: usize = 10;
+
 #[derive(Serialize)]
 struct BenchmarkResults {
     total_files: usize,
@@ -53,6 +75,22 @@ struct BenchmarkResults {
     avg_embedding_time_ms: f64,
     hnsw_build_time_secs: f64,
     search_results: Vec<SearchBenchmark>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mean_recall_at_k: Option<f64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    hnsw_sweep: Vec<HnswSweepResult>,
+}
+
+#[derive(Serialize)]
+struct HnswSweepResult {
+    m: usize,
+    ef_construction: usize,
+    ef_search: usize,
+    build_time_secs: f64,
+    /// Index lookup only; query embedding time is excluded
+    avg_search_time_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mean_recall_at_k: Option<f64>,
 }
 
 #[derive(Serialize)]
@@ -60,6 +98,9 @@ struct SearchBenchmark {
     query: String,
     results_count: usize,
     search_time_ms: f64,
+    /// Share of the exact top-k that the HNSW top-k found (before app re-ranking)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    recall_at_k: Option<f64>,
 }
 
 #[tokio::main]
@@ -69,6 +110,13 @@ async fn main() -> Result<()> {
     if !args.search_only && args.directory.is_none() {
         anyhow::bail!("--directory (-d) is required unless using --search-only");
     }
+    let wants_queries = args.measure_recall
+        || !args.sweep_m.is_empty()
+        || !args.sweep_ef_construction.is_empty()
+        || !args.sweep_ef_search.is_empty();
+    if wants_queries && args.search_queries.is_none() {
+        anyhow::bail!("--measure-recall and the --sweep-* flags need --search-queries (-s)");
+    }
 
     println!("=== NLP File Explorer Benchmark ===");
     println!("Mode: {}", if args.search_only { "search-only (using existing index)" } else { "full (index + search)" });
@@ -86,9 +134,14 @@ async fn main() -> Result<()> {
 
     // Initialize components
     let config = AppConfig::load_or_default().await?;
-    let storage = Arc::new(Storage::new(&AppConfig::data_dir()).await?);
-    let embedding_service = Arc::new(EmbeddingService::new(config.embedding_model.clone()));
-    let parser_registry = Arc::new(ParserRegistry::new(&config.file_type_filters));
+    nlp_file_explorer_backend::logging::init(&config.log_level);
+    let storage = Arc::new(
+        Storage::new(&AppConfig::data_dir()).await?.with_similarity_metric(config.similarity_metric)
+    );
+    let embedding_service = Arc::new(
+        EmbeddingService::from_config(&config)
+    );
+    let parser_registry = Arc::new(ParserRegistry::from_config(&config));
     let indexer = Arc::new(Indexer::new(
         storage.clone(),
         embedding_service.clone(),
@@ -129,7 +182,10 @@ async fn main() -> Result<()> {
     let hnsw_build_start = Instant::now();
     if !embeddings.is_empty() {
         let dimensions = embeddings[0].1.len();
-        let mut hnsw_index = HnswIndex::new(dimensions);
+        let hnsw_params = HnswParams::from_config(&config);
+        println!("Parameters: M={}, ef_construction={}, ef_search={}, metric={:?}",
+                 hnsw_params.m, hnsw_params.ef_construction, hnsw_params.ef_search, hnsw_params.metric);
+        let mut hnsw_index = HnswIndex::new(dimensions).with_params(hnsw_params);
         if let Err(e) = hnsw_index.rebuild_from_embeddings(embeddings.clone()) {
             eprintln!("Error building HNSW index: {}", e);
         } else {
@@ -163,27 +219,40 @@ async fn main() -> Result<()> {
                         .filter(|s| !s.is_empty())
                         .collect();
                     
-                    let top_k = 10;
-                    let candidate_count = top_k * 2; // Match main app: fetch 2x for re-ranking
+                    let candidate_count = TOP_K * 2; // Match main app: fetch 2x for re-ranking
+                    let filename_matcher = FilenameMatcher::from_config(&config);
                     
                     for query in queries {
                         let search_start = Instant::now();
                         // Generate embedding for query
                         let query_embedding = embedding_service.generate_embedding(&query).await?;
                         // Fetch more candidates, then apply same scoring pipeline as main search
-                        let raw_results = hnsw_index.search(query_embedding, candidate_count)?;
-                        let scored = score_search_results(&query, raw_results);
-                        let final_results: Vec<_> = scored.into_iter().take(top_k).collect();
+                        let raw_results = hnsw_index.search(query_embedding.clone(), candidate_count)?;
+                        let scored = score_search_results(&query, raw_results, &filename_matcher);
+                        let final_results: Vec<_> = scored.into_iter().take(TOP_K).collect();
                         let search_duration = search_start.elapsed();
+
+                        // Measured outside the timed section so the exact scan doesn't skew latency
+                        let recall_at_k = if args.measure_recall {
+                            Some(recall_at_k(&hnsw_index, &query_embedding)?)
+                        } else {
+                            None
+                        };
                         
                         search_results.push(SearchBenchmark {
                             query: query.clone(),
                             results_count: final_results.len(),
                             search_time_ms: search_duration.as_secs_f64() * 1000.0,
+                            recall_at_k,
                         });
                         
-                        println!("Query: '{}' -> {} results in {:.2}ms", 
-                                query, final_results.len(), search_duration.as_secs_f64() * 1000.0);
+                        match recall_at_k {
+                            Some(recall) => println!("Query: '{}' -> {} results in {:.2}ms, recall@{} {:.3}",
+                                                     query, final_results.len(), search_duration.as_secs_f64() * 1000.0,
+                                                     TOP_K, recall),
+                            None => println!("Query: '{}' -> {} results in {:.2}ms",
+                                             query, final_results.len(), search_duration.as_secs_f64() * 1000.0),
+                        }
 
                         if args.show_top > 0 && !final_results.is_empty() {
                             let n = args.show_top.min(final_results.len());
@@ -197,6 +266,20 @@ async fn main() -> Result<()> {
                 }
             }
 
+            let mean_recall_at_k = mean(search_results.iter().filter_map(|r| r.recall_at_k));
+            if let Some(recall) = mean_recall_at_k {
+                println!("Mean recall@{}: {:.3}", TOP_K, recall);
+            }
+
+            let hnsw_sweep = if args.sweep_m.is_empty()
+                && args.sweep_ef_construction.is_empty()
+                && args.sweep_ef_search.is_empty()
+            {
+                Vec::new()
+            } else {
+                run_hnsw_sweep(&args, hnsw_params, &embeddings, &embedding_service).await?
+            };
+
             // Compile results
             let results = BenchmarkResults {
                 total_files: indexed_count,
@@ -211,6 +294,8 @@ async fn main() -> Result<()> {
                 avg_embedding_time_ms: 0.0, // Not separately measured
                 hnsw_build_time_secs,
                 search_results,
+                mean_recall_at_k,
+                hnsw_sweep,
             };
 
             // Output results
@@ -228,6 +313,25 @@ async fn main() -> Result<()> {
                     println!("embedding_time_secs,{}", results.embedding_time_secs);
                     println!("avg_embedding_time_ms,{}", results.avg_embedding_time_ms);
                     println!("hnsw_build_time_secs,{}", results.hnsw_build_time_secs);
+                    if let Some(recall) = results.mean_recall_at_k {
+                        println!("mean_recall_at_k,{}", recall);
+                    }
+                    if !results.search_results.is_empty() {
+                        println!();
+                        println!("query,results_count,search_time_ms,recall_at_k");
+                        for search in &results.search_results {
+                            println!("\"{}\",{},{},{}", search.query.replace('"', "\"\""), search.results_count,
+                                     search.search_time_ms, csv_optional(search.recall_at_k));
+                        }
+                    }
+                    if !results.hnsw_sweep.is_empty() {
+                        println!();
+                        println!("m,ef_construction,ef_search,build_time_secs,avg_search_time_ms,mean_recall_at_k");
+                        for run in &results.hnsw_sweep {
+                            println!("{},{},{},{},{},{}", run.m, run.ef_construction, run.ef_search,
+                                     run.build_time_secs, run.avg_search_time_ms, csv_optional(run.mean_recall_at_k));
+                        }
+                    }
                 }
                 _ => {
                     // Already printed above
@@ -240,3 +344,102 @@ async fn main() -> Result<()> {
 
     Ok(())
 }
+
+/// Build an index for every (M, ef_construction) pair and time queries at every ef_search
+async fn run_hnsw_sweep(
+    args: &Args,
+    configured: HnswParams,
+    embeddings: &[(FileMetadata, Vec<f32>)],
+    embedding_service: &EmbeddingService,
+) -> Result<Vec<HnswSweepResult>> {
+    let Some(queries_file) = &args.search_queries else {
+        anyhow::bail!("HNSW sweep flags need --search-queries (-s)");
+    };
+    let mut query_embeddings = Vec::new();
+    for query in fs::read_to_string(queries_file)?.lines().map(str::trim).filter(|q| !q.is_empty()) {
+        query_embeddings.push(embedding_service.generate_embedding(query).await?);
+    }
+    if query_embeddings.is_empty() {
+        anyhow::bail!("No queries in {}", queries_file);
+    }
+
+    let or_configured = |values: &[usize], fallback: usize| {
+        if values.is_empty() { vec![fallback] } else { values.to_vec() }
+    };
+    let m_values = or_configured(&args.sweep_m, configured.m);
+    let ef_construction_values = or_configured(&args.sweep_ef_construction, configured.ef_construction);
+    let ef_search_values = or_configured(&args.sweep_ef_search, configured.ef_search);
+
+    println!("\n=== HNSW Parameter Sweep ({} queries) ===", query_embeddings.len());
+    let mut sweep = Vec::new();
+    for &m in &m_values {
+        for &ef_construction in &ef_construction_values {
+            let build_start = Instant::now();
+            let mut index = HnswIndex::new(embeddings[0].1.len())
+                .with_params(HnswParams { m, ef_construction, ..configured });
+            if let Err(e) = index.rebuild_from_embeddings(embeddings.to_vec()) {
+                eprintln!("Warning: {}", e);
+            }
+            let build_time_secs = build_start.elapsed().as_secs_f64();
+
+            for &ef_search in &ef_search_values {
+                index.set_ef_search(ef_search);
+                let index_params = index.params();
+                let search_start = Instant::now();
+                for query_embedding in &query_embeddings {
+                    index.search(query_embedding.clone(), TOP_K)?;
+                }
+                let avg_search_time_ms =
+                    search_start.elapsed().as_secs_f64() * 1000.0 / query_embeddings.len() as f64;
+                let mean_recall_at_k = if args.measure_recall {
+                    let recalls = query_embeddings
+                        .iter()
+                        .map(|query_embedding| recall_at_k(&index, query_embedding))
+                        .collect::<Result<Vec<_>>>()?;
+                    mean(recalls.into_iter())
+                } else {
+                    None
+                };
+
+                let recall_note = mean_recall_at_k
+                    .map(|recall| format!(", recall@{} {:.3}", TOP_K, recall))
+                    .unwrap_or_default();
+                println!("M={:<3} ef_construction={:<5} ef_search={:<5} build {:.2}s, search {:.3}ms/query{}",
+                         index_params.m, ef_construction, ef_search, build_time_secs, avg_search_time_ms, recall_note);
+                sweep.push(HnswSweepResult {
+                    m: index_params.m,
+                    ef_construction,
+                    ef_search,
+                    build_time_secs,
+                    avg_search_time_ms,
+                    mean_recall_at_k,
+                });
+            }
+        }
+    }
+    Ok(sweep)
+}
+
+/// Fraction of the exact (brute-force, same metric) top-k that the HNSW search also returns
+fn recall_at_k(index: &HnswIndex, query_embedding: &[f32]) -> Result<f64> {
+    let exact = index.search_exact(query_embedding.to_vec(), TOP_K)?;
+    if exact.is_empty() {
+        return Ok(1.0);
+    }
+    let approximate: std::collections::HashSet<i64> = index
+        .search(query_embedding.to_vec(), TOP_K)?
+        .into_iter()
+        .map(|(meta, _)| meta.id)
+        .collect();
+    let found = exact.iter().filter(|(meta, _)| approximate.contains(&meta.id)).count();
+    Ok(found as f64 / exact.len() as f64)
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let (sum, count) = values.fold((0.0, 0usize), |(sum, count), v| (sum + v, count + 1));
+    (count > 0).then(|| sum / count as f64)
+}
+
+fn csv_optional(value: Option<f64>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
@@ -3,6 +3,9 @@ use std::path::PathBuf;
 use anyhow::Result;
 use dirs;
 
+/// Default Ollama server used for embeddings, chat, and model management
+pub const DEFAULT_OLLAMA_BASE_URL: &str = "http://localhost:11434";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppConfig {
     pub performance_mode: PerformanceMode,
@@ -10,11 +13,17 @@ pub struct AppConfig {
     pub indexed_directories: Vec<String>,
     pub file_type_filters: FileTypeFilters,
     pub chunk_size: usize,
+    /// Words shared by consecutive passage chunks (always kept below `chunk_size`)
+    #[serde(default = "default_chunk_overlap")]
+    pub chunk_overlap: usize,
     #[serde(default = "default_max_context_tokens")]
     pub max_context_tokens: usize,
     pub auto_index: bool,
     #[serde(default = "default_max_search_results")]
     pub max_search_results: usize,
+    /// Upper bound on any search's `limit`; every candidate is held in memory while ranking
+    #[serde(default = "default_max_search_results_cap")]
+    pub max_search_results_cap: usize,
     #[serde(default = "default_filter_duplicate_files")]
     pub filter_duplicate_files: bool,
     #[serde(default = "default_ai_features_enabled")]
@@ -25,12 +34,259 @@ pub struct AppConfig {
     pub ollama_model: Option<String>,
     #[serde(default)]
     pub gemini_model: Option<String>,
-    #[serde(default)]
+    /// Held in plaintext in memory only; on disk it is in the OS keychain, or in
+    /// `api_key_encrypted` when no keychain is available. A plaintext value left by older
+    /// versions is read once and moved out of config.json on load.
+    #[serde(default, skip_serializing)]
     pub api_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    api_key_encrypted: Option<String>,
+    /// Set on load when config.json still held a key in plaintext
+    #[serde(skip)]
+    plaintext_secrets: bool,
     #[serde(default = "default_action_search_parsing_model")]
     pub action_search_parsing_model: String,
     #[serde(default = "default_action_search_analysis_model")]
     pub action_search_analysis_model: String,
+    #[serde(default = "default_rag_max_chars_per_doc")]
+    pub rag_max_chars_per_doc: usize,
+    /// Length of the quote shown for each Active RAG source
+    #[serde(default = "default_rag_excerpt_chars")]
+    pub rag_excerpt_chars: usize,
+    /// Gitignore-style patterns skipped during indexing (in addition to each root's .indexignore)
+    #[serde(default = "default_ignore_patterns")]
+    pub ignore_patterns: Vec<String>,
+    /// Maximum directory depth to descend into below each indexed root (None = unlimited)
+    #[serde(default)]
+    pub max_index_depth: Option<usize>,
+    /// Files larger than this are indexed by metadata only, regardless of type
+    #[serde(default = "default_max_content_file_size_bytes")]
+    pub max_content_file_size_bytes: u64,
+    /// Legacy megabyte form of `max_content_file_size_bytes`, migrated on load
+    #[serde(default, skip_serializing)]
+    max_file_size_mb: Option<u64>,
+    /// Follow symlinked directories while indexing (cycles are detected and skipped)
+    #[serde(default)]
+    pub follow_symlinks: bool,
+    /// Index dotfiles and hidden directories (and, on Windows, entries with the hidden attribute)
+    #[serde(default)]
+    pub index_hidden: bool,
+    /// Hidden directories indexed anyway while `index_hidden` is off
+    #[serde(default)]
+    pub index_hidden_dirs: Vec<String>,
+    /// Also store folder names as metadata-only entries, so searches with `include_directories` find folders
+    #[serde(default)]
+    pub index_directory_names: bool,
+    /// Extensions indexed by file name only, never by content (without the dot, lowercase)
+    #[serde(default = "default_metadata_only_extensions")]
+    pub metadata_only_extensions: Vec<String>,
+    /// File names (case-insensitive) never indexed because they match almost any query
+    #[serde(default = "default_excluded_filenames")]
+    pub excluded_filenames: Vec<String>,
+    /// Correct misspelled query words against terms from indexed file names and content
+    #[serde(default)]
+    pub spell_correction: bool,
+    /// Let query date filters point into the future ("deadlines next March"), for task or
+    /// planning corpora; by default they're rejected unless spelled out, and capped at today
+    #[serde(default)]
+    pub allow_future_dates: bool,
+    /// Max edit distance for typo-tolerant filename matching (0 disables)
+    #[serde(default = "default_fuzzy_max_edit_distance")]
+    pub fuzzy_max_edit_distance: usize,
+    /// Also match filenames with boilerplate stripped ("Copy of", "final", version markers, numbers)
+    #[serde(default = "default_normalize_filenames")]
+    pub normalize_filenames: bool,
+    /// Words and phrases dropped from filenames when `normalize_filenames` is on
+    #[serde(default = "default_filename_stop_tokens")]
+    pub filename_stop_tokens: Vec<String>,
+    /// Domain terms that make a single-word query a topic search rather than a filename lookup
+    /// (matched as the whole query or its prefix)
+    #[serde(default = "default_semantic_keywords")]
+    pub semantic_keywords: Vec<String>,
+    #[serde(default = "default_ollama_base_url")]
+    pub ollama_base_url: String,
+    /// Which service produces embeddings; changing it means reindexing
+    #[serde(default = "default_embedding_provider")]
+    pub embedding_provider: EmbeddingProvider,
+    /// Model used when `embedding_provider` is "openai"
+    #[serde(default = "default_openai_embedding_model")]
+    pub openai_embedding_model: String,
+    /// Extra embedding models (same provider) also stored per file, for `"mode": "fusion"` search
+    #[serde(default)]
+    pub fusion_embedding_models: Vec<String>,
+    /// Separate server for embedding requests; falls back to `ollama_base_url`
+    /// (or `openai_base_url` for the OpenAI provider) when unset
+    #[serde(default)]
+    pub embedding_base_url: Option<String>,
+    /// Bearer token for the OpenAI embedding provider, kept apart from `api_key` so the chat
+    /// key is never sent to another host; no key is sent when unset. Stored like `api_key`.
+    #[serde(default, skip_serializing)]
+    pub embedding_api_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    embedding_api_key_encrypted: Option<String>,
+    /// Endpoint for the OpenAI-compatible provider (OpenAI, LiteLLM, vLLM, ...)
+    #[serde(default = "default_openai_base_url")]
+    pub openai_base_url: String,
+    #[serde(default)]
+    pub openai_model: Option<String>,
+    #[serde(default = "default_greenpt_model")]
+    pub greenpt_model: String,
+    /// Response length cap for OpenAI-compatible providers (None = default of 2000)
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+    /// Sampling temperature for OpenAI-compatible providers (None = default of 0.7)
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    /// Timeout for a single AI provider request (summarize, chat, RAG analysis)
+    #[serde(default = "default_ai_request_timeout_secs")]
+    pub ai_request_timeout_secs: u64,
+    /// Overall budget for an Active RAG search (retrieval + analysis)
+    #[serde(default = "default_rag_total_timeout_secs")]
+    pub rag_total_timeout_secs: u64,
+    /// LLM requests (summarize, chat, Active RAG) allowed to run at once; applied at startup
+    #[serde(default = "default_ai_max_concurrent_requests")]
+    pub ai_max_concurrent_requests: usize,
+    /// LLM requests allowed to wait for a free slot before new ones get a 429
+    #[serde(default = "default_ai_max_queued_requests")]
+    pub ai_max_queued_requests: usize,
+    /// Store an embedding per chunk so search can return individual passages; off by default
+    /// since it multiplies embedding work and storage
+    #[serde(default = "default_index_passages")]
+    pub index_passages: bool,
+    /// Index the entry names of zip archives (and the text of small text entries) as their content
+    #[serde(default)]
+    pub index_archives: bool,
+    /// Zips nested deeper than this inside an archive are listed by name only
+    #[serde(default = "default_archive_max_depth")]
+    pub archive_max_depth: usize,
+    /// Archives larger than this are indexed metadata-only
+    #[serde(default = "default_archive_max_mb")]
+    pub archive_max_mb: u64,
+    /// Text entries up to this size contribute their contents to the archive's text; 0 for names only
+    #[serde(default = "default_archive_text_entry_max_bytes")]
+    pub archive_text_entry_max_bytes: u64,
+    /// Lead spreadsheet text with sheet names and header rows so sheet and column names weigh in the embedding
+    #[serde(default = "default_spreadsheet_header_context")]
+    pub spreadsheet_header_context: bool,
+    /// Embed each sheet of a multi-sheet workbook separately (as sections) instead of as one text
+    #[serde(default)]
+    pub spreadsheet_sheet_sections: bool,
+    /// Hash file contents so a file whose mtime or size changed but whose bytes didn't isn't re-embedded.
+    /// Costs a full read of each changed file; turn off for very large files on slow disks.
+    #[serde(default = "default_hash_file_contents")]
+    pub hash_file_contents: bool,
+    /// Leave zero-byte files (placeholders, lock files) out of the index entirely
+    #[serde(default = "default_skip_empty_files")]
+    pub skip_empty_files: bool,
+    /// Index files matched by `excluded_filenames` or the temporary/lock extensions by name only,
+    /// so filename search can still find them
+    #[serde(default = "default_index_excluded_as_metadata")]
+    pub index_excluded_as_metadata: bool,
+    /// Which part of a text to keep when it's too long for the embedding model's context
+    #[serde(default = "default_embedding_truncation")]
+    pub embedding_truncation: EmbeddingTruncation,
+    /// How query and document vectors are compared; changing away from cosine needs a reindex
+    #[serde(default = "default_similarity_metric")]
+    pub similarity_metric: SimilarityMetric,
+    /// How returned similarities are rescaled for display; ranking is unaffected
+    #[serde(default)]
+    pub score_calibration: ScoreCalibration,
+    /// Keep LLM query parses on disk (data_dir/query_cache.json) across restarts
+    #[serde(default = "default_persist_query_cache")]
+    pub persist_query_cache: bool,
+    #[serde(default = "default_query_cache_ttl_secs")]
+    pub query_cache_ttl_secs: u64,
+    /// Log filter used when `RUST_LOG` is unset (e.g. "info", "debug", "warn,nlp_file_explorer_backend::indexer=debug").
+    /// Applied at startup.
+    #[serde(default = "default_log_level")]
+    pub log_level: String,
+    /// Extensions whose contents the file browser search scans
+    #[serde(default = "default_content_search_extensions")]
+    pub content_search_extensions: Vec<String>,
+    /// Bytes read per file when the file browser search scans contents
+    #[serde(default = "default_content_search_max_bytes")]
+    pub content_search_max_bytes: u64,
+    /// Compact embeddings.bin and rebuild the HNSW index in the background while the server is idle
+    #[serde(default)]
+    pub auto_maintenance: bool,
+    /// Seconds without a search or index request before auto maintenance may run
+    #[serde(default = "default_auto_maintenance_idle_secs")]
+    pub auto_maintenance_idle_secs: u64,
+    /// Auto maintenance compacts once embeddings.bin is this many times the size of its live vectors
+    #[serde(default = "default_auto_maintenance_bloat_ratio")]
+    pub auto_maintenance_bloat_ratio: f32,
+    /// Disk budget for text extracted for previews and AI features (data_dir/text_cache); 0 disables it
+    #[serde(default = "default_text_cache_max_mb")]
+    pub text_cache_max_mb: u64,
+    /// HNSW graph degree; rounded to one of `hnsw_index::SUPPORTED_M`. Applied on the next index rebuild.
+    #[serde(default = "default_hnsw_m")]
+    pub hnsw_m: usize,
+    /// Candidates explored while building the HNSW graph
+    #[serde(default = "default_hnsw_ef_construction")]
+    pub hnsw_ef_construction: usize,
+    /// Candidates explored per HNSW query (never fewer than the number of results requested)
+    #[serde(default = "default_hnsw_ef_search")]
+    pub hnsw_ef_search: usize,
+    /// Search results scoring below this (0.0-1.0) are dropped; requests can override it
+    #[serde(default = "default_min_search_similarity")]
+    pub min_search_similarity: f32,
+    /// Embedding similarity (0.5-1.0) at which `cluster_duplicates` searches group two results
+    #[serde(default = "default_duplicate_cluster_similarity")]
+    pub duplicate_cluster_similarity: f32,
+    /// Score multipliers for results under particular directories (the most specific prefix wins)
+    #[serde(default)]
+    pub boost_paths: Vec<PathBoost>,
+    /// Age in days at which a file's freshness boost has halved
+    #[serde(default = "default_freshness_half_life_days")]
+    pub freshness_half_life_days: f32,
+    /// Extra score multiplier for a file modified just now (0.0 disables the freshness boost)
+    #[serde(default)]
+    pub freshness_weight: f32,
+    /// Score multipliers by file extension, e.g. `{"xlsx": 1.3, "txt": 0.9}`; unlisted types get 1.0
+    #[serde(default)]
+    pub type_boosts: std::collections::HashMap<String, f32>,
+    /// Prompt budget in tokens for document chat (document + history + question) and summarize, for
+    /// providers without an entry in `provider_context_tokens`. The default leaves room for the reply in
+    /// Ollama's default 4096-token window; raise it for larger-context models.
+    #[serde(default = "default_chat_context_tokens")]
+    pub chat_context_tokens: usize,
+    /// Per-provider prompt budgets for document chat and summarize; providers left unset use `chat_context_tokens`
+    #[serde(default)]
+    pub provider_context_tokens: ProviderContextTokens,
+    /// Active RAG only analyzes documents scoring at least this (0.0-1.0); separate from `min_search_similarity`
+    #[serde(default = "default_rag_min_similarity")]
+    pub rag_min_similarity: f32,
+    /// Documents Active RAG analyzes when a request doesn't set `document_limit`
+    #[serde(default = "default_rag_default_documents")]
+    pub rag_default_documents: usize,
+    /// Hard upper bound on Active RAG documents; larger requested limits are capped to it
+    #[serde(default = "default_rag_max_documents")]
+    pub rag_max_documents: usize,
+}
+
+/// Prompt budget in tokens for each AI provider, overriding `chat_context_tokens` where set
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProviderContextTokens {
+    #[serde(default)]
+    pub ollama: Option<usize>,
+    #[serde(default)]
+    pub openai: Option<usize>,
+    #[serde(default)]
+    pub greenpt: Option<usize>,
+    /// Gemini models take far longer prompts than local ones, so this is set out of the box
+    #[serde(default = "default_gemini_context_tokens")]
+    pub gemini: Option<usize>,
+}
+
+impl Default for ProviderContextTokens {
+    fn default() -> Self {
+        Self {
+            ollama: None,
+            openai: None,
+            greenpt: None,
+            gemini: default_gemini_context_tokens(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -42,6 +298,14 @@ pub enum AiProvider {
     Gemini,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum EmbeddingProvider {
+    Ollama,
+    /// Any OpenAI-compatible `/embeddings` endpoint
+    OpenAI,
+}
+
 fn default_ai_features_enabled() -> bool {
     false
 }
@@ -50,10 +314,26 @@ fn default_ai_provider() -> AiProvider {
     AiProvider::Ollama
 }
 
+fn default_embedding_provider() -> EmbeddingProvider {
+    EmbeddingProvider::Ollama
+}
+
+fn default_openai_embedding_model() -> String {
+    "text-embedding-3-small".to_string()
+}
+
+fn default_chunk_overlap() -> usize {
+    50
+}
+
 fn default_max_search_results() -> usize {
     100
 }
 
+fn default_max_search_results_cap() -> usize {
+    200
+}
+
 fn default_filter_duplicate_files() -> bool {
     true
 }
@@ -66,10 +346,277 @@ fn default_action_search_analysis_model() -> String {
     "same-as-main".to_string()
 }
 
+fn default_rag_max_chars_per_doc() -> usize {
+    4000 // Per-document budget for Active RAG prompts (head + tail)
+}
+
+fn default_metadata_only_extensions() -> Vec<String> {
+    [
+        // Config files
+        "json", "yaml", "yml", "toml", "ini", "cfg", "conf", "properties", "config",
+        // Binaries and game/data files
+        "exe", "dll", "jar", "so", "dylib", "dll.a", "dat", "mca", "rrf", "igt", "class",
+        // Logs
+        "log",
+        // Images: searchable by name, but their content shouldn't surface in semantic results
+        "jpg", "jpeg", "png", "gif", "bmp", "webp", "svg", "ico", "tiff", "tif",
+    ]
+    .iter()
+    .map(|ext| ext.to_string())
+    .collect()
+}
+
+fn default_excluded_filenames() -> Vec<String> {
+    // Common config/boilerplate files that cause false positives
+    vec!["config.js".to_string(), "index.html".to_string(), "aca.conf.ini".to_string()]
+}
+
+fn default_rag_excerpt_chars() -> usize {
+    300
+}
+
+fn default_ignore_patterns() -> Vec<String> {
+    vec!["node_modules/".to_string(), ".git/".to_string()]
+}
+
+fn default_max_content_file_size_bytes() -> u64 {
+    50 * 1024 * 1024
+}
+
+fn default_fuzzy_max_edit_distance() -> usize {
+    crate::search::DEFAULT_FUZZY_MAX_EDIT_DISTANCE
+}
+
+fn default_normalize_filenames() -> bool {
+    true
+}
+
+fn default_filename_stop_tokens() -> Vec<String> {
+    ["copy of", "copy", "final", "draft", "img", "dsc", "scan", "screenshot", "untitled"]
+        .iter()
+        .map(|token| token.to_string())
+        .collect()
+}
+
+fn default_semantic_keywords() -> Vec<String> {
+    [
+        "calculus", "algebra", "geometry", "physics", "chemistry", "biology",
+        "history", "literature", "philosophy", "psychology", "sociology",
+        "programming", "algorithm", "database", "network", "security",
+        "homework", "assignment", "project", "report", "essay", "thesis",
+        "mathematics", "math", "science", "engineering", "computer",
+    ]
+    .iter()
+    .map(|keyword| keyword.to_string())
+    .collect()
+}
+
+fn default_ollama_base_url() -> String {
+    DEFAULT_OLLAMA_BASE_URL.to_string()
+}
+
+fn default_openai_base_url() -> String {
+    "https://api.openai.com/v1".to_string()
+}
+
+fn default_greenpt_model() -> String {
+    "greenpt".to_string()
+}
+
+fn default_ai_request_timeout_secs() -> u64 {
+    60
+}
+
+fn default_rag_total_timeout_secs() -> u64 {
+    90
+}
+
+fn default_ai_max_concurrent_requests() -> usize {
+    2
+}
+
+fn default_ai_max_queued_requests() -> usize {
+    8
+}
+
+fn default_index_passages() -> bool {
+    false
+}
+
+fn default_archive_max_depth() -> usize {
+    2
+}
+
+fn default_archive_max_mb() -> u64 {
+    500
+}
+
+fn default_archive_text_entry_max_bytes() -> u64 {
+    64 * 1024
+}
+
+fn default_spreadsheet_header_context() -> bool {
+    true
+}
+
+fn default_hash_file_contents() -> bool {
+    true
+}
+
+fn default_skip_empty_files() -> bool {
+    true
+}
+
+fn default_index_excluded_as_metadata() -> bool {
+    false
+}
+
+fn default_embedding_truncation() -> EmbeddingTruncation {
+    EmbeddingTruncation::Head
+}
+
+fn default_similarity_metric() -> SimilarityMetric {
+    SimilarityMetric::Cosine
+}
+
+fn default_persist_query_cache() -> bool {
+    true
+}
+
+fn default_query_cache_ttl_secs() -> u64 {
+    7 * 24 * 60 * 60
+}
+
+fn default_log_level() -> String {
+    "info".to_string()
+}
+
+fn default_content_search_extensions() -> Vec<String> {
+    [
+        "txt", "md", "js", "ts", "py", "rs", "java", "cpp", "c", "h", "hpp",
+        "json", "xml", "html", "css", "yaml", "yml", "toml", "ini", "log",
+    ]
+    .iter()
+    .map(|ext| ext.to_string())
+    .collect()
+}
+
+fn default_content_search_max_bytes() -> u64 {
+    1024 * 1024
+}
+
+fn default_auto_maintenance_idle_secs() -> u64 {
+    600
+}
+
+fn default_auto_maintenance_bloat_ratio() -> f32 {
+    1.5
+}
+
+fn default_text_cache_max_mb() -> u64 {
+    256
+}
+
+fn default_hnsw_m() -> usize {
+    crate::hnsw_index::HnswParams::default().m
+}
+
+fn default_hnsw_ef_construction() -> usize {
+    crate::hnsw_index::HnswParams::default().ef_construction
+}
+
+fn default_hnsw_ef_search() -> usize {
+    crate::hnsw_index::HnswParams::default().ef_search
+}
+
+fn default_min_search_similarity() -> f32 {
+    0.1
+}
+
+fn default_duplicate_cluster_similarity() -> f32 {
+    0.98
+}
+
+fn default_freshness_half_life_days() -> f32 {
+    30.0
+}
+
+fn default_chat_context_tokens() -> usize {
+    3000
+}
+
+fn default_gemini_context_tokens() -> Option<usize> {
+    Some(30000)
+}
+
+fn default_rag_min_similarity() -> f32 {
+    0.3
+}
+
+fn default_rag_default_documents() -> usize {
+    3
+}
+
+fn default_rag_max_documents() -> usize {
+    10
+}
+
 fn default_max_context_tokens() -> usize {
     1800 // Stay under 2K embedding context to prevent context length errors
 }
 
+/// How a text that overflows the embedding context is cut down before retrying
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum EmbeddingTruncation {
+    /// Keep the beginning only
+    Head,
+    /// Keep the beginning and the end, so conclusions survive
+    HeadTail,
+    /// Keep evenly spaced slices from across the whole text
+    Sampled,
+}
+
+/// Rescaling of the similarities a search returns, relative to the other results of that search
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ScoreCalibration {
+    /// Raw scores
+    #[default]
+    None,
+    /// Best result 1.0, worst 0.0, linear in between
+    MinMax,
+    /// Logistic curve around the results' mean, one standard deviation apart scoring ~0.27 and ~0.73
+    Sigmoid,
+}
+
+/// Vector comparison used for ranking, in both the linear scan and the HNSW graph
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SimilarityMetric {
+    /// Angle only; vectors are stored at unit length
+    Cosine,
+    /// Dot product of the vectors as the model returned them, magnitude included, scored as `tanh(dot / |query|²)`
+    Dot,
+    /// Euclidean distance, scored as `1 / (1 + distance / |query|)` so closer is higher
+    Euclidean,
+}
+
+impl SimilarityMetric {
+    /// The metric an embedding model was trained for, for models where that is known
+    pub fn recommended_for(model: &str) -> Option<Self> {
+        let model = model.to_lowercase();
+        if model.contains("tas-b") || model.contains("-dot-") {
+            return Some(Self::Dot);
+        }
+        const COSINE_MODELS: &[&str] = &[
+            "all-minilm", "embeddinggemma", "nomic-embed-text", "mxbai-embed-large", "bge-",
+            "snowflake-arctic-embed", "text-embedding-3", "text-embedding-ada",
+        ];
+        COSINE_MODELS.iter().any(|name| model.contains(name)).then_some(Self::Cosine)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum PerformanceMode {
@@ -77,6 +624,43 @@ pub enum PerformanceMode {
     Normal,
 }
 
+/// Concrete resource limits implied by a performance mode
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct PerformanceProfile {
+    /// Files parsed and embedded concurrently while indexing
+    pub index_concurrency: usize,
+    /// Sample beginning/middle/end (and split very large files into sections) instead of keeping only the head
+    pub sample_large_files: bool,
+    /// Embedded file count below which search uses a linear scan rather than building an HNSW index
+    pub hnsw_min_corpus_size: usize,
+}
+
+impl PerformanceMode {
+    /// Lightweight mode halves indexing parallelism, embeds only the head of large files,
+    /// and skips the HNSW index (and its memory) for corpora under 10k files.
+    pub fn profile(&self) -> PerformanceProfile {
+        match self {
+            PerformanceMode::Lightweight => PerformanceProfile {
+                index_concurrency: 2,
+                sample_large_files: false,
+                hnsw_min_corpus_size: 10_000,
+            },
+            PerformanceMode::Normal => PerformanceProfile {
+                index_concurrency: 5,
+                sample_large_files: true,
+                hnsw_min_corpus_size: 0,
+            },
+        }
+    }
+}
+
+/// Ranks results under `prefix` by `multiplier` times their similarity
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PathBoost {
+    pub prefix: String,
+    pub multiplier: f32,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileTypeFilters {
     pub include_pdf: bool,
@@ -101,22 +685,148 @@ impl Default for AppConfig {
                 excluded_extensions: Vec::new(),
             },
             chunk_size: 512,
+            chunk_overlap: default_chunk_overlap(),
             max_context_tokens: 1800,
             auto_index: true,
             max_search_results: 100,
+            max_search_results_cap: default_max_search_results_cap(),
             filter_duplicate_files: true,
             ai_features_enabled: false,
             ai_provider: AiProvider::Ollama,
             ollama_model: None,
             gemini_model: None,
             api_key: None,
+            api_key_encrypted: None,
+            plaintext_secrets: false,
+            embedding_api_key: None,
+            embedding_api_key_encrypted: None,
             action_search_parsing_model: "ollama".to_string(),
             action_search_analysis_model: "same-as-main".to_string(),
+            rag_max_chars_per_doc: default_rag_max_chars_per_doc(),
+            rag_excerpt_chars: default_rag_excerpt_chars(),
+            ignore_patterns: default_ignore_patterns(),
+            max_index_depth: None,
+            max_content_file_size_bytes: default_max_content_file_size_bytes(),
+            max_file_size_mb: None,
+            follow_symlinks: false,
+            index_hidden: false,
+            index_directory_names: false,
+            index_hidden_dirs: Vec::new(),
+            metadata_only_extensions: default_metadata_only_extensions(),
+            excluded_filenames: default_excluded_filenames(),
+            spell_correction: false,
+            allow_future_dates: false,
+            fuzzy_max_edit_distance: default_fuzzy_max_edit_distance(),
+            normalize_filenames: default_normalize_filenames(),
+            filename_stop_tokens: default_filename_stop_tokens(),
+            semantic_keywords: default_semantic_keywords(),
+            ollama_base_url: default_ollama_base_url(),
+            embedding_provider: default_embedding_provider(),
+            openai_embedding_model: default_openai_embedding_model(),
+            fusion_embedding_models: Vec::new(),
+            embedding_base_url: None,
+            openai_base_url: default_openai_base_url(),
+            openai_model: None,
+            greenpt_model: default_greenpt_model(),
+            max_tokens: None,
+            temperature: None,
+            ai_request_timeout_secs: default_ai_request_timeout_secs(),
+            rag_total_timeout_secs: default_rag_total_timeout_secs(),
+            ai_max_concurrent_requests: default_ai_max_concurrent_requests(),
+            ai_max_queued_requests: default_ai_max_queued_requests(),
+            index_passages: default_index_passages(),
+            index_archives: false,
+            archive_max_depth: default_archive_max_depth(),
+            archive_max_mb: default_archive_max_mb(),
+            archive_text_entry_max_bytes: default_archive_text_entry_max_bytes(),
+            spreadsheet_header_context: default_spreadsheet_header_context(),
+            spreadsheet_sheet_sections: false,
+            hash_file_contents: default_hash_file_contents(),
+            skip_empty_files: default_skip_empty_files(),
+            index_excluded_as_metadata: default_index_excluded_as_metadata(),
+            embedding_truncation: default_embedding_truncation(),
+            similarity_metric: default_similarity_metric(),
+            score_calibration: ScoreCalibration::None,
+            persist_query_cache: default_persist_query_cache(),
+            query_cache_ttl_secs: default_query_cache_ttl_secs(),
+            log_level: default_log_level(),
+            content_search_extensions: default_content_search_extensions(),
+            content_search_max_bytes: default_content_search_max_bytes(),
+            auto_maintenance: false,
+            auto_maintenance_idle_secs: default_auto_maintenance_idle_secs(),
+            auto_maintenance_bloat_ratio: default_auto_maintenance_bloat_ratio(),
+            text_cache_max_mb: default_text_cache_max_mb(),
+            hnsw_m: default_hnsw_m(),
+            hnsw_ef_construction: default_hnsw_ef_construction(),
+            hnsw_ef_search: default_hnsw_ef_search(),
+            min_search_similarity: default_min_search_similarity(),
+            duplicate_cluster_similarity: default_duplicate_cluster_similarity(),
+            boost_paths: Vec::new(),
+            freshness_half_life_days: default_freshness_half_life_days(),
+            freshness_weight: 0.0,
+            type_boosts: std::collections::HashMap::new(),
+            chat_context_tokens: default_chat_context_tokens(),
+            provider_context_tokens: ProviderContextTokens::default(),
+            rag_min_similarity: default_rag_min_similarity(),
+            rag_default_documents: default_rag_default_documents(),
+            rag_max_documents: default_rag_max_documents(),
         }
     }
 }
 
 impl AppConfig {
+    /// Base URL for embedding requests
+    pub fn embedding_base_url(&self) -> &str {
+        let provider_url = match self.embedding_provider {
+            EmbeddingProvider::Ollama => &self.ollama_base_url,
+            EmbeddingProvider::OpenAI => &self.openai_base_url,
+        };
+        self.embedding_base_url.as_deref().unwrap_or(provider_url)
+    }
+
+    /// Name of the model that produces embeddings for the selected provider
+    pub fn active_embedding_model(&self) -> &str {
+        match self.embedding_provider {
+            EmbeddingProvider::Ollama => &self.embedding_model,
+            EmbeddingProvider::OpenAI => &self.openai_embedding_model,
+        }
+    }
+
+    /// Name of the chat model the selected AI provider answers with; empty when the
+    /// OpenAI-compatible provider has none configured
+    pub fn active_chat_model(&self) -> &str {
+        match self.ai_provider {
+            AiProvider::Ollama => self.ollama_model.as_deref().unwrap_or("llama3.2:1b"),
+            AiProvider::OpenAI => self.openai_model.as_deref().unwrap_or_default(),
+            AiProvider::GreenPT => &self.greenpt_model,
+            AiProvider::Gemini => self.gemini_model.as_deref().unwrap_or("gemini-pro"),
+        }
+    }
+
+    /// Prompt budget in tokens for document chat and summarize with the selected AI provider
+    pub fn ai_context_tokens(&self) -> usize {
+        let budgets = &self.provider_context_tokens;
+        let budget = match self.ai_provider {
+            AiProvider::Ollama => budgets.ollama,
+            AiProvider::OpenAI => budgets.openai,
+            AiProvider::GreenPT => budgets.greenpt,
+            AiProvider::Gemini => budgets.gemini,
+        };
+        budget.unwrap_or(self.chat_context_tokens)
+    }
+
+    /// Why `similarity_metric` looks wrong for the active embedding model, if it does
+    pub fn similarity_metric_mismatch(&self) -> Option<String> {
+        let model = self.active_embedding_model();
+        let recommended = SimilarityMetric::recommended_for(model)?;
+        (recommended != self.similarity_metric).then(|| {
+            format!(
+                "similarity_metric is {:?} but embedding model {} is meant to be compared with {:?}",
+                self.similarity_metric, model, recommended
+            )
+        })
+    }
+
     pub fn config_dir() -> PathBuf {
         dirs::home_dir()
             .unwrap_or_else(|| PathBuf::from("."))
@@ -131,41 +841,100 @@ impl AppConfig {
         Self::config_dir().join("data")
     }
 
+    /// Load config.json, creating it with defaults only when it doesn't exist. A file that
+    /// exists but can't be parsed is an error (with a copy kept as config.json.bak) rather
+    /// than a silent reset that would drop the user's directories and keys.
     pub async fn load_or_default() -> Result<Self> {
-        let config_file = Self::config_file();
-        
-        if config_file.exists() {
-            let content = tokio::fs::read_to_string(&config_file).await?;
-            let mut config: AppConfig = serde_json::from_str(&content)?;
-            
-            // Ensure model matches performance mode
-            config.update_model_for_mode();
-            
-            // Set default for max_search_results if missing (for backward compatibility)
-            if config.max_search_results == 0 {
-                config.max_search_results = 100;
+        match Self::load_from(&Self::config_file()).await? {
+            Some(config) if config.plaintext_secrets => {
+                // Written by a version that stored a key in plaintext; re-save it securely
+                config.save().await?;
+                Ok(config)
+            }
+            Some(config) => Ok(config),
+            None => {
+                let config = Self::default();
+                config.save().await?;
+                Ok(config)
             }
-            
-            // Don't save here - only save when explicitly updating settings
-            // Saving here can overwrite user changes if there's a race condition
-            // The config is already loaded correctly from disk
-            
-            Ok(config)
-        } else {
-            let config = Self::default();
-            config.save().await?;
-            Ok(config)
         }
     }
 
+    /// Read the config at `config_file`, or None if there is no file
+    async fn load_from(config_file: &std::path::Path) -> Result<Option<Self>> {
+        let content = match tokio::fs::read_to_string(config_file).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let mut config: AppConfig = match serde_json::from_str(&content) {
+            Ok(config) => config,
+            Err(e) => {
+                let backup = config_file.with_extension("json.bak");
+                tokio::fs::copy(config_file, &backup).await?;
+                return Err(anyhow::anyhow!(
+                    "{} could not be parsed ({}); a copy was kept at {}. Fix or remove it to continue.",
+                    config_file.display(),
+                    e,
+                    backup.display()
+                ));
+            }
+        };
+
+        let key_dir = config_file.parent().unwrap_or(std::path::Path::new("."));
+        config.plaintext_secrets = (config.api_key.is_some() && config.api_key_encrypted.is_none())
+            || (config.embedding_api_key.is_some() && config.embedding_api_key_encrypted.is_none());
+        if config.api_key_encrypted.is_some() || config.api_key.is_none() {
+            config.api_key = load_secret(key_dir, API_KEY_ENTRY, config.api_key_encrypted.as_deref()).await;
+        }
+        if config.embedding_api_key_encrypted.is_some() || config.embedding_api_key.is_none() {
+            config.embedding_api_key =
+                load_secret(key_dir, EMBEDDING_API_KEY_ENTRY, config.embedding_api_key_encrypted.as_deref()).await;
+        }
+
+        // Ensure model matches performance mode
+        config.update_model_for_mode();
+
+        if let Some(mb) = config.max_file_size_mb.take() {
+            config.max_content_file_size_bytes = mb.saturating_mul(1024 * 1024);
+        }
+
+        // Set default for max_search_results if missing (for backward compatibility)
+        if config.max_search_results == 0 {
+            config.max_search_results = 100;
+        }
+
+        // Don't save here - only save when explicitly updating settings
+        // Saving here can overwrite user changes if there's a race condition
+        // The config is already loaded correctly from disk
+
+        Ok(Some(config))
+    }
+
     pub async fn save(&self) -> Result<()> {
         let config_dir = Self::config_dir();
         tokio::fs::create_dir_all(&config_dir).await?;
-        
-        let config_file = Self::config_file();
-        let content = serde_json::to_string_pretty(self)?;
-        tokio::fs::write(&config_file, content).await?;
-        
+        self.save_to(&Self::config_file()).await
+    }
+
+    /// Write to a temp file and rename it over `config_file`, so a crash mid-write
+    /// leaves either the old config or the new one, never a truncated file
+    async fn save_to(&self, config_file: &std::path::Path) -> Result<()> {
+        use tokio::io::AsyncWriteExt;
+
+        let key_dir = config_file.parent().unwrap_or(std::path::Path::new("."));
+        let mut stored = self.clone();
+        stored.api_key_encrypted = store_secret(key_dir, API_KEY_ENTRY, self.api_key.as_deref()).await?;
+        stored.embedding_api_key_encrypted =
+            store_secret(key_dir, EMBEDDING_API_KEY_ENTRY, self.embedding_api_key.as_deref()).await?;
+        let content = serde_json::to_string_pretty(&stored)?;
+        let tmp_file = config_file.with_extension("json.tmp");
+        let mut file = tokio::fs::File::create(&tmp_file).await?;
+        file.write_all(content.as_bytes()).await?;
+        file.sync_all().await?;
+        drop(file);
+        tokio::fs::rename(&tmp_file, config_file).await?;
+
         Ok(())
     }
 
@@ -189,3 +958,238 @@ impl AppConfig {
         self.update_model_for_mode();
     }
 }
+
+/// OS keychain (macOS Keychain, Windows Credential Manager, Secret Service on Linux) service
+/// the API keys are filed under
+const KEYCHAIN_SERVICE: &str = "nlpfileexplorer";
+const API_KEY_ENTRY: &str = "api_key";
+const EMBEDDING_API_KEY_ENTRY: &str = "embedding_api_key";
+
+/// Keychain entry for `name`, keyed by the config directory so separate config dirs never
+/// share a key
+fn keychain_entry(key_dir: &std::path::Path, name: &str) -> keyring::Result<keyring::Entry> {
+    keyring::Entry::new(KEYCHAIN_SERVICE, &format!("{}@{}", name, key_dir.display()))
+}
+
+/// Put `secret` in the keychain, or remove its entry when None. Fails when no keychain is available.
+async fn keychain_store(key_dir: &std::path::Path, name: &'static str, secret: Option<String>) -> Result<()> {
+    let key_dir = key_dir.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        let entry = keychain_entry(&key_dir, name)?;
+        match secret {
+            Some(secret) => entry.set_password(&secret),
+            None => match entry.delete_credential() {
+                Err(keyring::Error::NoEntry) => Ok(()),
+                result => result,
+            },
+        }
+    })
+    .await?
+    .map_err(Into::into)
+}
+
+/// The keychain's copy of `name`, or None when it has none or no keychain is available
+async fn keychain_load(key_dir: &std::path::Path, name: &'static str) -> Option<String> {
+    let key_dir = key_dir.to_path_buf();
+    tokio::task::spawn_blocking(move || match keychain_entry(&key_dir, name).and_then(|entry| entry.get_password()) {
+        Ok(secret) => Some(secret),
+        Err(keyring::Error::NoEntry) => None,
+        Err(e) => {
+            tracing::debug!("Keychain unavailable for {}: {}", name, e);
+            None
+        }
+    })
+    .await
+    .ok()
+    .flatten()
+}
+
+/// Store `secret` in the keychain and return None for config.json. Without a keychain, return
+/// it encrypted under the machine key for config.json instead.
+async fn store_secret(key_dir: &std::path::Path, name: &'static str, secret: Option<&str>) -> Result<Option<String>> {
+    match (keychain_store(key_dir, name, secret.map(str::to_string)).await, secret) {
+        (Ok(()), _) | (Err(_), None) => Ok(None),
+        (Err(e), Some(secret)) => {
+            tracing::debug!("No keychain for {} ({}); storing it encrypted in config.json", name, e);
+            Ok(Some(encrypt_secret(secret, &machine_key(key_dir)?)?))
+        }
+    }
+}
+
+/// Decrypt the config.json fallback when there is one, otherwise read the keychain
+async fn load_secret(key_dir: &std::path::Path, name: &'static str, encrypted: Option<&str>) -> Option<String> {
+    let Some(encrypted) = encrypted else {
+        return keychain_load(key_dir, name).await;
+    };
+    match machine_key(key_dir).and_then(|key| decrypt_secret(encrypted, &key)) {
+        Ok(secret) => Some(secret),
+        Err(e) => {
+            tracing::warn!("Stored {} could not be decrypted ({}); enter it again in Settings", name, e);
+            None
+        }
+    }
+}
+
+/// Random per-machine key (owner-only, beside config.json) that API keys are encrypted under
+/// when no OS keychain is available, so config.json alone doesn't reveal them
+const SECRET_KEY_FILE: &str = ".secret";
+/// XChaCha20-Poly1305 nonce length; random nonces this long never realistically repeat
+const SECRET_NONCE_LEN: usize = 24;
+
+fn machine_key(dir: &std::path::Path) -> Result<[u8; 32]> {
+    let key_file = dir.join(SECRET_KEY_FILE);
+    match std::fs::read(&key_file) {
+        Ok(bytes) => bytes
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("{} is corrupt", key_file.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => create_machine_key(dir, &key_file),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Write a new key to a private temp file, then link it into place. Linking fails if another
+/// process created the key first, in which case that key is used, so both end up agreeing.
+fn create_machine_key(dir: &std::path::Path, key_file: &std::path::Path) -> Result<[u8; 32]> {
+    use rand::RngCore;
+    use std::io::Write;
+
+    let mut key = [0u8; 32];
+    rand::rngs::OsRng.fill_bytes(&mut key);
+    std::fs::create_dir_all(dir)?;
+    let tmp_file = dir.join(format!("{}.{}.{}.tmp", SECRET_KEY_FILE, std::process::id(), rand::random::<u64>()));
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let written = options
+        .open(&tmp_file)
+        .and_then(|mut file| file.write_all(&key).and_then(|_| file.sync_all()))
+        .map_err(anyhow::Error::from)
+        .and_then(|_| restrict_to_owner(&tmp_file));
+    let linked = written.and_then(|_| std::fs::hard_link(&tmp_file, key_file).map_err(anyhow::Error::from));
+    let _ = std::fs::remove_file(&tmp_file);
+    match linked {
+        Ok(()) => Ok(key),
+        Err(e) if e.downcast_ref::<std::io::Error>().is_some_and(|e| e.kind() == std::io::ErrorKind::AlreadyExists) => {
+            machine_key(dir)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Limit `path` to the current user. Unix files are already created 0600; on Windows the
+/// inherited ACL (which can include other accounts) is replaced with one granting only the user.
+#[cfg(windows)]
+fn restrict_to_owner(path: &std::path::Path) -> Result<()> {
+    let user = std::env::var("USERNAME").map_err(|_| anyhow::anyhow!("USERNAME is not set"))?;
+    let status = std::process::Command::new("icacls")
+        .arg(path)
+        .args(["/inheritance:r", "/grant:r"])
+        .arg(format!("{}:F", user))
+        .stdout(std::process::Stdio::null())
+        .status()?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("icacls could not restrict {}", path.display()));
+    }
+    Ok(())
+}
+
+#[cfg(not(windows))]
+fn restrict_to_owner(path: &std::path::Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+/// base64(nonce || ciphertext || tag) under XChaCha20-Poly1305, with a fresh random nonce per call
+fn encrypt_secret(plaintext: &str, key: &[u8; 32]) -> Result<String> {
+    use base64::{Engine as _, engine::general_purpose::STANDARD};
+    use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+    use chacha20poly1305::XChaCha20Poly1305;
+
+    let cipher = XChaCha20Poly1305::new(key.into());
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext.as_bytes())
+        .map_err(|_| anyhow::anyhow!("failed to encrypt secret"))?;
+    Ok(STANDARD.encode([nonce.as_slice(), &ciphertext].concat()))
+}
+
+/// Fails if the value was tampered with or encrypted under a different machine key
+fn decrypt_secret(encoded: &str, key: &[u8; 32]) -> Result<String> {
+    use base64::{Engine as _, engine::general_purpose::STANDARD};
+    use chacha20poly1305::aead::{Aead, KeyInit};
+    use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+
+    let bytes = STANDARD.decode(encoded)?;
+    if bytes.len() < SECRET_NONCE_LEN {
+        return Err(anyhow::anyhow!("encrypted value is truncated"));
+    }
+    let (nonce, ciphertext) = bytes.split_at(SECRET_NONCE_LEN);
+    let plaintext = XChaCha20Poly1305::new(key.into())
+        .decrypt(XNonce::from_slice(nonce), ciphertext)
+        .map_err(|_| anyhow::anyhow!("encrypted value was altered or uses a different machine key"))?;
+    Ok(String::from_utf8(plaintext)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_round_trip_and_wrong_key() {
+        let key = [7u8; 32];
+        let encrypted = encrypt_secret("AIza-secret", &key).unwrap();
+        assert_ne!(encrypted, encrypt_secret("AIza-secret", &key).unwrap());
+        assert_eq!(decrypt_secret(&encrypted, &key).unwrap(), "AIza-secret");
+        assert!(decrypt_secret(&encrypted, &[8u8; 32]).is_err());
+
+        // Flipping any ciphertext bit is detected rather than decrypting to garbage
+        use base64::{Engine as _, engine::general_purpose::STANDARD};
+        let mut bytes = STANDARD.decode(&encrypted).unwrap();
+        bytes[SECRET_NONCE_LEN] ^= 1;
+        assert!(decrypt_secret(&STANDARD.encode(bytes), &key).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unparseable_config_is_an_error_not_a_reset() {
+        let dir = std::env::temp_dir().join(format!("gist-config-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let config_file = dir.join("config.json");
+
+        assert!(AppConfig::load_from(&config_file).await.unwrap().is_none());
+
+        let config = AppConfig {
+            indexed_directories: vec!["/home/user/docs".to_string()],
+            api_key: Some("sk-test-123".to_string()),
+            embedding_api_key: Some("sk-embed-456".to_string()),
+            ..AppConfig::default()
+        };
+        config.save_to(&config_file).await.unwrap();
+        let loaded = AppConfig::load_from(&config_file).await.unwrap().unwrap();
+        assert_eq!(loaded.indexed_directories, config.indexed_directories);
+        assert_eq!(loaded.api_key.as_deref(), Some("sk-test-123"));
+        assert_eq!(loaded.embedding_api_key.as_deref(), Some("sk-embed-456"));
+        let stored = std::fs::read_to_string(&config_file).unwrap();
+        assert!(!stored.contains("sk-test-123") && !stored.contains("sk-embed-456"));
+        assert!(!dir.join("config.json.tmp").exists());
+
+        std::fs::write(&config_file, "{\"performance_mode\": \"norm").unwrap();
+        assert!(AppConfig::load_from(&config_file).await.is_err());
+        assert_eq!(std::fs::read_to_string(dir.join("config.json.bak")).unwrap(), "{\"performance_mode\": \"norm");
+
+        // Saving without keys removes any keychain entries the save above made
+        AppConfig::default().save_to(&config_file).await.unwrap();
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,8 +1,104 @@
 use anyhow::Result;
+use async_trait::async_trait;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+use tracing::{debug, error};
 
-const OLLAMA_URL: &str = "http://localhost:11434";
+use crate::config::{AppConfig, EmbeddingProvider, DEFAULT_OLLAMA_BASE_URL};
+
+/// A source of text embeddings (a local Ollama model, an OpenAI-compatible API, ...)
+#[async_trait]
+pub trait EmbeddingBackend: Send + Sync {
+    async fn generate_embedding(&self, text: &str) -> Result<Vec<f32>>;
+
+    /// Embed several texts, in order. Backends without a batch endpoint send one request per text.
+    async fn generate_embeddings_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for text in texts {
+            embeddings.push(self.generate_embedding(text).await?);
+        }
+        Ok(embeddings)
+    }
+}
+
+/// Embeddings from the configured backend, checked to all have the same dimension
+pub struct EmbeddingService {
+    backend: Box<dyn EmbeddingBackend>,
+    /// Dimension of the first vector produced; every later vector must match it
+    dimensions: OnceLock<usize>,
+}
+
+impl EmbeddingService {
+    pub fn new(backend: impl EmbeddingBackend + 'static) -> Self {
+        Self {
+            backend: Box::new(backend),
+            dimensions: OnceLock::new(),
+        }
+    }
+
+    /// Build the backend selected by `embedding_provider`
+    pub fn from_config(config: &AppConfig) -> Self {
+        Self::for_model(config, config.active_embedding_model())
+    }
+
+    /// The configured provider, but embedding with `model` instead of the active one
+    pub fn for_model(config: &AppConfig, model: &str) -> Self {
+        match config.embedding_provider {
+            EmbeddingProvider::Ollama => Self::new(
+                OllamaBackend::new(model.to_string()).with_base_url(config.embedding_base_url()),
+            ),
+            EmbeddingProvider::OpenAI => Self::new(OpenAiBackend::new(
+                model.to_string(),
+                config.embedding_base_url(),
+                config.embedding_api_key.clone(),
+            )),
+        }
+    }
+
+    pub async fn generate_embedding(&self, text: &str) -> Result<Vec<f32>> {
+        let embedding = self.backend.generate_embedding(text).await?;
+        self.check_dimensions(&embedding)?;
+        Ok(embedding)
+    }
+
+    /// Embed several texts, in one request where the backend supports batching
+    pub async fn generate_embeddings(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+        let embeddings = self.backend.generate_embeddings_batch(texts).await?;
+        if embeddings.len() != texts.len() {
+            anyhow::bail!("Embedding backend returned {} vectors for {} texts", embeddings.len(), texts.len());
+        }
+        for embedding in &embeddings {
+            self.check_dimensions(embedding)?;
+        }
+        Ok(embeddings)
+    }
+
+    /// Dimension of the vectors this service produces, once it has produced one
+    pub fn dimensions(&self) -> Option<usize> {
+        self.dimensions.get().copied()
+    }
+
+    /// Reject empty vectors and vectors whose size differs from earlier ones, so a
+    /// misbehaving backend can't mix dimensions into storage or the HNSW index
+    fn check_dimensions(&self, embedding: &[f32]) -> Result<()> {
+        if embedding.is_empty() {
+            anyhow::bail!("Embedding backend returned an empty vector");
+        }
+        let expected = *self.dimensions.get_or_init(|| embedding.len());
+        if embedding.len() != expected {
+            anyhow::bail!(
+                "Embedding dimension changed: expected {}, got {}",
+                expected,
+                embedding.len()
+            );
+        }
+        Ok(())
+    }
+}
 
 #[derive(Debug, Serialize)]
 struct EmbeddingRequest {
@@ -15,46 +111,31 @@ struct EmbeddingResponse {
     embedding: Vec<f32>,
 }
 
-pub struct EmbeddingService {
+/// Ollama's /api/embed endpoint, or /api/embeddings on servers that predate it
+pub struct OllamaBackend {
     client: Client,
     model: String,
+    base_url: String,
 }
 
-impl EmbeddingService {
+impl OllamaBackend {
     pub fn new(model: String) -> Self {
         Self {
             client: Client::new(),
             model,
+            base_url: DEFAULT_OLLAMA_BASE_URL.to_string(),
         }
     }
 
-    pub async fn generate_embedding(&self, text: &str) -> Result<Vec<f32>> {
-        let request = EmbeddingRequest {
-            model: self.model.clone(),
-            prompt: text.to_string(),
-        };
-
-        let response = self
-            .client
-            .post(&format!("{}/api/embeddings", OLLAMA_URL))
-            .json(&request)
-            .send()
-            .await?;
-
-        let status = response.status();
-        if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            anyhow::bail!("Ollama API error: {} - {}", status, error_text);
-        }
-
-        let embedding_response: EmbeddingResponse = response.json().await?;
-        Ok(embedding_response.embedding)
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
     }
 
     pub async fn check_model_available(&self) -> Result<bool> {
         let response = self
             .client
-            .get(&format!("{}/api/tags", OLLAMA_URL))
+            .get(format!("{}/api/tags", self.base_url))
             .send()
             .await?;
 
@@ -78,6 +159,7 @@ impl EmbeddingService {
 
     /// Test if Ollama's /api/embeddings endpoint supports image embeddings
     /// Returns Ok(true) if images are supported, Ok(false) if not, or Err if test failed
+    #[tracing::instrument(name = "IMAGE_EMBEDDING_TEST", skip_all)]
     pub async fn test_image_embedding_support(&self, image_path: &str) -> Result<bool> {
         use std::fs;
         use base64::{Engine as _, engine::general_purpose::STANDARD};
@@ -114,7 +196,7 @@ impl EmbeddingService {
         // Try format 1
         let response1 = self
             .client
-            .post(&format!("{}/api/embeddings", OLLAMA_URL))
+            .post(format!("{}/api/embeddings", self.base_url))
             .json(&request_format1)
             .send()
             .await;
@@ -123,7 +205,7 @@ impl EmbeddingService {
             if resp.status().is_success() {
                 if let Ok(embedding_resp) = resp.json::<EmbeddingResponse>().await {
                     if !embedding_resp.embedding.is_empty() {
-                        eprintln!("[IMAGE_EMBEDDING_TEST] Format 1 (images array) succeeded!");
+                        debug!("Format 1 (images array) succeeded!");
                         return Ok(true);
                     }
                 }
@@ -133,7 +215,7 @@ impl EmbeddingService {
         // Try format 2
         let response2 = self
             .client
-            .post(&format!("{}/api/embeddings", OLLAMA_URL))
+            .post(format!("{}/api/embeddings", self.base_url))
             .json(&request_format2)
             .send()
             .await;
@@ -142,7 +224,7 @@ impl EmbeddingService {
             if resp.status().is_success() {
                 if let Ok(embedding_resp) = resp.json::<EmbeddingResponse>().await {
                     if !embedding_resp.embedding.is_empty() {
-                        eprintln!("[IMAGE_EMBEDDING_TEST] Format 2 (image field) succeeded!");
+                        debug!("Format 2 (image field) succeeded!");
                         return Ok(true);
                     }
                 }
@@ -152,7 +234,7 @@ impl EmbeddingService {
         // Try format 3
         let response3 = self
             .client
-            .post(&format!("{}/api/embeddings", OLLAMA_URL))
+            .post(format!("{}/api/embeddings", self.base_url))
             .json(&request_format3)
             .send()
             .await;
@@ -161,14 +243,219 @@ impl EmbeddingService {
             if resp.status().is_success() {
                 if let Ok(embedding_resp) = resp.json::<EmbeddingResponse>().await {
                     if !embedding_resp.embedding.is_empty() {
-                        eprintln!("[IMAGE_EMBEDDING_TEST] Format 3 (base64 in prompt) succeeded!");
+                        debug!("Format 3 (base64 in prompt) succeeded!");
                         return Ok(true);
                     }
                 }
             }
         }
         
-        eprintln!("[IMAGE_EMBEDDING_TEST] All formats failed - images not supported by /api/embeddings endpoint");
+        error!("All formats failed - images not supported by /api/embeddings endpoint");
         Ok(false)
     }
 }
+
+impl OllamaBackend {
+    /// Embed `texts` with /api/embed, or None if the server has no such endpoint (404).
+    /// Input is never truncated: a text past the model's context fails instead of being
+    /// embedded from its beginning only.
+    async fn embed(&self, texts: &[String]) -> Result<Option<Vec<Vec<f32>>>> {
+        let response = self
+            .client
+            .post(format!("{}/api/embed", self.base_url))
+            .json(&OllamaBatchRequest { model: &self.model, input: texts, truncate: false })
+            .send()
+            .await?;
+
+        let status = response.status();
+        if status == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(ollama_error(status, &error_text));
+        }
+
+        Ok(Some(response.json::<OllamaBatchResponse>().await?.embeddings))
+    }
+
+    /// One text through the legacy /api/embeddings endpoint
+    async fn embed_legacy(&self, text: &str) -> Result<Vec<f32>> {
+        let request = EmbeddingRequest {
+            model: self.model.clone(),
+            prompt: text.to_string(),
+        };
+
+        let response = self
+            .client
+            .post(format!("{}/api/embeddings", self.base_url))
+            .json(&request)
+            .send()
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(ollama_error(status, &error_text));
+        }
+
+        let embedding_response: EmbeddingResponse = response.json().await?;
+        Ok(embedding_response.embedding)
+    }
+}
+
+/// An Ollama error response, explaining context overflows (which `truncate: false` turns into errors)
+fn ollama_error(status: reqwest::StatusCode, error_text: &str) -> anyhow::Error {
+    if error_text.contains("context length") {
+        return anyhow::anyhow!(
+            "Text is longer than the embedding model's context; lower max_context_tokens to match the model ({})",
+            error_text.trim()
+        );
+    }
+    anyhow::anyhow!("Ollama API error: {} - {}", status, error_text)
+}
+
+#[async_trait]
+impl EmbeddingBackend for OllamaBackend {
+    /// Queries go through the same endpoint as indexed documents, so both get the same
+    /// treatment (/api/embed normalizes its vectors, /api/embeddings doesn't)
+    async fn generate_embedding(&self, text: &str) -> Result<Vec<f32>> {
+        match self.embed(&[text.to_string()]).await? {
+            Some(mut embeddings) => embeddings
+                .pop()
+                .ok_or_else(|| anyhow::anyhow!("Ollama returned no embedding")),
+            None => self.embed_legacy(text).await,
+        }
+    }
+
+    /// Uses /api/embed, which takes a list of inputs; servers older than that endpoint
+    /// get one /api/embeddings request per text
+    async fn generate_embeddings_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        if let Some(embeddings) = self.embed(texts).await? {
+            return Ok(embeddings);
+        }
+        debug!("Ollama has no /api/embed endpoint, embedding {} texts one at a time", texts.len());
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for text in texts {
+            embeddings.push(self.embed_legacy(text).await?);
+        }
+        Ok(embeddings)
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct OllamaBatchRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+    truncate: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaBatchResponse {
+    embeddings: Vec<Vec<f32>>,
+}
+
+#[derive(Debug, Serialize)]
+struct OpenAiEmbeddingRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiEmbeddingResponse {
+    data: Vec<OpenAiEmbedding>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiEmbedding {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+/// The `/embeddings` endpoint of OpenAI and compatible servers (LiteLLM, vLLM, LocalAI, ...)
+pub struct OpenAiBackend {
+    client: Client,
+    model: String,
+    base_url: String,
+    api_key: Option<String>,
+}
+
+impl OpenAiBackend {
+    pub fn new(model: String, base_url: &str, api_key: Option<String>) -> Self {
+        Self {
+            client: Client::new(),
+            model,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+        }
+    }
+}
+
+#[async_trait]
+impl EmbeddingBackend for OpenAiBackend {
+    async fn generate_embedding(&self, text: &str) -> Result<Vec<f32>> {
+        self.generate_embeddings_batch(&[text.to_string()])
+            .await?
+            .pop()
+            .ok_or_else(|| anyhow::anyhow!("OpenAI-compatible API returned no embedding"))
+    }
+
+    async fn generate_embeddings_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let mut request = self
+            .client
+            .post(format!("{}/embeddings", self.base_url))
+            .json(&OpenAiEmbeddingRequest { model: &self.model, input: texts });
+        if let Some(ref api_key) = self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+        let response = request.send().await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            anyhow::bail!("OpenAI-compatible embeddings API error: {} - {}", status, error_text);
+        }
+
+        // Entries carry their input index; don't rely on response order
+        let mut data = response.json::<OpenAiEmbeddingResponse>().await?.data;
+        data.sort_by_key(|entry| entry.index);
+        Ok(data.into_iter().map(|entry| entry.embedding).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a vector as long as the input text
+    struct LengthBackend;
+
+    #[async_trait]
+    impl EmbeddingBackend for LengthBackend {
+        async fn generate_embedding(&self, text: &str) -> Result<Vec<f32>> {
+            Ok(vec![1.0; text.len()])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_service_rejects_dimension_changes() {
+        let service = EmbeddingService::new(LengthBackend);
+        assert_eq!(service.dimensions(), None);
+
+        let batch = service.generate_embeddings(&["abc".to_string(), "xyz".to_string()]).await.unwrap();
+        assert_eq!(batch.len(), 2);
+        assert_eq!(service.dimensions(), Some(3));
+
+        assert!(service.generate_embedding("abcd").await.is_err());
+        assert!(service.generate_embedding("").await.is_err());
+    }
+
+    #[test]
+    fn test_ollama_context_overflow_names_the_setting() {
+        let error = ollama_error(reqwest::StatusCode::BAD_REQUEST, r#"{"error":"the input length exceeds the context length"}"#);
+        assert!(error.to_string().contains("max_context_tokens"));
+
+        let error = ollama_error(reqwest::StatusCode::INTERNAL_SERVER_ERROR, "boom");
+        assert_eq!(error.to_string(), "Ollama API error: 500 Internal Server Error - boom");
+    }
+}
@@ -3,6 +3,7 @@ use notify::{Watcher, RecursiveMode, Event, EventKind};
 use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::mpsc;
+use tracing::{error, info, warn};
 
 use crate::indexer::Indexer;
 use crate::storage::Storage;
@@ -15,7 +16,13 @@ pub struct FileWatcher {
 }
 
 impl FileWatcher {
-    pub fn new(indexer: Arc<Indexer>, storage: Arc<Storage>, directories: Vec<String>) -> Result<Self> {
+    /// Watch `directories`, applying each event's index writes under `write_lock`
+    pub fn new(
+        indexer: Arc<Indexer>,
+        storage: Arc<Storage>,
+        directories: Vec<String>,
+        write_lock: Arc<tokio::sync::Mutex<()>>,
+    ) -> Result<Self> {
         let (tx, _rx) = mpsc::unbounded_channel();
         
         let indexer_clone = indexer.clone();
@@ -25,6 +32,7 @@ impl FileWatcher {
         // Spawn task to handle file events
         tokio::spawn(async move {
             while let Some(event) = watcher_rx.recv().await {
+                let _writing = write_lock.lock().await;
                 Self::handle_event(&indexer_clone, &storage_clone, event).await;
             }
         });
@@ -38,7 +46,7 @@ impl FileWatcher {
         // Watch all directories
         for dir in directories {
             if let Err(e) = watcher.watch(PathBuf::from(&dir).as_path(), RecursiveMode::Recursive) {
-                eprintln!("Warning: Failed to watch directory {}: {}", dir, e);
+                warn!("Failed to watch directory {}: {}", dir, e);
             }
         }
         
@@ -57,16 +65,33 @@ impl FileWatcher {
                     if path.is_file() {
                         if let Some(path_str) = path.to_str() {
                             // Skip files that tend to give false positives
-                            if Indexer::should_exclude_file(path_str) {
+                            if indexer.should_exclude_file(path_str) {
                                 continue;
                             }
                             // Skip user-excluded extensions
                             if indexer.is_excluded_by_config(path_str) {
                                 continue;
                             }
+                            // Skip dotfiles unless hidden entries are opted in
+                            if indexer.is_hidden_file_excluded(path_str) {
+                                continue;
+                            }
+                            // Skip files removed from the index unless they changed since
+                            if indexer.is_removed_from_index(path_str).await {
+                                continue;
+                            }
                             
                             if let Err(e) = indexer.index_file(path_str).await {
-                                eprintln!("Error auto-indexing {}: {}", path_str, e);
+                                error!("Error auto-indexing {}: {}", path_str, e);
+                            }
+                        }
+                    } else if event.kind.is_create() && path.is_dir() && indexer.indexes_directory_names() {
+                        if let Some(path_str) = path.to_str() {
+                            if indexer.is_hidden_file_excluded(path_str) {
+                                continue;
+                            }
+                            if let Err(e) = indexer.index_directory_entry(path_str).await {
+                                error!("Error auto-indexing folder {}: {}", path_str, e);
                             }
                         }
                     }
@@ -76,12 +101,15 @@ impl FileWatcher {
                 // Remove deleted files from index
                 for path in event.paths {
                     if let Some(path_str) = path.to_str() {
+                        // Stored paths are canonical (see Indexer::canonical_path)
+                        let path_str = Indexer::canonical_path(path_str);
+                        let path_str = path_str.as_str();
                         // Check if it's a file (not a directory)
                         if path.is_file() {
                             if let Err(e) = storage.delete_file(path_str).await {
-                                eprintln!("Error removing file {} from index: {}", path_str, e);
+                                error!("Error removing file {} from index: {}", path_str, e);
                             } else {
-                                println!("Removed file from index: {}", path_str);
+                                info!("Removed file from index: {}", path_str);
                             }
                         } else {
                             // If it's a directory, remove all files in that directory from index
@@ -90,11 +118,11 @@ impl FileWatcher {
                                 for file in all_files {
                                     if file.file_path.starts_with(path_str) {
                                         if let Err(e) = storage.delete_file(&file.file_path).await {
-                                            eprintln!("Error removing file {} from index: {}", file.file_path, e);
+                                            error!("Error removing file {} from index: {}", file.file_path, e);
                                         }
                                     }
                                 }
-                                println!("Removed directory and its files from index: {}", path_str);
+                                info!("Removed directory and its files from index: {}", path_str);
                             }
                         }
                     }
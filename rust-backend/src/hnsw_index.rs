@@ -1,28 +1,180 @@
 use anyhow::Result;
-use crate::storage::FileMetadata;
+use hnsw::{Hnsw, Params, Searcher};
+use rand::rngs::StdRng;
+use space::{Metric, Neighbor};
 use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{error, info};
+use crate::config::SimilarityMetric;
+use crate::search::{prepare_vector, scale_similarity, vector_norm, vector_similarity};
+use crate::storage::FileMetadata;
+
+/// Values of `M` the graph can be built with. The hnsw crate takes `M` as a const generic,
+/// so a configured value is rounded to the nearest of these.
+pub const SUPPORTED_M: &[usize] = &[8, 12, 16, 24, 32, 48];
+
+/// Graph construction and search parameters.
+/// Larger `m` and `ef_construction` give better recall at the cost of memory and build time;
+/// larger `ef_search` gives better recall at the cost of query latency.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HnswParams {
+    pub m: usize,
+    pub ef_construction: usize,
+    pub ef_search: usize,
+    /// Used for graph distances and result scores alike
+    pub metric: SimilarityMetric,
+}
+
+impl Default for HnswParams {
+    fn default() -> Self {
+        Self {
+            m: 16,
+            ef_construction: 200,
+            ef_search: 64,
+            metric: SimilarityMetric::Cosine,
+        }
+    }
+}
+
+impl HnswParams {
+    pub fn from_config(config: &crate::config::AppConfig) -> Self {
+        Self {
+            m: config.hnsw_m,
+            ef_construction: config.hnsw_ef_construction,
+            ef_search: config.hnsw_ef_search,
+            metric: config.similarity_metric,
+        }
+    }
+}
+
+/// Round `m` to the closest value in `SUPPORTED_M`
+pub fn nearest_supported_m(m: usize) -> usize {
+    *SUPPORTED_M
+        .iter()
+        .min_by_key(|&&supported| supported.abs_diff(m))
+        .expect("SUPPORTED_M is not empty")
+}
 
-// Use a simpler approach: implement HNSW using the actual crate API
-// Based on hnsw 0.11 crate structure
+/// Negated similarity under the configured metric, mapped onto `u32` so the graph can order neighbours
+struct MetricDistance(SimilarityMetric);
+
+impl Metric<Arc<Vec<f32>>> for MetricDistance {
+    type Unit = u32;
+
+    fn distance(&self, a: &Arc<Vec<f32>>, b: &Arc<Vec<f32>>) -> u32 {
+        // Dot products can be negative, so flip the bits into an order-preserving unsigned key
+        let bits = (-vector_similarity(self.0, a, b)).to_bits();
+        if bits >> 31 == 0 {
+            bits | 0x8000_0000
+        } else {
+            !bits
+        }
+    }
+}
+
+type Graph<const M: usize, const M0: usize> = Hnsw<MetricDistance, Arc<Vec<f32>>, StdRng, M, M0>;
+
+enum HnswGraph {
+    M8(Graph<8, 16>),
+    M12(Graph<12, 24>),
+    M16(Graph<16, 32>),
+    M24(Graph<24, 48>),
+    M32(Graph<32, 64>),
+    M48(Graph<48, 96>),
+}
+
+macro_rules! with_graph {
+    ($graph:expr, $g:ident => $body:expr) => {
+        match $graph {
+            HnswGraph::M8($g) => $body,
+            HnswGraph::M12($g) => $body,
+            HnswGraph::M16($g) => $body,
+            HnswGraph::M24($g) => $body,
+            HnswGraph::M32($g) => $body,
+            HnswGraph::M48($g) => $body,
+        }
+    };
+}
+
+impl HnswGraph {
+    fn new(params: &HnswParams) -> Self {
+        let graph_params = Params::new().ef_construction(params.ef_construction);
+        let metric = MetricDistance(params.metric);
+        match nearest_supported_m(params.m) {
+            8 => Self::M8(Hnsw::new_params(metric, graph_params)),
+            12 => Self::M12(Hnsw::new_params(metric, graph_params)),
+            16 => Self::M16(Hnsw::new_params(metric, graph_params)),
+            24 => Self::M24(Hnsw::new_params(metric, graph_params)),
+            32 => Self::M32(Hnsw::new_params(metric, graph_params)),
+            _ => Self::M48(Hnsw::new_params(metric, graph_params)),
+        }
+    }
+
+    fn insert(&mut self, feature: Arc<Vec<f32>>, searcher: &mut Searcher<u32>) -> usize {
+        with_graph!(self, g => g.insert(feature, searcher))
+    }
+
+    /// Approximate `k` nearest items (by insertion order), exploring `ef` candidates
+    fn nearest(&self, query: &Arc<Vec<f32>>, k: usize, ef: usize) -> Vec<usize> {
+        let mut searcher = Searcher::default();
+        let mut dest = vec![Neighbor { index: !0, distance: !0 }; k];
+        with_graph!(self, g => g.nearest(query, ef, &mut searcher, &mut dest))
+            .iter()
+            .map(|neighbor| neighbor.index)
+            .collect()
+    }
+}
+
+/// Approximate nearest-neighbour index over file embeddings, backed by an HNSW graph.
+/// Vectors and metadata are also kept flat for exact scans and integrity checks.
 pub struct HnswIndex {
-    // Store embeddings and metadata separately
-    // We'll use a simple vector-based approach with cosine similarity
-    embeddings: Vec<Vec<f32>>,
+    embeddings: Vec<Arc<Vec<f32>>>,
     metadata_list: Vec<FileMetadata>,
     id_to_index: HashMap<i64, usize>, // Map from file ID to vector index
     dimensions: usize,
+    /// Paths skipped by the last rebuild because their vector had the wrong dimension
+    rejected_paths: Vec<String>,
+    params: HnswParams,
+    /// Graph item ids equal positions in `embeddings`. Dropped on `remove` (which reorders
+    /// vectors), after which searches fall back to an exact scan until the next rebuild.
+    graph: Option<HnswGraph>,
+    insert_searcher: Searcher<u32>,
 }
 
 impl HnswIndex {
     pub fn new(dimensions: usize) -> Self {
+        let params = HnswParams::default();
         Self {
             embeddings: Vec::new(),
             metadata_list: Vec::new(),
             id_to_index: HashMap::new(),
             dimensions,
+            rejected_paths: Vec::new(),
+            params,
+            graph: Some(HnswGraph::new(&params)),
+            insert_searcher: Searcher::default(),
         }
     }
 
+    /// Use these graph parameters; call before adding any vectors
+    pub fn with_params(mut self, params: HnswParams) -> Self {
+        self.params = HnswParams {
+            m: nearest_supported_m(params.m),
+            ..params
+        };
+        self.graph = Some(HnswGraph::new(&self.params));
+        self
+    }
+
+    /// Change the query-time candidate pool; unlike the other parameters this needs no rebuild
+    pub fn set_ef_search(&mut self, ef_search: usize) {
+        self.params.ef_search = ef_search;
+    }
+
+    pub fn params(&self) -> HnswParams {
+        self.params
+    }
+
     pub fn add(&mut self, embedding: Vec<f32>, metadata: FileMetadata) -> Result<()> {
         if embedding.len() != self.dimensions {
             return Err(anyhow::anyhow!(
@@ -33,6 +185,12 @@ impl HnswIndex {
         }
 
         let index = self.embeddings.len();
+        let mut embedding = embedding;
+        prepare_vector(self.params.metric, &mut embedding);
+        let embedding = Arc::new(embedding);
+        if let Some(graph) = self.graph.as_mut() {
+            graph.insert(embedding.clone(), &mut self.insert_searcher);
+        }
         self.embeddings.push(embedding);
         self.metadata_list.push(metadata.clone());
         self.id_to_index.insert(metadata.id, index);
@@ -40,6 +198,8 @@ impl HnswIndex {
         Ok(())
     }
 
+    /// Approximate top-`k` search through the graph, exploring `max(ef_search, k)` candidates.
+    /// Scores are exact similarities of the returned items under the configured metric.
     pub fn search(&self, query_embedding: Vec<f32>, k: usize) -> Result<Vec<(FileMetadata, f32)>> {
         if query_embedding.len() != self.dimensions {
             return Err(anyhow::anyhow!(
@@ -49,12 +209,49 @@ impl HnswIndex {
             ));
         }
 
+        let Some(graph) = self.graph.as_ref() else {
+            return self.search_exact(query_embedding, k);
+        };
+        if self.embeddings.is_empty() || k == 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut query_embedding = query_embedding;
+        prepare_vector(self.params.metric, &mut query_embedding);
+        let query_norm = vector_norm(&query_embedding);
+        let query = Arc::new(query_embedding);
+        let metric = self.params.metric;
+        let mut results: Vec<(FileMetadata, f32)> = graph
+            .nearest(&query, k, self.params.ef_search.max(k))
+            .into_iter()
+            .filter_map(|idx| {
+                let embedding = self.embeddings.get(idx)?;
+                let meta = self.metadata_list.get(idx)?;
+                let similarity = vector_similarity(metric, &query, embedding);
+                Some((meta.clone(), scale_similarity(metric, similarity, query_norm)))
+            })
+            .collect();
+        results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+
+        Ok(results)
+    }
+
+    /// Exact top-`k` by scanning every vector (the ground truth for recall measurements)
+    pub fn search_exact(&self, query_embedding: Vec<f32>, k: usize) -> Result<Vec<(FileMetadata, f32)>> {
+        if query_embedding.len() != self.dimensions {
+            return Err(anyhow::anyhow!(
+                "Query embedding dimension mismatch: expected {}, got {}",
+                self.dimensions,
+                query_embedding.len()
+            ));
+        }
+
         if self.embeddings.is_empty() {
             return Ok(Vec::new());
         }
 
-        // Use cosine similarity for search
-        use crate::search::cosine_similarity;
+        let mut query_embedding = query_embedding;
+        prepare_vector(self.params.metric, &mut query_embedding);
         
         // Optimized: Use a binary heap to maintain top k results without full sort
         // For large datasets, this avoids sorting all similarities
@@ -86,7 +283,7 @@ impl HnswIndex {
         let mut heap = BinaryHeap::new();
         
         for (idx, emb) in self.embeddings.iter().enumerate() {
-            let similarity = cosine_similarity(&query_embedding, emb);
+            let similarity = vector_similarity(self.params.metric, &query_embedding, emb);
             
             if heap.len() < k {
                 heap.push(SimilarityItem { similarity, index: idx });
@@ -105,8 +302,10 @@ impl HnswIndex {
         results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
 
         // Convert to (metadata, similarity) pairs
+        let query_norm = vector_norm(&query_embedding);
         let search_results: Vec<(FileMetadata, f32)> = results.into_iter()
             .filter_map(|(idx, similarity)| {
+                let similarity = scale_similarity(self.params.metric, similarity, query_norm);
                 self.metadata_list.get(idx).map(|meta| (meta.clone(), similarity))
             })
             .collect();
@@ -120,6 +319,9 @@ impl HnswIndex {
             .enumerate()
             .find(|(_, meta)| meta.file_path == file_path) {
             
+            // The graph can't delete nodes and its ids are about to shift
+            self.graph = None;
+
             // Remove from vectors (swap with last for O(1) removal)
             let last_idx = self.embeddings.len() - 1;
             if idx != last_idx {
@@ -146,34 +348,54 @@ impl HnswIndex {
         self.embeddings.clear();
         self.metadata_list.clear();
         self.id_to_index.clear();
+        self.rejected_paths.clear();
+        self.graph = Some(HnswGraph::new(&self.params));
         Ok(())
     }
 
+    #[tracing::instrument(name = "HNSW", skip_all)]
     pub fn rebuild_from_embeddings(&mut self, embeddings: Vec<(FileMetadata, Vec<f32>)>) -> Result<()> {
-        eprintln!("[HNSW] Rebuilding index with {} embeddings", embeddings.len());
+        info!("Rebuilding index with {} embeddings", embeddings.len());
         
         if embeddings.is_empty() {
             self.clear()?;
             return Ok(());
         }
 
-        // Get dimensions from first embedding
-        let dims = embeddings[0].1.len();
+        // Use the most common dimension so one stale vector can't reject the whole corpus
+        let mut dimension_counts: HashMap<usize, usize> = HashMap::new();
+        for (_, embedding) in &embeddings {
+            *dimension_counts.entry(embedding.len()).or_default() += 1;
+        }
+        let dims = dimension_counts
+            .into_iter()
+            .max_by_key(|&(dims, count)| (count, dims))
+            .map(|(dims, _)| dims)
+            .unwrap_or(self.dimensions);
         if dims != self.dimensions {
-            eprintln!("[HNSW] Dimension mismatch, recreating index: {} -> {}", self.dimensions, dims);
-            *self = Self::new(dims);
+            info!("Dimension mismatch, recreating index: {} -> {}", self.dimensions, dims);
+            *self = Self::new(dims).with_params(self.params);
         } else {
             self.clear()?;
         }
 
-        // Add all embeddings to the index
+        // Add all embeddings to the index, keeping track of any that don't fit
         for (metadata, embedding) in embeddings {
+            let file_path = metadata.file_path.clone();
             if let Err(e) = self.add(embedding, metadata) {
-                eprintln!("[HNSW] Error adding embedding: {}", e);
+                error!("Rejected {}: {}", file_path, e);
+                self.rejected_paths.push(file_path);
             }
         }
 
-        eprintln!("[HNSW] Index rebuilt successfully with {} items", self.len());
+        info!("Index rebuilt successfully with {} items", self.len());
+        if !self.rejected_paths.is_empty() {
+            return Err(anyhow::anyhow!(
+                "{} embeddings rejected for not matching index dimension {} (was the embedding model changed?)",
+                self.rejected_paths.len(),
+                self.dimensions
+            ));
+        }
         Ok(())
     }
 
@@ -186,7 +408,7 @@ impl HnswIndex {
         self.id_to_index
             .get(&metadata.id)
             .and_then(|&idx| self.embeddings.get(idx))
-            .cloned()
+            .map(|embedding| embedding.as_ref().clone())
     }
 
     /// Get statistics about the index
@@ -207,6 +429,11 @@ impl HnswIndex {
         let mut errors = Vec::new();
         let mut warnings = Vec::new();
 
+        for path in &self.rejected_paths {
+            warnings.push(format!("Embedding for {} was rejected: dimension differs from index dimension {}",
+                                path, self.dimensions));
+        }
+
         // Check if index is empty
         if self.embeddings.is_empty() {
             warnings.push("Index is empty".to_string());
@@ -284,3 +511,79 @@ pub struct HnswIndexVerification {
     pub errors: Vec<String>,
     pub warnings: Vec<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(id: i64, path: &str) -> FileMetadata {
+        FileMetadata {
+            id,
+            file_path: path.to_string(),
+            file_name: path.to_string(),
+            file_size: 0,
+            modified_time: 0,
+            file_type: "txt".to_string(),
+            embedding_offset: 0,
+            embedding_length: 0,
+        }
+    }
+
+    #[test]
+    fn test_wrong_dimension_vectors_are_rejected() {
+        let mut index = HnswIndex::new(3);
+        let err = index.add(vec![1.0, 2.0], metadata(1, "a")).unwrap_err();
+        assert!(err.to_string().contains("dimension mismatch"));
+
+        let err = index.rebuild_from_embeddings(vec![
+            (metadata(1, "a"), vec![1.0, 0.0, 0.0]),
+            (metadata(2, "b"), vec![0.0, 1.0]),
+            (metadata(3, "c"), vec![0.0, 0.0, 1.0]),
+        ]).unwrap_err();
+        assert!(err.to_string().contains("1 embeddings rejected"));
+        assert_eq!(index.len(), 2);
+
+        let verification = index.verify_index();
+        assert!(verification.is_valid);
+        assert!(verification.warnings.iter().any(|w| w.contains("b")));
+    }
+
+    #[test]
+    fn test_graph_search_finds_stored_vectors() {
+        use rand::{Rng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let embeddings: Vec<_> = (0..300)
+            .map(|i| (metadata(i, &format!("f{}", i)), (0..16).map(|_| rng.gen_range(-1.0..1.0)).collect::<Vec<f32>>()))
+            .collect();
+        let mut index = HnswIndex::new(16).with_params(HnswParams { m: 10, ef_construction: 100, ef_search: 50, ..Default::default() });
+        assert_eq!(index.params().m, 8);
+        index.rebuild_from_embeddings(embeddings.clone()).unwrap();
+
+        for (meta, embedding) in embeddings.iter().step_by(25) {
+            let results = index.search(embedding.clone(), 5).unwrap();
+            assert_eq!(results[0].0.id, meta.id);
+            assert!((results[0].1 - 1.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_metric_decides_whether_magnitude_counts() {
+        let embeddings = vec![
+            (metadata(1, "short"), vec![1.0, 0.0]),
+            (metadata(2, "long"), vec![3.0, 0.3]),
+        ];
+        let search = |metric: SimilarityMetric| {
+            let mut index = HnswIndex::new(2).with_params(HnswParams { metric, ..Default::default() });
+            index.rebuild_from_embeddings(embeddings.clone()).unwrap();
+            let graph = index.search(vec![1.0, 0.0], 1).unwrap()[0].0.id;
+            let exact = index.search_exact(vec![1.0, 0.0], 1).unwrap()[0].0.id;
+            assert_eq!(graph, exact);
+            exact
+        };
+
+        assert_eq!(search(SimilarityMetric::Cosine), 1);
+        assert_eq!(search(SimilarityMetric::Dot), 2);
+        assert_eq!(search(SimilarityMetric::Euclidean), 1);
+    }
+}
@@ -0,0 +1,107 @@
+// Resume points for interrupted directory index runs.
+// `index_directory` walks files in sorted order and records the last path of each finished
+// batch, so a run killed part-way can skip everything up to that path next time.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// Serializes read-modify-write of the cursor file across concurrent index runs
+static CURSOR_FILE_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// How far an unfinished `index_directory` run got
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IndexCursor {
+    pub directory: String,
+    /// Last file, in sorted order, whose batch finished; everything up to it was processed
+    pub last_path: String,
+    pub processed: usize,
+    pub total: usize,
+    /// Unix timestamp of the last update
+    pub updated_at: i64,
+}
+
+/// Cursors of unfinished runs keyed by directory, stored as JSON in the data dir
+#[derive(Debug, Clone)]
+pub struct IndexCursors {
+    file: PathBuf,
+}
+
+impl IndexCursors {
+    pub fn new(data_dir: &Path) -> Self {
+        Self {
+            file: data_dir.join("index_cursors.json"),
+        }
+    }
+
+    /// All saved cursors; a missing or unreadable file means there is nothing to resume
+    pub async fn load_all(&self) -> HashMap<String, IndexCursor> {
+        match tokio::fs::read_to_string(&self.file).await {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                warn!("Ignoring unreadable index cursor file {}: {}", self.file.display(), e);
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        }
+    }
+
+    pub async fn get(&self, directory: &str) -> Option<IndexCursor> {
+        self.load_all().await.remove(directory)
+    }
+
+    pub async fn save(&self, cursor: IndexCursor) -> Result<()> {
+        let _lock = CURSOR_FILE_LOCK.lock().await;
+        let mut cursors = self.load_all().await;
+        cursors.insert(cursor.directory.clone(), cursor);
+        self.write(&cursors).await
+    }
+
+    /// Forget a directory's cursor once its run completes
+    pub async fn remove(&self, directory: &str) -> Result<()> {
+        let _lock = CURSOR_FILE_LOCK.lock().await;
+        let mut cursors = self.load_all().await;
+        if cursors.remove(directory).is_none() {
+            return Ok(());
+        }
+        self.write(&cursors).await
+    }
+
+    /// Write via a temp file so a crash never leaves the cursors half-written
+    async fn write(&self, cursors: &HashMap<String, IndexCursor>) -> Result<()> {
+        if let Some(parent) = self.file.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let tmp_file = self.file.with_extension("json.tmp");
+        tokio::fs::write(&tmp_file, serde_json::to_string(cursors)?).await?;
+        tokio::fs::rename(&tmp_file, &self.file).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_cursor_round_trip() {
+        let data_dir = std::env::temp_dir().join(format!("gist-index-cursor-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&data_dir);
+        let cursors = IndexCursors::new(&data_dir);
+        let cursor = IndexCursor {
+            directory: "/docs".to_string(),
+            last_path: "/docs/b.txt".to_string(),
+            processed: 2,
+            total: 5,
+            updated_at: 0,
+        };
+
+        cursors.save(cursor.clone()).await.unwrap();
+        assert_eq!(cursors.get("/docs").await, Some(cursor));
+
+        cursors.remove("/docs").await.unwrap();
+        assert!(cursors.load_all().await.is_empty());
+        let _ = std::fs::remove_dir_all(&data_dir);
+    }
+}
@@ -1,15 +1,23 @@
 use anyhow::Result;
-use std::path::PathBuf;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::collections::HashMap;
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
 use tokio::sync::RwLock;
+use tracing::{debug, error, info, warn};
 
-use crate::config::AppConfig;
+use crate::config::{AppConfig, EmbeddingTruncation};
 use crate::embedding::EmbeddingService;
+use crate::index_cursor::{IndexCursor, IndexCursors};
 use crate::parsers::ParserRegistry;
-use crate::storage::{Storage, FileMetadata};
+use crate::storage::{Storage, FileMetadata, Passage, DIRECTORY_FILE_TYPE};
+use crate::tokenizer::{token_counter_for_model, TokenCounter};
 
-#[derive(Clone)]
+/// Workbooks with more sheets than this are embedded as one text even with `spreadsheet_sheet_sections`
+const MAX_SHEET_SECTIONS: usize = 16;
+
+#[derive(Clone, Debug, serde::Serialize)]
 pub struct IndexingProgress {
     pub is_indexing: bool,
     pub current: usize,
@@ -18,14 +26,68 @@ pub struct IndexingProgress {
     pub directory: String,
 }
 
+/// Summary of the most recent `index_directory` run
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct IndexRunStats {
+    pub directory: String,
+    pub files_indexed: usize,
+    pub errors: usize,
+    pub bytes_processed: u64,
+    pub duration_secs: f64,
+    pub files_per_sec: f64,
+    /// Unix timestamp when the run finished
+    pub completed_at: i64,
+}
+
+/// Outcome of `sync_indexed_directories`
+#[derive(Clone, Debug, Default, serde::Serialize)]
+pub struct SyncStats {
+    pub added: usize,
+    pub updated: usize,
+    pub removed: usize,
+    /// Changed mtime or size but identical content, so only the metadata was updated
+    pub unchanged: usize,
+}
+
+/// A content file parsed and split into the texts that need embedding
+struct PreparedFile {
+    file_path: String,
+    file_name: String,
+    file_type: String,
+    file_size: i64,
+    modified_time: i64,
+    /// One text per stored section; the first is stored under the file's own path
+    section_texts: Vec<String>,
+    ranged_chunks: Vec<(String, std::ops::Range<usize>)>,
+    /// The whole file is one chunk, so its passage can reuse the file embedding
+    single_chunk: bool,
+    /// Set when `hash_file_contents` is on
+    content_hash: Option<String>,
+}
+
+/// Counts of entries pruned by ignore rules during a directory walk
+#[derive(Default)]
+struct WalkStats {
+    pruned_dirs: Cell<usize>,
+    pruned_files: Cell<usize>,
+}
+
 #[derive(Clone)]
 pub struct Indexer {
     storage: Arc<Storage>,
     embedding_service: Arc<EmbeddingService>,
+    /// One service per `fusion_embedding_models` entry other than the active model
+    fusion_services: Vec<(String, Arc<EmbeddingService>)>,
     parser_registry: Arc<ParserRegistry>,
     config: Arc<AppConfig>,
+    token_counter: Arc<dyn TokenCounter>,
     is_indexing: Arc<RwLock<bool>>,
     progress: Option<Arc<tokio::sync::RwLock<Option<IndexingProgress>>>>,
+    progress_channel: Option<Arc<tokio::sync::watch::Sender<Option<IndexingProgress>>>>,
+    last_run_stats: Option<Arc<tokio::sync::RwLock<Option<IndexRunStats>>>>,
+    cursors: Option<IndexCursors>,
+    shutdown: Option<tokio::sync::watch::Receiver<bool>>,
+    cancel: Option<tokio::sync::watch::Receiver<bool>>,
 }
 
 impl Indexer {
@@ -35,13 +97,27 @@ impl Indexer {
         parser_registry: Arc<ParserRegistry>,
         config: Arc<AppConfig>,
     ) -> Self {
+        let token_counter = token_counter_for_model(config.active_embedding_model());
+        let fusion_services = config
+            .fusion_embedding_models
+            .iter()
+            .filter(|model| model.as_str() != config.active_embedding_model())
+            .map(|model| (model.clone(), Arc::new(EmbeddingService::for_model(&config, model))))
+            .collect();
         Self {
             storage,
             embedding_service,
+            fusion_services,
             parser_registry,
             config,
+            token_counter,
             is_indexing: Arc::new(RwLock::new(false)),
             progress: None,
+            progress_channel: None,
+            last_run_stats: None,
+            cursors: None,
+            shutdown: None,
+            cancel: None,
         }
     }
     
@@ -50,7 +126,71 @@ impl Indexer {
         self
     }
 
+    /// Publish every progress change to a watch channel (used by the progress WebSocket)
+    pub fn with_progress_channel(mut self, channel: Arc<tokio::sync::watch::Sender<Option<IndexingProgress>>>) -> Self {
+        self.progress_channel = Some(channel);
+        self
+    }
+
+    /// Apply a change to the shared progress and push the result to live subscribers
+    async fn update_progress(&self, update: impl FnOnce(&mut Option<IndexingProgress>)) {
+        let Some(ref progress_tracker) = self.progress else {
+            return;
+        };
+        let mut progress = progress_tracker.write().await;
+        update(&mut progress);
+        if let Some(ref channel) = self.progress_channel {
+            channel.send_replace(progress.clone());
+        }
+    }
+
+    pub fn with_stats_tracker(mut self, stats: Arc<tokio::sync::RwLock<Option<IndexRunStats>>>) -> Self {
+        self.last_run_stats = Some(stats);
+        self
+    }
+
+    /// Record a resume point after every batch of `index_directory`, so an interrupted run can
+    /// continue with `resume_directory`
+    pub fn with_cursors(mut self, cursors: IndexCursors) -> Self {
+        self.cursors = Some(cursors);
+        self
+    }
+
+    /// Stop `index_directory` after the current batch, its cursor saved, or a sync after the current
+    /// file, once `shutdown` turns true
+    pub fn with_shutdown(mut self, shutdown: tokio::sync::watch::Receiver<bool>) -> Self {
+        self.shutdown = Some(shutdown);
+        self
+    }
+
+    /// Like `with_shutdown`, but for cancelling this one run (an index job) rather than the whole app
+    pub fn with_cancel(mut self, cancel: tokio::sync::watch::Receiver<bool>) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Why the run should stop at this batch boundary, if `with_shutdown` or `with_cancel` fired
+    fn stop_reason(&self) -> Option<&'static str> {
+        if self.shutdown.as_ref().is_some_and(|shutdown| *shutdown.borrow()) {
+            Some("shutdown")
+        } else if self.cancel.as_ref().is_some_and(|cancel| *cancel.borrow()) {
+            Some("cancellation")
+        } else {
+            None
+        }
+    }
+
+    #[tracing::instrument(name = "INDEXING", skip_all)]
     pub async fn index_directory(&self, directory: &str) -> Result<usize> {
+        self.index_directory_from(directory, false).await
+    }
+
+    /// Like `index_directory`, but skip files before the saved cursor of an interrupted run
+    pub async fn resume_directory(&self, directory: &str) -> Result<usize> {
+        self.index_directory_from(directory, true).await
+    }
+
+    async fn index_directory_from(&self, directory: &str, resume: bool) -> Result<usize> {
         let mut indexing = self.is_indexing.write().await;
         if *indexing {
             return Err(anyhow::anyhow!("Indexing already in progress"));
@@ -61,15 +201,12 @@ impl Indexer {
         // First pass: count total files to index
         let dir_path = PathBuf::from(directory);
         let mut total_files = 0;
-        for entry in walkdir::WalkDir::new(&dir_path)
-            .into_iter()
-            .filter_map(|e| e.ok())
-        {
+        for entry in self.walk_directory(&dir_path, &WalkStats::default()) {
             if entry.file_type().is_file() {
                 let file_path = entry.path().to_string_lossy().to_string();
-                if !Self::should_exclude_file(&file_path) && !self.is_excluded_by_config(&file_path) {
+                if !self.should_exclude_file(&file_path) && !self.is_excluded_by_config(&file_path) {
                     // Count files that will be indexed (either metadata-only or content-indexed)
-                    if Self::should_index_metadata_only(&file_path) || self.parser_registry.can_parse(&file_path) {
+                    if self.should_index_metadata_only(&file_path) || self.parser_registry.can_parse(&file_path) {
                         total_files += 1;
                     }
                 }
@@ -77,8 +214,7 @@ impl Indexer {
         }
 
         // Initialize progress
-        if let Some(ref progress_tracker) = self.progress {
-            let mut progress = progress_tracker.write().await;
+        self.update_progress(|progress| {
             *progress = Some(IndexingProgress {
                 is_indexing: true,
                 current: 0,
@@ -86,7 +222,7 @@ impl Indexer {
                 current_file: String::new(),
                 directory: directory.to_string(),
             });
-        }
+        }).await;
 
         // Benchmark tracking: start timer for first 1000 files
         let start_time = std::time::Instant::now();
@@ -94,18 +230,24 @@ impl Indexer {
         
         let mut count = 0;
         let mut current = 0;
+        let mut errors = 0;
+        let mut bytes_processed: u64 = 0;
 
-        // Collect all files to index
+        // Collect all files to index, once per canonical path (symlinks can reach a file twice)
         let mut files_to_index = Vec::new();
-        for entry in walkdir::WalkDir::new(&dir_path)
-            .into_iter()
-            .filter_map(|e| e.ok())
-        {
-            if entry.file_type().is_file() {
+        let mut directories_to_index = Vec::new();
+        let mut seen_paths = HashSet::new();
+        let walk_stats = WalkStats::default();
+        for entry in self.walk_directory(&dir_path, &walk_stats) {
+            if entry.file_type().is_dir() {
+                if self.config.index_directory_names && entry.depth() > 0 {
+                    directories_to_index.push(entry.path().to_string_lossy().to_string());
+                }
+            } else if entry.file_type().is_file() {
                 let file_path = entry.path().to_string_lossy().to_string();
                 
                 // Skip files that tend to give false positives
-                if Self::should_exclude_file(&file_path) {
+                if self.should_exclude_file(&file_path) {
                     continue;
                 }
                 // Skip files in user's excluded extensions list (applies to both indexing and search)
@@ -114,138 +256,330 @@ impl Indexer {
                 }
                 
                 // Check if this file should be metadata-only or content-indexed
-                if Self::should_index_metadata_only(&file_path) || self.parser_registry.can_parse(&file_path) {
-                    files_to_index.push(file_path);
+                if self.should_index_metadata_only(&file_path) || self.parser_registry.can_parse(&file_path) {
+                    let file_path = Self::canonical_path(&file_path);
+                    if seen_paths.insert(file_path.clone()) {
+                        files_to_index.push(file_path);
+                    }
                 }
             }
         }
 
-        // Process files in batches for parallel embedding generation
-        const BATCH_SIZE: usize = 5; // Process 5 files concurrently
-        for batch in files_to_index.chunks(BATCH_SIZE) {
+        // Folder entries are metadata only and cheap to rewrite, so they're stored up front even on resume
+        for dir in &directories_to_index {
+            if let Err(e) = self.index_directory_entry(dir).await {
+                warn!("Failed to index folder {}: {}", dir, e);
+            }
+        }
+        if !directories_to_index.is_empty() {
+            info!("Indexed {} folder names under {}", directories_to_index.len(), directory);
+        }
+
+        // A stable order lets a cursor (the last finished path) mark how far a run got
+        files_to_index.sort();
+        let cursor = match self.cursors {
+            Some(ref cursors) if resume => cursors.get(directory).await,
+            _ => None,
+        };
+        if let Some(cursor) = cursor {
+            let skipped = files_to_index.partition_point(|path| *path <= cursor.last_path);
+            info!("Resuming {} after {} ({} files already processed)", directory, cursor.last_path, skipped);
+            files_to_index.drain(..skipped);
+            current = skipped;
+            self.update_progress(|progress| {
+                if let Some(ref mut p) = *progress {
+                    p.current = current;
+                }
+            }).await;
+        }
+
+        // Process files in batches: parse in parallel, embed the whole batch in one request, then store
+        let batch_size = self.config.performance_mode.profile().index_concurrency;
+        for batch in files_to_index.chunks(batch_size) {
             // Create tasks for parallel processing
             let mut tasks = Vec::new();
             for file_path in batch {
                 let file_path = file_path.clone();
                 let indexer = self.clone();
-                let progress_tracker = self.progress.clone();
                 
                     tasks.push(tokio::spawn(async move {
                     // Update progress before starting
-                    if let Some(ref tracker) = progress_tracker {
-                        let mut progress = tracker.write().await;
+                    indexer.update_progress(|progress| {
                         if let Some(ref mut p) = *progress {
                             p.current_file = file_path.clone();
                         }
-                    }
+                    }).await;
                     
                     // Route to appropriate indexing method
-                    let result = if Self::should_index_metadata_only(&file_path) {
-                        indexer.index_file_metadata_only(&file_path).await
+                    let result = if indexer.should_index_metadata_only(&file_path) {
+                        indexer.index_file_metadata_only(&file_path).await.map(|_| None)
                     } else {
-                        indexer.index_file(&file_path).await
+                        indexer.prepare_file(&file_path).await
                     };
                     (file_path, result)
                 }));
             }
-            
-            // Wait for all tasks in batch to complete
+
+            let mut outcomes = Vec::with_capacity(batch.len());
+            let mut prepared_files = Vec::new();
             for task in tasks {
                 match task.await {
+                    Ok((_, Ok(Some(prepared)))) => prepared_files.push(prepared),
+                    Ok((file_path, result)) => outcomes.push(Ok((file_path, result.map(|_| ())))),
+                    Err(e) => outcomes.push(Err(e)),
+                }
+            }
+
+            let embeddings = self.embed_prepared_batch(&prepared_files).await;
+            let mut store_tasks = Vec::new();
+            for (prepared, embeddings) in prepared_files.into_iter().zip(embeddings) {
+                let indexer = self.clone();
+                store_tasks.push(tokio::spawn(async move {
+                    let file_path = prepared.file_path.clone();
+                    let result = match embeddings {
+                        Ok(embeddings) => indexer.store_prepared(prepared, embeddings).await,
+                        Err(e) => Err(e),
+                    };
+                    (file_path, result)
+                }));
+            }
+            for task in store_tasks {
+                outcomes.push(task.await);
+            }
+            
+            // Tally the batch
+            for outcome in outcomes {
+                match outcome {
                     Ok((file_path, Ok(_))) => {
                         count += 1;
                         current += 1;
+                        bytes_processed += std::fs::metadata(&file_path).map(|m| m.len()).unwrap_or(0);
                         
                         // Benchmark: Log time for first 1000 files
                         if count == 1000 && !benchmark_1000_logged {
                             let elapsed = start_time.elapsed();
                             let elapsed_secs = elapsed.as_secs_f64();
                             let files_per_sec = 1000.0 / elapsed_secs;
-                            
-                            eprintln!("═══════════════════════════════════════════════════════════");
-                            eprintln!("[BENCHMARK] First 1000 files processed!");
-                            eprintln!("[BENCHMARK] Time elapsed: {:.2} seconds ({:.2} minutes)", elapsed_secs, elapsed_secs / 60.0);
-                            eprintln!("[BENCHMARK] Processing rate: {:.2} files/second", files_per_sec);
-                            eprintln!("[BENCHMARK] Average time per file: {:.3} seconds", elapsed_secs / 1000.0);
-                            eprintln!("═══════════════════════════════════════════════════════════");
+                            info!("[BENCHMARK] First 1000 files processed!");
+                            info!("[BENCHMARK] Time elapsed: {:.2} seconds ({:.2} minutes)", elapsed_secs, elapsed_secs / 60.0);
+                            info!("[BENCHMARK] Processing rate: {:.2} files/second", files_per_sec);
+                            info!("[BENCHMARK] Average time per file: {:.3} seconds", elapsed_secs / 1000.0);
                             
                             benchmark_1000_logged = true;
                         }
                         
                         // Update progress
-                        if let Some(ref progress_tracker) = self.progress {
-                            let mut progress = progress_tracker.write().await;
+                        self.update_progress(|progress| {
                             if let Some(ref mut p) = *progress {
                                 p.current = current;
                                 p.current_file = file_path;
                             }
-                        }
+                        }).await;
                     }
                     Ok((file_path, Err(e))) => {
-                        eprintln!("Error indexing {}: {}", file_path, e);
+                        error!("Error indexing {}: {}", file_path, e);
                         current += 1;
+                        errors += 1;
                         
                         // Update progress even on error
-                        if let Some(ref progress_tracker) = self.progress {
-                            let mut progress = progress_tracker.write().await;
+                        self.update_progress(|progress| {
                             if let Some(ref mut p) = *progress {
                                 p.current = current;
                             }
-                        }
+                        }).await;
                     }
                     Err(e) => {
-                        eprintln!("Task error: {}", e);
+                        error!("Task error: {}", e);
                         current += 1;
+                        errors += 1;
                     }
                 }
             }
+
+            if let (Some(ref cursors), Some(last_path)) = (&self.cursors, batch.last()) {
+                let cursor = IndexCursor {
+                    directory: directory.to_string(),
+                    last_path: last_path.clone(),
+                    processed: current,
+                    total: total_files,
+                    updated_at: chrono::Utc::now().timestamp(),
+                };
+                if let Err(e) = cursors.save(cursor).await {
+                    warn!("Failed to save index cursor for {}: {}", directory, e);
+                }
+            }
+
+            if let Some(reason) = self.stop_reason() {
+                info!("Stopped indexing {} at {}/{} files on {}", directory, current, total_files, reason);
+                self.update_progress(|progress| *progress = None).await;
+                *self.is_indexing.write().await = false;
+                return Err(anyhow::anyhow!(
+                    "Indexing of {} interrupted by {}; start it with \"resume\": true to continue",
+                    directory,
+                    reason
+                ));
+            }
+        }
+
+        if let Some(ref cursors) = self.cursors {
+            if let Err(e) = cursors.remove(directory).await {
+                warn!("Failed to clear index cursor for {}: {}", directory, e);
+            }
         }
 
         // Log final benchmark if we processed at least 1000 files
         if count >= 1000 && benchmark_1000_logged {
             let total_elapsed = start_time.elapsed();
             let total_elapsed_secs = total_elapsed.as_secs_f64();
-            eprintln!("[BENCHMARK] Total files indexed: {}", count);
-            eprintln!("[BENCHMARK] Total time: {:.2} seconds ({:.2} minutes)", total_elapsed_secs, total_elapsed_secs / 60.0);
+            info!("[BENCHMARK] Total files indexed: {}", count);
+            info!("[BENCHMARK] Total time: {:.2} seconds ({:.2} minutes)", total_elapsed_secs, total_elapsed_secs / 60.0);
         } else if count < 1000 {
             let elapsed = start_time.elapsed();
-            eprintln!("[BENCHMARK] Indexed {} files in {:.2} seconds (less than 1000 files, no 1k benchmark)", count, elapsed.as_secs_f64());
+            info!("[BENCHMARK] Indexed {} files in {:.2} seconds (less than 1000 files, no 1k benchmark)", count, elapsed.as_secs_f64());
         }
+        info!("[BENCHMARK] Pruned {} directories and {} files via ignore rules", walk_stats.pruned_dirs.get(), walk_stats.pruned_files.get());
 
-        // Clear progress
-        if let Some(ref progress_tracker) = self.progress {
-            let mut progress = progress_tracker.write().await;
-            *progress = None;
+        if let Some(ref stats_tracker) = self.last_run_stats {
+            let duration_secs = start_time.elapsed().as_secs_f64();
+            *stats_tracker.write().await = Some(IndexRunStats {
+                directory: directory.to_string(),
+                files_indexed: count,
+                errors,
+                bytes_processed,
+                duration_secs,
+                files_per_sec: if duration_secs > 0.0 { count as f64 / duration_secs } else { 0.0 },
+                completed_at: chrono::Utc::now().timestamp(),
+            });
         }
 
+        // Clear progress (subscribers treat this as completion)
+        self.update_progress(|progress| *progress = None).await;
+
         let mut indexing = self.is_indexing.write().await;
         *indexing = false;
 
         Ok(count)
     }
 
+    #[tracing::instrument(name = "INDEXING", skip_all)]
     pub async fn index_file(&self, file_path: &str) -> Result<()> {
+        match self.refresh_if_content_unchanged(file_path).await {
+            Ok(true) => return Ok(()),
+            Ok(false) => {}
+            Err(e) => warn!("Failed to compare content of {}: {}", file_path, e),
+        }
+        self.embed_file(file_path).await
+    }
+
+    /// Parse, embed and store a content file unconditionally
+    async fn embed_file(&self, file_path: &str) -> Result<()> {
+        let Some(prepared) = self.prepare_file(file_path).await? else {
+            return Ok(());
+        };
+        let embeddings = self.embed_texts(&prepared.section_texts, &prepared.file_name).await?;
+        self.store_prepared(prepared, embeddings).await
+    }
+
+    /// When a file's mtime or size changed but its bytes hash the same as when it was embedded
+    /// (a `touch`, or a tool rewriting identical content), record the new mtime and size and
+    /// keep its vectors. Returns whether the file was found unchanged.
+    pub async fn refresh_if_content_unchanged(&self, file_path: &str) -> Result<bool> {
+        if !self.config.hash_file_contents {
+            return Ok(false);
+        }
+        let file_path = Self::canonical_path(file_path);
+        let Some(stored_hash) = self.storage.get_content_hash(&file_path).await? else {
+            return Ok(false);
+        };
+        if Self::content_hash(&file_path).await? != stored_hash {
+            return Ok(false);
+        }
+
+        let metadata = std::fs::metadata(&file_path)?;
+        let modified_time = metadata.modified()?
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs() as i64;
+        self.storage.touch_file(&file_path, modified_time, metadata.len() as i64).await?;
+        debug!("Content unchanged, kept existing embeddings: {}", file_path);
+        Ok(true)
+    }
+
+    /// Whether the user removed `file_path` from the index (`/api/index/remove`) and it hasn't
+    /// been modified since, so syncs and the file watcher leave it out
+    pub async fn is_removed_from_index(&self, file_path: &str) -> bool {
+        let file_path = Self::canonical_path(file_path);
+        match self.storage.removed_files().await {
+            Ok(removed) => removed.get(&file_path).is_some_and(|&removed| file_modified_time(&file_path) == Some(removed)),
+            Err(e) => {
+                warn!("Failed to load removed files: {}", e);
+                false
+            }
+        }
+    }
+
+    /// Hex SHA-1 of a file's bytes, read in blocks off the async runtime
+    async fn content_hash(file_path: &str) -> Result<String> {
+        use sha1::{Digest, Sha1};
+        use std::io::Read;
+
+        let file_path = file_path.to_string();
+        tokio::task::spawn_blocking(move || {
+            let mut file = std::fs::File::open(&file_path)?;
+            let mut hasher = Sha1::new();
+            let mut buffer = vec![0u8; 64 * 1024];
+            loop {
+                let read = file.read(&mut buffer)?;
+                if read == 0 {
+                    break;
+                }
+                hasher.update(&buffer[..read]);
+            }
+            Ok(hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect())
+        }).await?
+    }
+
+    /// Parse and chunk a content file, deciding which texts to embed. Files that turn out
+    /// to have no usable content are stored metadata-only here and yield None.
+    async fn prepare_file(&self, file_path: &str) -> Result<Option<PreparedFile>> {
+        let file_path = &Self::canonical_path(file_path);
         // Check if this file should be metadata-only
-        if Self::should_index_metadata_only(file_path) {
-            return self.index_file_metadata_only(file_path).await;
+        if self.should_index_metadata_only(file_path) || self.exceeds_size_cap(file_path) {
+            return self.index_file_metadata_only(file_path).await.map(|_| None);
         }
         
         // Extract text - on failure, store metadata-only so we don't reindex every run
-        let text = match self.parser_registry.extract_text(file_path) {
-            Ok(t) => t,
+        let parts = if self.config.spreadsheet_sheet_sections {
+            self.parser_registry.extract_parts(file_path)
+        } else {
+            self.parser_registry.extract_text(file_path).map(|text| vec![text])
+        };
+        let parts: Vec<String> = match parts {
+            Ok(parts) => parts.into_iter().filter(|part| !part.trim().is_empty()).collect(),
             Err(e) => {
-                eprintln!("[INDEXING] Text extraction failed for {}: {}. Indexing metadata only.", file_path, e);
-                return self.index_file_metadata_only(file_path).await;
+                error!("Text extraction failed for {}: {}. Indexing metadata only.", file_path, e);
+                return self.index_file_metadata_only(file_path).await.map(|_| None);
             }
         };
+        let text = parts.join("\n\n");
         
         if text.trim().is_empty() {
             // No extractable text - store metadata-only so we don't reindex every run
-            return self.index_file_metadata_only(file_path).await;
+            return self.index_file_metadata_only(file_path).await.map(|_| None);
         }
 
-        // Chunk text if needed
-        let chunks = self.chunk_text(&text);
+        // Chunk text if needed. Only passages overlap: the file-level texts below join or sample
+        // chunks, and overlapping ones would repeat words at every boundary.
+        let chunk_size = self.config.chunk_size;
+        let chunks: Vec<String> = Self::chunk_text_with_ranges(&text, chunk_size, 0)
+            .into_iter()
+            .map(|(chunk, _)| chunk)
+            .collect();
+        let ranged_chunks = if self.config.index_passages {
+            Self::chunk_text_with_ranges(&text, chunk_size, self.config.chunk_overlap)
+        } else {
+            Vec::new()
+        };
+        let mut single_chunk = false;
         
         // Get file metadata (needed for both single and multiple embeddings)
         let metadata = std::fs::metadata(file_path)?;
@@ -265,54 +599,97 @@ impl Indexer {
             .duration_since(std::time::UNIX_EPOCH)?
             .as_secs() as i64;
         let file_size = metadata.len() as i64;
+        let content_hash = if self.config.hash_file_contents {
+            Some(Self::content_hash(file_path).await?)
+        } else {
+            None
+        };
 
-        // Estimate total tokens (rough: 1 token ≈ 4 characters)
+        // Count total tokens with the model's tokenizer
         let total_estimated_tokens: usize = chunks.iter()
-            .map(|c| c.len() / 4)
+            .map(|c| self.token_counter.count_tokens(c))
             .sum();
         
         let max_context = self.config.max_context_tokens;
         let multiple_embedding_threshold = max_context * 4; // 4x threshold for multiple embeddings
 
         // Decide strategy based on file size
-        if total_estimated_tokens <= max_context {
-            // File fits in context - use all chunks
+        let sample_large_files = self.config.performance_mode.profile().sample_large_files;
+        let section_texts = if (2..=MAX_SHEET_SECTIONS).contains(&parts.len()) {
+            // One embedding per sheet (spreadsheet_sheet_sections), so unrelated sheets don't blend
+            debug!("Workbook '{}' - embedding {} sheets separately", file_name, parts.len());
+            parts
+                .iter()
+                .map(|part| self.token_counter.truncate(part, max_context))
+                .collect()
+        } else if total_estimated_tokens <= max_context || !sample_large_files {
+            // File fits in context - use all chunks (Lightweight mode keeps just the head of larger files)
+            if total_estimated_tokens > max_context {
+                debug!("Large file '{}' ({:.1}K tokens) - truncated to head (lightweight mode)",
+                    file_name, total_estimated_tokens as f64 / 1000.0);
+            }
             let combined_text = chunks.join("\n\n");
             
-            // Double check length just in case
-            let final_text = if combined_text.len() > max_context * 4 {
-                let max_chars = max_context * 4;
-                 combined_text.chars().take(max_chars).collect()
+            // Double check length just in case (joining adds separator tokens)
+            let final_text = if self.token_counter.count_tokens(&combined_text) > max_context {
+                self.token_counter.truncate(&combined_text, max_context)
             } else {
                 combined_text
             };
-
-            let embedding = self.generate_safe_embedding(&final_text, &file_name).await?;
-            
-            let file_metadata = FileMetadata {
-                id: 0,
-                file_path: file_path.to_string(),
-                file_name: file_name.clone(),
-                file_size,
-                modified_time,
-                file_type: file_type.clone(),
-                embedding_offset: 0,
-                embedding_length: 0,
-            };
-            
-            self.storage.add_file(&file_metadata, Some(&embedding)).await?;
+            single_chunk = chunks.len() == 1;
+            vec![final_text]
         } else if total_estimated_tokens <= multiple_embedding_threshold {
             // File is 1x-4x context size - use intelligent sampling
-            let sampled_text = Self::intelligent_chunk_sampling(&chunks, max_context);
-            let embedding = self.generate_safe_embedding(&sampled_text, &file_name).await?;
-            
-            eprintln!("[INDEXING] Large file '{}' ({:.1}K tokens) - used intelligent sampling", 
+            info!("Large file '{}' ({:.1}K tokens) - used intelligent sampling", 
+                file_name, total_estimated_tokens as f64 / 1000.0);
+            vec![Self::intelligent_chunk_sampling(&chunks, max_context, self.token_counter.as_ref())]
+        } else {
+            // File is >4x context size - generate multiple embeddings
+            info!("Very large file '{}' ({:.1}K tokens) - generating multiple embeddings", 
                 file_name, total_estimated_tokens as f64 / 1000.0);
+            Self::create_multiple_embedding_sections(&chunks, max_context, self.token_counter.as_ref())
+        };
+
+        Ok(Some(PreparedFile {
+            file_path: file_path.to_string(),
+            file_name,
+            file_type,
+            file_size,
+            modified_time,
+            section_texts,
+            ranged_chunks,
+            single_chunk,
+            content_hash,
+        }))
+    }
+
+    /// Store a prepared file given one embedding per section text, then its passages
+    async fn store_prepared(&self, prepared: PreparedFile, embeddings: Vec<Vec<f32>>) -> Result<()> {
+        let PreparedFile {
+            file_path, file_name, file_type, file_size, modified_time, section_texts, ranged_chunks, single_chunk, content_hash,
+        } = prepared;
+        if embeddings.len() != section_texts.len() {
+            anyhow::bail!("Expected {} embeddings for '{}', got {}", section_texts.len(), file_name, embeddings.len());
+        }
+
+        for (section_idx, embedding) in embeddings.iter().enumerate() {
+            // Create unique file path for this embedding (for storage)
+            let section_path = if section_idx == 0 {
+                file_path.to_string()
+            } else {
+                format!("{}#section{}", file_path, section_idx + 1)
+            };
+            
+            let section_file_name = if section_idx == 0 {
+                file_name.clone()
+            } else {
+                format!("{} (section {})", file_name, section_idx + 1)
+            };
             
             let file_metadata = FileMetadata {
                 id: 0,
-                file_path: file_path.to_string(),
-                file_name: file_name.clone(),
+                file_path: section_path,
+                file_name: section_file_name,
                 file_size,
                 modified_time,
                 file_type: file_type.clone(),
@@ -320,51 +697,115 @@ impl Indexer {
                 embedding_length: 0,
             };
             
-            self.storage.add_file(&file_metadata, Some(&embedding)).await?;
-        } else {
-            // File is >4x context size - generate multiple embeddings
-            eprintln!("[INDEXING] Very large file '{}' ({:.1}K tokens) - generating multiple embeddings", 
-                file_name, total_estimated_tokens as f64 / 1000.0);
-            
-            let embedding_sections = Self::create_multiple_embedding_sections(&chunks, max_context);
-            
-            for (section_idx, section_text) in embedding_sections.iter().enumerate() {
-                let embedding = self.generate_safe_embedding(section_text, &file_name).await?;
-                
-                // Create unique file path for this embedding (for storage)
-                let section_path = if section_idx == 0 {
-                    file_path.to_string()
-                } else {
-                    format!("{}#section{}", file_path, section_idx + 1)
-                };
-                
-                let section_file_name = if section_idx == 0 {
-                    file_name.clone()
-                } else {
-                    format!("{} (section {})", file_name, section_idx + 1)
-                };
-                
-                let file_metadata = FileMetadata {
-                    id: 0,
-                    file_path: section_path,
-                    file_name: section_file_name,
-                    file_size,
-                    modified_time,
-                    file_type: file_type.clone(),
-                    embedding_offset: 0,
-                    embedding_length: 0,
-                };
-                
-                self.storage.add_file(&file_metadata, Some(&embedding)).await?;
+            self.storage.add_file(&file_metadata, Some(embedding)).await?;
+        }
+        if embeddings.len() > 1 {
+            info!("Generated {} embeddings for '{}'", embeddings.len(), file_name);
+        }
+
+        // Fusion models only get a file-level vector; a failure leaves the file searchable by the active model
+        if let Some(text) = section_texts.first() {
+            for (model, service) in &self.fusion_services {
+                match service.generate_embedding(text).await {
+                    Ok(embedding) => self.storage.add_model_embedding(&file_path, model, &embedding).await?,
+                    Err(e) => warn!("Skipping {} embedding for '{}': {}", model, file_name, e),
+                }
             }
-            
-            eprintln!("[INDEXING] Generated {} embeddings for '{}'", embedding_sections.len(), file_name);
+        }
+
+        if self.config.index_passages {
+            let single_chunk_embedding = if single_chunk { embeddings.into_iter().next() } else { None };
+            self.index_passages(&file_path, &file_name, ranged_chunks, single_chunk_embedding).await?;
+        } else {
+            self.storage.replace_passages(&file_path, &[]).await?;
+        }
+
+        // Recorded last, so a file whose passages failed isn't later mistaken for fully embedded
+        if let Some(content_hash) = content_hash {
+            self.storage.set_content_hash(&file_path, &content_hash).await?;
         }
 
         Ok(())
     }
 
+    /// Embed texts in one request where the backend supports it. If that fails, embed them one
+    /// at a time so context-length errors can be retried with truncation.
+    async fn embed_texts(&self, texts: &[String], file_name: &str) -> Result<Vec<Vec<f32>>> {
+        match self.embedding_service.generate_embeddings(texts).await {
+            Ok(embeddings) => Ok(embeddings),
+            Err(e) => {
+                debug!("Batch embedding failed for '{}' ({}), embedding texts one at a time", file_name, e);
+                let mut embeddings = Vec::with_capacity(texts.len());
+                for text in texts {
+                    embeddings.push(self.generate_safe_embedding(text, file_name).await?);
+                }
+                Ok(embeddings)
+            }
+        }
+    }
+
+    /// Embed every prepared file's texts in a single request, splitting the vectors back out
+    /// per file. Falls back to per-file embedding so one bad file doesn't fail the batch.
+    async fn embed_prepared_batch(&self, files: &[PreparedFile]) -> Vec<Result<Vec<Vec<f32>>>> {
+        let texts: Vec<String> = files.iter().flat_map(|f| f.section_texts.iter().cloned()).collect();
+        match self.embedding_service.generate_embeddings(&texts).await {
+            Ok(embeddings) => {
+                let mut embeddings = embeddings.into_iter();
+                files
+                    .iter()
+                    .map(|f| Ok(embeddings.by_ref().take(f.section_texts.len()).collect()))
+                    .collect()
+            }
+            Err(e) => {
+                debug!("Batch embedding of {} files failed ({}), embedding per file", files.len(), e);
+                let mut results = Vec::with_capacity(files.len());
+                for file in files {
+                    results.push(self.embed_texts(&file.section_texts, &file.file_name).await);
+                }
+                results
+            }
+        }
+    }
+
+    /// Embed each chunk separately so search can point at the passage that matched.
+    /// A file with a single chunk reuses its file-level embedding.
+    async fn index_passages(
+        &self,
+        file_path: &str,
+        file_name: &str,
+        ranged_chunks: Vec<(String, std::ops::Range<usize>)>,
+        single_chunk_embedding: Option<Vec<f32>>,
+    ) -> Result<()> {
+        let max_context = self.config.max_context_tokens;
+        let texts: Vec<String> = ranged_chunks
+            .iter()
+            .skip(usize::from(single_chunk_embedding.is_some()))
+            .map(|(chunk, _)| self.token_counter.truncate(chunk, max_context))
+            .collect();
+
+        let mut embeddings = self.embed_texts(&texts, file_name).await?;
+        if let Some(embedding) = single_chunk_embedding {
+            embeddings.insert(0, embedding);
+        }
+
+        let mut passages = Vec::with_capacity(ranged_chunks.len());
+        for ((chunk_index, (chunk, range)), embedding) in ranged_chunks.into_iter().enumerate().zip(embeddings) {
+            passages.push((
+                Passage {
+                    chunk_index: chunk_index as i64,
+                    byte_start: range.start as i64,
+                    byte_end: range.end as i64,
+                    text: chunk,
+                },
+                embedding,
+            ));
+        }
+
+        self.storage.replace_passages(file_path, &passages).await
+    }
+
     /// Wrapper for generating embeddings with retry logic for context length errors
+    #[tracing::instrument(name = "INDEXING", skip_all)]
     async fn generate_safe_embedding(&self, text: &str, file_name: &str) -> Result<Vec<f32>> {
         match self.embedding_service.generate_embedding(text).await {
             Ok(emb) => Ok(emb),
@@ -372,29 +813,23 @@ impl Indexer {
                 let error_msg = e.to_string();
                 // Match "500 Internal" strictly, or "context length"
                 if error_msg.contains("500") || error_msg.contains("context length") || error_msg.contains("Internal Server Error") {
-                    let original_len = text.len();
-                    let half_len = original_len / 2;
-                    println!("[INDEXING] Context length error for '{}' | original input: {} chars | trying 50% truncation: {} chars", file_name, original_len, half_len);
-                    
-                    // Ensure we don't slice in the middle of a char
-                    let truncated: String = text.chars().take(half_len).collect();
-                    let truncated_len = truncated.len();
-                    
-                    if half_len < 10 {
-                         return Err(e); // Stop if too small
+                    let strategy = self.config.embedding_truncation;
+                    let original_len = text.chars().count();
+                    if original_len / 2 < 10 {
+                        return Err(e); // Stop if too small
                     }
 
-                     match self.embedding_service.generate_embedding(&truncated).await {
-                         Ok(emb) => Ok(emb),
-                         Err(_e2) => {
-                             // Try one more time at 25%?
-                             let quarter_len = text.len() / 4;
-                             let truncated_q: String = text.chars().take(quarter_len).collect();
-                             let quarter_actual = truncated_q.len();
-                             println!("[INDEXING] Context length error again for '{}' | 50% input was {} chars | trying 25% truncation: {} chars", file_name, truncated_len, quarter_actual);
-                             self.embedding_service.generate_embedding(&truncated_q).await
-                         }
-                     }
+                    // Retry at 50%, then 25% of the original, cut with the configured strategy
+                    let half = Self::truncate_for_embedding(text, original_len / 2, strategy);
+                    warn!("Context length error for '{}' | original input: {} chars | trying 50% {:?} truncation: {} chars", file_name, original_len, strategy, half.len());
+                    match self.embedding_service.generate_embedding(&half).await {
+                        Ok(emb) => Ok(emb),
+                        Err(_e2) => {
+                            let quarter = Self::truncate_for_embedding(text, original_len / 4, strategy);
+                            warn!("Context length error again for '{}' | 50% input was {} chars | trying 25% {:?} truncation: {} chars", file_name, half.len(), strategy, quarter.len());
+                            self.embedding_service.generate_embedding(&quarter).await
+                        }
+                    }
                 } else {
                     Err(e)
                 }
@@ -402,18 +837,54 @@ impl Indexer {
         }
     }
 
-    fn chunk_text(&self, text: &str) -> Vec<String> {
-        let chunk_size = self.config.chunk_size;
+    /// Cut `text` to about `max_chars` characters, keeping the parts `strategy` asks for
+    fn truncate_for_embedding(text: &str, max_chars: usize, strategy: EmbeddingTruncation) -> String {
+        let total_chars = text.chars().count();
+        if total_chars <= max_chars {
+            return text.to_string();
+        }
+        match strategy {
+            EmbeddingTruncation::Head => text.chars().take(max_chars).collect(),
+            EmbeddingTruncation::HeadTail => crate::active_rag_agent::truncate_head_tail(text, max_chars),
+            EmbeddingTruncation::Sampled => {
+                // Equal slices spread from the start to the very end of the text
+                const SAMPLES: usize = 4;
+                let slice_chars = max_chars / SAMPLES;
+                let last_start = total_chars - slice_chars;
+                (0..SAMPLES)
+                    .map(|i| text.chars().skip(i * last_start / (SAMPLES - 1)).take(slice_chars).collect::<String>())
+                    .collect::<Vec<_>>()
+                    .join("\n...\n")
+            }
+        }
+    }
+
+    /// Split text into chunks of `chunk_size` words, keeping each chunk's byte range in `text`.
+    /// Consecutive chunks share `overlap` words (clamped below `chunk_size`).
+    fn chunk_text_with_ranges(text: &str, chunk_size: usize, overlap: usize) -> Vec<(String, std::ops::Range<usize>)> {
+        let chunk_size = chunk_size.max(1);
+        let step = chunk_size - overlap.min(chunk_size - 1);
         let mut chunks = Vec::new();
         
+        // split_whitespace yields subslices of `text`, so pointer offsets give byte positions
         let words: Vec<&str> = text.split_whitespace().collect();
+        let offset_of = |word: &str| word.as_ptr() as usize - text.as_ptr() as usize;
         
-        for chunk in words.chunks(chunk_size) {
-            chunks.push(chunk.join(" "));
+        let mut start = 0;
+        while start < words.len() {
+            let end = (start + chunk_size).min(words.len());
+            let chunk = &words[start..end];
+            let first = offset_of(chunk[0]);
+            let last = chunk[chunk.len() - 1];
+            chunks.push((chunk.join(" "), first..offset_of(last) + last.len()));
+            if end == words.len() {
+                break;
+            }
+            start += step;
         }
         
         if chunks.is_empty() {
-            chunks.push(text.to_string());
+            chunks.push((text.to_string(), 0..text.len()));
         }
         
         chunks
@@ -421,7 +892,7 @@ impl Indexer {
 
     /// Intelligent chunk sampling: takes beginning, middle samples, and end
     /// This preserves information from different parts of the document
-    fn intelligent_chunk_sampling(chunks: &[String], max_tokens: usize) -> String {
+    fn intelligent_chunk_sampling(chunks: &[String], max_tokens: usize, token_counter: &dyn TokenCounter) -> String {
         if chunks.is_empty() {
             return String::new();
         }
@@ -460,22 +931,15 @@ impl Indexer {
         // Combine selected chunks
         let combined = selected_chunks.join("\n\n");
         
-        // Estimate tokens and truncate if still too large
-        let estimated_tokens = combined.len() / 4;
+        // Count tokens and truncate if still too large
+        let token_count = token_counter.count_tokens(&combined);
         
-        // Use a much safer margin (75% of max) to avoid edge cases with tokenization
-        // Some models have very different token/char ratios for code/special chars
+        // Use a safer margin (75% of max) since our tokenizer only approximates
+        // the embedding model's own vocabulary
         let safe_limit = (max_tokens as f64 * 0.75) as usize;
         
-        if estimated_tokens > safe_limit {
-            // Truncate to safe limit
-            let max_chars = safe_limit * 4;
-            
-            if combined.len() > max_chars {
-                 combined.chars().take(max_chars).collect()
-            } else {
-                combined
-            }
+        if token_count > safe_limit {
+            token_counter.truncate(&combined, safe_limit)
         } else {
             combined
         }
@@ -483,18 +947,18 @@ impl Indexer {
 
     /// Create multiple embedding sections for very large files (>4x context size)
     /// Uses log-based scaling to limit embeddings, then samples from each region.
-    fn create_multiple_embedding_sections(chunks: &[String], max_tokens: usize) -> Vec<String> {
+    fn create_multiple_embedding_sections(chunks: &[String], max_tokens: usize, token_counter: &dyn TokenCounter) -> Vec<String> {
         if chunks.is_empty() {
             return vec![String::new()];
         }
         
         // Log scaling: limits number of sections for large files
-        let total_tokens: usize = chunks.iter().map(|c| c.len() / 4).sum();
+        let total_tokens: usize = chunks.iter().map(|c| token_counter.count_tokens(c)).sum();
         let ratio = total_tokens as f64 / max_tokens as f64;
         let num_sections = ((ratio + 1.0).log2().ceil() as usize).max(2).min(16);
         
-        // ~4 chars per token; safety margin to stay under context limit
-        let max_chars_per_section = (max_tokens * 4).saturating_sub(64);
+        // Safety margin to stay under context limit
+        let max_tokens_per_section = max_tokens.saturating_sub(16);
         
         let chunks_per_region = chunks.len() / num_sections;
         let mut sections = Vec::new();
@@ -512,7 +976,7 @@ impl Indexer {
             }
             
             let region_chunks = &chunks[start..end];
-            let section_text = Self::sample_region_for_embedding(region_chunks, max_chars_per_section);
+            let section_text = Self::sample_region_for_embedding(region_chunks, max_tokens_per_section, token_counter);
             if !section_text.is_empty() {
                 sections.push(section_text);
             }
@@ -525,22 +989,20 @@ impl Indexer {
         sections
     }
 
-    /// Sample chunks from a region (beginning, distributed middle, end) up to max_chars.
-    fn sample_region_for_embedding(region_chunks: &[String], max_chars: usize) -> String {
+    /// Sample chunks from a region (beginning, distributed middle, end) up to max_tokens.
+    fn sample_region_for_embedding(region_chunks: &[String], max_tokens: usize, token_counter: &dyn TokenCounter) -> String {
         if region_chunks.is_empty() {
             return String::new();
         }
         if region_chunks.len() == 1 {
-            let s = &region_chunks[0];
-            return s.chars().take(max_chars).collect();
+            return token_counter.truncate(&region_chunks[0], max_tokens);
         }
         
         let mut selected = Vec::new();
-        let mut total_chars = 0;
         
         // First chunk
         selected.push(region_chunks[0].as_str());
-        total_chars = region_chunks[0].len();
+        let mut total_tokens = token_counter.count_tokens(&region_chunks[0]);
         
         // Sample from middle (evenly spaced)
         if region_chunks.len() > 2 {
@@ -550,9 +1012,10 @@ impl Indexer {
             let step = ((middle_end - middle_start) / num_middle.max(1)).max(1);
             
             for j in (middle_start..middle_end).step_by(step).take(num_middle) {
-                if total_chars + 2 + region_chunks[j].len() <= max_chars {
+                let chunk_tokens = token_counter.count_tokens(&region_chunks[j]);
+                if total_tokens + 1 + chunk_tokens <= max_tokens {
                     selected.push(region_chunks[j].as_str());
-                    total_chars += 2 + region_chunks[j].len();
+                    total_tokens += 1 + chunk_tokens;
                 } else {
                     break;
                 }
@@ -562,61 +1025,185 @@ impl Indexer {
         // Last chunk (if different from first)
         if region_chunks.len() > 1 {
             let last = region_chunks.len() - 1;
-            if last != 0 && total_chars + 2 + region_chunks[last].len() <= max_chars {
+            if last != 0 && total_tokens + 1 + token_counter.count_tokens(&region_chunks[last]) <= max_tokens {
                 selected.push(region_chunks[last].as_str());
             }
         }
         
         let combined = selected.join("\n\n");
-        if combined.len() > max_chars {
-            combined.chars().take(max_chars).collect()
+        if token_counter.count_tokens(&combined) > max_tokens {
+            token_counter.truncate(&combined, max_tokens)
         } else {
             combined
         }
     }
 
+    /// Build the ignore rules for an indexed root: its `.indexignore` (gitignore syntax)
+    /// plus the configured `ignore_patterns`.
+    #[tracing::instrument(name = "INDEXER", skip_all)]
+    fn ignore_matcher(&self, root: &Path) -> Gitignore {
+        let mut builder = GitignoreBuilder::new(root);
+        let ignore_file = root.join(".indexignore");
+        if ignore_file.is_file() {
+            if let Some(e) = builder.add(&ignore_file) {
+                error!("Error in {}: {}", ignore_file.display(), e);
+            }
+        }
+        for pattern in &self.config.ignore_patterns {
+            if let Err(e) = builder.add_line(None, pattern) {
+                info!("Invalid ignore pattern '{}': {}", pattern, e);
+            }
+        }
+        builder.build().unwrap_or_else(|e| {
+            error!("Failed to build ignore rules for {}: {}", root.display(), e);
+            Gitignore::empty()
+        })
+    }
+
+    /// Walk a directory, pruning ignored subtrees before descending into them
+    /// and stopping at the configured max depth. Symlinks are only followed if
+    /// `follow_symlinks` is set, in which case each directory is visited once.
+    #[tracing::instrument(name = "INDEXER", skip_all)]
+    fn walk_directory<'a>(&self, root: &Path, stats: &'a WalkStats) -> impl Iterator<Item = walkdir::DirEntry> + 'a {
+        let matcher = self.ignore_matcher(root);
+        let follow_symlinks = self.config.follow_symlinks;
+        let mut walker = walkdir::WalkDir::new(root).follow_links(follow_symlinks);
+        if let Some(depth) = self.config.max_index_depth {
+            walker = walker.max_depth(depth);
+        }
+        let mut visited_dirs: HashSet<PathBuf> = HashSet::new();
+        // None when hidden entries are indexed; otherwise the directories opted back in
+        let hidden_dirs: Option<Vec<PathBuf>> = (!self.config.index_hidden)
+            .then(|| self.config.index_hidden_dirs.iter().map(PathBuf::from).collect());
+        let root = root.to_path_buf();
+        walker
+            .into_iter()
+            .filter_entry(move |entry| {
+                let is_dir = entry.file_type().is_dir();
+                if entry.depth() > 0
+                    && (matcher.matched(entry.path(), is_dir).is_ignore() || hidden_dirs.as_ref().is_some_and(|dirs| Self::is_hidden_excluded(dirs, &root, entry.path())))
+                {
+                    let counter = if is_dir { &stats.pruned_dirs } else { &stats.pruned_files };
+                    counter.set(counter.get() + 1);
+                    return false;
+                }
+                // Skip directories already reached through another link (cycles or duplicates)
+                if follow_symlinks && is_dir {
+                    if let Ok(canonical) = entry.path().canonicalize() {
+                        if !visited_dirs.insert(canonical) {
+                            info!("Skipping already visited directory: {}", entry.path().display());
+                            return false;
+                        }
+                    }
+                }
+                true
+            })
+            .filter_map(|e| e.ok())
+    }
+
+    /// True if `path` is hidden below `root` (a dot-named component, or the Windows hidden
+    /// attribute on the entry itself) and not inside one of the opted-in `hidden_dirs`.
+    fn is_hidden_excluded(hidden_dirs: &[PathBuf], root: &Path, path: &Path) -> bool {
+        let relative = path.strip_prefix(root).unwrap_or(path);
+        let dotted = relative.components().any(|c| c.as_os_str().to_string_lossy().starts_with('.'));
+        if !dotted && !Self::has_hidden_attribute(path) {
+            return false;
+        }
+        // Opted-in directories, and the hidden directories leading down to them, stay walkable
+        !hidden_dirs.iter().any(|dir| path.starts_with(dir) || dir.starts_with(path))
+    }
+
+    #[cfg(windows)]
+    fn has_hidden_attribute(path: &Path) -> bool {
+        use std::os::windows::fs::MetadataExt;
+        const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+        std::fs::symlink_metadata(path).is_ok_and(|m| m.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0)
+    }
+
+    #[cfg(not(windows))]
+    fn has_hidden_attribute(_path: &Path) -> bool {
+        false
+    }
+
+    /// `is_hidden_excluded` for a single file, judged relative to the indexed root containing it
+    pub(crate) fn is_hidden_file_excluded(&self, file_path: &str) -> bool {
+        if self.config.index_hidden {
+            return false;
+        }
+        let path = Path::new(file_path);
+        let root = self.config.indexed_directories
+            .iter()
+            .map(Path::new)
+            .filter(|root| path.starts_with(root))
+            .max_by_key(|root| root.components().count());
+        let hidden_dirs: Vec<PathBuf> = self.config.index_hidden_dirs.iter().map(PathBuf::from).collect();
+        Self::is_hidden_excluded(&hidden_dirs, root.or(path.parent()).unwrap_or(path), path)
+    }
+
+    /// Resolve a path to one spelling per file (symlinks, casing and separators resolved) so
+    /// the `file_path` UNIQUE constraint dedupes logically identical files. Paths that no
+    /// longer exist resolve through their parent directory; failing that they're unchanged.
+    pub fn canonical_path(file_path: &str) -> String {
+        let path = Path::new(file_path);
+        let resolved = std::fs::canonicalize(path).ok().or_else(|| {
+            let parent = std::fs::canonicalize(path.parent()?).ok()?;
+            Some(parent.join(path.file_name()?))
+        });
+        match resolved {
+            Some(resolved) => Self::strip_verbatim_prefix(&resolved.to_string_lossy()),
+            None => file_path.to_string(),
+        }
+    }
+
+    /// Windows `canonicalize` returns `\\?\C:\...` or `\\?\UNC\server\...`; keep the familiar form
+    fn strip_verbatim_prefix(path: &str) -> String {
+        if let Some(rest) = path.strip_prefix(r"\\?\UNC\") {
+            format!(r"\\{}", rest)
+        } else {
+            path.strip_prefix(r"\\?\").unwrap_or(path).to_string()
+        }
+    }
+
+    /// True if the file is larger than the configured `max_content_file_size_bytes`.
+    fn exceeds_size_cap(&self, file_path: &str) -> bool {
+        let Ok(metadata) = std::fs::metadata(file_path) else {
+            return false;
+        };
+        let limit = self.config.max_content_file_size_bytes;
+        if metadata.len() > limit {
+            info!("Skipping content of {} ({} bytes > {} byte limit), indexing metadata only",
+                file_path, metadata.len(), limit);
+            return true;
+        }
+        false
+    }
+
     pub async fn is_indexing(&self) -> bool {
         *self.is_indexing.read().await
     }
 
-    /// Check if a file should be indexed with metadata only (filename only, no content)
-    pub fn should_index_metadata_only(file_path: &str) -> bool {
-        let path = PathBuf::from(file_path);
-        let ext = path.extension()
+    /// Check if a file should be indexed with metadata only (filename only, no content),
+    /// per the configured `metadata_only_extensions` or `index_excluded_as_metadata`
+    pub fn should_index_metadata_only(&self, file_path: &str) -> bool {
+        if self.config.index_excluded_as_metadata && self.is_excluded_name(file_path) {
+            return true;
+        }
+        let ext = PathBuf::from(file_path)
+            .extension()
             .and_then(|e| e.to_str())
             .unwrap_or("")
             .to_lowercase();
-        
-        // Config file extensions
-        let config_extensions = [
-            "json", "yaml", "yml", "toml", "ini", "cfg", 
-            "conf", "properties", "config"
-        ];
-        
-        // Binary/executable extensions
-        let binary_extensions = [
-            "exe", "dll", "jar", "so", "dylib", "dll.a", 
-            "dat", "mca", "rrf", "igt", "class"
-        ];
-        
-    // Log file extensions
-    let log_extensions = ["log"];
-    
-    // Image extensions - index by filename only, not content
-    // This prevents random images from appearing in semantic search results
-    let image_extensions = [
-        "jpg", "jpeg", "png", "gif", "bmp", "webp", 
-        "svg", "ico", "tiff", "tif"
-    ];
-    
-    config_extensions.contains(&ext.as_str()) ||
-    binary_extensions.contains(&ext.as_str()) ||
-    log_extensions.contains(&ext.as_str()) ||
-    image_extensions.contains(&ext.as_str())
+        if ext.is_empty() {
+            return false;
+        }
+        self.config.metadata_only_extensions.iter().any(|e| {
+            e.trim_start_matches('.').to_lowercase() == ext
+        })
     }
 
     /// Index a file with metadata only (filename only, no content)
     async fn index_file_metadata_only(&self, file_path: &str) -> Result<()> {
+        let file_path = &Self::canonical_path(file_path);
         // Get file metadata
         let metadata = std::fs::metadata(file_path)?;
         let file_name = PathBuf::from(file_path)
@@ -646,9 +1233,39 @@ impl Indexer {
         };
         
         self.storage.add_file(&file_metadata, None).await?;
+        self.storage.replace_passages(file_path, &[]).await?;
         Ok(())
     }
 
+    /// Store a folder as a metadata-only entry, matched by name when a search sets `include_directories`
+    pub async fn index_directory_entry(&self, dir_path: &str) -> Result<()> {
+        let dir_path = &Self::canonical_path(dir_path);
+        let metadata = std::fs::metadata(dir_path)?;
+        let file_name = PathBuf::from(dir_path)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown")
+            .to_string();
+        let file_metadata = FileMetadata {
+            id: 0,
+            file_path: dir_path.to_string(),
+            file_name,
+            file_size: 0,
+            modified_time: metadata.modified()?
+                .duration_since(std::time::UNIX_EPOCH)?
+                .as_secs() as i64,
+            file_type: DIRECTORY_FILE_TYPE.to_string(),
+            embedding_offset: 0,
+            embedding_length: 0,
+        };
+        self.storage.add_file(&file_metadata, None).await
+    }
+
+    /// Whether folder events from the file watcher should update folder entries
+    pub fn indexes_directory_names(&self) -> bool {
+        self.config.index_directory_names
+    }
+
     /// Check if a file's extension is in the user's excluded extensions list (from config).
     /// Normalizes extension comparison: "mca" and ".mca" both match .mca files.
     pub(crate) fn is_excluded_by_config(&self, file_path: &str) -> bool {
@@ -665,8 +1282,20 @@ impl Indexer {
         })
     }
 
-    /// Check if a file should be excluded from indexing due to high false positive rates
-    pub fn should_exclude_file(file_path: &str) -> bool {
+    /// Check if a file should be excluded from indexing: a configured `excluded_filenames`
+    /// entry or temporary/incomplete download (unless `index_excluded_as_metadata` keeps those
+    /// by name), or (with `skip_empty_files`) a zero-byte file
+    pub fn should_exclude_file(&self, file_path: &str) -> bool {
+        if self.config.skip_empty_files && std::fs::metadata(file_path).is_ok_and(|m| m.is_file() && m.len() == 0) {
+            return true;
+        }
+
+        !self.config.index_excluded_as_metadata && self.is_excluded_name(file_path)
+    }
+
+    /// Whether a file's name is one whose content gives high false positive rates: a configured
+    /// `excluded_filenames` entry or a temporary/lock/incomplete-download extension
+    fn is_excluded_name(&self, file_path: &str) -> bool {
         let path = PathBuf::from(file_path);
         let file_name = path
             .file_name()
@@ -674,15 +1303,8 @@ impl Indexer {
             .unwrap_or("")
             .to_lowercase();
         
-        // Exclude common config/boilerplate files that cause false positives
-        let excluded_patterns = [
-            "config.js",
-            "index.html",
-            "aca.conf.ini",
-        ];
-        
-        if excluded_patterns.iter().any(|pattern| {
-            file_name == pattern.to_lowercase()
+        if self.config.excluded_filenames.iter().any(|name| {
+            file_name == name.trim().to_lowercase()
         }) {
             return true;
         }
@@ -703,51 +1325,95 @@ impl Indexer {
             file_name.ends_with(ext)
         })
     }
+    #[tracing::instrument(name = "STARTUP", skip_all)]
     pub async fn perform_startup_scan(&self) -> Result<()> {
         if !self.config.auto_index || self.config.indexed_directories.is_empty() {
             return Ok(());
         }
 
-        println!("[STARTUP] Starting file synchronization...");
+        self.sync_indexed_directories().await.map(|_| ())
+    }
+
+    /// Bring the index in line with the configured directories: index new files, reindex
+    /// those whose modified time or size changed, and purge entries for deleted files.
+    pub async fn sync_indexed_directories(&self) -> Result<SyncStats> {
+        info!("Starting file synchronization...");
+        let mut stats = SyncStats::default();
         
         let mut indexing = self.is_indexing.write().await;
         if *indexing {
-            return Ok(());
+            return Ok(stats);
         }
         *indexing = true;
         drop(indexing);
 
-        // Get all files currently in the database
-        let db_files = self.storage.get_all_files().await?;
-        let mut db_files_map: HashMap<String, FileMetadata> = db_files
-            .into_iter()
-            .map(|f| (f.file_path.clone(), f))
-            .collect();
+        // Empty files indexed before `skip_empty_files` only clutter filename matches
+        if self.config.skip_empty_files {
+            match self.storage.remove_empty_files().await {
+                Ok(0) => {}
+                Ok(removed) => {
+                    info!("Removed {} empty files from the index", removed);
+                    stats.removed += removed;
+                }
+                Err(e) => warn!("Failed to remove empty files from the index: {}", e),
+            }
+        }
+
+        // Files the user removed from the index stay out until they change, and everything
+        // currently in the database. A failed read must not leave the sync marked as running.
+        let loaded = async {
+            anyhow::Ok((self.storage.removed_files().await?, self.storage.get_all_files().await?))
+        }.await;
+        let (removed_files, db_files) = match loaded {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                *self.is_indexing.write().await = false;
+                return Err(e);
+            }
+        };
+        // Extra sections are stored as "{path}#section{n}" and live or die with their base file
+        let mut sections: HashMap<String, Vec<String>> = HashMap::new();
+        let mut db_files_map: HashMap<String, FileMetadata> = HashMap::new();
+        for file in db_files {
+            match file.file_path.rsplit_once("#section") {
+                Some((base_path, _)) => sections.entry(base_path.to_string()).or_default().push(file.file_path),
+                None => {
+                    db_files_map.insert(file.file_path.clone(), file);
+                }
+            }
+        }
             
-        println!("[STARTUP] Database contains {} files. Scanning disk...", db_files_map.len());
+        info!("Database contains {} files. Scanning disk...", db_files_map.len());
 
         // Collect files to index (new or modified)
-        let mut files_to_index = Vec::new();
+        let mut files_to_index: Vec<(String, bool)> = Vec::new();
+        // Folder entries are only added here; a folder's mtime changing says nothing about its name
+        let mut new_directories: Vec<String> = Vec::new();
 
-        println!("[STARTUP] Configured to scan {} directories:", self.config.indexed_directories.len());
+        info!("Configured to scan {} directories:", self.config.indexed_directories.len());
         for dir in &self.config.indexed_directories {
-            println!("[STARTUP] - {}", dir);
+            info!("- {}", dir);
              if !std::path::Path::new(dir).exists() {
-                println!("[STARTUP]   (Directory does not exist, skipping)");
+                info!("  (Directory does not exist, skipping)");
                 continue;
             }
             
-            for entry in walkdir::WalkDir::new(dir)
-                .into_iter()
-                .filter_map(|e| e.ok()) 
-            {
-                if entry.file_type().is_file() {
-                     let file_path = entry.path().to_string_lossy().to_string();
+            for entry in self.walk_directory(Path::new(dir), &WalkStats::default()) {
+                if entry.file_type().is_dir() {
+                    // With the option off, stored folder entries stay in db_files_map and are purged below
+                    if self.config.index_directory_names && entry.depth() > 0 {
+                        let dir_path = Self::canonical_path(&entry.path().to_string_lossy());
+                        if db_files_map.remove(&dir_path).is_none() {
+                            new_directories.push(dir_path);
+                        }
+                    }
+                } else if entry.file_type().is_file() {
+                     let file_path = Self::canonical_path(&entry.path().to_string_lossy());
                      
                      // Diagnostic logging for EVERY file to debug detection
-                     // println!("[STARTUP] Checking: {}", file_path); 
+                     // info!("Checking: {}", file_path); 
                      
-                     if Self::should_exclude_file(&file_path) {
+                     if self.should_exclude_file(&file_path) {
                          continue;
                      }
                      if self.is_excluded_by_config(&file_path) {
@@ -766,20 +1432,22 @@ impl Indexer {
                             let size = fs_metadata.len() as i64;
                             
                              if modified != metadata.modified_time || size != metadata.file_size {
-                                 println!("[STARTUP] File changed: {} (Time: {} vs {}, Size: {} vs {})", 
+                                 info!("File changed: {} (Time: {} vs {}, Size: {} vs {})", 
                                      file_path, modified, metadata.modified_time, size, metadata.file_size);
-                                 if Self::should_index_metadata_only(&file_path) || self.parser_registry.can_parse(&file_path) {
-                                     files_to_index.push(file_path.clone());
+                                 if self.should_index_metadata_only(&file_path) || self.parser_registry.can_parse(&file_path) {
+                                     files_to_index.push((file_path.clone(), true));
                                  } else {
-                                     println!("[STARTUP] Skipping changed file (unsupported type): {}", file_path);
+                                     info!("Skipping changed file (unsupported type): {}", file_path);
                                  }
                              }
                          }
+                     } else if removed_files.get(&file_path).is_some_and(|&removed| file_modified_time(&file_path) == Some(removed)) {
+                         continue;
                      } else {
                          // File NOT in DB - it's a new file
-                         if Self::should_index_metadata_only(&file_path) || self.parser_registry.can_parse(&file_path) {
-                             println!("[STARTUP] New file found: {}", file_path);
-                             files_to_index.push(file_path.clone());
+                         if self.should_index_metadata_only(&file_path) || self.parser_registry.can_parse(&file_path) {
+                             info!("New file found: {}", file_path);
+                             files_to_index.push((file_path.clone(), false));
                          }
                      }
                 }
@@ -788,15 +1456,29 @@ impl Indexer {
         
         // Remove deleted files (those remaining in db_files_map)
         if !db_files_map.is_empty() {
-            println!("[STARTUP] Found {} deleted files. Removing from index...", db_files_map.len());
+            info!("Found {} deleted files. Removing from index...", db_files_map.len());
             for (path, _) in db_files_map {
-                if let Err(e) = self.storage.delete_file(&path).await {
-                    eprintln!("Failed to delete file from index: {}: {}", path, e);
+                let section_paths = sections.remove(&path).unwrap_or_default();
+                match self.storage.delete_file(&path).await {
+                    Ok(()) => stats.removed += 1,
+                    Err(e) => error!("Failed to delete file from index: {}: {}", path, e),
+                }
+                for section_path in section_paths {
+                    if let Err(e) = self.storage.delete_file(&section_path).await {
+                        error!("Failed to delete file from index: {}: {}", section_path, e);
+                    }
                 }
             }
         }
         
-        println!("[STARTUP] Found {} new/modified files to index.", files_to_index.len());
+        for dir_path in new_directories {
+            match self.index_directory_entry(&dir_path).await {
+                Ok(()) => stats.added += 1,
+                Err(e) => error!("Error indexing folder {}: {}", dir_path, e),
+            }
+        }
+
+        info!("Found {} new/modified files to index.", files_to_index.len());
         
         // Index new/modified files
         // We can reuse the logic from index_directory but it takes a directory path.
@@ -804,35 +1486,129 @@ impl Indexer {
         // For simplicity reusing the logic similar to index_directory but for a specific list.
         
         if !files_to_index.is_empty() {
-             // Initialize progress if tracker exists (optional for startup scan but good for UI)
-             // For now just process them.
-             
-            for file_path in files_to_index {
+            let total = files_to_index.len();
+            self.update_progress(|progress| {
+                *progress = Some(IndexingProgress {
+                    is_indexing: true,
+                    current: 0,
+                    total,
+                    current_file: String::new(),
+                    directory: self.config.indexed_directories.join(", "),
+                });
+            }).await;
+
+            for (current, (file_path, is_update)) in files_to_index.into_iter().enumerate() {
+                if let Some(reason) = self.stop_reason() {
+                    info!("Stopped sync at {}/{} files on {}", current, total, reason);
+                    self.update_progress(|progress| *progress = None).await;
+                    *self.is_indexing.write().await = false;
+                    return Err(anyhow::anyhow!("Sync interrupted by {}; changes so far are kept", reason));
+                }
+                self.update_progress(|progress| {
+                    if let Some(ref mut p) = *progress {
+                        p.current = current;
+                        p.current_file = file_path.clone();
+                    }
+                }).await;
+
+                if is_update && !self.should_index_metadata_only(&file_path) {
+                    match self.refresh_if_content_unchanged(&file_path).await {
+                        Ok(true) => {
+                            stats.unchanged += 1;
+                            continue;
+                        }
+                        Ok(false) => {}
+                        Err(e) => warn!("Failed to compare content of {}: {}", file_path, e),
+                    }
+                }
+
                 // Determine if metadata only
-                let result = if Self::should_index_metadata_only(&file_path) {
-                    println!("[STARTUP] Indexing metadata: {}", file_path);
+                let result = if self.should_index_metadata_only(&file_path) {
+                    info!("Indexing metadata: {}", file_path);
                     self.index_file_metadata_only(&file_path).await
                 } else {
-                    println!("[STARTUP] Indexing content: {}", file_path);
+                    info!("Indexing content: {}", file_path);
                     
-                    // IMPORTANT: We need to use index_file here, but index_file checks filtering again.
-                    // It's safe to call.
-                    self.index_file(&file_path).await
+                    // IMPORTANT: embed_file checks filtering again. It's safe to call.
+                    self.embed_file(&file_path).await
                 };
                 
                 if let Err(e) = result {
-                    eprintln!("Error indexing {}: {}", file_path, e);
+                    error!("Error indexing {}: {}", file_path, e);
                 } else {
-                    println!("[STARTUP] Successfully indexed: {}", file_path);
+                    info!("Successfully indexed: {}", file_path);
+                    if is_update {
+                        stats.updated += 1;
+                    } else {
+                        stats.added += 1;
+                    }
                 }
             }
         }
         
+        self.update_progress(|progress| *progress = None).await;
         let mut indexing = self.is_indexing.write().await;
         *indexing = false;
         
-        println!("[STARTUP] Sync complete.");
-        Ok(())
+        info!("Sync complete: {} added, {} updated, {} unchanged, {} removed.",
+            stats.added, stats.updated, stats.unchanged, stats.removed);
+        Ok(stats)
     }
 }
 
+
+/// Modified time of `file_path` in seconds since the epoch, as stored in the index
+fn file_modified_time(file_path: &str) -> Option<i64> {
+    let modified = std::fs::metadata(file_path).ok()?.modified().ok()?;
+    Some(modified.duration_since(std::time::UNIX_EPOCH).ok()?.as_secs() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hidden_entries_excluded_unless_opted_in() {
+        let root = Path::new("/home/user");
+        let opted_in = vec![PathBuf::from("/home/user/.config/nvim")];
+
+        assert!(!Indexer::is_hidden_excluded(&opted_in, root, Path::new("/home/user/notes.txt")));
+        assert!(Indexer::is_hidden_excluded(&opted_in, root, Path::new("/home/user/.bashrc")));
+        assert!(Indexer::is_hidden_excluded(&opted_in, root, Path::new("/home/user/.cache/x/data.txt")));
+        assert!(Indexer::is_hidden_excluded(&opted_in, root, Path::new("/home/user/.config/app.toml")));
+        // The path down to an opted-in directory stays walkable, as does everything inside it
+        assert!(!Indexer::is_hidden_excluded(&opted_in, root, Path::new("/home/user/.config")));
+        assert!(!Indexer::is_hidden_excluded(&opted_in, root, Path::new("/home/user/.config/nvim/init.lua")));
+        // A hidden root itself is not judged, only what lies below it
+        assert!(!Indexer::is_hidden_excluded(&[], Path::new("/home/user/.notes"), Path::new("/home/user/.notes/a.md")));
+    }
+
+    #[test]
+    fn test_chunk_overlap_shares_words_between_chunks() {
+        let text = "a b c d e f g";
+        let chunks: Vec<String> = Indexer::chunk_text_with_ranges(text, 3, 1).into_iter().map(|(c, _)| c).collect();
+        assert_eq!(chunks, vec!["a b c", "c d e", "e f g"]);
+
+        let plain = Indexer::chunk_text_with_ranges(text, 3, 0);
+        assert_eq!(plain.len(), 3);
+        assert_eq!(plain[2], ("g".to_string(), 12..13));
+
+        // An overlap at or above the chunk size still advances one word at a time
+        assert_eq!(Indexer::chunk_text_with_ranges("a b c", 2, 5).len(), 2);
+    }
+
+    #[test]
+    fn test_truncation_strategies_keep_the_end() {
+        let text = format!("intro {} conclusion", "body ".repeat(100));
+        let head = Indexer::truncate_for_embedding(&text, 100, EmbeddingTruncation::Head);
+        assert!(head.starts_with("intro") && !head.contains("conclusion"));
+        assert_eq!(head.chars().count(), 100);
+
+        for strategy in [EmbeddingTruncation::HeadTail, EmbeddingTruncation::Sampled] {
+            let cut = Indexer::truncate_for_embedding(&text, 100, strategy);
+            assert!(cut.starts_with("intro") && cut.ends_with("conclusion"), "{:?}: {}", strategy, cut);
+            assert!(cut.chars().count() < 120);
+        }
+        assert_eq!(Indexer::truncate_for_embedding("short", 100, EmbeddingTruncation::Sampled), "short");
+    }
+}
@@ -3,25 +3,35 @@ pub mod config;
 pub mod embedding;
 pub mod file_watcher;
 pub mod hnsw_index;
+pub mod index_cursor;
 pub mod indexer;
+pub mod llm_json;
+pub mod logging;
+pub mod maintenance;
 pub mod parsers;
 pub mod query_parser;
 pub mod search;
+pub mod spell;
 pub mod storage;
+pub mod text_cache;
+pub mod tokenizer;
 pub mod active_rag_agent;
 
 use axum::{
+    extract::State,
     http::StatusCode,
     response::Json,
 };
+use std::collections::HashMap;
 use std::sync::Arc;
 
 pub use crate::config::PerformanceMode;
 use crate::storage::Storage;
 use crate::config::AppConfig;
 use crate::file_watcher::FileWatcher;
-use crate::indexer::IndexingProgress;
+use crate::indexer::{IndexRunStats, IndexingProgress};
 use crate::hnsw_index::HnswIndex;
+use crate::active_rag_agent::ActiveRagResponse;
 
 #[derive(Clone)]
 pub struct AppState {
@@ -29,12 +39,35 @@ pub struct AppState {
     pub config: Arc<AppConfig>,
     pub file_watcher: Option<Arc<tokio::sync::Mutex<FileWatcher>>>,
     pub indexing_progress: Arc<tokio::sync::RwLock<Option<IndexingProgress>>>,
+    /// Pushes each `indexing_progress` change to WebSocket subscribers
+    pub indexing_progress_tx: Arc<tokio::sync::watch::Sender<Option<IndexingProgress>>>,
+    /// When the last search or index request arrived, for idle-time maintenance
+    pub activity: Arc<crate::maintenance::Activity>,
+    /// Flipped to true on SIGINT/SIGTERM; running index jobs stop after their current batch
+    pub shutdown: Arc<tokio::sync::watch::Sender<bool>>,
+    /// Jobs started by `POST /api/index/start`, with recent finished ones kept as history
+    pub index_jobs: Arc<std::sync::Mutex<crate::api::index::IndexJobs>>,
+    /// Stats from the last completed directory index run
+    pub last_index_stats: Arc<tokio::sync::RwLock<Option<IndexRunStats>>>,
     pub hnsw_index: Arc<tokio::sync::RwLock<Option<HnswIndex>>>,
+    /// In-flight Active RAG requests keyed by query + question; duplicates await the shared result
+    pub active_rag_in_flight: Arc<tokio::sync::Mutex<HashMap<String, tokio::sync::watch::Receiver<Option<ActiveRagResponse>>>>>,
+    /// Phase and cancel handle of each running Active RAG pipeline, keyed like `active_rag_in_flight`
+    pub active_rag_runs: Arc<std::sync::Mutex<HashMap<String, crate::api::active_rag::ActiveRagRun>>>,
+    /// Corpus terms for query spell correction and when they were counted; rebuilt when stale
+    pub spell_vocabulary: Arc<tokio::sync::RwLock<Option<crate::spell::CountedVocabulary>>>,
+    /// LLM query parses shared across requests; loaded from query_cache.json once at startup
+    pub query_cache: Arc<crate::query_parser::QueryCache>,
+    /// Bounds concurrent and queued LLM requests across summarize, chat and Active RAG
+    pub ai_limiter: Arc<crate::api::ai::AiLimiter>,
 }
 
-pub async fn health_check() -> Result<Json<serde_json::Value>, StatusCode> {
+pub async fn health_check(State(state): State<AppState>) -> Result<Json<serde_json::Value>, StatusCode> {
     Ok(Json(serde_json::json!({
         "status": "ok",
-        "service": "gist-vector-search-backend"
+        "service": "gist-vector-search-backend",
+        "ollama_base_url": state.config.ollama_base_url,
+        "embedding_base_url": state.config.embedding_base_url(),
+        "embeddings_missing": state.storage.embeddings_missing()
     })))
 }
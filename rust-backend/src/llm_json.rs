@@ -0,0 +1,131 @@
+// Lenient JSON parsing for LLM output.
+// Models asked for JSON still emit trailing commas, single-quoted strings, curly quotes and
+// objects cut off mid-way; those are repaired before giving up on the response.
+
+use serde::de::DeserializeOwned;
+use tracing::warn;
+
+/// Parse `text` as JSON, retrying once with `repair` applied if it doesn't parse as-is.
+/// Only text that opens an object or array is repaired, so plain prose still fails.
+/// `source` names the caller in the log line written when a repair was needed.
+pub fn parse_lenient<T: DeserializeOwned>(text: &str, source: &str) -> serde_json::Result<T> {
+    let error = match serde_json::from_str(text) {
+        Ok(value) => return Ok(value),
+        Err(e) => e,
+    };
+    if !text.trim_start().starts_with(['{', '[']) {
+        return Err(error);
+    }
+    let repaired = repair(text);
+    match serde_json::from_str(&repaired) {
+        Ok(value) => {
+            warn!("Repaired malformed JSON from {} ({})", source, error);
+            Ok(value)
+        }
+        Err(_) => Err(error),
+    }
+}
+
+/// Fix the usual ways LLM JSON is malformed: single-quoted and curly-quoted strings become
+/// double-quoted, trailing commas before `}`/`]` are dropped, and unclosed strings, objects
+/// and arrays are closed at the end. Text that is already valid JSON comes back unchanged.
+pub fn repair(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 8);
+    let mut closers: Vec<char> = Vec::new();
+    // Closing quote of the string being copied, if inside one
+    let mut in_string: Option<char> = None;
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        match in_string {
+            Some(closing) => {
+                if c == closing {
+                    out.push('"');
+                    in_string = None;
+                } else if c == '\\' {
+                    match chars.next() {
+                        // JSON has no \' escape
+                        Some('\'') => out.push('\''),
+                        Some(escaped) => {
+                            out.push('\\');
+                            out.push(escaped);
+                        }
+                        None => {}
+                    }
+                } else if c == '"' {
+                    // Only reachable inside a single- or curly-quoted string
+                    out.push_str("\\\"");
+                } else {
+                    out.push(c);
+                }
+            }
+            None => match c {
+                '"' | '\'' | '“' => {
+                    in_string = Some(match c {
+                        '“' => '”',
+                        quote => quote,
+                    });
+                    out.push('"');
+                }
+                '{' => {
+                    closers.push('}');
+                    out.push(c);
+                }
+                '[' => {
+                    closers.push(']');
+                    out.push(c);
+                }
+                '}' | ']' => {
+                    if closers.last() != Some(&c) {
+                        // Unmatched closer; dropping it is the best guess
+                        continue;
+                    }
+                    closers.pop();
+                    strip_trailing_comma(&mut out);
+                    out.push(c);
+                }
+                _ => out.push(c),
+            },
+        }
+    }
+
+    if in_string.is_some() {
+        out.push('"');
+    }
+    if !closers.is_empty() {
+        strip_trailing_comma(&mut out);
+        out.extend(closers.into_iter().rev());
+    }
+    out
+}
+
+fn strip_trailing_comma(out: &mut String) {
+    let trimmed_len = out.trim_end().len();
+    if out[..trimmed_len].ends_with(',') {
+        out.truncate(trimmed_len - 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repairs_common_llm_mistakes() {
+        let value: serde_json::Value =
+            parse_lenient("{'answer': 'It\\'s \"fine\"', 'sources': [1, 2,],}", "test").unwrap();
+        assert_eq!(value["answer"], "It's \"fine\"");
+        assert_eq!(value["sources"], serde_json::json!([1, 2]));
+
+        let value: serde_json::Value = parse_lenient("{\"answer\": \"cut off, mid\", \"items\": [\"a\"", "test").unwrap();
+        assert_eq!(value["items"], serde_json::json!(["a"]));
+
+        let value: serde_json::Value = parse_lenient("{“answer”: “curly”}", "test").unwrap();
+        assert_eq!(value["answer"], "curly");
+
+        // Valid JSON, including apostrophes inside strings, passes through untouched
+        let valid = r#"{"answer": "don't, [stop]"}"#;
+        assert_eq!(repair(valid), valid);
+        assert!(parse_lenient::<serde_json::Value>("not json at all", "test").is_err());
+    }
+}
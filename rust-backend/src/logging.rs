@@ -0,0 +1,20 @@
+use tracing_subscriber::EnvFilter;
+
+/// Install the global tracing subscriber, writing to stderr.
+/// `RUST_LOG` (e.g. `debug` or `nlp_file_explorer_backend::api::search=trace`) overrides `default_level`.
+pub fn init(default_level: &str) {
+    let filter = EnvFilter::try_from_default_env()
+        .or_else(|_| EnvFilter::try_new(default_level))
+        .unwrap_or_else(|_| EnvFilter::new("info"));
+
+    // Ignore the error if a subscriber is already installed (e.g. in tests)
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .try_init();
+}
+
+/// Whether `level` is a valid filter directive for the `log_level` setting
+pub fn is_valid_filter(level: &str) -> bool {
+    EnvFilter::try_new(level).is_ok()
+}
@@ -11,28 +11,36 @@ use nlp_file_explorer_backend::{
     file_watcher::FileWatcher,
     indexer::Indexer,
     hnsw_index::HnswIndex,
+    index_cursor::IndexCursors,
+    query_parser::QueryCache,
     AppState,
     api,
     health_check,
 };
+use tracing::{error, info, warn};
+
+/// How long shutdown waits for a running index batch before exiting anyway
+const SHUTDOWN_GRACE: std::time::Duration = std::time::Duration::from_secs(120);
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     // Initialize config
     let config = Arc::new(AppConfig::load_or_default().await?);
+    nlp_file_explorer_backend::logging::init(&config.log_level);
     
     // Initialize storage
-    let storage = Arc::new(Storage::new(&AppConfig::data_dir()).await?);
+    let storage = Arc::new(
+        Storage::new(&AppConfig::data_dir()).await?.with_similarity_metric(config.similarity_metric)
+    );
+    if let Some(mismatch) = config.similarity_metric_mismatch() {
+        warn!("{}", mismatch);
+    }
     
     // Initialize embedding service
-    let embedding_service = Arc::new(nlp_file_explorer_backend::embedding::EmbeddingService::new(
-        config.embedding_model.clone()
-    ));
+    let embedding_service = Arc::new(nlp_file_explorer_backend::embedding::EmbeddingService::from_config(&config));
     
     // Initialize parser registry
-    let parser_registry = Arc::new(nlp_file_explorer_backend::parsers::ParserRegistry::new(
-        &config.file_type_filters
-    ));
+    let parser_registry = Arc::new(nlp_file_explorer_backend::parsers::ParserRegistry::from_config(&config));
     
     // Initialize indexer
     let indexer = Arc::new(Indexer::new(
@@ -42,23 +50,35 @@ async fn main() -> anyhow::Result<()> {
         config.clone(),
     ));
 
+    for cursor in IndexCursors::new(&AppConfig::data_dir()).load_all().await.into_values() {
+        warn!(
+            "Indexing of {} was interrupted at {}/{} files; POST /api/index/start with \"resume\": true to continue",
+            cursor.directory, cursor.processed, cursor.total
+        );
+    }
+
+    // Held by whatever is writing to the index: index jobs, syncs, the file watcher and maintenance
+    let index_write_lock = Arc::new(tokio::sync::Mutex::new(()));
+
     // Start startup scan in background
     let indexer_clone = indexer.clone();
+    let write_lock = index_write_lock.clone();
     tokio::spawn(async move {
         // Wait a bit for server to start
         tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+        let _writing = write_lock.lock().await;
         if let Err(e) = indexer_clone.perform_startup_scan().await {
-            eprintln!("Startup scan failed: {}", e);
+            error!("Startup scan failed: {}", e);
         }
     });
 
     
     // Initialize file watcher if auto_index is enabled
     let file_watcher = if config.auto_index && !config.indexed_directories.is_empty() {
-        match FileWatcher::new(indexer.clone(), storage.clone(), config.indexed_directories.clone()) {
+        match FileWatcher::new(indexer.clone(), storage.clone(), config.indexed_directories.clone(), index_write_lock.clone()) {
             Ok(watcher) => Some(Arc::new(tokio::sync::Mutex::new(watcher))),
             Err(e) => {
-                eprintln!("Warning: Failed to initialize file watcher: {}", e);
+                warn!("Failed to initialize file watcher: {}", e);
                 None
             }
         }
@@ -69,14 +89,36 @@ async fn main() -> anyhow::Result<()> {
     // Initialize HNSW index (will be built lazily on first search or after indexing)
     let hnsw_index = Arc::new(tokio::sync::RwLock::new(None));
     
+    let ai_limiter = Arc::new(api::ai::AiLimiter::from_config(&config));
+
+    let query_cache = if config.persist_query_cache {
+        QueryCache::load(&QueryCache::default_file(), config.query_cache_ttl_secs).await
+    } else {
+        QueryCache::default()
+    };
+
     let app_state = AppState { 
         storage, 
         config,
         file_watcher,
         indexing_progress: Arc::new(tokio::sync::RwLock::new(None)),
+        indexing_progress_tx: Arc::new(tokio::sync::watch::channel(None).0),
+        activity: Arc::new(nlp_file_explorer_backend::maintenance::Activity::default()),
+        shutdown: Arc::new(tokio::sync::watch::channel(false).0),
+        index_jobs: Arc::new(std::sync::Mutex::new(api::index::IndexJobs::with_run_lock(index_write_lock))),
+        last_index_stats: Arc::new(tokio::sync::RwLock::new(None)),
         hnsw_index,
+        active_rag_in_flight: Arc::new(tokio::sync::Mutex::new(std::collections::HashMap::new())),
+        active_rag_runs: Arc::new(std::sync::Mutex::new(std::collections::HashMap::new())),
+        spell_vocabulary: Arc::new(tokio::sync::RwLock::new(None)),
+        query_cache: Arc::new(query_cache),
+        ai_limiter,
     };
 
+    if app_state.config.auto_maintenance {
+        nlp_file_explorer_backend::maintenance::spawn_scheduler(app_state.clone());
+    }
+
     // Build router
     let app = Router::new()
         .route("/api/health", get(health_check))
@@ -84,33 +126,103 @@ async fn main() -> anyhow::Result<()> {
         .route("/api/settings", put(api::settings::update_settings))
         .route("/api/system-info", get(api::system_info::get_system_info))
         .route("/api/search", post(api::search::search_files))
+        .route("/api/search/stream", post(api::search::search_files_stream))
         .route("/api/search/parse", post(api::parse::parse_query))
+        .route("/api/query/analyze", post(api::parse::analyze_query))
+        .route("/api/search/similar", post(api::search::similar_files))
         .route("/api/files", get(api::files::list_files))
+        .route("/api/files/record", get(api::files::get_file_record))
         .route("/api/files/browse", get(api::files_browser::browse_directory))
         .route("/api/files/search", get(api::files_browser::search_files))
         .route("/api/files/tree", get(api::tree::get_file_tree))
         .route("/api/preview", get(api::preview::get_file_preview))
+        .route("/api/files/preview", get(api::preview::get_browser_preview))
         .route("/api/files/special-folders", get(api::files_browser::get_special_folders))
         .route("/api/files/create-folder", post(api::files_browser::create_folder))
         .route("/api/files/delete", post(api::files_browser::delete_item))
+        .route("/api/files/delete-batch", post(api::files_browser::delete_batch))
         .route("/api/files/rename", put(api::files_browser::rename_item))
+        .route("/api/files/move", post(api::files_browser::move_item))
+        .route("/api/files/copy", post(api::files_browser::copy_item))
+        .route("/api/files/reveal", post(api::files_browser::reveal_item))
         .route("/api/index/start", post(api::index::start_indexing))
+        .route("/api/index/sync", post(api::index::sync_index))
         .route("/api/index/status", get(api::index::get_index_status))
+        .route("/api/index/jobs", get(api::index::list_index_jobs))
+        .route("/api/index/job/:id", get(api::index::get_index_job))
+        .route("/api/index/job/:id/cancel", post(api::index::cancel_index_job))
+        .route("/api/index/resumable", get(api::index::get_resumable_indexing))
+        .route("/api/index/stats", get(api::index::get_index_stats))
+        .route("/api/index/recent", get(api::index::get_recently_indexed))
+        .route("/api/index/aggregate", get(api::index::get_index_aggregates))
+        .route("/api/index/progress/ws", get(api::index::index_progress_ws))
         .route("/api/index/clear", post(api::index::clear_index))
+        .route("/api/index/reset-embeddings", post(api::index::reset_embeddings))
+        .route("/api/index/clear-directory", post(api::index::clear_directory))
+        .route("/api/index/remove", post(api::index::remove_from_index))
         .route("/api/ai/summarize", post(api::ai::summarize_document))
         .route("/api/ai/chat", post(api::ai::chat_about_document))
         .route("/api/ai/gemini-models", get(api::ai::get_gemini_models))
+        .route("/api/ai/validate-key", post(api::ai::validate_api_key))
         .route("/api/search/active-rag", post(api::active_rag::active_rag_search))
+        .route("/api/search/active-rag/status", get(api::active_rag::active_rag_status))
+        .route("/api/search/active-rag/cancel", post(api::active_rag::cancel_active_rag))
         .route("/api/test/image-embedding", get(api::test_image_embedding::test_image_embedding))
         .route("/api/setup/status", get(api::setup::get_setup_status))
         .route("/api/setup/pull", post(api::setup::pull_model))
+        .layer(axum::middleware::from_fn_with_state(
+            app_state.activity.clone(),
+            nlp_file_explorer_backend::maintenance::track_activity,
+        ))
         .layer(CorsLayer::permissive())
-        .with_state(app_state);
+        .with_state(app_state.clone());
 
     let listener = tokio::net::TcpListener::bind("127.0.0.1:8080").await?;
-    println!("Backend server running on http://127.0.0.1:8080");
-    
-    axum::serve(listener, app).await?;
+    info!("Backend server running on http://127.0.0.1:8080");
     
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown_signal(app_state.shutdown.clone()))
+        .await?;
+
+    // Let the running index job finish its batch and save its cursor, then make the index durable
+    if tokio::time::timeout(SHUTDOWN_GRACE, api::index::wait_for_index_jobs(&app_state.index_jobs)).await.is_err() {
+        warn!("Index job still running after {:?}, exiting without waiting for it", SHUTDOWN_GRACE);
+    }
+    if let Err(e) = app_state.storage.flush().await {
+        error!("Failed to flush storage on shutdown: {}", e);
+    }
+    info!("Backend server stopped");
+
     Ok(())
 }
+
+/// Resolve on Ctrl+C or SIGTERM, telling index jobs to stop at their next batch boundary
+async fn shutdown_signal(shutdown: Arc<tokio::sync::watch::Sender<bool>>) {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!("Failed to listen for Ctrl+C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                warn!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+    info!("Shutting down, waiting for the current index batch to finish");
+    shutdown.send_replace(true);
+}
@@ -0,0 +1,147 @@
+// Idle-time upkeep of the index.
+// Removing and reindexing files leaves dead vectors in embeddings.bin that only compaction
+// reclaims. When `auto_maintenance` is on, a background task compacts once the server has seen
+// no search or index request and no index write for a while and the file has bloated enough,
+// then rebuilds the HNSW index. It holds the index writer lock throughout, so index jobs, syncs
+// and the file watcher wait for it. Any search or index request makes it give up until the next
+// idle stretch.
+
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info, warn};
+
+use crate::AppState;
+
+/// How often the scheduler checks whether maintenance is due
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// When the last search or index request arrived
+pub struct Activity {
+    started: Instant,
+    /// Milliseconds after `started` of the last request
+    last_request_ms: AtomicU64,
+    /// Requests seen so far; a change tells running maintenance to stop
+    requests: AtomicU64,
+}
+
+impl Default for Activity {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            last_request_ms: AtomicU64::new(0),
+            requests: AtomicU64::new(0),
+        }
+    }
+}
+
+impl Activity {
+    pub fn touch(&self) {
+        self.last_request_ms.store(self.started.elapsed().as_millis() as u64, Ordering::SeqCst);
+        self.requests.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub fn idle_for(&self) -> Duration {
+        let last_request = Duration::from_millis(self.last_request_ms.load(Ordering::SeqCst));
+        self.started.elapsed().saturating_sub(last_request)
+    }
+
+    pub fn requests(&self) -> u64 {
+        self.requests.load(Ordering::SeqCst)
+    }
+}
+
+/// Whether a request to `path` counts as use of the index. Status polling and settings don't,
+/// or an open UI would keep the server from ever looking idle.
+fn is_index_activity(path: &str) -> bool {
+    path.starts_with("/api/search")
+        || path == "/api/files/search"
+        || matches!(path, "/api/index/start" | "/api/index/sync")
+}
+
+/// Middleware recording search and index requests in `Activity`
+pub async fn track_activity(State(activity): State<Arc<Activity>>, request: Request, next: Next) -> Response {
+    if is_index_activity(request.uri().path()) {
+        activity.touch();
+    }
+    next.run(request).await
+}
+
+/// Start the background scheduler configured by `auto_maintenance_idle_secs` and
+/// `auto_maintenance_bloat_ratio`. It exits on shutdown.
+pub fn spawn_scheduler(state: AppState) {
+    let idle_after = Duration::from_secs(state.config.auto_maintenance_idle_secs);
+    let bloat_ratio = state.config.auto_maintenance_bloat_ratio as f64;
+    info!("Auto maintenance on: compacting after {:?} idle once embeddings.bin exceeds {:.1}x its live size", idle_after, bloat_ratio);
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            ticker.tick().await;
+            if *state.shutdown.borrow() {
+                return;
+            }
+            // Index jobs, syncs (including the startup one) and the file watcher write under this lock
+            let run_lock = state.index_jobs.lock().unwrap().run_lock();
+            let Ok(_writing) = run_lock.try_lock() else {
+                // Count the write as activity, so the idle stretch starts once it's done
+                state.activity.touch();
+                continue;
+            };
+            if state.activity.idle_for() < idle_after {
+                continue;
+            }
+
+            let ratio = match state.storage.embeddings_bloat_ratio().await {
+                Ok(ratio) => ratio,
+                Err(e) => {
+                    warn!("Auto maintenance couldn't measure embeddings.bin: {}", e);
+                    continue;
+                }
+            };
+            if ratio < bloat_ratio {
+                debug!("Auto maintenance: embeddings.bin at {:.2}x its live size, nothing to do", ratio);
+                continue;
+            }
+
+            info!("Auto maintenance: idle with embeddings.bin at {:.2}x its live size, compacting", ratio);
+            let requests = state.activity.requests();
+            let activity = state.activity.clone();
+            let shutdown = state.shutdown.subscribe();
+            let interrupted = move || activity.requests() != requests || *shutdown.borrow();
+            match state.storage.compact_embeddings_unless(interrupted).await {
+                Ok(Some(reclaimed)) => {
+                    info!("Auto maintenance reclaimed {} bytes, rebuilding the HNSW index", reclaimed);
+                    crate::api::files_browser::spawn_hnsw_rebuild(&state);
+                }
+                Ok(None) => info!("Auto maintenance: request arrived, compaction postponed"),
+                Err(e) => error!("Auto maintenance compaction failed: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_search_and_index_requests_count_as_activity() {
+        assert!(is_index_activity("/api/search"));
+        assert!(is_index_activity("/api/search/active-rag"));
+        assert!(is_index_activity("/api/index/start"));
+        assert!(!is_index_activity("/api/index/status"));
+        assert!(!is_index_activity("/api/health"));
+
+        let activity = Activity::default();
+        let requests = activity.requests();
+        activity.touch();
+        assert_ne!(activity.requests(), requests);
+        assert!(activity.idle_for() < Duration::from_secs(1));
+    }
+}
@@ -1,9 +1,20 @@
 use anyhow::Result;
 use std::path::Path;
+use tracing::warn;
+
+use crate::text_cache::TextCache;
 
 pub trait DocumentParser: Send + Sync {
     fn can_parse(&self, file_path: &str) -> bool;
     fn extract_text(&self, file_path: &str) -> Result<String>;
+    /// Whether extraction is slow enough to be worth caching; false for parsers that just read the file
+    fn cacheable(&self) -> bool {
+        true
+    }
+    /// The text split into independent parts (a workbook's sheets); one part for most formats
+    fn extract_parts(&self, file_path: &str) -> Result<Vec<String>> {
+        Ok(vec![self.extract_text(file_path)?])
+    }
 }
 
 pub struct TextParser;
@@ -23,6 +34,10 @@ impl DocumentParser for TextParser {
     fn extract_text(&self, file_path: &str) -> Result<String> {
         Ok(std::fs::read_to_string(file_path)?)
     }
+
+    fn cacheable(&self) -> bool {
+        false
+    }
 }
 
 pub struct PdfParser;
@@ -98,7 +113,34 @@ impl DocumentParser for DocxParser {
     }
 }
 
-pub struct XlsxParser;
+/// Excel workbooks. With `header_context` each sheet is introduced by its name and header row,
+/// and the text opens with an overview of every sheet's columns, so searches by sheet or column
+/// name match; without it the cells are concatenated row by row.
+pub struct XlsxParser {
+    pub header_context: bool,
+}
+
+impl XlsxParser {
+    /// Each non-empty sheet's name and rows of cell text
+    fn read_sheets(&self, file_path: &str) -> Result<Vec<(String, Vec<Vec<String>>)>> {
+        use calamine::{open_workbook, Reader, Xlsx, Data};
+
+        let mut workbook: Xlsx<_> = open_workbook(file_path)?;
+        let mut sheets = Vec::new();
+        for sheet_name in workbook.sheet_names().to_vec() {
+            if let Ok(range) = workbook.worksheet_range(&sheet_name) {
+                let rows: Vec<Vec<String>> = range
+                    .rows()
+                    .map(|row| row.iter().map(|cell: &Data| cell.to_string()).collect())
+                    .collect();
+                if !rows.is_empty() {
+                    sheets.push((sheet_name, rows));
+                }
+            }
+        }
+        Ok(sheets)
+    }
+}
 
 impl DocumentParser for XlsxParser {
     fn can_parse(&self, file_path: &str) -> bool {
@@ -110,24 +152,242 @@ impl DocumentParser for XlsxParser {
     }
 
     fn extract_text(&self, file_path: &str) -> Result<String> {
-        use calamine::{open_workbook, Reader, Xlsx, Data};
-        
-        let mut workbook: Xlsx<_> = open_workbook(file_path)?;
-        let mut text_parts = Vec::new();
-        
-        for sheet_name in workbook.sheet_names().to_vec() {
-            if let Ok(range) = workbook.worksheet_range(&sheet_name) {
-                for row in range.rows() {
-                    let row_text: Vec<String> = row
-                        .iter()
-                        .map(|cell: &Data| cell.to_string())
-                        .collect();
-                    text_parts.push(row_text.join(" "));
+        let sheets = self.read_sheets(file_path)?;
+        let mut text_parts = Vec::with_capacity(sheets.len() + 1);
+        if self.header_context {
+            text_parts.push(workbook_overview(&sheets));
+        }
+        for (name, rows) in &sheets {
+            text_parts.push(sheet_text(Some(name), rows, self.header_context));
+        }
+        Ok(text_parts.join("\n\n"))
+    }
+
+    fn extract_parts(&self, file_path: &str) -> Result<Vec<String>> {
+        Ok(self
+            .read_sheets(file_path)?
+            .iter()
+            .map(|(name, rows)| sheet_text(Some(name), rows, self.header_context))
+            .collect())
+    }
+}
+
+/// Comma-separated values, flattened like a single-sheet workbook
+pub struct CsvParser {
+    pub header_context: bool,
+}
+
+impl DocumentParser for CsvParser {
+    fn can_parse(&self, file_path: &str) -> bool {
+        Path::new(file_path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.eq_ignore_ascii_case("csv"))
+            .unwrap_or(false)
+    }
+
+    fn extract_text(&self, file_path: &str) -> Result<String> {
+        let content = std::fs::read_to_string(file_path)?;
+        Ok(sheet_text(None, &parse_csv(&content), self.header_context))
+    }
+
+    fn cacheable(&self) -> bool {
+        false
+    }
+}
+
+/// The first row with any non-empty cell, taken to be the column headers
+fn header_row(rows: &[Vec<String>]) -> Option<&Vec<String>> {
+    rows.iter().find(|row| row.iter().any(|cell| !cell.trim().is_empty()))
+}
+
+/// "Sheets: Budget (Date, Amount); Contacts (Name, Email)"
+fn workbook_overview(sheets: &[(String, Vec<Vec<String>>)]) -> String {
+    let summaries: Vec<String> = sheets
+        .iter()
+        .map(|(name, rows)| match header_row(rows) {
+            Some(header) => {
+                let columns: Vec<&str> = header.iter().map(|c| c.trim()).filter(|c| !c.is_empty()).collect();
+                format!("{} ({})", name, columns.join(", "))
+            }
+            None => name.clone(),
+        })
+        .collect();
+    format!("Sheets: {}", summaries.join("; "))
+}
+
+/// One sheet's non-empty rows, one line each. With `header_context` they are preceded by the
+/// sheet's name (if it has one) and its header row stated as columns.
+fn sheet_text(name: Option<&str>, rows: &[Vec<String>], header_context: bool) -> String {
+    let mut lines = Vec::with_capacity(rows.len() + 2);
+    if header_context {
+        if let Some(name) = name {
+            lines.push(format!("Sheet: {}", name));
+        }
+        if let Some(header) = header_row(rows) {
+            let columns: Vec<&str> = header.iter().map(|c| c.trim()).filter(|c| !c.is_empty()).collect();
+            lines.push(format!("Columns: {}", columns.join(" | ")));
+        }
+    }
+    for row in rows {
+        let cells: Vec<&str> = row.iter().map(|c| c.trim()).filter(|c| !c.is_empty()).collect();
+        if !cells.is_empty() {
+            lines.push(cells.join(" "));
+        }
+    }
+    lines.join("\n")
+}
+
+/// Split CSV text into rows of fields, honoring quoted fields with embedded commas, quotes and newlines
+fn parse_csv(content: &str) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = content.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => row.push(std::mem::take(&mut field)),
+            '\n' if !in_quotes => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            '\r' if !in_quotes => {}
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows
+}
+
+/// Most entries listed per archive, nested ones included
+const MAX_ARCHIVE_ENTRIES: usize = 10_000;
+
+/// Most text entry contents kept per archive, nested ones included
+const MAX_ARCHIVE_TEXT_BYTES: u64 = 1024 * 1024;
+
+/// Largest nested zip read into memory to be listed; bigger ones are listed by name only
+const MAX_NESTED_ARCHIVE_BYTES: u64 = 32 * 1024 * 1024;
+
+/// Zip archives, indexed by the paths of their entries so a file inside a backup is findable
+/// without extracting it. Zips inside the archive are listed too, down to `max_depth` levels,
+/// and text entries up to `text_entry_max_bytes` contribute their contents.
+pub struct ArchiveParser {
+    /// Archives larger than this are left to metadata-only indexing
+    pub max_bytes: u64,
+    pub max_depth: usize,
+    /// 0 lists entry names only
+    pub text_entry_max_bytes: u64,
+    /// Text entry contents kept across the whole archive; once spent, nested zips aren't opened either
+    pub text_budget_bytes: u64,
+    /// Nested zips larger than this are not read into memory
+    pub nested_max_bytes: u64,
+}
+
+impl ArchiveParser {
+    pub fn from_config(config: &crate::config::AppConfig) -> Self {
+        Self {
+            max_bytes: config.archive_max_mb * 1024 * 1024,
+            max_depth: config.archive_max_depth,
+            text_entry_max_bytes: config.archive_text_entry_max_bytes,
+            text_budget_bytes: MAX_ARCHIVE_TEXT_BYTES,
+            nested_max_bytes: MAX_NESTED_ARCHIVE_BYTES,
+        }
+    }
+
+    /// Append the entries of `archive` to `names` and the text of small text entries to `texts`,
+    /// recursing into nested zips. `prefix` is the path of `archive` within the outermost one;
+    /// `text_budget` is what remains of `text_budget_bytes` and is spent by each text entry kept.
+    fn list_entries<R: std::io::Read + std::io::Seek>(
+        &self,
+        archive: &mut zip::ZipArchive<R>,
+        prefix: &str,
+        depth: usize,
+        names: &mut Vec<String>,
+        texts: &mut Vec<String>,
+        text_budget: &mut u64,
+    ) -> Result<()> {
+        use std::io::Read;
+
+        for index in 0..archive.len() {
+            if names.len() >= MAX_ARCHIVE_ENTRIES {
+                return Ok(());
+            }
+            let mut entry = archive.by_index(index)?;
+            if entry.is_dir() {
+                continue;
+            }
+            let name = format!("{}{}", prefix, entry.name());
+            names.push(name.clone());
+
+            let is_zip = Path::new(entry.name())
+                .extension()
+                .is_some_and(|e| e.eq_ignore_ascii_case("zip"));
+            let nested_max_bytes = self.max_bytes.min(self.nested_max_bytes);
+            if is_zip {
+                if depth >= self.max_depth || *text_budget == 0 || entry.size() > nested_max_bytes {
+                    continue;
+                }
+                // Nested archives need seeking, so they are read into memory (bounded by nested_max_bytes)
+                let mut bytes = Vec::new();
+                entry.by_ref().take(nested_max_bytes).read_to_end(&mut bytes)?;
+                match zip::ZipArchive::new(std::io::Cursor::new(bytes)) {
+                    Ok(mut nested) => {
+                        self.list_entries(&mut nested, &format!("{}/", name), depth + 1, names, texts, text_budget)?
+                    }
+                    Err(e) => warn!("Skipping nested archive {}: {}", name, e),
+                }
+            } else if entry.size() <= self.text_entry_max_bytes.min(*text_budget) && TextParser.can_parse(entry.name()) {
+                let mut bytes = Vec::new();
+                entry.by_ref().take(self.text_entry_max_bytes.min(*text_budget)).read_to_end(&mut bytes)?;
+                *text_budget -= bytes.len() as u64;
+                if let Ok(text) = String::from_utf8(bytes) {
+                    if !text.trim().is_empty() {
+                        texts.push(format!("{}:\n{}", name, text.trim()));
+                    }
                 }
             }
         }
-        
-        Ok(text_parts.join("\n"))
+        Ok(())
+    }
+}
+
+impl DocumentParser for ArchiveParser {
+    fn can_parse(&self, file_path: &str) -> bool {
+        Path::new(file_path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.eq_ignore_ascii_case("zip"))
+            .unwrap_or(false)
+    }
+
+    fn extract_text(&self, file_path: &str) -> Result<String> {
+        let size = std::fs::metadata(file_path)?.len();
+        if size > self.max_bytes {
+            anyhow::bail!("Archive is {} bytes, over the {} byte limit", size, self.max_bytes);
+        }
+
+        let mut archive = zip::ZipArchive::new(std::fs::File::open(file_path)?)?;
+        let mut names = Vec::new();
+        let mut texts = Vec::new();
+        let mut text_budget = self.text_budget_bytes;
+        self.list_entries(&mut archive, "", 0, &mut names, &mut texts, &mut text_budget)?;
+
+        let mut text = format!("Archive contents:\n{}", names.join("\n"));
+        for entry_text in texts {
+            text.push_str("\n\n");
+            text.push_str(&entry_text);
+        }
+        Ok(text)
     }
 }
 
@@ -157,15 +417,34 @@ impl DocumentParser for ImageParser {
         // This allows semantic search on image filenames
         Ok(format!("image file: {}", file_name))
     }
+
+    fn cacheable(&self) -> bool {
+        false
+    }
 }
 
 pub struct ParserRegistry {
     parsers: Vec<Box<dyn DocumentParser>>,
     excluded_extensions: Vec<String>,
+    text_cache: Option<TextCache>,
 }
 
 impl ParserRegistry {
     pub fn new(config: &crate::config::FileTypeFilters) -> Self {
+        Self::build(config, true, None)
+    }
+
+    /// Registry for indexing, honoring the spreadsheet and archive settings as well as the file type filters
+    pub fn from_config(config: &crate::config::AppConfig) -> Self {
+        let archives = config.index_archives.then(|| ArchiveParser::from_config(config));
+        Self::build(&config.file_type_filters, config.spreadsheet_header_context, archives)
+    }
+
+    fn build(
+        config: &crate::config::FileTypeFilters,
+        spreadsheet_header_context: bool,
+        archives: Option<ArchiveParser>,
+    ) -> Self {
         let mut parsers: Vec<Box<dyn DocumentParser>> = vec![Box::new(TextParser)];
         
         // Always include image parser (images are indexed by filename)
@@ -178,7 +457,11 @@ impl ParserRegistry {
             parsers.push(Box::new(DocxParser));
         }
         if config.include_xlsx {
-            parsers.push(Box::new(XlsxParser));
+            parsers.push(Box::new(XlsxParser { header_context: spreadsheet_header_context }));
+            parsers.push(Box::new(CsvParser { header_context: spreadsheet_header_context }));
+        }
+        if let Some(archives) = archives {
+            parsers.push(Box::new(archives));
         }
         
         Self { 
@@ -186,9 +469,16 @@ impl ParserRegistry {
             excluded_extensions: config.excluded_extensions.iter()
                 .map(|s| s.trim_start_matches('.').to_lowercase())
                 .collect(),
+            text_cache: None,
         }
     }
 
+    /// Reuse text extracted earlier from an unchanged file; None keeps every extraction fresh
+    pub fn with_text_cache(mut self, text_cache: Option<TextCache>) -> Self {
+        self.text_cache = text_cache;
+        self
+    }
+
     fn is_excluded(&self, file_path: &str) -> bool {
         if self.excluded_extensions.is_empty() {
             return false;
@@ -208,13 +498,34 @@ impl ParserRegistry {
             anyhow::bail!("File type is globally excluded: {}", file_path);
         }
 
-        for parser in &self.parsers {
-            if parser.can_parse(file_path) {
-                return parser.extract_text(file_path);
-            }
+        let Some(parser) = self.parsers.iter().find(|parser| parser.can_parse(file_path)) else {
+            anyhow::bail!("No parser available for file: {}", file_path)
+        };
+        let Some(cache) = self.text_cache.as_ref().filter(|_| parser.cacheable()) else {
+            return parser.extract_text(file_path);
+        };
+
+        let metadata = std::fs::metadata(file_path)?;
+        let modified = metadata.modified()?;
+        if let Some(text) = cache.get(file_path, modified, metadata.len()) {
+            return Ok(text);
+        }
+        let text = parser.extract_text(file_path)?;
+        if let Err(e) = cache.put(file_path, modified, metadata.len(), &text) {
+            warn!("Failed to cache extracted text of {}: {}", file_path, e);
+        }
+        Ok(text)
+    }
+
+    /// Like `extract_text`, split into the parser's parts (one per sheet for workbooks); not cached
+    pub fn extract_parts(&self, file_path: &str) -> Result<Vec<String>> {
+        if self.is_excluded(file_path) {
+            anyhow::bail!("File type is globally excluded: {}", file_path);
+        }
+        match self.parsers.iter().find(|parser| parser.can_parse(file_path)) {
+            Some(parser) => parser.extract_parts(file_path),
+            None => anyhow::bail!("No parser available for file: {}", file_path),
         }
-        
-        anyhow::bail!("No parser available for file: {}", file_path)
     }
 
     pub fn can_parse(&self, file_path: &str) -> bool {
@@ -224,3 +535,74 @@ impl ParserRegistry {
         self.parsers.iter().any(|p| p.can_parse(file_path))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spreadsheet_text_leads_with_headers() {
+        let rows = parse_csv("Date,Amount,\"Note, free text\"\r\n2024-01-02,12.50,\"said \"\"hi\"\"\"\n,,\n");
+        assert_eq!(rows[0], vec!["Date", "Amount", "Note, free text"]);
+        assert_eq!(rows[1][2], "said \"hi\"");
+
+        assert_eq!(
+            sheet_text(Some("Expenses"), &rows, true),
+            "Sheet: Expenses\nColumns: Date | Amount | Note, free text\nDate Amount Note, free text\n2024-01-02 12.50 said \"hi\""
+        );
+        assert_eq!(sheet_text(None, &rows, false), "Date Amount Note, free text\n2024-01-02 12.50 said \"hi\"");
+        assert_eq!(
+            workbook_overview(&[("Expenses".to_string(), rows)]),
+            "Sheets: Expenses (Date, Amount, Note, free text)"
+        );
+    }
+
+    #[test]
+    fn test_archive_lists_nested_entries_and_small_text() {
+        use std::io::Write;
+
+        fn zip_bytes(entries: &[(&str, &[u8])]) -> Vec<u8> {
+            let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+            for (name, contents) in entries {
+                writer.start_file(*name, zip::write::SimpleFileOptions::default()).unwrap();
+                writer.write_all(contents).unwrap();
+            }
+            writer.finish().unwrap().into_inner()
+        }
+
+        let inner = zip_bytes(&[("deep/notes.txt", b"inner notes")]);
+        let outer = zip_bytes(&[
+            ("backup/Budget 2023.xlsx", b"not really a workbook"),
+            ("readme.md", b"quarterly backup"),
+            ("old.zip", &inner),
+        ]);
+        let path = std::env::temp_dir().join(format!("gist-archive-test-{}.zip", std::process::id()));
+        std::fs::write(&path, outer).unwrap();
+
+        let parser = ArchiveParser {
+            max_bytes: 1024 * 1024,
+            max_depth: 1,
+            text_entry_max_bytes: 1024,
+            text_budget_bytes: 1024,
+            nested_max_bytes: 1024 * 1024,
+        };
+        let text = parser.extract_text(path.to_str().unwrap()).unwrap();
+        assert!(text.contains("backup/Budget 2023.xlsx"));
+        assert!(text.contains("old.zip/deep/notes.txt"));
+        assert!(text.contains("readme.md:\nquarterly backup"));
+        assert!(text.contains("inner notes"));
+
+        let names_only = ArchiveParser { max_depth: 0, text_entry_max_bytes: 0, ..parser };
+        let text = names_only.extract_text(path.to_str().unwrap()).unwrap();
+        assert!(!text.contains("deep/notes.txt"));
+        assert!(!text.contains("quarterly backup"));
+
+        // readme.md spends the whole budget, so the nested zip is listed but not opened
+        let budgeted = ArchiveParser { text_budget_bytes: "quarterly backup".len() as u64, ..parser };
+        let text = budgeted.extract_text(path.to_str().unwrap()).unwrap();
+        assert!(text.contains("readme.md:\nquarterly backup"));
+        assert!(text.contains("old.zip"));
+        assert!(!text.contains("deep/notes.txt"));
+        let _ = std::fs::remove_file(&path);
+    }
+}
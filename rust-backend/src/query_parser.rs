@@ -1,34 +1,285 @@
 use chrono::{Local, NaiveDate, Datelike, TimeZone};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock};
 use tokio::sync::RwLock;
 use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{debug, info, warn};
 
 use crate::api::search::{DateRange, FilterOptions};
+use crate::spell::{Correction, Vocabulary};
+
+/// A named group of file extensions (e.g. "image") and the query words that refer to it
+pub struct FileTypeCategory {
+    pub name: &'static str,
+    pub keywords: &'static [&'static str],
+    pub extensions: &'static [&'static str],
+}
+
+pub const FILE_TYPE_CATEGORIES: &[FileTypeCategory] = &[
+    FileTypeCategory { name: "pdf", keywords: &["pdf", "pdf files", "pdf documents", "pdfs", ".pdf"], extensions: &["pdf"] },
+    FileTypeCategory { name: "document", keywords: &["word", "word documents", "docx", "doc files", "documents", "microsoft word", "ms word", ".docx", ".doc"], extensions: &["docx", "doc"] },
+    FileTypeCategory { name: "spreadsheet", keywords: &["excel", "spreadsheet", "spreadsheets", "xlsx", "xls files", "microsoft excel", "ms excel", ".xlsx", ".xls"], extensions: &["xlsx", "xls"] },
+    FileTypeCategory { name: "text", keywords: &["text files", "text", "txt files", "plain text", ".txt"], extensions: &["txt"] },
+    FileTypeCategory { name: "image", keywords: &["images", "image", "pictures", "photos", "picture", "jpg", "jpeg", "png", "gif", "bmp", "webp", ".jpg", ".jpeg", ".png", ".gif", ".bmp", ".webp"], extensions: &["jpg", "jpeg", "png", "gif", "bmp", "webp"] },
+    FileTypeCategory { name: "video", keywords: &["videos", "video", "mp4", "movie", "movies", "avi", "mov", ".mp4", ".avi", ".mov"], extensions: &["mp4", "avi", "mov"] },
+    FileTypeCategory { name: "archive", keywords: &["zip", "zip files", "archives", "compressed", ".zip", ".rar", ".7z"], extensions: &["zip", "rar", "7z"] },
+    FileTypeCategory { name: "audio", keywords: &["audio", "music", "songs", "mp3", "sound", ".mp3", ".wav", ".flac"], extensions: &["mp3", "wav", "flac"] },
+    FileTypeCategory { name: "presentation", keywords: &["powerpoint", "presentation", "ppt", "pptx", ".pptx", ".ppt"], extensions: &["pptx", "ppt"] },
+    FileTypeCategory { name: "csv", keywords: &["csv", "csv files", "comma separated", ".csv"], extensions: &["csv"] },
+];
+
+/// Look up a category by name, accepting plurals ("images") and any case
+pub fn file_type_category(name: &str) -> Option<&'static FileTypeCategory> {
+    let name = name.trim().to_lowercase();
+    let singular = name.strip_suffix('s').unwrap_or(&name);
+    FILE_TYPE_CATEGORIES.iter().find(|c| c.name == name || c.name == singular)
+}
+
+/// Expand a mix of raw extensions and category names into a deduplicated extension list
+pub fn expand_file_types(file_types: &[String]) -> Vec<String> {
+    let mut expanded: Vec<String> = Vec::new();
+    for file_type in file_types {
+        let normalized = file_type.trim().trim_start_matches('.').to_lowercase();
+        let extensions = match file_type_category(&normalized) {
+            Some(category) => category.extensions.iter().map(|e| e.to_string()).collect(),
+            None => vec![normalized],
+        };
+        for ext in extensions {
+            if !ext.is_empty() && !expanded.contains(&ext) {
+                expanded.push(ext);
+            }
+        }
+    }
+    expanded
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ParsedQuery {
     pub query: String,
     pub filters: FilterOptions,
+    /// `query` as it was before spelling correction, when anything was corrected
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_query: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub corrections: Vec<Correction>,
+}
+
+/// LLM parse cache lifetime when nothing is persisted to disk
+const MEMORY_CACHE_TTL_SECS: u64 = 300;
+
+/// Queries scoring at least this complexity (0.0 to 1.0) are sent to the LLM parser
+pub const LLM_COMPLEXITY_THRESHOLD: f32 = 0.3;
+
+/// Month names that open a query as verbs more often than as dates ("May I find ...")
+const VERB_MONTHS: [&str; 2] = ["may", "march"];
+
+/// Month names and abbreviations recognized in queries, with their month number
+const MONTH_NAMES: [(&str, u32); 24] = [
+    ("january", 1), ("jan", 1), ("february", 2), ("feb", 2), ("march", 3), ("mar", 3),
+    ("april", 4), ("apr", 4), ("may", 5), ("june", 6), ("jun", 6), ("july", 7), ("jul", 7),
+    ("august", 8), ("aug", 8), ("september", 9), ("sept", 9), ("sep", 9),
+    ("october", 10), ("oct", 10), ("november", 11), ("nov", 11), ("december", 12), ("dec", 12),
+];
+
+/// Per month name: the name, optionally after from/in/during and optionally followed by a year
+static DATED_MONTH_PATTERNS: LazyLock<HashMap<&'static str, regex::Regex>> = LazyLock::new(|| {
+    MONTH_NAMES
+        .iter()
+        .map(|(month_name, _)| {
+            let pattern = format!(r"(?i)\b(?:(?:from|in|during)\s+)?({})\b(\s+\d{{4}}\b)?", regex::escape(month_name));
+            (*month_name, regex::Regex::new(&pattern).expect("month pattern is valid"))
+        })
+        .collect()
+});
+
+/// Components of a query's complexity score; `total` is their sum, capped at 1.0
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryComplexity {
+    pub length: f32,
+    pub filter: f32,
+    pub semantic: f32,
+    pub ambiguity: f32,
+    pub structure: f32,
+    pub total: f32,
+}
+
+/// How `parse` routes a query: pattern matching alone, or the LLM fallback
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryAnalysis {
+    pub complexity: QueryComplexity,
+    pub threshold: f32,
+    /// Pattern matching found filters, so the LLM is never consulted
+    pub pattern_filters_found: bool,
+    pub uses_llm: bool,
+}
+
+type LlmCache = HashMap<String, (ParsedQuery, u64)>;
+
+/// LLM parses shared by every request through `AppState`, loaded from disk once at startup
+#[derive(Default)]
+pub struct QueryCache {
+    entries: RwLock<LlmCache>,
+    /// Held while writing to disk, so snapshots land one at a time and the newest lands last
+    writer: tokio::sync::Mutex<()>,
+}
+
+impl QueryCache {
+    /// Where the cache is persisted when `persist_query_cache` is on
+    pub fn default_file() -> PathBuf {
+        crate::config::AppConfig::data_dir().join("query_cache.json")
+    }
+
+    /// Read the unexpired entries of `cache_file`; a missing or unreadable file starts empty
+    #[tracing::instrument(name = "QUERY PARSER", skip_all)]
+    pub async fn load(cache_file: &Path, ttl_secs: u64) -> Self {
+        let cache = Self::default();
+        let content = match tokio::fs::read_to_string(cache_file).await {
+            Ok(content) => content,
+            Err(_) => return cache,
+        };
+        match serde_json::from_str::<LlmCache>(&content) {
+            Ok(mut entries) => {
+                let now = QueryParser::now_secs();
+                entries.retain(|_, (_, ts)| now.saturating_sub(*ts) < ttl_secs);
+                *cache.entries.write().await = entries;
+            }
+            Err(e) => warn!("Ignoring unreadable cache {}: {}", cache_file.display(), e),
+        }
+        cache
+    }
+
+    async fn get(&self, key: &str, ttl_secs: u64) -> Option<ParsedQuery> {
+        let entries = self.entries.read().await;
+        let (parsed, timestamp) = entries.get(key)?;
+        (QueryParser::now_secs().saturating_sub(*timestamp) < ttl_secs).then(|| parsed.clone())
+    }
+
+    /// Add a parse, drop expired entries, and write the cache to `cache_file` when given
+    async fn insert(&self, key: String, parsed: ParsedQuery, ttl_secs: u64, cache_file: Option<&Path>) {
+        let now = QueryParser::now_secs();
+        {
+            let mut entries = self.entries.write().await;
+            entries.insert(key, (parsed, now));
+            entries.retain(|_, (_, ts)| now.saturating_sub(*ts) < ttl_secs);
+        }
+        if let Some(cache_file) = cache_file {
+            self.persist(cache_file).await;
+        }
+    }
+
+    /// Write the cache to disk via a uniquely named temp file, so a crash never leaves it
+    /// half-written and concurrent writers never share a temp file
+    #[tracing::instrument(name = "QUERY PARSER", skip_all)]
+    async fn persist(&self, cache_file: &Path) {
+        static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+        let _writing = self.writer.lock().await;
+        let result = async {
+            // Snapshot under the writer lock, so a later insert is never overwritten by an older one
+            let json = serde_json::to_string(&*self.entries.read().await)?;
+            if let Some(parent) = cache_file.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            let tmp_file = cache_file.with_extension(format!(
+                "json.{}-{}.tmp",
+                std::process::id(),
+                TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+            ));
+            tokio::fs::write(&tmp_file, json).await?;
+            if let Err(e) = tokio::fs::rename(&tmp_file, cache_file).await {
+                let _ = tokio::fs::remove_file(&tmp_file).await;
+                return Err(e.into());
+            }
+            Ok::<(), anyhow::Error>(())
+        }.await;
+        if let Err(e) = result {
+            warn!("Failed to persist cache to {}: {}", cache_file.display(), e);
+        }
+    }
 }
 
 pub struct QueryParser {
-    llm_cache: Arc<RwLock<HashMap<String, (ParsedQuery, u64)>>>,
+    llm_cache: Arc<QueryCache>,
     llm_model: String,
+    ollama_url: String,
+    cache_ttl_secs: u64,
+    cache_file: Option<PathBuf>,
+    vocabulary: Option<Arc<Vocabulary>>,
+    allow_future_dates: bool,
 }
 
 impl QueryParser {
     pub fn new(llm_model: String) -> Self {
         Self {
-            llm_cache: Arc::new(RwLock::new(HashMap::new())),
+            llm_cache: Arc::new(QueryCache::default()),
             llm_model,
+            ollama_url: crate::config::DEFAULT_OLLAMA_BASE_URL.to_string(),
+            cache_ttl_secs: MEMORY_CACHE_TTL_SECS,
+            cache_file: None,
+            vocabulary: None,
+            allow_future_dates: false,
         }
     }
 
-    /// Parse natural language query into structured query and filters
-    /// Uses pattern matching first, then LLM fallback for complex queries
+    pub fn with_ollama_url(mut self, ollama_url: &str) -> Self {
+        self.ollama_url = ollama_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Share `cache` (normally `AppState::query_cache`) instead of a cache of this parser's own
+    pub fn with_cache(mut self, cache: Arc<QueryCache>) -> Self {
+        self.llm_cache = cache;
+        self
+    }
+
+    /// Write new parses through to `cache_file` so they survive restarts, and keep entries
+    /// for `ttl_secs` instead of the in-memory default
+    pub fn with_persistent_cache(mut self, cache_file: PathBuf, ttl_secs: u64) -> Self {
+        self.cache_ttl_secs = ttl_secs;
+        self.cache_file = Some(cache_file);
+        self
+    }
+
+    /// Keep date filters that lie in the future ("deadlines next March") instead of rejecting them
+    /// unless explicitly mentioned and capping them at today
+    pub fn with_future_dates(mut self, allow_future_dates: bool) -> Self {
+        self.allow_future_dates = allow_future_dates;
+        self
+    }
+
+    /// Correct misspelled words in the parsed query against the indexed corpus
+    pub fn with_spell_correction(mut self, vocabulary: Arc<Vocabulary>) -> Self {
+        self.vocabulary = Some(vocabulary);
+        self
+    }
+
+    fn now_secs() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0)
+    }
+
+    /// Parse natural language query into structured query and filters, then spell-correct
+    /// what remains of the query (filter words such as month names are never corrected)
     pub async fn parse(&self, query: &str) -> ParsedQuery {
+        let mut parsed = self.parse_filters(query).await;
+        if let Some(ref vocabulary) = self.vocabulary {
+            let (corrected, corrections) = vocabulary.correct(&parsed.query);
+            if !corrections.is_empty() {
+                info!("Spell-corrected query '{}' -> '{}'", parsed.query, corrected);
+                parsed.original_query = Some(std::mem::replace(&mut parsed.query, corrected));
+                parsed.corrections = corrections;
+            }
+        }
+        parsed
+    }
+
+    /// Uses pattern matching first, then LLM fallback for complex queries
+    async fn parse_filters(&self, query: &str) -> ParsedQuery {
         let mut remaining_query = query.to_string();
         let mut filters = FilterOptions {
             date_range: None,
@@ -37,7 +288,7 @@ impl QueryParser {
         };
 
         // Extract date filters
-        if let Some((date_range, cleaned_query)) = Self::extract_date_filters(&remaining_query) {
+        if let Some((date_range, cleaned_query)) = self.extract_date_filters(&remaining_query) {
             filters.date_range = Some(date_range);
             remaining_query = cleaned_query;
         }
@@ -64,6 +315,8 @@ impl QueryParser {
             return ParsedQuery {
                 query: remaining_query.trim().to_string(),
                 filters,
+                original_query: None,
+                corrections: Vec::new(),
             };
         }
 
@@ -72,33 +325,15 @@ impl QueryParser {
         if !self.llm_model.is_empty() && Self::should_try_llm(query) {
             // Check cache first
             let cache_key = query.to_lowercase().trim().to_string();
-            {
-                let cache = self.llm_cache.read().await;
-                if let Some((cached_result, timestamp)) = cache.get(&cache_key) {
-                    // Cache valid for 5 minutes
-                    let now = SystemTime::now()
-                        .duration_since(UNIX_EPOCH)
-                        .unwrap()
-                        .as_secs();
-                    if now - *timestamp < 300 {
-                        return cached_result.clone();
-                    }
-                }
+            if let Some(cached_result) = self.llm_cache.get(&cache_key, self.cache_ttl_secs).await {
+                return cached_result;
             }
 
             // Try LLM parsing
             if let Ok(llm_result) = self.parse_with_llm(query).await {
-                // Cache the result
-                let now = SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap()
-                    .as_secs();
-                let mut cache = self.llm_cache.write().await;
-                cache.insert(cache_key, (llm_result.clone(), now));
-                
-                // Clean old cache entries (older than 5 minutes)
-                cache.retain(|_, (_, ts)| now - *ts < 300);
-                
+                self.llm_cache
+                    .insert(cache_key, llm_result.clone(), self.cache_ttl_secs, self.cache_file.as_deref())
+                    .await;
                 return llm_result;
             }
         }
@@ -107,6 +342,8 @@ impl QueryParser {
         ParsedQuery {
             query: remaining_query.trim().to_string(),
             filters,
+            original_query: None,
+            corrections: Vec::new(),
         }
     }
 
@@ -114,16 +351,37 @@ impl QueryParser {
     /// Uses a scoring-based approach that considers multiple factors
     fn should_try_llm(query: &str) -> bool {
         let complexity_score = Self::calculate_query_complexity(query);
-        let threshold = 0.3; // Threshold for LLM parsing (0.0 to 1.0)
         // Lower threshold (0.3) allows more queries to use LLM when they have filter indicators
         // This helps catch complex queries that pattern matching might miss
         
-        complexity_score >= threshold
+        complexity_score >= LLM_COMPLEXITY_THRESHOLD
+    }
+
+    /// Explain how `parse` would handle `query` without calling the LLM
+    pub fn analyze(&self, query: &str) -> QueryAnalysis {
+        let filters = self.parse_pattern_only(query).filters;
+        let pattern_filters_found = filters.date_range.is_some()
+            || filters.file_types.is_some()
+            || filters.folder_paths.is_some();
+        let complexity = Self::query_complexity(query);
+        QueryAnalysis {
+            uses_llm: !pattern_filters_found
+                && !self.llm_model.is_empty()
+                && complexity.total >= LLM_COMPLEXITY_THRESHOLD,
+            complexity,
+            threshold: LLM_COMPLEXITY_THRESHOLD,
+            pattern_filters_found,
+        }
     }
 
     /// Calculate query complexity score (0.0 to 1.0)
     /// Higher scores indicate more complex queries that benefit from LLM parsing
     fn calculate_query_complexity(query: &str) -> f32 {
+        Self::query_complexity(query).total
+    }
+
+    #[tracing::instrument(name = "QUERY_COMPLEXITY", skip_all)]
+    pub fn query_complexity(query: &str) -> QueryComplexity {
         let query_lower = query.to_lowercase();
         let words: Vec<&str> = query_lower.split_whitespace().collect();
         let word_count = words.len();
@@ -223,11 +481,18 @@ impl QueryParser {
         
         // Debug logging (can be removed in production)
         if total_score > 0.3 {
-            eprintln!("[QUERY_COMPLEXITY] Query: '{}' | Score: {:.2} (length: {:.2}, filter: {:.2}, semantic: {:.2}, ambiguity: {:.2}, structure: {:.2})",
+            debug!("Query: '{}' | Score: {:.2} (length: {:.2}, filter: {:.2}, semantic: {:.2}, ambiguity: {:.2}, structure: {:.2})",
                 query, total_score, length_score, filter_score, semantic_score, ambiguity_score, structure_score);
         }
         
-        total_score.min(1.0)
+        QueryComplexity {
+            length: length_score,
+            filter: filter_score,
+            semantic: semantic_score,
+            ambiguity: ambiguity_score,
+            structure: structure_score,
+            total: total_score.min(1.0),
+        }
     }
 
     /// Heuristic check: does the original query contain explicit date-like tokens?
@@ -302,13 +567,33 @@ impl QueryParser {
             .map_or(false, |re| re.is_match(query))
     }
 
+    /// Byte range of the first mention of `month_name` that reads as a date rather than a plain word:
+    /// after from/in/during, followed by a year, or capitalized on its own ("December", not "March Madness").
+    /// "may" and "march" opening a query are taken as verbs even when capitalized.
+    fn find_dated_month(query: &str, month_name: &str) -> Option<std::ops::Range<usize>> {
+        let re = DATED_MONTH_PATTERNS.get(month_name)?;
+        re.captures_iter(query).find_map(|caps| {
+            let phrase = caps.get(0)?;
+            let month = caps.get(1)?;
+            let has_preposition = month.start() > phrase.start();
+            let has_year = caps.get(2).is_some();
+            let starts_upper = |word: &str| word.starts_with(|c: char| c.is_uppercase());
+            let part_of_name = query[month.end()..]
+                .split_whitespace()
+                .next()
+                .is_some_and(|next| starts_upper(next) && next.chars().any(|c| c.is_lowercase()));
+            let opens_with_verb = month.start() == 0 && VERB_MONTHS.contains(&month_name);
+            let capitalized = starts_upper(month.as_str()) && !part_of_name && !opens_with_verb;
+            (has_preposition || has_year || capitalized).then(|| phrase.start()..month.end())
+        })
+    }
+
     /// Parse query using LLM (Ollama)
+    #[tracing::instrument(name = "DATE_FILTER", skip_all)]
     async fn parse_with_llm(&self, query: &str) -> anyhow::Result<ParsedQuery> {
         use reqwest::Client;
         use serde::{Deserialize, Serialize};
         
-        const OLLAMA_URL: &str = "http://localhost:11434";
-        
         let prompt = format!(
             r#"Parse this search query into JSON format. Extract filters and remove filter words from the search query.
 
@@ -355,7 +640,7 @@ Return ONLY valid JSON, no other text:
         };
 
         let response = client
-            .post(&format!("{}/api/generate", OLLAMA_URL))
+            .post(format!("{}/api/generate", self.ollama_url))
             .json(&request)
             .send()
             .await?;
@@ -394,7 +679,7 @@ Return ONLY valid JSON, no other text:
             year: Option<i32>,
         }
 
-        let mut parsed: LlmParsedQuery = serde_json::from_str(json_text)?;
+        let mut parsed: LlmParsedQuery = crate::llm_json::parse_lenient(json_text, "query parser")?;
 
         // Safety guard: if the original query doesn't contain any explicit date-like
         // tokens, ignore any date_filter the LLM tries to invent.
@@ -415,7 +700,7 @@ Return ONLY valid JSON, no other text:
                 let current_year = now.year();
                 
                 // Check if the date is in the future
-                let is_future = Self::is_future_date(date_filter.month, date_filter.year);
+                let is_future = !self.allow_future_dates && Self::is_future_date(date_filter.month, date_filter.year);
                 
                 // If future date, check if it's explicitly mentioned in the query
                 if is_future {
@@ -445,11 +730,11 @@ Return ONLY valid JSON, no other text:
                     
                     // Only allow future dates if explicitly mentioned
                     if !month_explicitly_mentioned && !year_explicitly_mentioned {
-                        eprintln!("[DATE_FILTER] Rejecting future date filter (month={:?}, year={:?}) - not explicitly mentioned in query", 
+                        debug!("Rejecting future date filter (month={:?}, year={:?}) - not explicitly mentioned in query", 
                                  date_filter.month, date_filter.year);
                         // Don't set the date filter
                     } else {
-                        eprintln!("[DATE_FILTER] Allowing future date filter (month={:?}, year={:?}) - explicitly mentioned in query", 
+                        debug!("Allowing future date filter (month={:?}, year={:?}) - explicitly mentioned in query", 
                                  date_filter.month, date_filter.year);
                     }
                 }
@@ -499,7 +784,7 @@ Return ONLY valid JSON, no other text:
                                         if let Some(end_dt) = Local.from_local_datetime(&end_naive).single() {
                                             let end_ts = end_dt.timestamp();
                                             // Cap to current date if future
-                                            if end_ts > now_ts {
+                                            if end_ts > now_ts && !self.allow_future_dates {
                                                 now_ts
                                             } else {
                                                 end_ts
@@ -525,13 +810,13 @@ Return ONLY valid JSON, no other text:
                                 if let Some(start_dt) = Local.from_local_datetime(&start_naive).single() {
                                     let start_ts = start_dt.timestamp();
                                     // Only set start if not in the future (unless explicitly mentioned)
-                                    if start_ts <= now_ts || is_future {
+                                    if start_ts <= now_ts || is_future || self.allow_future_dates {
                                         date_range.start = Some(start_ts);
                                         date_range.end = Some(end_date_ts);
-                                        eprintln!("[DATE_FILTER] Setting date range: month={}, year={}, start={}, end={}", 
+                                        debug!("Setting date range: month={}, year={}, start={}, end={}", 
                                                 month, year, start_ts, end_date_ts);
                                     } else {
-                                        eprintln!("[DATE_FILTER] Skipping future date range: month={}, year={}", month, year);
+                                        debug!("Skipping future date range: month={}, year={}", month, year);
                                     }
                                 }
                             }
@@ -543,7 +828,7 @@ Return ONLY valid JSON, no other text:
                             if let Some(end_naive) = end_date.and_hms_opt(23, 59, 59) {
                                 if let Some(end_dt) = Local.from_local_datetime(&end_naive).single() {
                                     let ts = end_dt.timestamp();
-                                    if ts > now_ts {
+                                    if ts > now_ts && !self.allow_future_dates {
                                         now_ts
                                     } else {
                                         ts
@@ -562,13 +847,13 @@ Return ONLY valid JSON, no other text:
                             if let Some(start_naive) = start_date.and_hms_opt(0, 0, 0) {
                                 if let Some(start_dt) = Local.from_local_datetime(&start_naive).single() {
                                     let start_ts = start_dt.timestamp();
-                                    if start_ts <= now_ts || (year > current_year && query.contains(&year.to_string())) {
+                                    if start_ts <= now_ts || self.allow_future_dates || (year > current_year && query.contains(&year.to_string())) {
                                         date_range.start = Some(start_ts);
                                         date_range.end = Some(end_ts);
-                                        eprintln!("[DATE_FILTER] Setting year range: year={}, start={}, end={}", 
+                                        debug!("Setting year range: year={}, start={}, end={}", 
                                                 year, start_ts, end_ts);
                                     } else {
-                                        eprintln!("[DATE_FILTER] Skipping future year range: year={}", year);
+                                        debug!("Skipping future year range: year={}", year);
                                     }
                                 }
                             }
@@ -597,6 +882,8 @@ Return ONLY valid JSON, no other text:
         Ok(ParsedQuery {
             query: parsed.search_query.trim().to_string(),
             filters,
+            original_query: None,
+            corrections: Vec::new(),
         })
     }
 
@@ -610,7 +897,7 @@ Return ONLY valid JSON, no other text:
         };
 
         // Extract date filters
-        if let Some((date_range, cleaned_query)) = Self::extract_date_filters(&remaining_query) {
+        if let Some((date_range, cleaned_query)) = self.extract_date_filters(&remaining_query) {
             filters.date_range = Some(date_range);
             remaining_query = cleaned_query;
         }
@@ -630,11 +917,14 @@ Return ONLY valid JSON, no other text:
         ParsedQuery {
             query: remaining_query.trim().to_string(),
             filters,
+            original_query: None,
+            corrections: Vec::new(),
         }
     }
 
     /// Extract date filters from query - Enhanced with more patterns
-    fn extract_date_filters(query: &str) -> Option<(DateRange, String)> {
+    #[tracing::instrument(name = "DATE_FILTER", skip_all)]
+    fn extract_date_filters(&self, query: &str) -> Option<(DateRange, String)> {
         let query_lower = query.to_lowercase();
         let mut cleaned_query = query.to_string();
         let mut date_range = DateRange {
@@ -663,24 +953,8 @@ Return ONLY valid JSON, no other text:
 
         // Pattern: "from December", "in December", "December", "during December"
         // Use word boundaries to avoid false positives (e.g. "mar" in "market", "may" in "maybe")
-        let month_patterns = vec![
-            ("january", 1), ("jan", 1), ("february", 2), ("feb", 2), ("march", 3), ("mar", 3),
-            ("april", 4), ("apr", 4), ("may", 5), ("june", 6), ("jun", 6), ("july", 7), ("jul", 7),
-            ("august", 8), ("aug", 8), ("september", 9), ("sept", 9), ("sep", 9),
-            ("october", 10), ("oct", 10), ("november", 11), ("nov", 11), ("december", 12), ("dec", 12),
-        ];
-
-        for (month_name, month_num) in month_patterns {
-            let escaped = regex::escape(month_name);
-            let word_boundary_re = regex::Regex::new(&format!(
-                r"(?i)(?:\bfrom\s+{}|\bin\s+{}|\bduring\s+{}|\b{})\b",
-                &escaped, &escaped, &escaped, &escaped
-            )).ok();
-
-            if let Some(ref re) = word_boundary_re {
-                if !re.is_match(&query_lower) {
-                    continue;
-                }
+        for (month_name, month_num) in MONTH_NAMES {
+            if let Some(month_match) = Self::find_dated_month(&cleaned_query, month_name) {
 
                 // Check if year is specified with month (e.g., "December 2023")
                 let month_year_pattern = regex::Regex::new(
@@ -705,8 +979,24 @@ Return ONLY valid JSON, no other text:
                     Some(current_year)
                 };
 
+                // With future dates allowed, "next March" is the coming March rather than this year's
+                let mut month_match = month_match;
+                let mut year = year;
+                if self.allow_future_dates {
+                    let next_month = regex::Regex::new(&format!(r"(?i)\bnext\s+{}\b", regex::escape(month_name)))
+                        .ok()
+                        .and_then(|re| re.find(&cleaned_query))
+                        .filter(|next| next.end() == month_match.end);
+                    if let Some(next) = next_month {
+                        if year == Some(current_year) && month_num <= current_month {
+                            year = Some(current_year + 1);
+                        }
+                        month_match = next.start()..month_match.end;
+                    }
+                }
+
                 // Check if this is a future date
-                let is_future = Self::is_future_date(Some(month_num), year);
+                let is_future = !self.allow_future_dates && Self::is_future_date(Some(month_num), year);
 
                 // Only set future dates if explicitly mentioned (which they are, since we matched the pattern)
                 // But still validate and cap to current date if needed
@@ -736,7 +1026,7 @@ Return ONLY valid JSON, no other text:
                                             if let Some(end_dt) = Local.from_local_datetime(&end_naive).single() {
                                                 let ts = end_dt.timestamp();
                                                 // Cap to current date if future
-                                                if ts > now_ts {
+                                                if ts > now_ts && !self.allow_future_dates {
                                                     now_ts
                                                 } else {
                                                     ts
@@ -755,25 +1045,26 @@ Return ONLY valid JSON, no other text:
                                 };
 
                                 // Only set if start is not in future (unless explicitly mentioned)
-                                if start_ts <= now_ts || is_future {
+                                if start_ts <= now_ts || is_future || self.allow_future_dates {
                                     date_range.start = Some(start_ts);
                                     date_range.end = Some(end_ts);
-                                    eprintln!("[DATE_FILTER] extract_date_filters: Setting month={}, year={}, start={}, end={}",
+                                    debug!("extract_date_filters: Setting month={}, year={}, start={}, end={}",
                                             month_num, year_val, start_ts, end_ts);
                                 } else {
-                                    eprintln!("[DATE_FILTER] extract_date_filters: Skipping future month={}, year={}",
+                                    debug!("extract_date_filters: Skipping future month={}, year={}",
                                             month_num, year_val);
                                 }
                             }
                         }
                     }
                 } else {
-                    eprintln!("[DATE_FILTER] extract_date_filters: Rejecting future date - month={}, year={:?}, not explicitly mentioned",
+                    debug!("extract_date_filters: Rejecting future date - month={}, year={:?}, not explicitly mentioned",
                             month_num, year);
                 }
 
                 // Remove matched month phrase from query
-                cleaned_query = re.replace(&cleaned_query, "").trim().to_string();
+                cleaned_query.replace_range(month_match, "");
+                cleaned_query = cleaned_query.trim().to_string();
                 break;
             }
         }
@@ -942,22 +1233,6 @@ Return ONLY valid JSON, no other text:
         let mut cleaned_query = query.to_string();
         let mut file_types = Vec::new();
 
-        // Enhanced file type mappings - map keywords to actual extensions
-        // Format: (primary_ext, keywords, all_extensions)
-        let type_patterns: Vec<(&str, Vec<&str>, Vec<&str>)> = vec![
-            ("pdf", vec!["pdf", "pdf files", "pdf documents", "pdfs", ".pdf"], vec!["pdf"]),
-            ("docx", vec!["word", "word documents", "docx", "doc files", "documents", "microsoft word", "ms word", ".docx", ".doc"], vec!["docx", "doc"]),
-            ("xlsx", vec!["excel", "spreadsheet", "spreadsheets", "xlsx", "xls files", "microsoft excel", "ms excel", ".xlsx", ".xls"], vec!["xlsx", "xls"]),
-            ("txt", vec!["text files", "text", "txt files", "plain text", ".txt"], vec!["txt"]),
-            // Images: map to ALL image extensions
-            ("jpg", vec!["images", "image", "pictures", "photos", "picture", "jpg", "jpeg", "png", "gif", "bmp", "webp", ".jpg", ".jpeg", ".png", ".gif", ".bmp", ".webp"], vec!["jpg", "jpeg", "png", "gif", "bmp", "webp"]),
-            ("mp4", vec!["videos", "video", "mp4", "movie", "movies", "avi", "mov", ".mp4", ".avi", ".mov"], vec!["mp4", "avi", "mov"]),
-            ("zip", vec!["zip", "zip files", "archives", "compressed", ".zip", ".rar", ".7z"], vec!["zip", "rar", "7z"]),
-            ("mp3", vec!["audio", "music", "songs", "mp3", "sound", ".mp3", ".wav", ".flac"], vec!["mp3", "wav", "flac"]),
-            ("pptx", vec!["powerpoint", "presentation", "ppt", "pptx", ".pptx", ".ppt"], vec!["pptx", "ppt"]),
-            ("csv", vec!["csv", "csv files", "comma separated", ".csv"], vec!["csv"]),
-        ];
-
         // Also check for explicit file extensions in query
         let ext_pattern = regex::Regex::new(r"\.([a-z0-9]{2,4})\b").ok()?;
         for cap in ext_pattern.captures_iter(&query_lower) {
@@ -969,8 +1244,9 @@ Return ONLY valid JSON, no other text:
             }
         }
 
-        for (primary_ext, patterns, all_extensions) in type_patterns {
-            for pattern in &patterns {
+        // Map category keywords to ALL extensions in that category
+        for category in FILE_TYPE_CATEGORIES {
+            for pattern in category.keywords {
                 // Use word boundaries to avoid partial matches
                 let pattern_re = regex::Regex::new(&format!(r"\b{}\b", regex::escape(pattern))).ok();
                 let matched = if let Some(ref re) = pattern_re {
@@ -981,7 +1257,7 @@ Return ONLY valid JSON, no other text:
                 
                 if matched {
                     // Add ALL extensions for this file type, not just the primary one
-                    for ext in all_extensions {
+                    for ext in category.extensions {
                         if !file_types.contains(&ext.to_string()) {
                             file_types.push(ext.to_string());
                         }
@@ -1157,6 +1433,15 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_expand_file_types_accepts_categories() {
+        let expanded = expand_file_types(&["image".to_string(), ".PDF".to_string(), "Videos".to_string()]);
+        assert!(expanded.contains(&"png".to_string()));
+        assert!(expanded.contains(&"pdf".to_string()));
+        assert!(expanded.contains(&"mov".to_string()));
+        assert_eq!(expand_file_types(&["rs".to_string()]), vec!["rs".to_string()]);
+    }
+
     #[test]
     fn test_pattern_only_parse() {
         let parser = QueryParser::new("".to_string());
@@ -1166,6 +1451,37 @@ mod tests {
         assert!(result.filters.date_range.is_some());
     }
 
+    #[test]
+    fn test_month_words_need_date_context() {
+        let parser = QueryParser::new("".to_string());
+        for query in ["may I find my notes", "march madness bracket", "the marching band", "May I find my notes"] {
+            assert!(parser.parse_pattern_only(query).filters.date_range.is_none(), "{}", query);
+        }
+
+        for query in ["notes in may", "notes march 2024", "budget from Dec", "report December"] {
+            assert!(parser.parse_pattern_only(query).filters.date_range.is_some(), "{}", query);
+        }
+    }
+
+    #[test]
+    fn test_future_dates_when_allowed() {
+        let now = Local::now().timestamp();
+        let parser = QueryParser::new("".to_string()).with_future_dates(true);
+
+        let range = parser.parse_pattern_only("deadlines December 2099").filters.date_range.unwrap();
+        assert!(range.start.unwrap() > now && range.end.unwrap() > range.start.unwrap());
+
+        let parsed = parser.parse_pattern_only("meeting notes for next March");
+        let range = parsed.filters.date_range.unwrap();
+        assert_eq!(range.month, Some(3));
+        assert!(range.start.unwrap() > now);
+        assert!(!parsed.query.contains("next"));
+
+        // Without the opt-in a future month is cut off at today
+        let range = QueryParser::new("".to_string()).parse_pattern_only("deadlines December 2099").filters.date_range.unwrap();
+        assert!(range.end.unwrap() <= Local::now().timestamp());
+    }
+
     #[test]
     fn test_query_complexity_scoring() {
         // Simple queries should have low scores
@@ -1192,6 +1508,15 @@ mod tests {
         assert!(very_complex >= 0.6);
     }
 
+    #[test]
+    fn test_analyze_skips_llm_when_patterns_match() {
+        let parser = QueryParser::new("llama3.2:1b".to_string());
+        let analysis = parser.analyze("linear algebra homework from December 2023 in Downloads");
+        assert!(analysis.complexity.total >= analysis.threshold);
+        assert!(analysis.pattern_filters_found);
+        assert!(!analysis.uses_llm);
+    }
+
     #[test]
     fn test_should_try_llm() {
         // Simple queries should not trigger LLM
@@ -1215,4 +1540,29 @@ mod tests {
             "linear algebra homework from December 2023 in Downloads"
         ));
     }
+
+    #[tokio::test]
+    async fn test_query_cache_persists_concurrent_inserts() {
+        let dir = std::env::temp_dir().join(format!("gist-query-cache-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let cache_file = dir.join("query_cache.json");
+        let parsed = QueryParser::new("".to_string()).parse("homework").await;
+
+        let cache = Arc::new(QueryCache::default());
+        let inserts = (0..8).map(|i| {
+            let (cache, parsed, cache_file) = (cache.clone(), parsed.clone(), cache_file.clone());
+            tokio::spawn(async move { cache.insert(format!("query {}", i), parsed, 3600, Some(&cache_file)).await })
+        });
+        for insert in inserts.collect::<Vec<_>>() {
+            insert.await.unwrap();
+        }
+
+        let loaded = QueryCache::load(&cache_file, 3600).await;
+        for i in 0..8 {
+            assert!(loaded.get(&format!("query {}", i), 3600).await.is_some());
+        }
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1, "no temp files left behind");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
@@ -2,6 +2,8 @@
 // Note: For simplicity, we're using linear search with cosine similarity
 // For better performance with large datasets, consider using HNSW or other approximate nearest neighbor algorithms
 
+use crate::config::SimilarityMetric;
+
 pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
     if a.len() != b.len() {
         return 0.0;
@@ -18,10 +20,121 @@ pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
     dot_product / (norm_a * norm_b)
 }
 
+/// Scale a vector to unit length in place; zero vectors are left as-is
+pub fn normalize(v: &mut [f32]) {
+    let norm: f32 = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        v.iter_mut().for_each(|x| *x /= norm);
+    }
+}
+
+/// Cosine similarity of two vectors already scaled to unit length
+pub fn dot_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+
+    a.iter().zip(b.iter()).map(|(x, y)| x * y).sum()
+}
+
+/// `1 / (1 + distance)`: 1.0 for identical vectors, falling toward 0 as they move apart
+pub fn euclidean_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+
+    let distance: f32 = a.iter().zip(b.iter()).map(|(x, y)| (x - y) * (x - y)).sum::<f32>().sqrt();
+    1.0 / (1.0 + distance)
+}
+
+/// Score `b` against `a` with `metric`, higher meaning closer. For `Cosine` both must
+/// already be unit length (see `prepare_vector`), which makes it a plain dot product.
+pub fn vector_similarity(metric: SimilarityMetric, a: &[f32], b: &[f32]) -> f32 {
+    match metric {
+        SimilarityMetric::Cosine | SimilarityMetric::Dot => dot_similarity(a, b),
+        SimilarityMetric::Euclidean => euclidean_similarity(a, b),
+    }
+}
+
+/// Euclidean length of `v`
+pub fn vector_norm(v: &[f32]) -> f32 {
+    v.iter().map(|x| x * x).sum::<f32>().sqrt()
+}
+
+/// Map a raw `vector_similarity` score into 0..=1 so every metric blends with filename and
+/// content scores on the same scale. `query_norm` is the length of the query vector: dot
+/// products become `tanh(dot / |query|²)` and euclidean distances are measured in query
+/// lengths, so unnormalized magnitudes neither saturate at 1.0 nor collapse toward 0.
+/// Cosine is returned unchanged. The mapping is monotonic, so ranking is unaffected.
+pub fn scale_similarity(metric: SimilarityMetric, raw: f32, query_norm: f32) -> f32 {
+    match metric {
+        SimilarityMetric::Cosine => raw,
+        SimilarityMetric::Dot if query_norm > 0.0 => (raw / (query_norm * query_norm)).max(0.0).tanh(),
+        SimilarityMetric::Euclidean if query_norm > 0.0 && raw > 0.0 => {
+            let distance = 1.0 / raw - 1.0;
+            1.0 / (1.0 + distance / query_norm)
+        }
+        SimilarityMetric::Dot => 0.0,
+        SimilarityMetric::Euclidean => raw,
+    }
+}
+
+/// Get a query vector ready for `vector_similarity`: scaled to unit length for `Cosine`,
+/// left as-is for the metrics where magnitude counts
+pub fn prepare_vector(metric: SimilarityMetric, v: &mut [f32]) {
+    if metric == SimilarityMetric::Cosine {
+        normalize(v);
+    }
+}
+
+/// Multiplier for a result at `file_path` from the longest matching boost prefix (1.0 if none).
+/// Prefixes match whole path components, so "/work/app" doesn't boost "/work/app-old".
+pub fn path_boost(file_path: &str, boosts: &[crate::config::PathBoost]) -> f32 {
+    let path = std::path::Path::new(file_path);
+    boosts
+        .iter()
+        .filter(|boost| path.starts_with(&boost.prefix))
+        .max_by_key(|boost| boost.prefix.len())
+        .map_or(1.0, |boost| boost.multiplier)
+}
+
+/// Multiplier for a result from its extension's entry in `type_boosts` (1.0 if none).
+/// Keys match case-insensitively, with or without a leading dot.
+pub fn type_boost(file_path: &str, boosts: &std::collections::HashMap<String, f32>) -> f32 {
+    let Some(ext) = std::path::Path::new(file_path).extension().and_then(|e| e.to_str()) else {
+        return 1.0;
+    };
+    boosts
+        .iter()
+        .find(|(key, _)| key.trim_start_matches('.').eq_ignore_ascii_case(ext))
+        .map_or(1.0, |(_, multiplier)| *multiplier)
+}
+
+/// Multiplier favoring recently modified files: `1 + weight` for a file modified now, decaying
+/// halfway back to 1.0 every `half_life_days`. Files without a valid mtime get 1.0.
+pub fn freshness_boost(modified_time: i64, now: i64, half_life_days: f32, weight: f32) -> f32 {
+    if weight <= 0.0 || modified_time <= 0 || half_life_days <= 0.0 {
+        return 1.0;
+    }
+    let age_days = (now - modified_time).max(0) as f32 / 86_400.0;
+    1.0 + weight * 0.5f32.powf(age_days / half_life_days)
+}
+
+/// Default max edit distance for fuzzy (typo-tolerant) filename word matching
+pub const DEFAULT_FUZZY_MAX_EDIT_DISTANCE: usize = 2;
+
 /// Calculate filename similarity score (0.0 to 1.0)
 /// Uses fuzzy matching to find files by name even if query doesn't match exactly
 /// Stricter matching to avoid false positives
 pub fn filename_similarity(query: &str, filename: &str) -> f32 {
+    filename_similarity_with_fuzzy(query, filename, DEFAULT_FUZZY_MAX_EDIT_DISTANCE)
+}
+
+/// Like `filename_similarity`, with an explicit edit-distance threshold for typo matches.
+/// A `max_edit_distance` of 0 disables fuzzy matching.
+/// Only the stem is compared: an extension says nothing about what a file is about, so "pdf"
+/// shouldn't match every PDF. Extension intent is handled by the `file_types` filter instead.
+pub fn filename_similarity_with_fuzzy(query: &str, filename: &str, max_edit_distance: usize) -> f32 {
     let query_lower = query.to_lowercase();
     let filename_lower = filename.to_lowercase();
     
@@ -29,6 +142,20 @@ pub fn filename_similarity(query: &str, filename: &str) -> f32 {
     if filename_lower == query_lower {
         return 1.0;
     }
+
+    // Drop the extension (from the query too when it names the same one) and treat
+    // separators as spaces, so "budget_report.xlsx" compares as "budget report"
+    let path = std::path::Path::new(&filename_lower);
+    let (stem, extension) = match (path.file_stem().and_then(|s| s.to_str()), path.extension().and_then(|e| e.to_str())) {
+        (Some(stem), Some(extension)) => (stem, Some(format!(".{}", extension))),
+        _ => (filename_lower.as_str(), None),
+    };
+    let query_stem = extension
+        .as_deref()
+        .and_then(|extension| query_lower.strip_suffix(extension))
+        .unwrap_or(&query_lower);
+    let query_lower = separated_words(query_stem).join(" ");
+    let filename_lower = separated_words(stem).join(" ");
     
     // Exact substring match - but require minimum length to avoid false positives
     // Only match if query is substantial (>= 4 chars) to avoid "cal" matching "close"
@@ -46,10 +173,7 @@ pub fn filename_similarity(query: &str, filename: &str) -> f32 {
         return 0.0;
     }
     
-    let filename_words: Vec<&str> = filename_lower
-        .split(|c: char| c.is_whitespace() || c == '-' || c == '_' || c == '.')
-        .filter(|s| !s.is_empty())
-        .collect();
+    let filename_words: Vec<&str> = separated_words(&filename_lower);
     
     if filename_words.is_empty() {
         return 0.0;
@@ -57,17 +181,19 @@ pub fn filename_similarity(query: &str, filename: &str) -> f32 {
     
     // Count how many query words appear in filename
     // STRICTER: Only count exact word matches or very close matches (not loose substring)
-    let mut matched_words = 0;
+    let mut matched_words = 0.0;
     for query_word in &query_words {
         // Skip very short query words (1-2 chars) to avoid false matches
         if query_word.len() < 3 {
             continue;
         }
         
+        let matched_before = matched_words;
+        let mut fuzzy_match = false;
         for filename_word in &filename_words {
             // Exact word match
             if filename_word == query_word {
-                matched_words += 1;
+                matched_words += 1.0;
                 break;
             }
             // Only allow substring match if query word is substantial (>= 4 chars)
@@ -76,7 +202,7 @@ pub fn filename_similarity(query: &str, filename: &str) -> f32 {
             if query_word.len() >= 4 {
                 // Check if query word appears as a complete word or at start of filename word
                 if filename_word == query_word {
-                    matched_words += 1;
+                    matched_words += 1.0;
                     break;
                 }
                 // Allow substring only if filename word is similar length (not much longer)
@@ -86,24 +212,32 @@ pub fn filename_similarity(query: &str, filename: &str) -> f32 {
                     // Require that the match starts at the beginning or is a substantial portion
                     if filename_word.starts_with(query_word) 
                         || query_word.len() as f32 / filename_word.len() as f32 > 0.6 {
-                        matched_words += 1;
+                        matched_words += 1.0;
                         break;
                     }
                 }
+                // Typo tolerance: near-miss words ("assignement" vs "assignment")
+                if !fuzzy_match && is_fuzzy_word_match(query_word, filename_word, max_edit_distance) {
+                    fuzzy_match = true;
+                }
             }
         }
+        // Fuzzy matches count for less than exact ones (only if nothing matched exactly)
+        if fuzzy_match && matched_words == matched_before {
+            matched_words += 0.8;
+        }
     }
     
     // If no words matched, return 0 (don't use character similarity for false positives)
-    if matched_words == 0 {
+    if matched_words == 0.0 {
         return 0.0;
     }
     
     // Calculate score based on matched words ratio
-    let word_match_ratio = matched_words as f32 / query_words.len() as f32;
+    let word_match_ratio = matched_words / query_words.len() as f32;
     
     // Only use character similarity if we have some word matches
-    let char_similarity = if matched_words > 0 {
+    let char_similarity = if matched_words > 0.0 {
         calculate_char_similarity(&query_lower, &filename_lower)
     } else {
         0.0
@@ -114,6 +248,164 @@ pub fn filename_similarity(query: &str, filename: &str) -> f32 {
     (word_match_ratio * 0.8) + (char_similarity * 0.2)
 }
 
+/// Words of a file name or query, split on whitespace and the usual name separators
+fn separated_words(name: &str) -> Vec<&str> {
+    name.split(|c: char| c.is_whitespace() || c == '-' || c == '_' || c == '.')
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Filename matching settings shared by every search path: typo tolerance, boilerplate stripping,
+/// and the domain terms that mark a query as a topic rather than a filename
+#[derive(Debug, Clone, Default)]
+pub struct FilenameMatcher {
+    fuzzy_max_edit_distance: usize,
+    /// Stop phrases split into words; None leaves filenames as they are
+    stop_phrases: Option<Vec<Vec<String>>>,
+    /// Stored lowercase
+    semantic_keywords: Vec<String>,
+}
+
+impl FilenameMatcher {
+    pub fn new(fuzzy_max_edit_distance: usize) -> Self {
+        Self {
+            fuzzy_max_edit_distance,
+            stop_phrases: None,
+            semantic_keywords: Vec::new(),
+        }
+    }
+
+    /// Single-word queries that are, or start with, one of these are never treated as filename lookups
+    pub fn with_semantic_keywords(mut self, keywords: &[String]) -> Self {
+        self.semantic_keywords = keywords.iter().map(|keyword| keyword.to_lowercase()).collect();
+        self
+    }
+
+    /// Whether a lowercased query is, or starts with, a configured semantic keyword
+    pub fn is_semantic_keyword(&self, query_lower: &str) -> bool {
+        self.semantic_keywords.iter().any(|keyword| query_lower.starts_with(keyword.as_str()))
+    }
+
+    /// Also match against the filename with these words/phrases, numbers and version markers removed
+    pub fn with_stop_tokens(mut self, stop_tokens: &[String]) -> Self {
+        self.stop_phrases = Some(
+            stop_tokens
+                .iter()
+                .map(|token| name_tokens(token).map(str::to_string).collect::<Vec<_>>())
+                .filter(|phrase| !phrase.is_empty())
+                .collect(),
+        );
+        self
+    }
+
+    pub fn from_config(config: &crate::config::AppConfig) -> Self {
+        let matcher = Self::new(config.fuzzy_max_edit_distance).with_semantic_keywords(&config.semantic_keywords);
+        if config.normalize_filenames {
+            matcher.with_stop_tokens(&config.filename_stop_tokens)
+        } else {
+            matcher
+        }
+    }
+
+    /// Filename similarity for the name as written or normalized, whichever matches better
+    pub fn similarity(&self, query: &str, filename: &str) -> f32 {
+        let raw = filename_similarity_with_fuzzy(query, filename, self.fuzzy_max_edit_distance);
+        match self.normalize(filename) {
+            Some(normalized) => raw.max(filename_similarity_with_fuzzy(query, &normalized, self.fuzzy_max_edit_distance)),
+            None => raw,
+        }
+    }
+
+    /// Lowercased name with stop phrases, pure numbers and version markers ("v2", "rev3") removed,
+    /// extension kept. None when normalization is off, changes nothing, or would leave no words.
+    pub fn normalize(&self, filename: &str) -> Option<String> {
+        let stop_phrases = self.stop_phrases.as_ref()?;
+        let path = std::path::Path::new(filename);
+        let stem = path.file_stem()?.to_str()?.to_lowercase();
+        let tokens: Vec<&str> = name_tokens(&stem).collect();
+
+        let mut kept = Vec::with_capacity(tokens.len());
+        let mut i = 0;
+        while i < tokens.len() {
+            let stop_len = stop_phrases
+                .iter()
+                .filter(|phrase| phrase.len() <= tokens.len() - i && phrase.iter().zip(&tokens[i..]).all(|(p, t)| p == t))
+                .map(|phrase| phrase.len())
+                .max();
+            if let Some(len) = stop_len {
+                i += len;
+                continue;
+            }
+            if !is_numeric_or_version(tokens[i]) {
+                kept.push(tokens[i]);
+            }
+            i += 1;
+        }
+        if kept.is_empty() || kept.len() == tokens.len() {
+            return None;
+        }
+
+        let mut normalized = kept.join(" ");
+        if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+            normalized.push('.');
+            normalized.push_str(&ext.to_lowercase());
+        }
+        Some(normalized)
+    }
+}
+
+/// Alphanumeric runs of a filename; punctuation, brackets and separators all split
+fn name_tokens(name: &str) -> impl Iterator<Item = &str> {
+    name.split(|c: char| !c.is_alphanumeric()).filter(|token| !token.is_empty())
+}
+
+/// "2024", "003", "v2", "rev10", "ver3": parts of a name that identify a copy, not its content
+fn is_numeric_or_version(token: &str) -> bool {
+    let digits = ["version", "ver", "rev", "v"]
+        .iter()
+        .find_map(|prefix| token.strip_prefix(prefix))
+        .unwrap_or(token);
+    !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit())
+}
+
+/// True if two words are within the edit-distance threshold. Short words get at most one
+/// edit, and pairs whose lengths differ by more than the threshold are skipped without
+/// computing the distance, which keeps this cheap on large corpora.
+fn is_fuzzy_word_match(query_word: &str, filename_word: &str, max_edit_distance: usize) -> bool {
+    if max_edit_distance == 0 || query_word.len() < 4 || filename_word.len() < 4 {
+        return false;
+    }
+    let max_distance = if query_word.len() < 7 { 1 } else { max_edit_distance };
+    if query_word.len().abs_diff(filename_word.len()) > max_distance {
+        return false;
+    }
+    bounded_levenshtein(query_word, filename_word, max_distance).is_some()
+}
+
+/// Levenshtein distance, or None as soon as it's known to exceed `max_distance`
+pub(crate) fn bounded_levenshtein(a: &str, b: &str, max_distance: usize) -> Option<usize> {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0; b.len() + 1];
+
+    for i in 1..=a.len() {
+        curr[0] = i;
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            curr[j] = (prev[j] + 1).min(curr[j - 1] + 1).min(prev[j - 1] + cost);
+        }
+        // Every path through this row already exceeds the limit
+        if curr.iter().min().copied().unwrap_or(0) > max_distance {
+            return None;
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+
+    let distance = prev[b.len()];
+    (distance <= max_distance).then_some(distance)
+}
+
 /// Calculate character-level similarity using a simple approach
 fn calculate_char_similarity(query: &str, filename: &str) -> f32 {
     if query.is_empty() || filename.is_empty() {
@@ -171,3 +463,133 @@ pub fn hybrid_similarity(
     let (vector_weight, filename_weight) = weights;
     (vector_sim * vector_weight) + (filename_sim * filename_weight)
 }
+
+/// Rank offset for reciprocal rank fusion; damps the gap between the first few ranks
+pub const RRF_K: f32 = 60.0;
+
+/// Reciprocal rank fusion: each item scores the sum of 1 / (RRF_K + rank) over the lists it
+/// appears in (ranks from 1). Returned best first.
+pub fn reciprocal_rank_fusion<K>(ranked_lists: &[Vec<K>]) -> Vec<(K, f32)>
+where
+    K: Clone + Eq + std::hash::Hash,
+{
+    let mut scores: std::collections::HashMap<K, f32> = std::collections::HashMap::new();
+    for list in ranked_lists {
+        for (rank, item) in list.iter().enumerate() {
+            *scores.entry(item.clone()).or_insert(0.0) += 1.0 / (RRF_K + rank as f32 + 1.0);
+        }
+    }
+    let mut fused: Vec<(K, f32)> = scores.into_iter().collect();
+    fused.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    fused
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scale_similarity_separates_dot_magnitudes() {
+        let query = [6.0, 8.0];
+        let norm = vector_norm(&query);
+        let strong = scale_similarity(SimilarityMetric::Dot, vector_similarity(SimilarityMetric::Dot, &query, &[9.0, 12.0]), norm);
+        let weak = scale_similarity(SimilarityMetric::Dot, vector_similarity(SimilarityMetric::Dot, &query, &[6.0, 8.0]), norm);
+        assert!(strong > weak, "{} should beat {}", strong, weak);
+        assert!(strong < 1.0 && weak > 0.5);
+
+        let near = scale_similarity(SimilarityMetric::Euclidean, vector_similarity(SimilarityMetric::Euclidean, &query, &[6.0, 9.0]), norm);
+        assert!(near > 0.85 && near < 1.0);
+    }
+
+    #[test]
+    fn test_filename_similarity_tolerates_typos() {
+        assert!(filename_similarity("assignement", "assignment.pdf") > 0.5);
+        assert!(filename_similarity("calclus notes", "calculus_notes.docx") > 0.5);
+        assert_eq!(filename_similarity_with_fuzzy("assignement", "assignment.pdf", 0), 0.0);
+    }
+
+    #[test]
+    fn test_filename_similarity_ignores_extension() {
+        assert!(filename_similarity("report", "report.pdf") > 0.9);
+        assert!(filename_similarity("budget report", "budget_report.xlsx") > 0.9);
+        assert!(filename_similarity("report.pdf", "annual-report.pdf") > 0.8);
+        assert_eq!(filename_similarity("report.pdf", "report.pdf"), 1.0);
+
+        // The extension alone matches nothing, but it still counts inside the stem
+        assert_eq!(filename_similarity("pdf", "invoice.pdf"), 0.0);
+        assert_eq!(filename_similarity("pdf", "lecture_notes.pdf"), 0.0);
+        assert!(filename_similarity("pdf", "pdf_guide.docx") > 0.0);
+    }
+
+    #[test]
+    fn test_semantic_keywords_come_from_config() {
+        let mut config = crate::config::AppConfig::default();
+        assert!(FilenameMatcher::from_config(&config).is_semantic_keyword("calculus"));
+        assert!(!FilenameMatcher::from_config(&config).is_semantic_keyword("tort"));
+
+        config.semantic_keywords = vec!["Tort".to_string(), "deposition".to_string()];
+        let matcher = FilenameMatcher::from_config(&config);
+        assert!(matcher.is_semantic_keyword("torts"));
+        assert!(!matcher.is_semantic_keyword("calculus"));
+    }
+
+    #[test]
+    fn test_normalized_filename_ignores_boilerplate() {
+        let matcher = FilenameMatcher::new(DEFAULT_FUZZY_MAX_EDIT_DISTANCE)
+            .with_stop_tokens(&["copy of".to_string(), "final".to_string(), "img".to_string()]);
+        assert_eq!(
+            matcher.normalize("Copy of Budget Report (final)_v2.xlsx").as_deref(),
+            Some("budget report.xlsx")
+        );
+        assert_eq!(
+            matcher.similarity("budget report", "Copy of Budget Report (final).xlsx"),
+            matcher.similarity("budget report", "Budget Report.xlsx")
+        );
+        // Nothing left but boilerplate: match the name as written
+        assert_eq!(matcher.normalize("IMG_1234.jpg"), None);
+    }
+
+    #[test]
+    fn test_freshness_boost_halves_per_half_life() {
+        let now = 1_700_000_000;
+        assert_eq!(freshness_boost(now, now, 30.0, 0.5), 1.5);
+        assert!((freshness_boost(now - 30 * 86_400, now, 30.0, 0.5) - 1.25).abs() < 1e-5);
+        assert_eq!(freshness_boost(now, now, 30.0, 0.0), 1.0);
+        assert_eq!(freshness_boost(0, now, 30.0, 0.5), 1.0);
+    }
+
+    #[test]
+    fn test_path_boost_uses_most_specific_prefix() {
+        use crate::config::PathBoost;
+        let boosts = vec![
+            PathBoost { prefix: "/work".to_string(), multiplier: 1.2 },
+            PathBoost { prefix: "/work/app".to_string(), multiplier: 1.5 },
+        ];
+        assert_eq!(path_boost("/work/app/src/main.rs", &boosts), 1.5);
+        assert_eq!(path_boost("/work/app-old/main.rs", &boosts), 1.2);
+        assert_eq!(path_boost("/archive/app/main.rs", &boosts), 1.0);
+    }
+
+    #[test]
+    fn test_type_boost_matches_extension_case_insensitively() {
+        let boosts = std::collections::HashMap::from([(".XLSX".to_string(), 1.3), ("txt".to_string(), 0.9)]);
+        assert_eq!(type_boost("/docs/Budget.xlsx", &boosts), 1.3);
+        assert_eq!(type_boost("/docs/notes.TXT", &boosts), 0.9);
+        assert_eq!(type_boost("/docs/report.docx", &boosts), 1.0);
+        assert_eq!(type_boost("/docs/Makefile", &boosts), 1.0);
+    }
+
+    #[test]
+    fn test_reciprocal_rank_fusion_rewards_agreement() {
+        let fused = reciprocal_rank_fusion(&[vec!["a", "b", "c"], vec!["b", "c", "d"]]);
+        let order: Vec<&str> = fused.iter().map(|(item, _)| *item).collect();
+        assert_eq!(order, vec!["b", "c", "a", "d"]);
+    }
+
+    #[test]
+    fn test_bounded_levenshtein() {
+        assert_eq!(bounded_levenshtein("assignement", "assignment", 2), Some(1));
+        assert_eq!(bounded_levenshtein("kitten", "sitting", 3), Some(3));
+        assert_eq!(bounded_levenshtein("kitten", "sitting", 2), None);
+    }
+}
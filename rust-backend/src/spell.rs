@@ -0,0 +1,114 @@
+// Corpus-aware spelling correction for search queries.
+// The dictionary is built from indexed file names and passage text, so a word is only
+// ever "corrected" into something that actually occurs in the user's files.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::search::bounded_levenshtein;
+
+/// Words shorter than this are never corrected; they have too many near neighbours
+const MIN_WORD_LEN: usize = 4;
+
+/// A replacement must occur at least this often, so one-off typos in the corpus aren't suggested
+const MIN_TARGET_COUNT: usize = 2;
+
+/// Lowercase alphanumeric terms in `text`, split the same way for the dictionary and queries
+pub fn terms(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|term| !term.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// One word of a query replaced by spelling correction
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Correction {
+    pub from: String,
+    pub to: String,
+}
+
+/// A vocabulary and when it was counted
+pub type CountedVocabulary = (std::time::Instant, std::sync::Arc<Vocabulary>);
+
+/// Term frequencies across the indexed corpus
+#[derive(Debug, Default)]
+pub struct Vocabulary {
+    counts: HashMap<String, usize>,
+}
+
+impl Vocabulary {
+    pub fn from_counts(counts: HashMap<String, usize>) -> Self {
+        Self { counts }
+    }
+
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// Correct misspelled words in `query`, returning the corrected query and each change.
+    /// Words the corpus contains (however rare), short words, and words with capitals,
+    /// digits or punctuation (names, acronyms, identifiers) are left as typed.
+    pub fn correct(&self, query: &str) -> (String, Vec<Correction>) {
+        let mut corrections = Vec::new();
+        let words: Vec<String> = query
+            .split_whitespace()
+            .map(|word| {
+                let correctable = word.chars().count() >= MIN_WORD_LEN
+                    && word.chars().all(|c| c.is_alphabetic() && c.is_lowercase())
+                    && !self.counts.contains_key(word);
+                let suggestion = if correctable { self.suggest(word) } else { None };
+                match suggestion {
+                    Some(suggestion) => {
+                        corrections.push(Correction {
+                            from: word.to_string(),
+                            to: suggestion.to_string(),
+                        });
+                        suggestion.to_string()
+                    }
+                    None => word.to_string(),
+                }
+            })
+            .collect();
+        (words.join(" "), corrections)
+    }
+
+    /// Closest common corpus term: fewest edits, then most frequent
+    fn suggest(&self, word: &str) -> Option<&str> {
+        let max_distance = if word.len() < 7 { 1 } else { 2 };
+        self.counts
+            .iter()
+            .filter(|(term, count)| **count >= MIN_TARGET_COUNT && term.len().abs_diff(word.len()) <= max_distance)
+            .filter_map(|(term, count)| {
+                bounded_levenshtein(word, term, max_distance).map(|distance| (distance, *count, term.as_str()))
+            })
+            .min_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)).then(a.2.cmp(b.2)))
+            .map(|(_, _, term)| term)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_corrects_only_unknown_lowercase_words() {
+        let mut counts = HashMap::new();
+        for term in terms("calculus calculus homework homework notes kubectl") {
+            *counts.entry(term).or_insert(0) += 1;
+        }
+        let vocabulary = Vocabulary::from_counts(counts);
+
+        let (corrected, corrections) = vocabulary.correct("calclus homwork");
+        assert_eq!(corrected, "calculus homework");
+        assert_eq!(corrections.len(), 2);
+
+        // In the corpus once is enough to be left alone, and rare terms are never targets
+        let (corrected, corrections) = vocabulary.correct("kubectl kubectk Calclus");
+        assert_eq!(corrected, "kubectl kubectk Calclus");
+        assert!(corrections.is_empty());
+    }
+}
@@ -1,8 +1,13 @@
 use anyhow::Result;
-use rusqlite::{Connection, params};
+use rusqlite::{Connection, OptionalExtension, params};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
 use tokio::task;
+use tracing::{error, info, warn};
+use crate::search::normalize;
+use crate::text_cache::TextCache;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileMetadata {
@@ -16,9 +21,259 @@ pub struct FileMetadata {
     pub embedding_length: i64,
 }
 
+/// `file_type` of the entries stored for folders when `index_directory_names` is on
+pub const DIRECTORY_FILE_TYPE: &str = "directory";
+
+impl FileMetadata {
+    pub fn is_directory(&self) -> bool {
+        self.file_type == DIRECTORY_FILE_TYPE
+    }
+}
+
+/// Ordered schema migrations. `PRAGMA user_version` records how many have been applied,
+/// so each step runs exactly once. Only ever append new steps; never edit released ones.
+const MIGRATIONS: &[&str] = &[
+    // 1: file metadata (databases created before versioning already have this, hence IF NOT EXISTS)
+    "CREATE TABLE IF NOT EXISTS files (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        file_path TEXT NOT NULL UNIQUE,
+        file_name TEXT NOT NULL,
+        file_size INTEGER NOT NULL,
+        modified_time INTEGER NOT NULL,
+        file_type TEXT NOT NULL,
+        embedding_offset INTEGER NOT NULL,
+        embedding_length INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_file_path ON files(file_path);",
+    // 2: per-chunk embeddings for passage-level search
+    "CREATE TABLE IF NOT EXISTS passages (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        file_path TEXT NOT NULL,
+        chunk_index INTEGER NOT NULL,
+        byte_start INTEGER NOT NULL,
+        byte_end INTEGER NOT NULL,
+        text TEXT NOT NULL,
+        embedding_offset INTEGER NOT NULL,
+        embedding_length INTEGER NOT NULL,
+        UNIQUE(file_path, chunk_index)
+    );
+    CREATE INDEX IF NOT EXISTS idx_passages_file_path ON passages(file_path);",
+    // 3: vectors are stored unit-length; older rows are normalized on read until rewritten
+    "ALTER TABLE files ADD COLUMN normalized INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE passages ADD COLUMN normalized INTEGER NOT NULL DEFAULT 0;",
+    // 4: when each file row was last written by the indexer (0 = before this was tracked)
+    "ALTER TABLE files ADD COLUMN indexed_at INTEGER NOT NULL DEFAULT 0;",
+    // 5: file-level vectors from embedding models other than the active one, for fused search
+    "CREATE TABLE IF NOT EXISTS model_embeddings (
+        file_path TEXT NOT NULL,
+        model TEXT NOT NULL,
+        embedding_offset INTEGER NOT NULL,
+        embedding_length INTEGER NOT NULL,
+        normalized INTEGER NOT NULL DEFAULT 1,
+        PRIMARY KEY (file_path, model)
+    );
+    CREATE INDEX IF NOT EXISTS idx_model_embeddings_model ON model_embeddings(model);",
+    // 6: hash of the bytes a file's vectors were computed from, so touched-but-unchanged files skip re-embedding
+    "ALTER TABLE files ADD COLUMN content_hash TEXT;",
+    // 7: date, type and folder filters in SQL. LIKE is case-insensitive, so `file_path LIKE 'prefix%'`
+    // can only use a NOCASE index; pair it with an exact prefix check where case matters.
+    "CREATE INDEX IF NOT EXISTS idx_files_modified_time ON files(modified_time);
+    CREATE INDEX IF NOT EXISTS idx_files_file_type ON files(file_type);
+    CREATE INDEX IF NOT EXISTS idx_files_path_nocase ON files(file_path COLLATE NOCASE);",
+    // 8: newest-first activity feed of indexed files
+    "CREATE INDEX IF NOT EXISTS idx_files_indexed_at ON files(indexed_at);",
+    // 9: stored paths rewritten into their canonical spelling (see `migration_fixup`)
+    "",
+    // 10: files the user removed from the index, kept out by syncs until modified again
+    "CREATE TABLE IF NOT EXISTS removed_files (
+        file_path TEXT PRIMARY KEY,
+        modified_time INTEGER NOT NULL
+    );",
+];
+
+/// Data fixes that need Rust, run in the transaction of the migration `version` after its SQL
+fn migration_fixup(version: usize, tx: &rusqlite::Transaction) -> Result<()> {
+    if version == 9 {
+        // Rows stored under another spelling of a path would otherwise look deleted and re-embed
+        repair_duplicate_paths(tx, crate::indexer::Indexer::canonical_path)?;
+    }
+    Ok(())
+}
+
+/// Tables whose rows point into embeddings.bin
+const EMBEDDING_TABLES: [&str; 3] = ["files", "passages", "model_embeddings"];
+
+/// Bring the database schema up to date, applying each pending migration in its own transaction
+fn run_migrations(conn: &mut Connection) -> Result<()> {
+    let current: usize = conn.query_row("PRAGMA user_version", [], |row| row.get::<_, i64>(0))? as usize;
+    if current > MIGRATIONS.len() {
+        return Err(anyhow::anyhow!(
+            "Index database schema v{} is newer than this build supports (v{})",
+            current,
+            MIGRATIONS.len()
+        ));
+    }
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(current) {
+        let version = index + 1;
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)?;
+        migration_fixup(version, &tx)?;
+        tx.pragma_update(None, "user_version", version as i64)?;
+        tx.commit()?;
+        info!("Applied schema migration v{}", version);
+    }
+
+    Ok(())
+}
+
+/// Rewrite stored paths into their canonical spelling, collapsing rows that turn out to
+/// name the same file (keeping the most recently indexed). Paths `canonicalize` leaves
+/// unchanged, such as files no longer on disk, are untouched. Returns the rows removed.
+fn repair_duplicate_paths(tx: &rusqlite::Transaction, canonicalize: fn(&str) -> String) -> Result<usize> {
+    let rows: Vec<String> = {
+        let mut stmt = tx.prepare("SELECT file_path FROM files ORDER BY indexed_at DESC, id DESC")?;
+        let rows = stmt.query_map([], |row| row.get(0))?;
+        rows.collect::<rusqlite::Result<_>>()?
+    };
+    // Newest first, so the row that survives a collision is the most recently indexed
+    let mut claimed = std::collections::HashSet::new();
+    let mut duplicates = Vec::new();
+    let mut renames = Vec::new();
+    for file_path in rows {
+        // Extra sections ("{path}#section{n}") follow their base file
+        let canonical = match file_path.rsplit_once("#section") {
+            Some((base, n)) => format!("{}#section{}", canonicalize(base), n),
+            None => canonicalize(&file_path),
+        };
+        if !claimed.insert(canonical.clone()) {
+            duplicates.push(file_path);
+        } else if canonical != file_path {
+            renames.push((file_path, canonical));
+        }
+    }
+
+    // Drop the losers first so every canonical path is free to take
+    for file_path in &duplicates {
+        for table in EMBEDDING_TABLES {
+            tx.execute(&format!("DELETE FROM {} WHERE file_path = ?1", table), params![file_path])?;
+        }
+    }
+    for (file_path, canonical) in &renames {
+        for table in EMBEDDING_TABLES {
+            tx.execute(&format!("UPDATE {} SET file_path = ?1 WHERE file_path = ?2", table), params![canonical, file_path])?;
+        }
+    }
+    let (renamed, removed) = (renames.len(), duplicates.len());
+    if renamed + removed > 0 {
+        info!("[STORAGE] Canonicalized {} paths, collapsed {} duplicate rows", renamed, removed);
+    }
+    Ok(removed)
+}
+
+/// Returned by embedding reads once embeddings.bin is known to be missing or truncated
+pub const EMBEDDINGS_MISSING_ERROR: &str =
+    "embeddings.bin is missing or truncated; reindex required (POST /api/index/reset-embeddings, then sync)";
+
+/// Whether embeddings.bin still covers every vector the database points into
+#[derive(Debug, Clone, Serialize)]
+pub struct EmbeddingsFileStatus {
+    /// End of the furthest vector referenced by a file or passage row
+    pub required_bytes: u64,
+    /// None if the file doesn't exist
+    pub file_bytes: Option<u64>,
+}
+
+impl EmbeddingsFileStatus {
+    pub fn is_intact(&self) -> bool {
+        self.required_bytes == 0 || self.file_bytes.is_some_and(|bytes| bytes >= self.required_bytes)
+    }
+}
+
+/// Indexed files of one extension
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ExtensionCount {
+    pub extension: String,
+    pub file_count: usize,
+    pub total_size: i64,
+}
+
+/// Indexed files under one of the indexed directories
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct DirectorySize {
+    pub directory: String,
+    pub file_count: usize,
+    pub total_size: i64,
+}
+
+/// Indexed files last modified within an age bracket
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct AgeBucket {
+    pub label: &'static str,
+    pub file_count: usize,
+}
+
+/// Age brackets for `IndexAggregates::by_age`: label and upper bound in seconds, youngest first
+const AGE_BUCKETS: [(&str, i64); 5] = [
+    ("last_day", 86_400),
+    ("last_week", 7 * 86_400),
+    ("last_month", 30 * 86_400),
+    ("last_year", 365 * 86_400),
+    ("older", i64::MAX),
+];
+
+/// Index-wide breakdowns, one grouped query each
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexAggregates {
+    pub total_files: usize,
+    pub total_size: i64,
+    /// Largest file count first; extensions are lowercased
+    pub by_extension: Vec<ExtensionCount>,
+    pub by_directory: Vec<DirectorySize>,
+    pub by_age: Vec<AgeBucket>,
+}
+
+/// A chunk of a file's extracted text, located by byte range within that text
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Passage {
+    pub chunk_index: i64,
+    pub byte_start: i64,
+    pub byte_end: i64,
+    pub text: String,
+}
+
+/// How long a statement waits on a lock held by another process (e.g. the benchmark binary)
+const BUSY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Open the database in WAL mode, so readers in other processes don't block on our writes
+fn open_connection(db_path: &std::path::Path) -> Result<Connection> {
+    let conn = Connection::open(db_path)?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    conn.pragma_update(None, "journal_mode", "WAL")?;
+    conn.pragma_update(None, "synchronous", "NORMAL")?;
+    Ok(conn)
+}
+
+/// The shared connection; a panic mid-statement leaves nothing half-applied outside a
+/// transaction (which rolls back on drop), so a poisoned lock is still safe to reuse
+fn lock_connection(conn: &Mutex<Connection>) -> MutexGuard<'_, Connection> {
+    conn.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
 pub struct Storage {
-    db_path: PathBuf,
+    /// One long-lived connection reused by every call; statements run one at a time on blocking threads
+    conn: Arc<Mutex<Connection>>,
+    /// Holds metadata.db, embeddings.bin and the extracted text cache purged alongside index rows
+    data_dir: PathBuf,
     embeddings_path: PathBuf,
+    /// Readers of embeddings.bin share this; appends, compaction and clearing take it exclusively,
+    /// so a search never sees a vector that is still being written or a file mid-swap
+    embeddings_lock: Arc<RwLock<()>>,
+    /// Set when embeddings.bin no longer covers the stored offsets; reads fail fast until reset
+    embeddings_missing: Arc<AtomicBool>,
+    /// Store and read file and passage vectors at unit length. Off for metrics where the
+    /// magnitude counts; rows stored while it was on stay unit length until reindexed.
+    normalize_vectors: bool,
 }
 
 impl Storage {
@@ -28,45 +283,102 @@ impl Storage {
         let db_path = data_dir.join("metadata.db");
         
         // Initialize database in blocking thread
-        let db_path_clone = db_path.clone();
-        task::spawn_blocking(move || -> Result<()> {
-            let conn = Connection::open(&db_path_clone)?;
-            
-            // Create tables
-            conn.execute(
-                "CREATE TABLE IF NOT EXISTS files (
-                    id INTEGER PRIMARY KEY AUTOINCREMENT,
-                    file_path TEXT NOT NULL UNIQUE,
-                    file_name TEXT NOT NULL,
-                    file_size INTEGER NOT NULL,
-                    modified_time INTEGER NOT NULL,
-                    file_type TEXT NOT NULL,
-                    embedding_offset INTEGER NOT NULL,
-                    embedding_length INTEGER NOT NULL
-                )",
-                [],
-            )?;
-            
-            conn.execute(
-                "CREATE INDEX IF NOT EXISTS idx_file_path ON files(file_path)",
-                [],
-            )?;
-            
-            Ok(())
+        let conn = task::spawn_blocking(move || -> Result<Connection> {
+            let mut conn = open_connection(&db_path)?;
+            run_migrations(&mut conn)?;
+            Ok(conn)
         }).await??;
         
         let embeddings_path = data_dir.join("embeddings.bin");
         
-        Ok(Self {
-            db_path,
+        let storage = Self {
+            conn: Arc::new(Mutex::new(conn)),
+            data_dir: data_dir.clone(),
             embeddings_path,
-        })
+            embeddings_lock: Arc::new(RwLock::new(())),
+            embeddings_missing: Arc::new(AtomicBool::new(false)),
+            normalize_vectors: true,
+        };
+        storage.check_embeddings_file().await?;
+        Ok(storage)
+    }
+
+    /// Keep vector magnitudes unless `metric` only compares directions
+    pub fn with_similarity_metric(mut self, metric: crate::config::SimilarityMetric) -> Self {
+        self.normalize_vectors = metric == crate::config::SimilarityMetric::Cosine;
+        self
+    }
+
+    /// Compare embeddings.bin against the largest offset + length the database references,
+    /// remembering the result so later reads can report a missing file instead of retrying
+    pub async fn check_embeddings_file(&self) -> Result<EmbeddingsFileStatus> {
+        let conn = self.conn.clone();
+        let required_bytes = task::spawn_blocking(move || {
+            let conn = lock_connection(&conn);
+            let required: i64 = conn.query_row(
+                "SELECT MAX(
+                    COALESCE((SELECT MAX(embedding_offset + embedding_length) FROM files WHERE embedding_length > 0), 0),
+                    COALESCE((SELECT MAX(embedding_offset + embedding_length) FROM passages WHERE embedding_length > 0), 0),
+                    COALESCE((SELECT MAX(embedding_offset + embedding_length) FROM model_embeddings), 0)
+                 )",
+                [],
+                |row| row.get(0),
+            )?;
+            Ok::<u64, anyhow::Error>(required.max(0) as u64)
+        }).await??;
+
+        let file_bytes = std::fs::metadata(&self.embeddings_path).ok().map(|m| m.len());
+        let status = EmbeddingsFileStatus { required_bytes, file_bytes };
+        let missing = !status.is_intact();
+        if missing && !self.embeddings_missing.swap(true, Ordering::SeqCst) {
+            error!(
+                "[STORAGE] embeddings.bin is {} but the index references {} bytes; reindex required",
+                file_bytes.map_or("missing".to_string(), |b| format!("{} bytes", b)),
+                required_bytes
+            );
+        } else if !missing {
+            self.embeddings_missing.store(false, Ordering::SeqCst);
+        }
+        Ok(status)
+    }
+
+    pub fn embeddings_missing(&self) -> bool {
+        self.embeddings_missing.load(Ordering::SeqCst)
+    }
+
+    fn ensure_embeddings_present(&self) -> Result<()> {
+        if self.embeddings_missing() {
+            return Err(anyhow::anyhow!(EMBEDDINGS_MISSING_ERROR));
+        }
+        Ok(())
+    }
+
+    fn read_embeddings_file(lock: &RwLock<()>) -> Result<RwLockReadGuard<'_, ()>> {
+        lock.read().map_err(|_| anyhow::anyhow!("embeddings lock poisoned"))
+    }
+
+    fn write_embeddings_file(lock: &RwLock<()>) -> Result<RwLockWriteGuard<'_, ()>> {
+        lock.write().map_err(|_| anyhow::anyhow!("embeddings lock poisoned"))
+    }
+
+    /// Delete embeddings.bin once no reader or writer holds it
+    async fn remove_embeddings_file(&self) -> Result<()> {
+        let embeddings_path = self.embeddings_path.clone();
+        let lock = self.embeddings_lock.clone();
+        task::spawn_blocking(move || {
+            let _guard = Self::write_embeddings_file(&lock)?;
+            if embeddings_path.exists() {
+                std::fs::remove_file(&embeddings_path)?;
+            }
+            Ok(())
+        }).await?
     }
 
     pub async fn add_file(&self, metadata: &FileMetadata, embedding: Option<&[f32]>) -> Result<()> {
         // Check if file already exists in index
         let existing_metadata = self.get_file_metadata(&metadata.file_path).await?;
         
+        let mut reused = false;
         let (offset, length) = if let Some(emb) = embedding {
             if let Some(existing) = existing_metadata {
                 // File exists - check if it has changed
@@ -74,56 +386,15 @@ impl Storage {
                     && existing.file_size == metadata.file_size 
                     && existing.embedding_length > 0 {
                     // File hasn't changed, reuse existing embedding
+                    reused = true;
                     (existing.embedding_offset, existing.embedding_length)
                 } else {
                     // File has changed or was metadata-only, need new embedding
-                    // Get current file size for offset (append to end)
-                    let new_offset = if self.embeddings_path.exists() {
-                        std::fs::metadata(&self.embeddings_path)?.len() as i64
-                    } else {
-                        0
-                    };
-                    
-                    // Serialize and append new embedding
-                    let serialized = bincode::serialize(emb)?;
-                    let new_length = serialized.len() as i64;
-                    
-                    use std::io::Write;
-                    let mut file = std::fs::OpenOptions::new()
-                        .create(true)
-                        .append(true)
-                        .write(true)
-                        .open(&self.embeddings_path)?;
-                    
-                    file.write_all(&serialized)?;
-                    file.flush()?;
-                    
-                    (new_offset, new_length)
+                    self.append_embedding(emb, self.normalize_vectors).await?
                 }
             } else {
                 // New file, append embedding
-                let new_offset = if self.embeddings_path.exists() {
-                    std::fs::metadata(&self.embeddings_path)?.len() as i64
-                } else {
-                    0
-                };
-                
-                // Serialize embedding
-                let serialized = bincode::serialize(emb)?;
-                let new_length = serialized.len() as i64;
-                
-                // Append embedding to binary file
-                use std::io::Write;
-                let mut file = std::fs::OpenOptions::new()
-                    .create(true)
-                    .append(true)
-                    .write(true)
-                    .open(&self.embeddings_path)?;
-                
-                file.write_all(&serialized)?;
-                file.flush()?;
-                
-                (new_offset, new_length)
+                self.append_embedding(emb, self.normalize_vectors).await?
             }
         } else {
             // No embedding provided (metadata-only)
@@ -131,14 +402,18 @@ impl Storage {
         };
         
         // Update metadata in database
-        let db_path = self.db_path.clone();
+        let conn = self.conn.clone();
         let metadata_clone = metadata.clone();
+        let normalized = self.normalize_vectors;
         task::spawn_blocking(move || {
-            let conn = Connection::open(&db_path)?;
+            let conn = lock_connection(&conn);
+            // A reused vector keeps whatever normalization it was stored with
             conn.execute(
                 "INSERT OR REPLACE INTO files 
-                 (file_path, file_name, file_size, modified_time, file_type, embedding_offset, embedding_length)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                 (file_path, file_name, file_size, modified_time, file_type, embedding_offset, embedding_length, normalized, indexed_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7,
+                         CASE WHEN ?8 THEN COALESCE((SELECT normalized FROM files WHERE file_path = ?1), 0) ELSE ?9 END,
+                         CAST(strftime('%s', 'now') AS INTEGER))",
                 params![
                     metadata_clone.file_path,
                     metadata_clone.file_name,
@@ -146,21 +421,146 @@ impl Storage {
                     metadata_clone.modified_time,
                     metadata_clone.file_type,
                     offset,
-                    length
+                    length,
+                    reused,
+                    normalized
                 ],
             )?;
+            // Indexing a removed file again lifts its exclusion
+            conn.execute("DELETE FROM removed_files WHERE file_path = ?1", params![metadata_clone.file_path])?;
             Ok::<(), anyhow::Error>(())
         }).await??;
         
         Ok(())
     }
 
+    /// Append an embedding to embeddings.bin, scaled to unit length if `unit`, returning its
+    /// (offset, length). Rows pointing at the vector are only written after this returns, so
+    /// the bytes are complete before any search can look them up.
+    async fn append_embedding(&self, embedding: &[f32], unit: bool) -> Result<(i64, i64)> {
+        let mut embedding = embedding.to_vec();
+        if unit {
+            normalize(&mut embedding);
+        }
+        let serialized = bincode::serialize(&embedding)?;
+        let embeddings_path = self.embeddings_path.clone();
+        let lock = self.embeddings_lock.clone();
+
+        task::spawn_blocking(move || {
+            use std::io::Write;
+
+            // Exclusive for the whole append so concurrent writers can't claim the same offset
+            let _guard = Self::write_embeddings_file(&lock)?;
+            let mut file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&embeddings_path)?;
+            let offset = file.metadata()?.len() as i64;
+
+            file.write_all(&serialized)?;
+            file.flush()?;
+
+            Ok((offset, serialized.len() as i64))
+        }).await?
+    }
+
+    /// Replace all stored passages (and their embeddings) for a file
+    pub async fn replace_passages(&self, file_path: &str, passages: &[(Passage, Vec<f32>)]) -> Result<()> {
+        let mut rows = Vec::with_capacity(passages.len());
+        for (passage, embedding) in passages {
+            let (offset, length) = self.append_embedding(embedding, self.normalize_vectors).await?;
+            rows.push((passage.clone(), offset, length));
+        }
+
+        let conn = self.conn.clone();
+        let file_path = file_path.to_string();
+        let normalized = self.normalize_vectors;
+        task::spawn_blocking(move || {
+            let mut conn = lock_connection(&conn);
+            let tx = conn.transaction()?;
+            tx.execute("DELETE FROM passages WHERE file_path = ?1", params![file_path])?;
+            for (passage, offset, length) in &rows {
+                tx.execute(
+                    "INSERT INTO passages
+                     (file_path, chunk_index, byte_start, byte_end, text, embedding_offset, embedding_length, normalized)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                    params![file_path, passage.chunk_index, passage.byte_start, passage.byte_end, passage.text, offset, length, normalized],
+                )?;
+            }
+            tx.commit()?;
+            Ok::<(), anyhow::Error>(())
+        }).await?
+    }
+
+    /// Load every stored passage with its file's metadata and embedding
+    pub async fn get_all_passage_embeddings(&self) -> Result<Vec<(FileMetadata, Passage, Vec<f32>)>> {
+        self.ensure_embeddings_present()?;
+
+        let rows = self.read_resolved(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT f.id, f.file_path, f.file_name, f.file_size, f.modified_time, f.file_type,
+                        f.embedding_offset, f.embedding_length,
+                        p.chunk_index, p.byte_start, p.byte_end, p.text,
+                        p.embedding_offset, p.embedding_length, p.normalized
+                 FROM passages p
+                 JOIN files f ON f.file_path = p.file_path
+                 WHERE p.embedding_length > 0"
+            )?;
+
+            let rows = stmt.query_map([], |row| {
+                Ok((
+                    (
+                        FileMetadata {
+                            id: row.get(0)?,
+                            file_path: row.get(1)?,
+                            file_name: row.get(2)?,
+                            file_size: row.get(3)?,
+                            modified_time: row.get(4)?,
+                            file_type: row.get(5)?,
+                            embedding_offset: row.get(6)?,
+                            embedding_length: row.get(7)?,
+                        },
+                        Passage {
+                            chunk_index: row.get(8)?,
+                            byte_start: row.get(9)?,
+                            byte_end: row.get(10)?,
+                            text: row.get(11)?,
+                        },
+                        row.get::<_, bool>(14)?,
+                    ),
+                    row.get::<_, i64>(12)?,
+                    row.get::<_, i64>(13)?,
+                ))
+            })?;
+
+            rows.collect::<rusqlite::Result<Vec<_>>>().map_err(anyhow::Error::from)
+        }).await?;
+
+        let mut result = Vec::with_capacity(rows.len());
+        for ((metadata, passage, normalized), embedding) in rows {
+            match embedding {
+                Ok(mut embedding) => {
+                    if !normalized && self.normalize_vectors {
+                        normalize(&mut embedding);
+                    }
+                    result.push((metadata, passage, embedding))
+                }
+                Err(e) => warn!(
+                    "Failed to get passage {} embedding for {}: {}",
+                    passage.chunk_index, metadata.file_path, e
+                ),
+            }
+        }
+
+        Ok(result)
+    }
+
     pub async fn get_file_metadata(&self, file_path: &str) -> Result<Option<FileMetadata>> {
-        let db_path = self.db_path.clone();
+        let conn = self.conn.clone();
         let file_path = file_path.to_string();
         
         task::spawn_blocking(move || {
-            let conn = Connection::open(&db_path)?;
+            let conn = lock_connection(&conn);
             let mut stmt = conn.prepare(
                 "SELECT id, file_path, file_name, file_size, modified_time, file_type, 
                         embedding_offset, embedding_length
@@ -188,11 +588,211 @@ impl Storage {
         }).await?
     }
 
+    /// The `limit` files whose rows were written most recently, newest first, with when that was.
+    /// Extra "#section" rows are left out, and rows from before `indexed_at` was tracked never appear.
+    pub async fn recently_indexed(&self, limit: usize) -> Result<Vec<(FileMetadata, i64)>> {
+        let conn = self.conn.clone();
+        task::spawn_blocking(move || {
+            let conn = lock_connection(&conn);
+            let mut stmt = conn.prepare(
+                "SELECT id, file_path, file_name, file_size, modified_time, file_type,
+                        embedding_offset, embedding_length, indexed_at
+                 FROM files
+                 WHERE indexed_at > 0 AND instr(file_path, '#section') = 0
+                 ORDER BY indexed_at DESC, id DESC
+                 LIMIT ?1"
+            )?;
+            let rows = stmt.query_map(params![limit as i64], |row| {
+                Ok((
+                    FileMetadata {
+                        id: row.get(0)?,
+                        file_path: row.get(1)?,
+                        file_name: row.get(2)?,
+                        file_size: row.get(3)?,
+                        modified_time: row.get(4)?,
+                        file_type: row.get(5)?,
+                        embedding_offset: row.get(6)?,
+                        embedding_length: row.get(7)?,
+                    },
+                    row.get(8)?,
+                ))
+            })?;
+            Ok::<Vec<(FileMetadata, i64)>, anyhow::Error>(rows.collect::<rusqlite::Result<_>>()?)
+        }).await?
+    }
+
+    /// Counts and sizes of indexed files by extension, by each of `directories`, and by age
+    /// relative to `now` (Unix seconds). Extra "#section" rows are not counted.
+    pub async fn aggregate(&self, directories: &[String], now: i64) -> Result<IndexAggregates> {
+        let conn = self.conn.clone();
+        let directories: Vec<String> = directories
+            .iter()
+            .map(|dir| dir.trim_end_matches(['/', '\\']).to_string())
+            .collect();
+
+        task::spawn_blocking(move || {
+            let conn = lock_connection(&conn);
+            let (total_files, total_size): (i64, i64) = conn.query_row(
+                "SELECT COUNT(*), COALESCE(SUM(file_size), 0) FROM files WHERE instr(file_path, '#section') = 0",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )?;
+
+            let mut stmt = conn.prepare(
+                "SELECT lower(file_type), COUNT(*), COALESCE(SUM(file_size), 0)
+                 FROM files
+                 WHERE instr(file_path, '#section') = 0
+                 GROUP BY lower(file_type)
+                 ORDER BY COUNT(*) DESC, lower(file_type)"
+            )?;
+            let by_extension = stmt
+                .query_map([], |row| {
+                    Ok(ExtensionCount {
+                        extension: row.get(0)?,
+                        file_count: row.get::<_, i64>(1)? as usize,
+                        total_size: row.get(2)?,
+                    })
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+
+            // substr rather than LIKE so `_` and `%` in paths aren't treated as wildcards
+            let mut stmt = conn.prepare(
+                "SELECT COUNT(*), COALESCE(SUM(file_size), 0)
+                 FROM files
+                 WHERE (substr(file_path, 1, ?1) = ?2 OR substr(file_path, 1, ?1) = ?3)
+                   AND instr(file_path, '#section') = 0"
+            )?;
+            let mut by_directory = Vec::with_capacity(directories.len());
+            for directory in directories {
+                let prefixes = [format!("{}/", directory), format!("{}\\", directory)];
+                let prefix_len = prefixes[0].chars().count() as i64;
+                let (file_count, total_size): (i64, i64) = stmt.query_row(
+                    params![prefix_len, prefixes[0], prefixes[1]],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )?;
+                by_directory.push(DirectorySize { directory, file_count: file_count as usize, total_size });
+            }
+
+            let bucket_case: String = AGE_BUCKETS[..AGE_BUCKETS.len() - 1]
+                .iter()
+                .enumerate()
+                .map(|(index, (_, max_age))| format!("WHEN ?1 - modified_time < {} THEN {}", max_age, index))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let mut stmt = conn.prepare(&format!(
+                "SELECT CASE {} ELSE {} END AS bucket, COUNT(*)
+                 FROM files
+                 WHERE instr(file_path, '#section') = 0
+                 GROUP BY bucket",
+                bucket_case,
+                AGE_BUCKETS.len() - 1
+            ))?;
+            let mut by_age: Vec<AgeBucket> = AGE_BUCKETS
+                .iter()
+                .map(|(label, _)| AgeBucket { label, file_count: 0 })
+                .collect();
+            let mut rows = stmt.query(params![now])?;
+            while let Some(row) = rows.next()? {
+                let bucket = row.get::<_, i64>(0)? as usize;
+                by_age[bucket].file_count = row.get::<_, i64>(1)? as usize;
+            }
+
+            Ok::<_, anyhow::Error>(IndexAggregates {
+                total_files: total_files as usize,
+                total_size,
+                by_extension,
+                by_directory,
+                by_age,
+            })
+        }).await?
+    }
+
+    /// Hash of the content `file_path` was last embedded from, if one was recorded
+    pub async fn get_content_hash(&self, file_path: &str) -> Result<Option<String>> {
+        let conn = self.conn.clone();
+        let file_path = file_path.to_string();
+        task::spawn_blocking(move || {
+            let conn = lock_connection(&conn);
+            let hash = conn
+                .query_row(
+                    "SELECT content_hash FROM files WHERE file_path = ?1 AND embedding_length > 0",
+                    params![file_path],
+                    |row| row.get::<_, Option<String>>(0),
+                )
+                .optional()?
+                .flatten();
+            Ok::<Option<String>, anyhow::Error>(hash)
+        }).await?
+    }
+
+    /// Record the content hash of a freshly embedded file (the row is rewritten on every index)
+    pub async fn set_content_hash(&self, file_path: &str, content_hash: &str) -> Result<()> {
+        let conn = self.conn.clone();
+        let file_path = file_path.to_string();
+        let content_hash = content_hash.to_string();
+        task::spawn_blocking(move || {
+            let conn = lock_connection(&conn);
+            conn.execute(
+                "UPDATE files SET content_hash = ?2 WHERE file_path = ?1",
+                params![file_path, content_hash],
+            )?;
+            Ok::<(), anyhow::Error>(())
+        }).await?
+    }
+
+    /// Update the modified time and size of a file and its sections, keeping their vectors
+    /// (it still counts as freshly indexed for the activity feed)
+    pub async fn touch_file(&self, file_path: &str, modified_time: i64, file_size: i64) -> Result<()> {
+        let conn = self.conn.clone();
+        let file_path = file_path.to_string();
+        task::spawn_blocking(move || {
+            let conn = lock_connection(&conn);
+            conn.execute(
+                "UPDATE files SET modified_time = ?2, file_size = ?3, indexed_at = CAST(strftime('%s', 'now') AS INTEGER)
+                 WHERE file_path = ?1 OR substr(file_path, 1, length(?1) + 8) = ?1 || '#section'",
+                params![file_path, modified_time, file_size],
+            )?;
+            Ok::<(), anyhow::Error>(())
+        }).await?
+    }
+
+    /// Bookkeeping for one indexed file: when its row was last written (None if that predates
+    /// tracking), how many "#section" rows it has, and how many passages
+    pub async fn get_index_details(&self, file_path: &str) -> Result<(Option<i64>, usize, usize)> {
+        let conn = self.conn.clone();
+        let file_path = file_path.to_string();
+
+        task::spawn_blocking(move || {
+            let conn = lock_connection(&conn);
+            let indexed_at: Option<i64> = match conn.query_row(
+                "SELECT indexed_at FROM files WHERE file_path = ?1",
+                params![file_path],
+                |row| row.get(0),
+            ) {
+                Ok(0) | Err(rusqlite::Error::QueryReturnedNoRows) => None,
+                Ok(timestamp) => Some(timestamp),
+                Err(e) => return Err(e.into()),
+            };
+            let section_prefix = format!("{}#section", file_path);
+            let sections: i64 = conn.query_row(
+                "SELECT COUNT(*) FROM files WHERE substr(file_path, 1, ?1) = ?2",
+                params![section_prefix.chars().count() as i64, section_prefix],
+                |row| row.get(0),
+            )?;
+            let passages: i64 = conn.query_row(
+                "SELECT COUNT(*) FROM passages WHERE file_path = ?1",
+                params![file_path],
+                |row| row.get(0),
+            )?;
+            Ok((indexed_at, sections as usize, passages as usize))
+        }).await?
+    }
+
     pub async fn get_files_without_embeddings(&self) -> Result<Vec<FileMetadata>> {
-        let db_path = self.db_path.clone();
+        let conn = self.conn.clone();
         
         task::spawn_blocking(move || {
-            let conn = Connection::open(&db_path)?;
+            let conn = lock_connection(&conn);
             let mut stmt = conn.prepare(
                 "SELECT id, file_path, file_name, file_size, modified_time, file_type,
                         embedding_offset, embedding_length
@@ -221,11 +821,44 @@ impl Storage {
         }).await?
     }
 
+    /// Make everything written so far durable: fsync embeddings.bin and fold the WAL into the
+    /// database file. Run on shutdown so the next start opens a checkpointed index.
+    pub async fn flush(&self) -> Result<()> {
+        let conn = self.conn.clone();
+        let embeddings_path = self.embeddings_path.clone();
+        let lock = self.embeddings_lock.clone();
+        task::spawn_blocking(move || {
+            {
+                let _guard = Self::write_embeddings_file(&lock)?;
+                if embeddings_path.exists() {
+                    std::fs::OpenOptions::new().append(true).open(&embeddings_path)?.sync_all()?;
+                }
+            }
+            let conn = lock_connection(&conn);
+            conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+            Ok::<(), anyhow::Error>(())
+        }).await?
+    }
+
+    /// Number of indexed files, not counting extra "#section" rows
+    pub async fn file_count(&self) -> Result<usize> {
+        let conn = self.conn.clone();
+        task::spawn_blocking(move || {
+            let conn = lock_connection(&conn);
+            let count: i64 = conn.query_row(
+                "SELECT COUNT(*) FROM files WHERE instr(file_path, '#section') = 0",
+                [],
+                |row| row.get(0),
+            )?;
+            Ok::<usize, anyhow::Error>(count as usize)
+        }).await?
+    }
+
     pub async fn get_all_files(&self) -> Result<Vec<FileMetadata>> {
-        let db_path = self.db_path.clone();
+        let conn = self.conn.clone();
         
         task::spawn_blocking(move || {
-            let conn = Connection::open(&db_path)?;
+            let conn = lock_connection(&conn);
             let mut stmt = conn.prepare(
                 "SELECT id, file_path, file_name, file_size, modified_time, file_type,
                         embedding_offset, embedding_length
@@ -254,78 +887,152 @@ impl Storage {
         }).await?
     }
 
+    /// Count terms across indexed file names and passage text (the query spell-correction dictionary)
+    pub async fn term_counts(&self) -> Result<std::collections::HashMap<String, usize>> {
+        let conn = self.conn.clone();
+
+        task::spawn_blocking(move || {
+            let conn = lock_connection(&conn);
+            let mut counts = std::collections::HashMap::new();
+            for sql in ["SELECT file_name FROM files", "SELECT text FROM passages"] {
+                let mut stmt = conn.prepare(sql)?;
+                let mut rows = stmt.query([])?;
+                while let Some(row) = rows.next()? {
+                    let text: String = row.get(0)?;
+                    for term in crate::spell::terms(&text) {
+                        *counts.entry(term).or_insert(0) += 1;
+                    }
+                }
+            }
+            Ok::<_, anyhow::Error>(counts)
+        }).await?
+    }
+
+    /// Read a file's embedding, scaled to unit length unless magnitudes are kept. The location is
+    /// looked up again by path, since `metadata` may predate a compaction.
     pub async fn get_embedding(&self, metadata: &FileMetadata) -> Result<Vec<f32>> {
-        use std::io::{Seek, Read};
-        
+        self.ensure_embeddings_present()?;
+
         // Retry logic for Windows file locking issues
         let mut retries = 5;
-        let mut last_error = None;
-        
-        while retries > 0 {
-            match std::fs::File::open(&self.embeddings_path) {
-                Ok(mut file) => {
-                    match file.seek(std::io::SeekFrom::Start(metadata.embedding_offset as u64)) {
-                        Ok(_) => {
-                            let mut buffer = vec![0u8; metadata.embedding_length as usize];
-                            match file.read_exact(&mut buffer) {
-                                Ok(_) => {
-                                    match bincode::deserialize(&buffer) {
-                                        Ok(embedding) => return Ok(embedding),
-                                        Err(e) => {
-                                            last_error = Some(e.into());
-                                            break; // Deserialization error, don't retry
-                                        }
-                                    }
-                                }
-                                Err(e) => {
-                                    last_error = Some(e.into());
-                                    retries -= 1;
-                                    if retries > 0 {
-                                        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-                                        continue;
-                                    }
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            last_error = Some(e.into());
-                            retries -= 1;
-                            if retries > 0 {
-                                tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-                                continue;
-                            }
-                        }
+        loop {
+            let file_path = metadata.file_path.clone();
+            let mut rows = self.read_resolved(move |conn| {
+                let location = conn
+                    .query_row(
+                        "SELECT embedding_offset, embedding_length FROM files WHERE file_path = ?1 AND embedding_length > 0",
+                        params![file_path],
+                        |row| Ok(((), row.get(0)?, row.get(1)?)),
+                    )
+                    .optional()?;
+                Ok(location.into_iter().collect())
+            }).await?;
+            let Some((_, embedding)) = rows.pop() else {
+                return Err(anyhow::anyhow!("No embedding stored for {}", metadata.file_path));
+            };
+
+            match embedding {
+                Ok(mut embedding) => {
+                    if self.normalize_vectors {
+                        normalize(&mut embedding);
                     }
+                    return Ok(embedding);
                 }
                 Err(e) => {
-                    last_error = Some(e.into());
                     retries -= 1;
-                    if retries > 0 {
-                        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-                        continue;
+                    if retries == 0 {
+                        return Err(e);
                     }
+                    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
                 }
             }
         }
-        
-        Err(last_error.unwrap_or_else(|| anyhow::anyhow!("Failed to read embedding after retries")))
+    }
+
+    /// Look up vector locations with `resolve` and read them, both under the embeddings read lock
+    /// so compaction can't move a vector in between. Vectors are read through a single buffered
+    /// handle in offset order, so the disk sees one forward scan; results keep `resolve`'s order.
+    async fn read_resolved<R: Send + 'static>(
+        &self,
+        resolve: impl FnOnce(&Connection) -> Result<Vec<(R, i64, i64)>> + Send + 'static,
+    ) -> Result<Vec<(R, Result<Vec<f32>>)>> {
+        let conn = self.conn.clone();
+        let embeddings_path = self.embeddings_path.clone();
+        let lock = self.embeddings_lock.clone();
+
+        task::spawn_blocking(move || {
+            let _guard = Self::read_embeddings_file(&lock)?;
+            let rows = resolve(&lock_connection(&conn))?;
+            if rows.is_empty() {
+                return Ok(Vec::new());
+            }
+            let file = std::fs::File::open(&embeddings_path)?;
+            let mut reader = std::io::BufReader::with_capacity(1 << 20, file);
+            let mut position = 0u64;
+            let mut buffer = Vec::new();
+
+            let mut order: Vec<usize> = (0..rows.len()).collect();
+            order.sort_by_key(|&i| rows[i].1);
+
+            let mut results: Vec<Option<Result<Vec<f32>>>> = rows.iter().map(|_| None).collect();
+            for i in order {
+                let (_, offset, length) = rows[i];
+                let result = read_embedding_from(&mut reader, &mut position, offset as u64, length as usize, &mut buffer);
+                if result.is_err() {
+                    // Position is unknown after a failed read; re-sync before the next one
+                    use std::io::Seek;
+                    position = reader.stream_position()?;
+                }
+                results[i] = Some(result);
+            }
+
+            Ok(rows
+                .into_iter()
+                .zip(results)
+                .map(|((row, _, _), result)| (row, result.expect("every location is read")))
+                .collect())
+        }).await?
     }
 
     pub async fn get_all_embeddings(&self) -> Result<Vec<(FileMetadata, Vec<f32>)>> {
-        let files = self.get_all_files().await?;
+        self.ensure_embeddings_present()?;
         let mut result = Vec::new();
         let mut errors = Vec::new();
+
+        // One sequential pass over embeddings.bin instead of an open+seek+read per file
+        let files = self.read_resolved(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, file_path, file_name, file_size, modified_time, file_type,
+                        embedding_offset, embedding_length, normalized
+                 FROM files WHERE embedding_length > 0"
+            )?;
+            let rows = stmt.query_map([], |row| {
+                let file = FileMetadata {
+                    id: row.get(0)?,
+                    file_path: row.get(1)?,
+                    file_name: row.get(2)?,
+                    file_size: row.get(3)?,
+                    modified_time: row.get(4)?,
+                    file_type: row.get(5)?,
+                    embedding_offset: row.get(6)?,
+                    embedding_length: row.get(7)?,
+                };
+                let location = (file.embedding_offset, file.embedding_length);
+                Ok(((file, row.get::<_, bool>(8)?), location.0, location.1))
+            })?;
+            rows.collect::<rusqlite::Result<Vec<_>>>().map_err(anyhow::Error::from)
+        }).await?;
         
-        for file in files {
-            if file.embedding_length <= 0 {
-                continue; // Skip metadata-only files
-            }
-            match self.get_embedding(&file).await {
-                Ok(embedding) => {
+        for ((file, normalized), embedding) in files {
+            match embedding {
+                Ok(mut embedding) => {
+                    if !normalized && self.normalize_vectors {
+                        normalize(&mut embedding);
+                    }
                     result.push((file, embedding));
                 }
                 Err(e) => {
-                    eprintln!("Warning: Failed to get embedding for {}: {}", file.file_path, e);
+                    warn!("Failed to get embedding for {}: {}", file.file_path, e);
                     errors.push((file.file_path.clone(), e));
                     // Continue with other files even if one fails
                 }
@@ -333,82 +1040,807 @@ impl Storage {
         }
         
         if result.is_empty() && !errors.is_empty() {
+            // Most likely the file was deleted or truncated while running
+            if !self.check_embeddings_file().await?.is_intact() {
+                return Err(anyhow::anyhow!(EMBEDDINGS_MISSING_ERROR));
+            }
             return Err(anyhow::anyhow!("Failed to read any embeddings. First error: {}", errors[0].1));
         }
         
         Ok(result)
     }
 
+    /// Store a file-level vector from an embedding model other than the active one
+    pub async fn add_model_embedding(&self, file_path: &str, model: &str, embedding: &[f32]) -> Result<()> {
+        // Fusion only compares ranks, so these always use cosine
+        let (offset, length) = self.append_embedding(embedding, true).await?;
+        let conn = self.conn.clone();
+        let file_path = file_path.to_string();
+        let model = model.to_string();
+        task::spawn_blocking(move || {
+            let conn = lock_connection(&conn);
+            conn.execute(
+                "INSERT OR REPLACE INTO model_embeddings (file_path, model, embedding_offset, embedding_length, normalized)
+                 VALUES (?1, ?2, ?3, ?4, 1)",
+                params![file_path, model, offset, length],
+            )?;
+            Ok::<(), anyhow::Error>(())
+        }).await?
+    }
+
+    /// Models with vectors in `model_embeddings`
+    pub async fn embedding_models(&self) -> Result<Vec<String>> {
+        let conn = self.conn.clone();
+        task::spawn_blocking(move || {
+            let conn = lock_connection(&conn);
+            let mut stmt = conn.prepare("SELECT DISTINCT model FROM model_embeddings ORDER BY model")?;
+            let models = stmt.query_map([], |row| row.get(0))?.collect::<rusqlite::Result<Vec<String>>>()?;
+            Ok::<Vec<String>, anyhow::Error>(models)
+        }).await?
+    }
+
+    /// Every file with a vector from `model`, paired with that vector
+    pub async fn get_model_embeddings(&self, model: &str) -> Result<Vec<(FileMetadata, Vec<f32>)>> {
+        self.ensure_embeddings_present()?;
+        let model_name = model.to_string();
+        let rows = self.read_resolved(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT f.id, f.file_path, f.file_name, f.file_size, f.modified_time, f.file_type,
+                        f.embedding_offset, f.embedding_length, m.embedding_offset, m.embedding_length
+                 FROM model_embeddings m
+                 JOIN files f ON f.file_path = m.file_path
+                 WHERE m.model = ?1"
+            )?;
+            let rows = stmt.query_map(params![model_name], |row| {
+                Ok((
+                    FileMetadata {
+                        id: row.get(0)?,
+                        file_path: row.get(1)?,
+                        file_name: row.get(2)?,
+                        file_size: row.get(3)?,
+                        modified_time: row.get(4)?,
+                        file_type: row.get(5)?,
+                        embedding_offset: row.get(6)?,
+                        embedding_length: row.get(7)?,
+                    },
+                    row.get::<_, i64>(8)?,
+                    row.get::<_, i64>(9)?,
+                ))
+            })?;
+            Ok::<Vec<_>, anyhow::Error>(rows.collect::<rusqlite::Result<_>>()?)
+        }).await?;
+
+        let mut result = Vec::with_capacity(rows.len());
+        for (file, embedding) in rows {
+            match embedding {
+                Ok(embedding) => result.push((file, embedding)),
+                Err(e) => warn!("Failed to get {} embedding for {}: {}", model, file.file_path, e),
+            }
+        }
+        Ok(result)
+    }
+
     pub async fn delete_file(&self, file_path: &str) -> Result<()> {
-        let db_path = self.db_path.clone();
+        let conn = self.conn.clone();
+        let data_dir = self.data_dir.clone();
         let file_path = file_path.to_string();
         
         task::spawn_blocking(move || {
-            let conn = Connection::open(&db_path)?;
-            conn.execute("DELETE FROM files WHERE file_path = ?1", params![file_path])?;
+            let conn = lock_connection(&conn);
+            for table in EMBEDDING_TABLES {
+                conn.execute(&format!("DELETE FROM {} WHERE file_path = ?1", table), params![file_path])?;
+            }
+            TextCache::remove_files(&data_dir, [file_path.as_str()]);
             Ok::<(), anyhow::Error>(())
         }).await?
     }
 
+    /// Remove one file from the index along with its "#section" rows, passages and per-model
+    /// embeddings, and remember it in `removed_files` until it's indexed again. Returns whether
+    /// the file was indexed.
+    pub async fn remove_file(&self, file_path: &str) -> Result<bool> {
+        let conn = self.conn.clone();
+        let data_dir = self.data_dir.clone();
+        let file_path = file_path.to_string();
+
+        task::spawn_blocking(move || {
+            let mut conn = lock_connection(&conn);
+            let tx = conn.transaction()?;
+            tx.execute(
+                "INSERT OR REPLACE INTO removed_files (file_path, modified_time)
+                 SELECT file_path, modified_time FROM files WHERE file_path = ?1",
+                params![file_path],
+            )?;
+            let mut found = false;
+            for table in EMBEDDING_TABLES {
+                let removed = tx.execute(
+                    &format!(
+                        "DELETE FROM {} WHERE file_path = ?1 OR substr(file_path, 1, length(?1) + 8) = ?1 || '#section'",
+                        table
+                    ),
+                    params![file_path],
+                )?;
+                if table == "files" {
+                    found = removed > 0;
+                }
+            }
+            tx.commit()?;
+            TextCache::remove_files(&data_dir, [file_path.as_str()]);
+            Ok::<bool, anyhow::Error>(found)
+        }).await?
+    }
+
+    /// Files removed with `remove_file`, with the modified time they had when removed
+    pub async fn removed_files(&self) -> Result<std::collections::HashMap<String, i64>> {
+        let conn = self.conn.clone();
+
+        task::spawn_blocking(move || {
+            let conn = lock_connection(&conn);
+            let mut stmt = conn.prepare("SELECT file_path, modified_time FROM removed_files")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<rusqlite::Result<_>>().map_err(anyhow::Error::from)
+        }).await?
+    }
+
+    /// Re-point index entries for a moved file or directory (and everything beneath it).
+    /// Embeddings are reused as-is since the contents haven't changed.
+    pub async fn move_path(&self, old_path: &str, new_path: &str) -> Result<usize> {
+        self.relocate_path(old_path, new_path, false).await
+    }
+
+    /// Duplicate index entries for a copied file or directory, sharing the source's embeddings.
+    pub async fn copy_path(&self, source_path: &str, new_path: &str) -> Result<usize> {
+        self.relocate_path(source_path, new_path, true).await
+    }
+
+    async fn relocate_path(&self, old_path: &str, new_path: &str, keep_source: bool) -> Result<usize> {
+        let conn = self.conn.clone();
+        let old_path = old_path.to_string();
+        let new_path = new_path.to_string();
+
+        task::spawn_blocking(move || {
+            let mut conn = lock_connection(&conn);
+            let tx = conn.transaction()?;
+
+            // Match the path itself, its extra sections, and anything under it with either separator
+            let prefixes = [format!("{}/", old_path), format!("{}\\", old_path)];
+            let section_prefix = format!("{}#section", old_path);
+            // path, name, size, mtime, type, offset, length, normalized, indexed_at
+            type FileRow = (String, String, i64, i64, String, i64, i64, bool, i64);
+            let rows: Vec<FileRow> = {
+                let mut stmt = tx.prepare(
+                    "SELECT file_path, file_name, file_size, modified_time, file_type,
+                            embedding_offset, embedding_length, normalized, indexed_at
+                     FROM files
+                     WHERE file_path = ?1 OR substr(file_path, 1, ?2) = ?3 OR substr(file_path, 1, ?2) = ?4
+                        OR substr(file_path, 1, ?5) = ?6"
+                )?;
+                let rows = stmt.query_map(
+                    params![
+                        old_path,
+                        prefixes[0].chars().count() as i64,
+                        prefixes[0],
+                        prefixes[1],
+                        section_prefix.chars().count() as i64,
+                        section_prefix
+                    ],
+                    |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?, row.get(6)?, row.get(7)?, row.get(8)?)),
+                )?;
+                rows.collect::<rusqlite::Result<_>>()?
+            };
+
+            let old_name = std::path::Path::new(&old_path)
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string());
+            let new_name = std::path::Path::new(&new_path)
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string());
+
+            for (file_path, file_name, file_size, modified_time, file_type, offset, length, normalized, indexed_at) in &rows {
+                let relocated = format!("{}{}", new_path, &file_path[old_path.len()..]);
+                // Only the top-level entry changes name; children keep theirs
+                let relocated_name = if *file_path == old_path {
+                    new_name.clone().unwrap_or_else(|| file_name.clone())
+                } else if file_path.starts_with(&section_prefix) {
+                    // Section rows are named "{name} (section n)"
+                    match (&old_name, &new_name) {
+                        (Some(old_name), Some(new_name)) if file_name.starts_with(old_name.as_str()) => {
+                            format!("{}{}", new_name, &file_name[old_name.len()..])
+                        }
+                        _ => file_name.clone(),
+                    }
+                } else {
+                    file_name.clone()
+                };
+
+                if !keep_source {
+                    tx.execute("DELETE FROM files WHERE file_path = ?1", params![file_path])?;
+                }
+                tx.execute(
+                    "INSERT OR REPLACE INTO files
+                     (file_path, file_name, file_size, modified_time, file_type, embedding_offset, embedding_length, normalized, indexed_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                    params![relocated, relocated_name, file_size, modified_time, file_type, offset, length, normalized, indexed_at],
+                )?;
+
+                if keep_source {
+                    tx.execute(
+                        "INSERT OR REPLACE INTO passages
+                         (file_path, chunk_index, byte_start, byte_end, text, embedding_offset, embedding_length, normalized)
+                         SELECT ?1, chunk_index, byte_start, byte_end, text, embedding_offset, embedding_length, normalized
+                         FROM passages WHERE file_path = ?2",
+                        params![relocated, file_path],
+                    )?;
+                    tx.execute(
+                        "INSERT OR REPLACE INTO model_embeddings
+                         (file_path, model, embedding_offset, embedding_length, normalized)
+                         SELECT ?1, model, embedding_offset, embedding_length, normalized
+                         FROM model_embeddings WHERE file_path = ?2",
+                        params![relocated, file_path],
+                    )?;
+                } else {
+                    for table in ["passages", "model_embeddings"] {
+                        tx.execute(
+                            &format!("UPDATE {} SET file_path = ?1 WHERE file_path = ?2", table),
+                            params![relocated, file_path],
+                        )?;
+                    }
+                }
+            }
+
+            tx.commit()?;
+            Ok::<usize, anyhow::Error>(rows.len())
+        }).await?
+    }
+
     pub fn embeddings_path(&self) -> &PathBuf {
         &self.embeddings_path
     }
 
+    /// Remove every indexed file (and its passages) under `directory`. Matching respects path
+    /// separators, so clearing `/docs` leaves `/docs-old` alone.
     pub async fn remove_directory(&self, directory: &str) -> Result<usize> {
-        let db_path = self.db_path.clone();
-        let directory = directory.to_string();
+        let conn = self.conn.clone();
+        let data_dir = self.data_dir.clone();
+        let directory = directory.trim_end_matches(['/', '\\']).to_string();
         
         task::spawn_blocking(move || {
-            let conn = Connection::open(&db_path)?;
-            
-            // Normalize directory path to ensure consistent matching
-            // Ensure trailing slash for directory prefix matching
-            let mut dir_prefix = directory.clone();
-            if !dir_prefix.ends_with('/') && !dir_prefix.ends_with('\\') {
-                if dir_prefix.contains('\\') {
-                    dir_prefix.push('\\');
-                } else {
-                    dir_prefix.push('/');
-                }
+            let mut conn = lock_connection(&conn);
+            let tx = conn.transaction()?;
+
+            // substr rather than LIKE so `_` and `%` in paths aren't treated as wildcards
+            let prefixes = [format!("{}/", directory), format!("{}\\", directory)];
+            let prefix_len = prefixes[0].chars().count() as i64;
+            let removed_paths: Vec<String> = tx
+                .prepare("SELECT file_path FROM files WHERE substr(file_path, 1, ?1) = ?2 OR substr(file_path, 1, ?1) = ?3")?
+                .query_map(params![prefix_len, prefixes[0], prefixes[1]], |row| row.get(0))?
+                .collect::<rusqlite::Result<_>>()?;
+            for table in EMBEDDING_TABLES {
+                tx.execute(
+                    &format!(
+                        "DELETE FROM {} WHERE substr(file_path, 1, ?1) = ?2 OR substr(file_path, 1, ?1) = ?3",
+                        table
+                    ),
+                    params![prefix_len, prefixes[0], prefixes[1]],
+                )?;
             }
+            tx.commit()?;
+            TextCache::remove_files(&data_dir, removed_paths.iter().map(String::as_str));
             
-            // Prepare pattern for SQL LIKE query
-            let pattern = format!("{}%", dir_prefix);
-            
-            // Try exact match first
-            let mut count = conn.execute("DELETE FROM files WHERE file_path LIKE ?1", params![&pattern])?;
-            
-            // Also try alternate separator pattern
-            if count == 0 {
-                let alternate_prefix = if dir_prefix.contains('\\') {
-                    dir_prefix.replace('\\', "/")
-                } else {
-                    dir_prefix.replace('/', "\\")
-                };
-                let alternate_pattern = format!("{}%", alternate_prefix);
-                
-                count += conn.execute("DELETE FROM files WHERE file_path LIKE ?1", params![&alternate_pattern])?;
+            let count = removed_paths.len();
+            info!("[STORAGE] Removed {} files from index for directory: {}", count, directory);
+            Ok::<usize, anyhow::Error>(count)
+        }).await?
+    }
+
+    /// Drop every indexed zero-byte file along with anything stored for it (folder entries are
+    /// always zero bytes and stay). Returns how many were removed.
+    pub async fn remove_empty_files(&self) -> Result<usize> {
+        let conn = self.conn.clone();
+        task::spawn_blocking(move || {
+            let mut conn = lock_connection(&conn);
+            let tx = conn.transaction()?;
+            let mut count = 0;
+            // files last, so the others can still find which paths are empty
+            for table in EMBEDDING_TABLES.iter().rev() {
+                let removed = tx.execute(
+                    &format!(
+                        "DELETE FROM {} WHERE file_path IN
+                         (SELECT file_path FROM files WHERE file_size = 0 AND file_type != '{}')",
+                        table, DIRECTORY_FILE_TYPE
+                    ),
+                    [],
+                )?;
+                if *table == "files" {
+                    count = removed;
+                }
             }
-            
-            println!("[STORAGE] Removed {} files from index for directory: {}", count, directory);
+            tx.commit()?;
             Ok::<usize, anyhow::Error>(count)
         }).await?
     }
 
+    /// Size of embeddings.bin over the bytes still referenced by a row of `EMBEDDING_TABLES`;
+    /// 1.0 when nothing is wasted (or there is no file yet)
+    pub async fn embeddings_bloat_ratio(&self) -> Result<f64> {
+        let conn = self.conn.clone();
+        let embeddings_path = self.embeddings_path.clone();
+        task::spawn_blocking(move || {
+            let file_size = match std::fs::metadata(&embeddings_path) {
+                Ok(metadata) => metadata.len(),
+                Err(_) => return Ok(1.0),
+            };
+            let conn = lock_connection(&conn);
+            // Copied files share vectors, so count each distinct (offset, length) once
+            let mut locations = std::collections::HashSet::new();
+            for table in EMBEDDING_TABLES {
+                let mut stmt = conn.prepare(&format!(
+                    "SELECT DISTINCT embedding_offset, embedding_length FROM {} WHERE embedding_length > 0",
+                    table
+                ))?;
+                for row in stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)))? {
+                    locations.insert(row?);
+                }
+            }
+            let live_bytes: i64 = locations.iter().map(|(_, length)| length).sum();
+            Ok::<f64, anyhow::Error>(match (file_size, live_bytes) {
+                (0, _) => 1.0,
+                (_, 0) => f64::INFINITY,
+                (file_size, live_bytes) => file_size as f64 / live_bytes as f64,
+            })
+        }).await?
+    }
+
+    /// Rewrite embeddings.bin keeping only vectors still referenced by a row of `EMBEDDING_TABLES`,
+    /// and point every row at its new offset. Returns the number of bytes reclaimed.
+    /// Callers must hold the index writer lock (see `IndexJobs::run_lock`), or appended vectors
+    /// whose rows aren't written yet would be lost.
+    pub async fn compact_embeddings(&self) -> Result<u64> {
+        Ok(self.compact_embeddings_unless(|| false).await?.unwrap_or(0))
+    }
+
+    /// `compact_embeddings` that gives up, leaving embeddings.bin untouched, as soon as `interrupted`
+    /// returns true while vectors are being copied. None if it gave up.
+    pub async fn compact_embeddings_unless(
+        &self,
+        interrupted: impl Fn() -> bool + Send + 'static,
+    ) -> Result<Option<u64>> {
+        let conn = self.conn.clone();
+        let embeddings_path = self.embeddings_path.clone();
+        let lock = self.embeddings_lock.clone();
+        let normalize_vectors = self.normalize_vectors;
+
+        task::spawn_blocking(move || {
+            use std::io::{Read, Write};
+
+            let _guard = Self::write_embeddings_file(&lock)?;
+            if !embeddings_path.exists() {
+                return Ok(Some(0));
+            }
+            let old_size = std::fs::metadata(&embeddings_path)?.len();
+
+            let mut conn = lock_connection(&conn);
+            let tx = conn.transaction()?;
+
+            // Copied files share vectors, so compact per distinct (offset, length)
+            let mut locations: Vec<(i64, i64)> = Vec::new();
+            let mut legacy = std::collections::HashSet::new();
+            for table in EMBEDDING_TABLES {
+                let mut stmt = tx.prepare(&format!(
+                    "SELECT DISTINCT embedding_offset, embedding_length, normalized FROM {} WHERE embedding_length > 0",
+                    table
+                ))?;
+                let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get::<_, bool>(2)?)))?;
+                for row in rows {
+                    let (offset, length, normalized) = row?;
+                    if !normalized && normalize_vectors {
+                        legacy.insert((offset, length));
+                    }
+                    locations.push((offset, length));
+                }
+            }
+            locations.sort_unstable();
+            locations.dedup();
+
+            let tmp_path = embeddings_path.with_extension("bin.tmp");
+            let mut reader = std::io::BufReader::with_capacity(1 << 20, std::fs::File::open(&embeddings_path)?);
+            let mut writer = std::io::BufWriter::new(std::fs::File::create(&tmp_path)?);
+            let mut position = 0u64;
+            let mut new_offset = 0i64;
+            let mut buffer = Vec::new();
+            let mut relocations = Vec::with_capacity(locations.len());
+            for (offset, length) in locations {
+                if interrupted() {
+                    drop(writer);
+                    let _ = std::fs::remove_file(&tmp_path);
+                    return Ok(None);
+                }
+                if (offset as u64) < position {
+                    // Overlaps the previous vector; nothing sane to copy
+                    continue;
+                }
+                std::io::copy(&mut (&mut reader).take(offset as u64 - position), &mut std::io::sink())?;
+                buffer.resize(length as usize, 0);
+                reader.read_exact(&mut buffer)?;
+                if legacy.contains(&(offset, length)) {
+                    // Every vector is rewritten anyway, so finish the lazy normalization here
+                    let mut embedding: Vec<f32> = bincode::deserialize(&buffer)?;
+                    normalize(&mut embedding);
+                    buffer = bincode::serialize(&embedding)?;
+                }
+                writer.write_all(&buffer)?;
+                position = (offset + length) as u64;
+                relocations.push((offset, length, new_offset));
+                new_offset += length;
+            }
+            writer.flush()?;
+            writer.get_ref().sync_all()?;
+            drop(writer);
+
+            for table in EMBEDDING_TABLES {
+                let mut stmt = tx.prepare(&format!(
+                    "UPDATE {} SET embedding_offset = ?3 WHERE embedding_offset = ?1 AND embedding_length = ?2",
+                    table
+                ))?;
+                for (offset, length, relocated) in &relocations {
+                    stmt.execute(params![offset, length, relocated])?;
+                }
+                if normalize_vectors {
+                    tx.execute(&format!("UPDATE {} SET normalized = 1 WHERE embedding_length > 0", table), [])?;
+                }
+            }
+
+            // Swap the file in before committing; if the rename fails the old offsets stay valid
+            std::fs::rename(&tmp_path, &embeddings_path)?;
+            tx.commit()?;
+
+            let reclaimed = old_size.saturating_sub(new_offset as u64);
+            info!("[STORAGE] Compacted embeddings.bin, reclaimed {} bytes", reclaimed);
+            Ok::<Option<u64>, anyhow::Error>(Some(reclaimed))
+        }).await?
+    }
+
     pub async fn clear_all(&self) -> Result<()> {
         // Delete all records from database
-        let db_path = self.db_path.clone();
+        let conn = self.conn.clone();
         task::spawn_blocking(move || {
-            let conn = Connection::open(&db_path)?;
-            conn.execute("DELETE FROM files", [])?;
+            let conn = lock_connection(&conn);
+            for table in EMBEDDING_TABLES {
+                conn.execute(&format!("DELETE FROM {}", table), [])?;
+            }
+            conn.execute("DELETE FROM removed_files", [])?;
             Ok::<(), anyhow::Error>(())
         }).await??;
 
+        let data_dir = self.data_dir.clone();
+        task::spawn_blocking(move || TextCache::clear(&data_dir)).await??;
+
         // Delete embeddings file
-        if self.embeddings_path.exists() {
-            std::fs::remove_file(&self.embeddings_path)?;
-        }
+        self.remove_embeddings_file().await?;
+        self.embeddings_missing.store(false, Ordering::SeqCst);
 
         Ok(())
     }
+
+    /// Drop every stored vector but keep the file rows, marked so the next sync re-embeds
+    /// them: extra sections and passages are deleted, and embedded files get no embedding
+    /// and a zero modified time. Returns how many files were marked.
+    pub async fn reset_embeddings(&self) -> Result<usize> {
+        let conn = self.conn.clone();
+        let marked = task::spawn_blocking(move || {
+            let mut conn = lock_connection(&conn);
+            let tx = conn.transaction()?;
+            tx.execute("DELETE FROM files WHERE instr(file_path, '#section') > 0", [])?;
+            tx.execute("DELETE FROM passages", [])?;
+            tx.execute("DELETE FROM model_embeddings", [])?;
+            let marked = tx.execute(
+                "UPDATE files SET embedding_offset = 0, embedding_length = 0, modified_time = 0, content_hash = NULL
+                 WHERE embedding_length > 0",
+                [],
+            )?;
+            tx.commit()?;
+            Ok::<usize, anyhow::Error>(marked)
+        }).await??;
+
+        self.remove_embeddings_file().await?;
+        self.embeddings_missing.store(false, Ordering::SeqCst);
+        let data_dir = self.data_dir.clone();
+        task::spawn_blocking(move || TextCache::clear(&data_dir)).await??;
+        info!("[STORAGE] Reset embeddings, {} files marked for reindex", marked);
+
+        Ok(marked)
+    }
+}
+
+/// Read one serialized embedding at `offset`, seeking relative to `position` so buffered data is reused
+fn read_embedding_from(
+    reader: &mut std::io::BufReader<std::fs::File>,
+    position: &mut u64,
+    offset: u64,
+    length: usize,
+    buffer: &mut Vec<u8>,
+) -> Result<Vec<f32>> {
+    use std::io::{Read, Seek, SeekFrom};
+
+    if offset >= *position {
+        reader.seek_relative((offset - *position) as i64)?;
+    } else {
+        reader.seek(SeekFrom::Start(offset))?;
+    }
+    buffer.resize(length, 0);
+    reader.read_exact(buffer)?;
+    *position = offset + length as u64;
+    Ok(bincode::deserialize(buffer)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Temp data dir for one test, deleted when dropped
+    struct TestDir(PathBuf);
+
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// Storage over a fresh data dir named after the test; keep the `TestDir` alive until the end
+    async fn test_storage(name: &str) -> (Storage, TestDir) {
+        let data_dir = std::env::temp_dir().join(format!("gist-storage-{}-test-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&data_dir);
+        (Storage::new(&data_dir).await.unwrap(), TestDir(data_dir))
+    }
+
+    /// A one-byte txt row for `path`, modified at time 0; override fields with `..file_meta(path)`
+    fn file_meta(path: &str) -> FileMetadata {
+        FileMetadata {
+            id: 0,
+            file_path: path.to_string(),
+            file_name: path.rsplit(['/', '\\']).next().unwrap().to_string(),
+            file_size: 1,
+            modified_time: 0,
+            file_type: "txt".to_string(),
+            embedding_offset: 0,
+            embedding_length: 0,
+        }
+    }
+
+    #[test]
+    fn test_migrations_are_idempotent() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        run_migrations(&mut conn).unwrap();
+        run_migrations(&mut conn).unwrap();
+
+        let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0)).unwrap();
+        assert_eq!(version as usize, MIGRATIONS.len());
+        conn.execute("INSERT INTO passages (file_path, chunk_index, byte_start, byte_end, text, embedding_offset, embedding_length)
+                      VALUES ('a', 0, 0, 1, 'x', 0, 0)", []).unwrap();
+    }
+
+    #[test]
+    fn test_filter_queries_use_indexes() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        run_migrations(&mut conn).unwrap();
+        let plan = |sql: &str| {
+            let mut stmt = conn.prepare(&format!("EXPLAIN QUERY PLAN {}", sql)).unwrap();
+            let details: Vec<String> = stmt.query_map([], |row| row.get(3)).unwrap().map(Result::unwrap).collect();
+            details.join("; ")
+        };
+
+        assert!(plan("SELECT id FROM files WHERE modified_time BETWEEN 1 AND 2").contains("idx_files_modified_time"));
+        assert!(plan("SELECT id FROM files WHERE file_type = 'pdf'").contains("idx_files_file_type"));
+        assert!(plan("SELECT id FROM files WHERE file_path LIKE '/docs/%'").contains("idx_files_path_nocase"));
+    }
+
+    #[tokio::test]
+    async fn test_remove_directory_respects_separators_and_compacts() {
+        let (storage, _dir) = test_storage("directory").await;
+        storage.add_file(&file_meta("/docs/a.txt"), Some(&[1.0, 2.0])).await.unwrap();
+        storage.add_file(&file_meta("/docs-old/b.txt"), Some(&[3.0, 4.0])).await.unwrap();
+
+        let stale = storage.get_file_metadata("/docs-old/b.txt").await.unwrap().unwrap();
+
+        assert_eq!(storage.remove_directory("/docs").await.unwrap(), 1);
+        assert!(storage.compact_embeddings().await.unwrap() > 0);
+
+        let remaining = storage.get_all_embeddings().await.unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].0.file_path, "/docs-old/b.txt");
+        assert_eq!(remaining[0].1, vec![0.6, 0.8]);
+        // Metadata read before compaction still finds the moved vector
+        assert_ne!(stale.embedding_offset, remaining[0].0.embedding_offset);
+        assert_eq!(storage.get_embedding(&stale).await.unwrap(), vec![0.6, 0.8]);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_writes_share_one_connection() {
+        let (storage, _dir) = test_storage("concurrent").await;
+        let storage = Arc::new(storage);
+
+        let writers: Vec<_> = (0..16)
+            .map(|i| {
+                let storage = storage.clone();
+                tokio::spawn(async move {
+                    storage.add_file(&file_meta(&format!("/docs/{}.txt", i)), Some(&[1.0, i as f32])).await
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.await.unwrap().unwrap();
+        }
+
+        assert_eq!(storage.get_all_embeddings().await.unwrap().len(), 16);
+    }
+
+    #[tokio::test]
+    async fn test_recently_indexed_is_newest_first_without_sections() {
+        let (storage, _dir) = test_storage("recent").await;
+        for path in ["/docs/a.txt", "/docs/b.txt", "/docs/b.txt#section2", "/docs/c.txt"] {
+            storage.add_file(&file_meta(path), None).await.unwrap();
+        }
+
+        let recent = storage.recently_indexed(2).await.unwrap();
+        let paths: Vec<&str> = recent.iter().map(|(meta, _)| meta.file_path.as_str()).collect();
+        assert_eq!(paths, vec!["/docs/c.txt", "/docs/b.txt"]);
+        assert!(recent.iter().all(|(_, indexed_at)| *indexed_at > 0));
+    }
+
+    #[tokio::test]
+    async fn test_remove_file_drops_sections_only_for_that_file() {
+        let (storage, _dir) = test_storage("remove").await;
+        for path in ["/docs/a.txt", "/docs/a.txt#section2", "/docs/a.txt.bak"] {
+            storage.add_file(&file_meta(path), Some(&[1.0, 0.0])).await.unwrap();
+        }
+
+        assert!(storage.remove_file("/docs/a.txt").await.unwrap());
+        assert!(!storage.remove_file("/docs/a.txt").await.unwrap());
+        let remaining: Vec<String> = storage.get_all_files().await.unwrap().into_iter().map(|f| f.file_path).collect();
+        assert_eq!(remaining, vec!["/docs/a.txt.bak".to_string()]);
+
+        // Kept out of syncs until indexed again
+        assert_eq!(storage.removed_files().await.unwrap().get("/docs/a.txt"), Some(&0));
+        storage.add_file(&file_meta("/docs/a.txt"), Some(&[1.0, 0.0])).await.unwrap();
+        assert!(storage.removed_files().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_aggregate_groups_by_extension_directory_and_age() {
+        let (storage, _dir) = test_storage("aggregate").await;
+        let now = 1_700_000_000;
+        let file = |path: &str, file_size: i64, age: i64| FileMetadata {
+            file_size,
+            modified_time: now - age,
+            file_type: path.rsplit('.').next().unwrap().to_string(),
+            ..file_meta(path)
+        };
+        storage.add_file(&file("/docs/a.pdf", 10, 60), None).await.unwrap();
+        storage.add_file(&file("/docs/a.pdf#section2", 10, 60), None).await.unwrap();
+        storage.add_file(&file("/docs/b.PDF", 20, 3 * 86_400), None).await.unwrap();
+        storage.add_file(&file("/notes/c.txt", 5, 400 * 86_400), None).await.unwrap();
+
+        let aggregates = storage.aggregate(&["/docs/".to_string(), "/notes".to_string()], now).await.unwrap();
+        assert_eq!((aggregates.total_files, aggregates.total_size), (3, 35));
+        assert_eq!(
+            aggregates.by_extension,
+            vec![
+                ExtensionCount { extension: "pdf".to_string(), file_count: 2, total_size: 30 },
+                ExtensionCount { extension: "txt".to_string(), file_count: 1, total_size: 5 },
+            ]
+        );
+        assert_eq!(aggregates.by_directory[0], DirectorySize { directory: "/docs".to_string(), file_count: 2, total_size: 30 });
+        assert_eq!(aggregates.by_directory[1].total_size, 5);
+        let age_counts: Vec<usize> = aggregates.by_age.iter().map(|bucket| bucket.file_count).collect();
+        assert_eq!(age_counts, vec![1, 1, 0, 0, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_remove_empty_files_keeps_non_empty_and_folders() {
+        let (storage, _dir) = test_storage("empty").await;
+        storage.add_file(&FileMetadata { file_size: 0, ..file_meta("/docs/empty.txt") }, None).await.unwrap();
+        storage.add_file(&FileMetadata { file_size: 12, ..file_meta("/docs/notes.txt") }, Some(&[1.0, 0.0])).await.unwrap();
+        let folder = FileMetadata { file_size: 0, file_type: DIRECTORY_FILE_TYPE.to_string(), ..file_meta("/docs/taxes") };
+        storage.add_file(&folder, None).await.unwrap();
+
+        assert_eq!(storage.remove_empty_files().await.unwrap(), 1);
+        let mut remaining: Vec<String> = storage.get_all_files().await.unwrap().into_iter().map(|f| f.file_path).collect();
+        remaining.sort();
+        assert_eq!(remaining, vec!["/docs/notes.txt".to_string(), "/docs/taxes".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_touch_file_keeps_vectors_and_hash() {
+        let (storage, _dir) = test_storage("touch").await;
+        let file = |path: &str| FileMetadata { modified_time: 10, ..file_meta(path) };
+        storage.add_file(&file("/docs/a.txt"), Some(&[1.0, 0.0])).await.unwrap();
+        storage.add_file(&file("/docs/a.txt#section2"), Some(&[0.0, 1.0])).await.unwrap();
+        storage.add_file(&file("/docs/a.txt.bak"), Some(&[0.0, 1.0])).await.unwrap();
+        storage.set_content_hash("/docs/a.txt", "abc").await.unwrap();
+
+        storage.touch_file("/docs/a.txt", 20, 2).await.unwrap();
+        assert_eq!(storage.get_content_hash("/docs/a.txt").await.unwrap().as_deref(), Some("abc"));
+        let modified = |path: &'static str| {
+            let storage = &storage;
+            async move { storage.get_file_metadata(path).await.unwrap().unwrap().modified_time }
+        };
+        assert_eq!(modified("/docs/a.txt").await, 20);
+        assert_eq!(modified("/docs/a.txt#section2").await, 20);
+        assert_eq!(modified("/docs/a.txt.bak").await, 10);
+
+        // Re-embedding rewrites the row, so the stale hash goes with it
+        storage.add_file(&file("/docs/a.txt"), Some(&[1.0, 0.0])).await.unwrap();
+        assert_eq!(storage.get_content_hash("/docs/a.txt").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_repair_duplicate_paths_collapses_spellings() {
+        let (storage, _dir) = test_storage("repair").await;
+        storage.add_file(&file_meta("C:\\Docs\\a.txt"), Some(&[1.0, 0.0])).await.unwrap();
+        storage.add_file(&file_meta("c:\\docs\\A.txt"), Some(&[0.0, 1.0])).await.unwrap();
+        storage.add_file(&file_meta("C:\\Docs\\a.txt#section2"), Some(&[1.0, 1.0])).await.unwrap();
+        storage.add_model_embedding("C:\\Docs\\a.txt", "fusion", &[1.0, 0.0]).await.unwrap();
+        storage.add_model_embedding("c:\\docs\\A.txt", "fusion", &[0.0, 1.0]).await.unwrap();
+
+        {
+            let mut conn = lock_connection(&storage.conn);
+            let tx = conn.transaction().unwrap();
+            assert_eq!(repair_duplicate_paths(&tx, |path| path.to_lowercase()).unwrap(), 1);
+            tx.commit().unwrap();
+        }
+
+        let mut paths: Vec<String> = storage.get_all_files().await.unwrap().into_iter().map(|f| f.file_path).collect();
+        paths.sort();
+        assert_eq!(paths, vec!["c:\\docs\\a.txt", "c:\\docs\\a.txt#section2"]);
+
+        // The fusion vector follows its file to the canonical path, and the duplicate's is dropped
+        let fusion = storage.get_model_embeddings("fusion").await.unwrap();
+        assert_eq!(fusion.len(), 1);
+        assert_eq!(fusion[0].0.file_path, "c:\\docs\\a.txt");
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_appends_get_distinct_offsets() {
+        let (storage, _dir) = test_storage("appends").await;
+        let storage = Arc::new(storage);
+
+        let writers: Vec<_> = (0..32).map(|i| {
+            let storage = storage.clone();
+            tokio::spawn(async move {
+                storage.add_file(&file_meta(&format!("/docs/{}.txt", i)), Some(&[i as f32, 1.0])).await.unwrap();
+                // Searches run alongside indexing
+                storage.get_all_embeddings().await.unwrap();
+            })
+        }).collect();
+        for writer in writers {
+            writer.await.unwrap();
+        }
+
+        let embeddings = storage.get_all_embeddings().await.unwrap();
+        assert_eq!(embeddings.len(), 32);
+        for (metadata, embedding) in embeddings {
+            let i: f32 = metadata.file_name.trim_end_matches(".txt").parse().unwrap();
+            let norm = (i * i + 1.0).sqrt();
+            assert_eq!(embedding, vec![i / norm, 1.0 / norm]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_missing_embeddings_file_is_detected_and_reset() {
+        let (storage, dir) = test_storage("missing").await;
+        let metadata = FileMetadata { modified_time: 42, ..file_meta("/docs/a.txt") };
+        storage.add_file(&metadata, Some(&[1.0, 0.0])).await.unwrap();
+        std::fs::remove_file(storage.embeddings_path()).unwrap();
+
+        let storage = Storage::new(&dir.0).await.unwrap();
+        assert!(storage.embeddings_missing());
+        let err = storage.get_all_embeddings().await.unwrap_err();
+        assert!(err.to_string().contains("reindex required"));
+
+        assert_eq!(storage.reset_embeddings().await.unwrap(), 1);
+        assert!(!storage.embeddings_missing());
+        assert!(storage.get_all_embeddings().await.unwrap().is_empty());
+        let reset = storage.get_file_metadata("/docs/a.txt").await.unwrap().unwrap();
+        assert_eq!((reset.embedding_length, reset.modified_time), (0, 0));
+    }
 }
@@ -0,0 +1,235 @@
+// Disk cache of text extracted by the document parsers.
+// Parsing a large PDF or DOCX takes seconds, and previews, Active RAG and AI chat/summarize keep
+// re-extracting the same files. Entries are keyed by path, modification time and size, so an edited
+// file misses; once the cache outgrows its budget the least recently used entries are deleted.
+// Storage purges a file's entries whenever its index rows are removed.
+
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::debug;
+
+/// Bytes held by each cache directory, counted by one scan when first used and kept up to date
+/// by every insert and purge. Holding it also serializes eviction and purging.
+static USAGE: Mutex<BTreeMap<PathBuf, u64>> = Mutex::new(BTreeMap::new());
+
+/// Makes temp file names unique across concurrent inserts of the same entry
+static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+fn lock_usage() -> MutexGuard<'static, BTreeMap<PathBuf, u64>> {
+    USAGE.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Extracted text stored under data_dir/text_cache, one subdirectory per source file (so
+/// de-indexing a file can purge it) holding one entry per version of that file
+#[derive(Debug, Clone)]
+pub struct TextCache {
+    dir: PathBuf,
+    max_bytes: u64,
+}
+
+impl TextCache {
+    pub fn new(data_dir: &Path, max_bytes: u64) -> Self {
+        Self {
+            dir: Self::dir_in(data_dir),
+            max_bytes,
+        }
+    }
+
+    /// The cache sized by `text_cache_max_mb`, or None when that is 0
+    pub fn from_config(config: &crate::config::AppConfig) -> Option<Self> {
+        (config.text_cache_max_mb > 0).then(|| {
+            Self::new(&crate::config::AppConfig::data_dir(), config.text_cache_max_mb * 1024 * 1024)
+        })
+    }
+
+    fn dir_in(data_dir: &Path) -> PathBuf {
+        data_dir.join("text_cache")
+    }
+
+    fn hex_digest(input: &str) -> String {
+        use sha1::{Digest, Sha1};
+
+        Sha1::digest(input.as_bytes()).iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    /// Where every cached version of `file_path` is stored
+    fn file_dir(dir: &Path, file_path: &str) -> PathBuf {
+        dir.join(Self::hex_digest(file_path))
+    }
+
+    /// Where the text of `file_path` as of (`modified`, `size`) is stored
+    fn entry_path(&self, file_path: &str, modified: SystemTime, size: u64) -> PathBuf {
+        let modified_nanos = modified.duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+        let name = Self::hex_digest(&format!("{}\0{}", modified_nanos, size));
+        Self::file_dir(&self.dir, file_path).join(format!("{}.txt", name))
+    }
+
+    /// Cached text for the file in its current state; a hit marks the entry as recently used
+    pub fn get(&self, file_path: &str, modified: SystemTime, size: u64) -> Option<String> {
+        let entry = self.entry_path(file_path, modified, size);
+        let text = std::fs::read_to_string(&entry).ok()?;
+        // Eviction orders entries by mtime, so that is what records their last use
+        if let Ok(file) = std::fs::File::options().write(true).open(&entry) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+        Some(text)
+    }
+
+    /// Store extracted text, then evict least recently used entries once over the size budget
+    pub fn put(&self, file_path: &str, modified: SystemTime, size: u64, text: &str) -> Result<()> {
+        if text.len() as u64 > self.max_bytes {
+            return Ok(());
+        }
+        let entry = self.entry_path(file_path, modified, size);
+        if let Some(parent) = entry.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // Write via a temp file so a concurrent reader never sees half an entry
+        let tmp_entry = entry.with_extension(format!(
+            "{}-{}.tmp",
+            std::process::id(),
+            TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::write(&tmp_entry, text)?;
+
+        let mut usage = lock_usage();
+        let total = usage.entry(self.dir.clone()).or_insert_with(|| Self::scan(&self.dir).1);
+        let replaced = std::fs::metadata(&entry).map(|m| m.len()).unwrap_or(0);
+        if let Err(e) = std::fs::rename(&tmp_entry, &entry) {
+            let _ = std::fs::remove_file(&tmp_entry);
+            return Err(e.into());
+        }
+        *total = total.saturating_sub(replaced) + text.len() as u64;
+        if *total > self.max_bytes {
+            *total = self.evict();
+        }
+        Ok(())
+    }
+
+    /// Every entry as (last used, bytes, path), and their total size. Files left by the older
+    /// flat layout are deleted, since no lookup can reach them any more.
+    fn scan(dir: &Path) -> (Vec<(SystemTime, u64, PathBuf)>, u64) {
+        let mut entries = Vec::new();
+        let mut total_bytes = 0;
+        for file_dir in std::fs::read_dir(dir).into_iter().flatten().flatten() {
+            let file_dir = file_dir.path();
+            if !file_dir.is_dir() {
+                let _ = std::fs::remove_file(&file_dir);
+                continue;
+            }
+            for entry in std::fs::read_dir(&file_dir).into_iter().flatten().flatten() {
+                let path = entry.path();
+                if path.extension().and_then(|e| e.to_str()) != Some("txt") {
+                    continue;
+                }
+                if let Ok(metadata) = entry.metadata() {
+                    total_bytes += metadata.len();
+                    entries.push((metadata.modified().unwrap_or(UNIX_EPOCH), metadata.len(), path));
+                }
+            }
+        }
+        (entries, total_bytes)
+    }
+
+    /// Delete the least recently used entries until the cache fills at most 90% of `max_bytes`,
+    /// leaving headroom so the next few inserts don't each rescan. Returns the bytes left.
+    fn evict(&self) -> u64 {
+        let (mut entries, mut total_bytes) = Self::scan(&self.dir);
+        let target = self.max_bytes / 10 * 9;
+
+        entries.sort();
+        for (_, len, path) in entries {
+            if total_bytes <= target {
+                break;
+            }
+            if std::fs::remove_file(&path).is_ok() {
+                debug!("Evicted cached text {}", path.display());
+                total_bytes -= len;
+                if let Some(file_dir) = path.parent() {
+                    // Only succeeds once the file has no other cached versions
+                    let _ = std::fs::remove_dir(file_dir);
+                }
+            }
+        }
+        total_bytes
+    }
+
+    /// Delete the cached text of `file_paths`, for files removed from the index under `data_dir`
+    pub fn remove_files<'a>(data_dir: &Path, file_paths: impl IntoIterator<Item = &'a str>) {
+        let dir = Self::dir_in(data_dir);
+        let mut usage = lock_usage();
+        for file_path in file_paths {
+            let file_dir = Self::file_dir(&dir, file_path);
+            let bytes: u64 = std::fs::read_dir(&file_dir)
+                .into_iter()
+                .flatten()
+                .flatten()
+                .filter_map(|entry| entry.metadata().ok())
+                .map(|metadata| metadata.len())
+                .sum();
+            if std::fs::remove_dir_all(&file_dir).is_ok() {
+                if let Some(total) = usage.get_mut(&dir) {
+                    *total = total.saturating_sub(bytes);
+                }
+            }
+        }
+    }
+
+    /// Delete all cached text under `data_dir`, for when the whole index is cleared
+    pub fn clear(data_dir: &Path) -> Result<()> {
+        let dir = Self::dir_in(data_dir);
+        let mut usage = lock_usage();
+        match std::fs::remove_dir_all(&dir) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        usage.insert(dir, 0);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_cache_misses_on_change_and_evicts_least_recently_used() {
+        let data_dir = std::env::temp_dir().join(format!("gist-text-cache-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&data_dir);
+        let cache = TextCache::new(&data_dir, 10);
+        let modified = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+
+        cache.put("/docs/a.pdf", modified, 100, "aaaa").unwrap();
+        assert_eq!(cache.get("/docs/a.pdf", modified, 100).as_deref(), Some("aaaa"));
+        assert_eq!(cache.get("/docs/a.pdf", modified, 101), None);
+        assert_eq!(cache.get("/docs/a.pdf", modified + Duration::from_secs(1), 100), None);
+
+        // Make b the older entry, then touch a so b is the one evicted
+        cache.put("/docs/b.pdf", modified, 100, "bbbb").unwrap();
+        let b_entry = cache.entry_path("/docs/b.pdf", modified, 100);
+        std::fs::File::options().write(true).open(&b_entry).unwrap().set_modified(modified).unwrap();
+        cache.get("/docs/a.pdf", modified, 100);
+        cache.put("/docs/c.pdf", modified, 100, "cccc").unwrap();
+
+        assert_eq!(cache.get("/docs/b.pdf", modified, 100), None);
+        assert!(cache.get("/docs/a.pdf", modified, 100).is_some());
+        assert!(cache.get("/docs/c.pdf", modified, 100).is_some());
+
+        // Purging a file frees its bytes, so the next insert has room without evicting
+        TextCache::remove_files(&data_dir, ["/docs/a.pdf"]);
+        assert_eq!(cache.get("/docs/a.pdf", modified, 100), None);
+        cache.put("/docs/d.pdf", modified, 100, "dddd").unwrap();
+        assert!(cache.get("/docs/c.pdf", modified, 100).is_some());
+
+        TextCache::clear(&data_dir).unwrap();
+        assert_eq!(cache.get("/docs/c.pdf", modified, 100), None);
+        assert!(!data_dir.join("text_cache").exists());
+        let _ = std::fs::remove_dir_all(&data_dir);
+    }
+}
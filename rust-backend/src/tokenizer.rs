@@ -0,0 +1,72 @@
+use std::sync::Arc;
+use tiktoken_rs::CoreBPE;
+use tracing::warn;
+
+/// Counts and truncates text in model tokens for context-budget decisions
+pub trait TokenCounter: Send + Sync {
+    fn count_tokens(&self, text: &str) -> usize;
+
+    /// Truncate text to at most `max_tokens` tokens (never splits a character)
+    fn truncate(&self, text: &str, max_tokens: usize) -> String;
+}
+
+/// Rough fallback: 1 token ≈ 4 characters
+pub struct HeuristicTokenCounter;
+
+impl TokenCounter for HeuristicTokenCounter {
+    fn count_tokens(&self, text: &str) -> usize {
+        text.len() / 4
+    }
+
+    fn truncate(&self, text: &str, max_tokens: usize) -> String {
+        text.chars().take(max_tokens * 4).collect()
+    }
+}
+
+/// BPE tokenizer backed by tiktoken
+pub struct TiktokenCounter {
+    bpe: CoreBPE,
+}
+
+impl TiktokenCounter {
+    pub fn cl100k() -> anyhow::Result<Self> {
+        Ok(Self {
+            bpe: tiktoken_rs::cl100k_base()?,
+        })
+    }
+}
+
+impl TokenCounter for TiktokenCounter {
+    fn count_tokens(&self, text: &str) -> usize {
+        self.bpe.encode_ordinary(text).len()
+    }
+
+    fn truncate(&self, text: &str, max_tokens: usize) -> String {
+        let tokens = self.bpe.encode_ordinary(text);
+        if tokens.len() <= max_tokens {
+            return text.to_string();
+        }
+        match self.bpe.decode_bytes(&tokens[..max_tokens]) {
+            // A token boundary can fall inside a multi-byte character; drop the partial char
+            Ok(bytes) => String::from_utf8_lossy(&bytes)
+                .trim_end_matches('\u{FFFD}')
+                .to_string(),
+            Err(_) => HeuristicTokenCounter.truncate(text, max_tokens),
+        }
+    }
+}
+
+/// Pick a token counter for `model`, an embedding or chat model.
+/// Ollama embedders (embeddinggemma, all-minilm, ...) and chat models use sub-word vocabularies
+/// that cl100k approximates far better than chars/4 for CJK text and code. Falls back to
+/// the heuristic if the tokenizer can't be loaded.
+#[tracing::instrument(name = "TOKENIZER", skip_all)]
+pub fn token_counter_for_model(model: &str) -> Arc<dyn TokenCounter> {
+    match TiktokenCounter::cl100k() {
+        Ok(counter) => Arc::new(counter),
+        Err(e) => {
+            warn!("No tokenizer available for model '{}' ({}), using chars/4 estimate", model, e);
+            Arc::new(HeuristicTokenCounter)
+        }
+    }
+}